@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE storage DROP COLUMN IF EXISTS version;
+ALTER TABLE drawers DROP COLUMN IF EXISTS version;
+ALTER TABLE freezers DROP COLUMN IF EXISTS version;
+ALTER TABLE products DROP COLUMN IF EXISTS version;
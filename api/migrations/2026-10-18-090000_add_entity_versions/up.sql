@@ -0,0 +1,5 @@
+-- Row versions used for optimistic concurrency (ETag / If-Match).
+ALTER TABLE products ADD COLUMN version INT NOT NULL DEFAULT (1);
+ALTER TABLE freezers ADD COLUMN version INT NOT NULL DEFAULT (1);
+ALTER TABLE drawers ADD COLUMN version INT NOT NULL DEFAULT (1);
+ALTER TABLE storage ADD COLUMN version INT NOT NULL DEFAULT (1);
@@ -0,0 +1,11 @@
+-- Responses of mutating requests, replayed when a client retries with the same Idempotency-Key.
+CREATE TABLE IF NOT EXISTS idempotency_keys
+(
+    idempotency_key       VARCHAR(255) PRIMARY KEY,
+    request_fingerprint   VARCHAR(64)  NOT NULL,
+    response_status       INT,
+    response_body         BYTEA,
+    response_content_type VARCHAR(255),
+    response_etag         VARCHAR(255),
+    created_at            TIMESTAMPTZ  NOT NULL DEFAULT (now())
+);
@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM storage WHERE deleted_at IS NOT NULL;
+DELETE FROM drawers WHERE deleted_at IS NOT NULL;
+DELETE FROM freezers WHERE deleted_at IS NOT NULL;
+DELETE FROM products WHERE deleted_at IS NOT NULL;
+
+DROP INDEX IF EXISTS drawers_freezer_id_name_active_key;
+DROP INDEX IF EXISTS freezers_name_active_key;
+DROP INDEX IF EXISTS products_name_active_key;
+
+ALTER TABLE drawers ADD CONSTRAINT drawers_freezer_id_name_key UNIQUE (freezer_id, name);
+ALTER TABLE freezers ADD CONSTRAINT freezers_name_key UNIQUE (name);
+ALTER TABLE products ADD CONSTRAINT products_name_key UNIQUE (name);
+
+ALTER TABLE storage DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE drawers DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE freezers DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE products DROP COLUMN IF EXISTS deleted_at;
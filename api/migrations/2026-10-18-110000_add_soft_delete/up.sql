@@ -0,0 +1,14 @@
+-- Soft deletion: rows are moved to the trash by setting deleted_at, and purged after a retention period.
+ALTER TABLE products ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE freezers ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE drawers ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE storage ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Names only have to be unique amongst rows that are not in the trash.
+ALTER TABLE products DROP CONSTRAINT IF EXISTS products_name_key;
+ALTER TABLE freezers DROP CONSTRAINT IF EXISTS freezers_name_key;
+ALTER TABLE drawers DROP CONSTRAINT IF EXISTS drawers_freezer_id_name_key;
+
+CREATE UNIQUE INDEX products_name_active_key ON products (name) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX freezers_name_active_key ON freezers (name) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX drawers_freezer_id_name_active_key ON drawers (freezer_id, name) WHERE deleted_at IS NULL;
@@ -0,0 +1,23 @@
+-- Product categories, organised as a tree through parent_id.
+CREATE TABLE IF NOT EXISTS categories
+(
+    category_id SERIAL PRIMARY KEY,
+    name        VARCHAR(50) UNIQUE NOT NULL,
+    parent_id   INT REFERENCES categories (category_id) ON DELETE SET NULL
+);
+
+ALTER TABLE products ADD COLUMN category_id INT REFERENCES categories (category_id) ON DELETE SET NULL;
+
+-- Free-form tags on products.
+CREATE TABLE IF NOT EXISTS tags
+(
+    tag_id SERIAL PRIMARY KEY,
+    name   VARCHAR(50) UNIQUE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS product_tags
+(
+    product_id INT NOT NULL REFERENCES products (product_id) ON DELETE CASCADE,
+    tag_id     INT NOT NULL REFERENCES tags (tag_id) ON DELETE CASCADE,
+    PRIMARY KEY (product_id, tag_id)
+);
@@ -0,0 +1,5 @@
+ALTER TABLE products DROP COLUMN default_unit;
+-- Quantities in other units have no weight to fall back to.
+DELETE FROM storage WHERE unit <> 'grams';
+ALTER TABLE storage DROP COLUMN unit;
+ALTER TABLE storage RENAME COLUMN quantity TO weight_grams;
@@ -0,0 +1,8 @@
+-- Storage items hold a quantity in a unit instead of a weight. Existing weights are in grams.
+ALTER TABLE storage RENAME COLUMN weight_grams TO quantity;
+ALTER TABLE storage ADD COLUMN unit VARCHAR(20) NOT NULL DEFAULT ('grams')
+    CHECK (unit IN ('grams', 'millilitres', 'pieces', 'portions'));
+
+-- Unit proposed when storing a product.
+ALTER TABLE products ADD COLUMN default_unit VARCHAR(20) NOT NULL DEFAULT ('grams')
+    CHECK (default_unit IN ('grams', 'millilitres', 'pieces', 'portions'));
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS product_shelf_lives;
+ALTER TABLE freezers DROP COLUMN freezer_type_id;
+DROP TABLE IF EXISTS freezer_types;
+ALTER TABLE products DROP COLUMN shelf_life_days;
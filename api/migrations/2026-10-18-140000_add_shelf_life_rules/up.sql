@@ -0,0 +1,21 @@
+-- Shelf life in days, overriding expiration_months when set.
+ALTER TABLE products ADD COLUMN shelf_life_days INT CHECK (shelf_life_days BETWEEN 1 AND 36500);
+
+-- Kinds of freezers, e.g. star ratings or chest and upright freezers, scaling the shelf life.
+CREATE TABLE IF NOT EXISTS freezer_types
+(
+    freezer_type_id       SERIAL PRIMARY KEY,
+    name                  VARCHAR(50) UNIQUE NOT NULL,
+    shelf_life_multiplier REAL NOT NULL DEFAULT 1 CHECK (shelf_life_multiplier > 0 AND shelf_life_multiplier <= 10)
+);
+
+ALTER TABLE freezers ADD COLUMN freezer_type_id INT REFERENCES freezer_types (freezer_type_id) ON DELETE SET NULL;
+
+-- Shelf life of a product in a freezer type, replacing the multiplied shelf life.
+CREATE TABLE IF NOT EXISTS product_shelf_lives
+(
+    product_id      INT NOT NULL REFERENCES products (product_id) ON DELETE CASCADE,
+    freezer_type_id INT NOT NULL REFERENCES freezer_types (freezer_type_id) ON DELETE CASCADE,
+    shelf_life_days INT NOT NULL CHECK (shelf_life_days BETWEEN 1 AND 36500),
+    PRIMARY KEY (product_id, freezer_type_id)
+);
@@ -0,0 +1,2 @@
+-- Explicit expiration date, e.g. a printed best-before date, replacing the computed one.
+ALTER TABLE storage ADD COLUMN expiration_date DATE;
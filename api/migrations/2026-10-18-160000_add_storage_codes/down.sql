@@ -0,0 +1,2 @@
+ALTER TABLE storage DROP COLUMN code;
+DROP FUNCTION IF EXISTS generate_storage_code();
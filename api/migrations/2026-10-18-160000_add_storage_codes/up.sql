@@ -0,0 +1,22 @@
+-- Short codes identifying storage items, e.g. on printed labels. The alphabet leaves out characters
+-- that are easily confused (0/O, 1/I/L), it must match crate::core::item_code::ALPHABET.
+CREATE OR REPLACE FUNCTION generate_storage_code() RETURNS VARCHAR(6) AS
+$$
+DECLARE
+    new_code VARCHAR(6);
+BEGIN
+    LOOP
+        SELECT string_agg(substr('23456789ABCDEFGHJKMNPQRSTUVWXYZ', floor(random() * 31)::INT + 1, 1), '')
+        INTO new_code
+        FROM generate_series(1, 6);
+        EXIT WHEN NOT EXISTS (SELECT 1 FROM storage WHERE code = new_code);
+    END LOOP;
+    RETURN new_code;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+ALTER TABLE storage ADD COLUMN code VARCHAR(6);
+UPDATE storage SET code = generate_storage_code();
+ALTER TABLE storage ALTER COLUMN code SET NOT NULL;
+ALTER TABLE storage ALTER COLUMN code SET DEFAULT generate_storage_code();
+ALTER TABLE storage ADD CONSTRAINT storage_code_key UNIQUE (code);
@@ -0,0 +1,9 @@
+-- Barcodes printed on store-bought products, stored as EAN-13 (UPC-A codes get a leading zero).
+-- A barcode belongs to a single product, a product can have many.
+CREATE TABLE IF NOT EXISTS product_barcodes
+(
+    barcode    VARCHAR(13) PRIMARY KEY,
+    product_id INT NOT NULL REFERENCES products (product_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS product_barcodes_product_id_idx ON product_barcodes (product_id);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS products_name_active_key;
+DROP INDEX IF EXISTS freezers_name_active_key;
+DROP INDEX IF EXISTS drawers_freezer_id_name_active_key;
+
+CREATE UNIQUE INDEX products_name_active_key ON products (name) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX freezers_name_active_key ON freezers (name) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX drawers_freezer_id_name_active_key ON drawers (freezer_id, name) WHERE deleted_at IS NULL;
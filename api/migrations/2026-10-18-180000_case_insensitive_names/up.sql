@@ -0,0 +1,28 @@
+-- Names are trimmed with inner whitespace collapsed, and unique regardless of case amongst rows that
+-- are not in the trash, see crate::core::name.
+UPDATE products SET name = regexp_replace(btrim(name), '\s+', ' ', 'g') WHERE name <> regexp_replace(btrim(name), '\s+', ' ', 'g');
+UPDATE freezers SET name = regexp_replace(btrim(name), '\s+', ' ', 'g') WHERE name <> regexp_replace(btrim(name), '\s+', ' ', 'g');
+UPDATE drawers SET name = regexp_replace(btrim(name), '\s+', ' ', 'g') WHERE name <> regexp_replace(btrim(name), '\s+', ' ', 'g');
+
+-- Existing names only differing in case get their id appended, keeping the oldest row as is.
+UPDATE products p SET name = left(p.name, 38) || ' (' || p.product_id || ')'
+WHERE p.deleted_at IS NULL
+  AND EXISTS (SELECT 1 FROM products o
+              WHERE o.deleted_at IS NULL AND lower(o.name) = lower(p.name) AND o.product_id < p.product_id);
+UPDATE freezers f SET name = left(f.name, 38) || ' (' || f.freezer_id || ')'
+WHERE f.deleted_at IS NULL
+  AND EXISTS (SELECT 1 FROM freezers o
+              WHERE o.deleted_at IS NULL AND lower(o.name) = lower(f.name) AND o.freezer_id < f.freezer_id);
+UPDATE drawers d SET name = left(d.name, 38) || ' (' || d.drawer_id || ')'
+WHERE d.deleted_at IS NULL
+  AND EXISTS (SELECT 1 FROM drawers o
+              WHERE o.deleted_at IS NULL AND o.freezer_id = d.freezer_id AND lower(o.name) = lower(d.name)
+                AND o.drawer_id < d.drawer_id);
+
+DROP INDEX IF EXISTS products_name_active_key;
+DROP INDEX IF EXISTS freezers_name_active_key;
+DROP INDEX IF EXISTS drawers_freezer_id_name_active_key;
+
+CREATE UNIQUE INDEX products_name_active_key ON products (lower(name)) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX freezers_name_active_key ON freezers (lower(name)) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX drawers_freezer_id_name_active_key ON drawers (freezer_id, lower(name)) WHERE deleted_at IS NULL;
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS shopping_list_items;
+
+ALTER TABLE products DROP CONSTRAINT IF EXISTS products_min_stock_check;
+ALTER TABLE products DROP COLUMN min_stock_unit;
+ALTER TABLE products DROP COLUMN min_stock_quantity;
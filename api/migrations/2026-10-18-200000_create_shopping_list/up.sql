@@ -0,0 +1,18 @@
+-- Minimum stock of a product, the shopping list suggests the product when less of it is available.
+ALTER TABLE products ADD COLUMN min_stock_quantity REAL CHECK (min_stock_quantity > 0);
+ALTER TABLE products ADD COLUMN min_stock_unit VARCHAR(20)
+    CHECK (min_stock_unit IN ('grams', 'millilitres', 'pieces', 'portions'));
+ALTER TABLE products ADD CONSTRAINT products_min_stock_check
+    CHECK ((min_stock_quantity IS NULL) = (min_stock_unit IS NULL));
+
+-- Items added to the shopping list by hand, kept once checked off.
+CREATE TABLE IF NOT EXISTS shopping_list_items
+(
+    item_id    SERIAL PRIMARY KEY,
+    name       VARCHAR(50) NOT NULL,
+    product_id INT REFERENCES products (product_id) ON DELETE SET NULL,
+    quantity   REAL CHECK (quantity > 0),
+    unit       VARCHAR(20) CHECK (unit IN ('grams', 'millilitres', 'pieces', 'portions')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    checked_at TIMESTAMPTZ
+);
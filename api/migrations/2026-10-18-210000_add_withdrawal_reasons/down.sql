@@ -0,0 +1,2 @@
+ALTER TABLE storage DROP COLUMN withdrawal_note;
+ALTER TABLE storage DROP COLUMN withdrawal_reason;
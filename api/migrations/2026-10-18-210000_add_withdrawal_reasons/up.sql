@@ -0,0 +1,4 @@
+-- Why a storage item was withdrawn, set when withdrawing and cleared when re-entering it.
+ALTER TABLE storage ADD COLUMN withdrawal_reason VARCHAR(20)
+    CHECK (withdrawal_reason IN ('eaten', 'discarded', 'givenAway'));
+ALTER TABLE storage ADD COLUMN withdrawal_note TEXT;
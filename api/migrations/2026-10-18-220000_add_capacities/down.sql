@@ -0,0 +1,7 @@
+ALTER TABLE drawers DROP CONSTRAINT IF EXISTS drawers_capacity_pair_check;
+ALTER TABLE drawers DROP COLUMN capacity_unit;
+ALTER TABLE drawers DROP COLUMN capacity;
+
+ALTER TABLE freezers DROP CONSTRAINT IF EXISTS freezers_capacity_pair_check;
+ALTER TABLE freezers DROP COLUMN capacity_unit;
+ALTER TABLE freezers DROP COLUMN capacity;
@@ -0,0 +1,8 @@
+-- Optional capacity of freezers and drawers, as a maximum weight or volume.
+ALTER TABLE freezers ADD COLUMN capacity REAL CHECK (capacity > 0);
+ALTER TABLE freezers ADD COLUMN capacity_unit VARCHAR(20) CHECK (capacity_unit IN ('grams', 'millilitres'));
+ALTER TABLE freezers ADD CONSTRAINT freezers_capacity_pair_check CHECK ((capacity IS NULL) = (capacity_unit IS NULL));
+
+ALTER TABLE drawers ADD COLUMN capacity REAL CHECK (capacity > 0);
+ALTER TABLE drawers ADD COLUMN capacity_unit VARCHAR(20) CHECK (capacity_unit IN ('grams', 'millilitres'));
+ALTER TABLE drawers ADD CONSTRAINT drawers_capacity_pair_check CHECK ((capacity IS NULL) = (capacity_unit IS NULL));
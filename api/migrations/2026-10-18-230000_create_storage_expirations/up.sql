@@ -0,0 +1,25 @@
+-- Effective expiration date of every storage item, as computed by the API from the shelf life rules:
+-- the explicit expiration date, else the override of the product for the freezer type, else the
+-- product shelf life (days or months) scaled by the freezer type multiplier. Used by aggregate queries.
+CREATE VIEW storage_expirations AS
+SELECT s.storage_id,
+       COALESCE(
+           s.expiration_date,
+           CASE
+               WHEN o.shelf_life_days IS NOT NULL THEN s.date_in + o.shelf_life_days
+               WHEN ft.shelf_life_multiplier IS NULL OR ft.shelf_life_multiplier = 1 THEN base.date_expires
+               ELSE s.date_in + GREATEST(1, round(((base.date_expires - s.date_in) * ft.shelf_life_multiplier)::numeric))::int
+           END
+       ) AS expiration_date
+FROM storage s
+         JOIN products p ON p.product_id = s.product_id
+         JOIN drawers d ON d.drawer_id = s.drawer_id
+         JOIN freezers f ON f.freezer_id = d.freezer_id
+         LEFT JOIN freezer_types ft ON ft.freezer_type_id = f.freezer_type_id
+         LEFT JOIN product_shelf_lives o ON o.product_id = s.product_id AND o.freezer_type_id = f.freezer_type_id
+         CROSS JOIN LATERAL (
+    SELECT CASE
+               WHEN p.shelf_life_days IS NOT NULL THEN s.date_in + p.shelf_life_days
+               ELSE (s.date_in + make_interval(months => p.expiration_months))::date
+           END AS date_expires
+    ) base;
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS stocktake_items;
+DROP TABLE IF EXISTS stocktakes;
+
+UPDATE storage SET withdrawal_reason = NULL WHERE withdrawal_reason = 'missing';
+ALTER TABLE storage DROP CONSTRAINT IF EXISTS storage_withdrawal_reason_check;
+ALTER TABLE storage ADD CONSTRAINT storage_withdrawal_reason_check
+    CHECK (withdrawal_reason IN ('eaten', 'discarded', 'givenAway'));
@@ -0,0 +1,23 @@
+-- Items not found during a stocktake are withdrawn as missing.
+ALTER TABLE storage DROP CONSTRAINT IF EXISTS storage_withdrawal_reason_check;
+ALTER TABLE storage ADD CONSTRAINT storage_withdrawal_reason_check
+    CHECK (withdrawal_reason IN ('eaten', 'discarded', 'givenAway', 'missing'));
+
+-- Stocktake of a freezer, open until completed. A freezer has at most one open stocktake.
+CREATE TABLE IF NOT EXISTS stocktakes
+(
+    stocktake_id SERIAL PRIMARY KEY,
+    freezer_id   INT         NOT NULL REFERENCES freezers (freezer_id) ON DELETE CASCADE,
+    started_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ
+);
+CREATE UNIQUE INDEX IF NOT EXISTS stocktakes_open_freezer_idx ON stocktakes (freezer_id) WHERE completed_at IS NULL;
+
+-- Storage items found during a stocktake. Items withdrawn as missing on completion are kept without `seen_at`.
+CREATE TABLE IF NOT EXISTS stocktake_items
+(
+    stocktake_id INT NOT NULL REFERENCES stocktakes (stocktake_id) ON DELETE CASCADE,
+    storage_id   INT NOT NULL REFERENCES storage (storage_id) ON DELETE CASCADE,
+    seen_at      TIMESTAMPTZ,
+    PRIMARY KEY (stocktake_id, storage_id)
+);
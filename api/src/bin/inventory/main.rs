@@ -0,0 +1,128 @@
+//! Command line tool to manage the inventory directly in the database, using `DATABASE_URL`.
+//!
+//! ```bash
+//! inventory import <file> [--format csv|json] [--dry-run] [--create-missing]
+//! inventory export <file.json|file.zip>
+//! inventory restore <file.json>
+//! ```
+//!
+//! See [api::core::import] for the import format. The format defaults to the extension of the file.
+//! The report is printed as JSON, the exit code is 1 when any row is invalid.
+//!
+//! See [api::core::backup] for exports. A `.zip` file gets the CSV export, anything else the JSON dump,
+//! which `restore` loads into an empty database.
+
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use diesel::PgConnection;
+use diesel_migrations::MigrationHarness;
+
+use api::core::backup::{export, export_csv_zip, restore, Dump};
+use api::core::connection::{establish_connection, MIGRATIONS};
+use api::core::events::EventBus;
+use api::core::import::{import_rows, parse_rows, ImportFormat, ImportOptions};
+
+const USAGE: &str = "Usage:
+    inventory import <file> [--format csv|json] [--dry-run] [--create-missing]
+    inventory export <file.json|file.zip>
+    inventory restore <file.json>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("import") => import(&args[1..]),
+        Some("export") => export_to_file(&args[1..]),
+        Some("restore") => restore_from_file(&args[1..]),
+        _ => Err(String::from(USAGE)),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Connection to the database of `DATABASE_URL`, with all migrations run.
+fn connect() -> Result<PgConnection, String> {
+    let mut conn = establish_connection(None);
+    conn.run_pending_migrations(MIGRATIONS).map_err(|err| err.to_string())?;
+
+    Ok(conn)
+}
+
+/// The single file argument of a command.
+fn file_argument(args: &[String]) -> Result<&str, String> {
+    match args {
+        [file] if !file.starts_with("--") => Ok(file),
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+/// `inventory export`.
+fn export_to_file(args: &[String]) -> Result<ExitCode, String> {
+    let file = file_argument(args)?;
+    let conn = &mut connect()?;
+
+    let content = if file.ends_with(".zip") {
+        export_csv_zip(conn).map_err(|(_, err)| err)?
+    } else {
+        let dump = export(conn).map_err(|err| err.to_string())?;
+        serde_json::to_vec_pretty(&dump).map_err(|err| err.to_string())?
+    };
+    fs::write(file, content).map_err(|err| format!("Can't write {}: {}", file, err))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `inventory restore`.
+fn restore_from_file(args: &[String]) -> Result<ExitCode, String> {
+    let file = file_argument(args)?;
+    let input = fs::read_to_string(file).map_err(|err| format!("Can't read {}: {}", file, err))?;
+    let dump = serde_json::from_str::<Dump>(&input).map_err(|err| format!("Invalid dump: {}", err))?;
+
+    let conn = &mut connect()?;
+    let report = restore(conn, &dump).map_err(|(_, err)| err)?;
+    println!("{}", serde_json::to_string_pretty(&report).map_err(|err| err.to_string())?);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `inventory import`.
+fn import(args: &[String]) -> Result<ExitCode, String> {
+    let mut file = None;
+    let mut format = None;
+    let mut options = ImportOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => options.dry_run = true,
+            "--create-missing" => options.create_missing = true,
+            "--format" => format = Some(args.next().ok_or(USAGE)?.parse::<ImportFormat>()?),
+            _ if file.is_none() && !arg.starts_with("--") => file = Some(arg.clone()),
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+    let file = file.ok_or(USAGE)?;
+    let format = match format {
+        Some(format) => format,
+        None => Path::new(&file)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .parse::<ImportFormat>()
+            .map_err(|_| String::from("Can't tell the format from the file name, use --format csv|json"))?,
+    };
+    let input = fs::read_to_string(&file).map_err(|err| format!("Can't read {}: {}", file, err))?;
+    let rows = parse_rows(&input, format)?;
+
+    let conn = &mut connect()?;
+    let report = import_rows(conn, rows, options, &EventBus::new()).map_err(|err| err.to_string())?;
+    println!("{}", serde_json::to_string_pretty(&report).map_err(|err| err.to_string())?);
+
+    Ok(if report.is_valid() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
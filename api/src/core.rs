@@ -1,5 +1,21 @@
 //! Contains core modules used by the API for its functionality.
 
+pub mod analytics;
+pub mod backup;
+pub mod barcode;
+pub mod capacity;
 pub mod connection;
 pub mod error;
+pub mod etag;
+pub mod events;
+pub mod idempotency;
+pub mod import;
+pub mod item_code;
+pub mod label;
+pub mod name;
 pub mod query;
+pub mod recommendation;
+pub mod search;
+pub mod shelf_life;
+pub mod shopping_list;
+pub mod trash;
@@ -0,0 +1,359 @@
+//! Consumption and waste statistics over a date range.
+//!
+//! An item counts as frozen in the range when its `date_in` falls in it, and as withdrawn when its
+//! `date_out` does. Dwell time and waste only look at the items withdrawn in the range: an item
+//! withdrawn after its expiration date is counted as expired, one withdrawn with
+//! [WithdrawalReason::Discarded] as discarded. Quantities are summed per [Unit], as quantities in
+//! different units can't be added up.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Unit, WithdrawalReason};
+
+/// Storage item as needed for the statistics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemRecord {
+    /// Id of the product.
+    pub product_id: i32,
+    /// Name of the product.
+    pub product_name: String,
+    /// Id of the freezer the item is in.
+    pub freezer_id: i32,
+    /// Name of the freezer the item is in.
+    pub freezer_name: String,
+    /// Quantity of the item, in [Self::unit].
+    pub quantity: f32,
+    /// Unit of the quantity.
+    pub unit: Unit,
+    /// Date of entry.
+    pub date_in: NaiveDate,
+    /// Date of withdrawal, `None` when still in storage.
+    pub date_out: Option<NaiveDate>,
+    /// Effective expiration date, see [crate::core::shelf_life].
+    pub expiration_date: NaiveDate,
+    /// Why the item was withdrawn, if given.
+    pub withdrawal_reason: Option<WithdrawalReason>,
+}
+
+/// Longest [DateRange] accepted, in years.
+pub const MAX_RANGE_YEARS: u32 = 10;
+
+/// Inclusive range of dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    /// First day of the range.
+    pub from: NaiveDate,
+    /// Last day of the range.
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    /// Range from `from` to `to`, both included. Errors when `from` is later than `to`, or when the
+    /// range spans more than [MAX_RANGE_YEARS].
+    pub fn new(from: NaiveDate, to: NaiveDate) -> Result<Self, String> {
+        if from > to {
+            return Err(String::from("from cannot be later than to"));
+        }
+        if from.checked_add_months(Months::new(12 * MAX_RANGE_YEARS)).is_some_and(|limit| to >= limit) {
+            return Err(format!("The range cannot span more than {} years", MAX_RANGE_YEARS));
+        }
+        Ok(DateRange { from, to })
+    }
+
+    /// Whether `date` lies in the range.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && date <= self.to
+    }
+
+    /// Ranges of the calendar months overlapping this range, cut off at its bounds.
+    pub fn months(&self) -> Vec<DateRange> {
+        let mut months = Vec::new();
+        let mut from = self.from;
+        while from <= self.to {
+            let next = first_of_next_month(from);
+            let to = next.and_then(|next| next.pred_opt()).map_or(self.to, |last| last.min(self.to));
+            months.push(DateRange { from, to });
+            match next {
+                Some(next) => from = next,
+                None => break,
+            }
+        }
+        months
+    }
+}
+
+/// First day of the month after `date`, `None` past the last representable date.
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    match date.month() {
+        12 => NaiveDate::from_ymd_opt(date.year().checked_add(1)?, 1, 1),
+        month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+    }
+}
+
+/// Frozen and withdrawn amounts over a date range.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    /// Amount of items put in the freezer.
+    pub frozen_items: i64,
+    /// Quantity put in the freezer, per unit.
+    pub frozen_quantity: BTreeMap<Unit, f32>,
+    /// Amount of items withdrawn from the freezer.
+    pub withdrawn_items: i64,
+    /// Quantity withdrawn from the freezer, per unit.
+    pub withdrawn_quantity: BTreeMap<Unit, f32>,
+    /// Average days between entry and withdrawal of the withdrawn items, `None` without withdrawals.
+    pub average_dwell_days: Option<f64>,
+    /// Amount of withdrawn items that were past their expiration date.
+    pub expired_items: i64,
+    /// Share of the withdrawn items that were past their expiration date, from 0 to 1. `None`
+    /// without withdrawals.
+    pub expired_share: Option<f64>,
+    /// Amount of withdrawn items that were discarded.
+    pub discarded_items: i64,
+    /// Share of the withdrawn items that were discarded, from 0 to 1. Items withdrawn without a
+    /// reason count as not discarded. `None` without withdrawals.
+    pub discard_rate: Option<f64>,
+}
+
+impl Stats {
+    /// Statistics of the `records` over `range`.
+    pub fn of<'a>(records: impl IntoIterator<Item = &'a ItemRecord>, range: &DateRange) -> Self {
+        let mut stats = Stats::default();
+        let mut dwell_days = 0;
+        for record in records {
+            if range.contains(record.date_in) {
+                stats.frozen_items += 1;
+                *stats.frozen_quantity.entry(record.unit).or_insert(0.0) += record.quantity;
+            }
+            if let Some(date_out) = record.date_out.filter(|date_out| range.contains(*date_out)) {
+                stats.withdrawn_items += 1;
+                *stats.withdrawn_quantity.entry(record.unit).or_insert(0.0) += record.quantity;
+                dwell_days += (date_out - record.date_in).num_days();
+                if date_out > record.expiration_date {
+                    stats.expired_items += 1;
+                }
+                if record.withdrawal_reason == Some(WithdrawalReason::Discarded) {
+                    stats.discarded_items += 1;
+                }
+            }
+        }
+        if stats.withdrawn_items > 0 {
+            let withdrawn = stats.withdrawn_items as f64;
+            stats.average_dwell_days = Some(dwell_days as f64 / withdrawn);
+            stats.expired_share = Some(stats.expired_items as f64 / withdrawn);
+            stats.discard_rate = Some(stats.discarded_items as f64 / withdrawn);
+        }
+        stats
+    }
+}
+
+/// Statistics of one product.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductStats {
+    /// Id of the product.
+    pub product_id: i32,
+    /// Name of the product.
+    pub product_name: String,
+    /// Statistics of the product over the range.
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// Statistics of one freezer.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezerStats {
+    /// Id of the freezer.
+    pub freezer_id: i32,
+    /// Name of the freezer.
+    pub freezer_name: String,
+    /// Statistics of the items in the freezer over the range.
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// Statistics of one calendar month.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthStats {
+    /// Month, formatted `<YYYY>-<MM>`.
+    pub month: String,
+    /// Statistics over the part of the month inside the range.
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// Statistics per product over `range`, leaving out products without any activity in it. Sorted by
+/// product name.
+pub fn per_product(records: &[ItemRecord], range: &DateRange) -> Vec<ProductStats> {
+    let mut result: Vec<ProductStats> = grouped(records, range, |record| record.product_id)
+        .into_iter()
+        .map(|(record, stats)| ProductStats {
+            product_id: record.product_id,
+            product_name: record.product_name.clone(),
+            stats,
+        })
+        .collect();
+    result.sort_by(|a, b| a.product_name.cmp(&b.product_name));
+
+    result
+}
+
+/// Statistics per freezer over `range`, leaving out freezers without any activity in it. Sorted by
+/// freezer name. Items count for the freezer they are in now.
+pub fn per_freezer(records: &[ItemRecord], range: &DateRange) -> Vec<FreezerStats> {
+    let mut result: Vec<FreezerStats> = grouped(records, range, |record| record.freezer_id)
+        .into_iter()
+        .map(|(record, stats)| FreezerStats {
+            freezer_id: record.freezer_id,
+            freezer_name: record.freezer_name.clone(),
+            stats,
+        })
+        .collect();
+    result.sort_by(|a, b| a.freezer_name.cmp(&b.freezer_name));
+
+    result
+}
+
+/// Statistics of the records grouped by `key`, with a record of each group, leaving out groups
+/// without any activity.
+fn grouped<'a>(records: &'a [ItemRecord], range: &DateRange, key: impl Fn(&ItemRecord) -> i32) -> Vec<(&'a ItemRecord, Stats)> {
+    let mut groups: HashMap<i32, Vec<&ItemRecord>> = HashMap::new();
+    for record in records {
+        groups.entry(key(record)).or_default().push(record);
+    }
+    groups
+        .into_values()
+        .map(|records| (records[0], Stats::of(records.iter().copied(), range)))
+        .filter(|(_, stats)| stats.frozen_items > 0 || stats.withdrawn_items > 0)
+        .collect()
+}
+
+/// Statistics per calendar month of `range`, including months without activity.
+pub fn per_month(records: &[ItemRecord], range: &DateRange) -> Vec<MonthStats> {
+    range
+        .months()
+        .iter()
+        .map(|month| MonthStats {
+            month: month.from.format("%Y-%m").to_string(),
+            stats: Stats::of(records, month),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod statistics {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn record(product_id: i32, quantity: f32, date_in: &str, date_out: Option<&str>, expiration_date: &str) -> ItemRecord {
+        ItemRecord {
+            product_id,
+            product_name: format!("Product {}", product_id),
+            freezer_id: product_id,
+            freezer_name: format!("Freezer {}", product_id),
+            quantity,
+            unit: Unit::Grams,
+            date_in: date(date_in),
+            date_out: date_out.map(date),
+            expiration_date: date(expiration_date),
+            withdrawal_reason: date_out.map(|_| WithdrawalReason::Eaten),
+        }
+    }
+
+    fn records() -> Vec<ItemRecord> {
+        vec![
+            record(1, 400.0, "2023-01-10", Some("2023-03-01"), "2024-01-10"),
+            record(1, 600.0, "2023-02-10", None, "2024-02-10"),
+            ItemRecord {
+                withdrawal_reason: Some(WithdrawalReason::Discarded),
+                ..record(2, 300.0, "2022-01-10", Some("2023-02-20"), "2023-01-10")
+            },
+        ]
+    }
+
+    #[test]
+    fn range_is_inclusive_and_ordered() {
+        let range = DateRange::new(date("2023-01-01"), date("2023-01-31")).unwrap();
+        assert!(range.contains(date("2023-01-01")));
+        assert!(range.contains(date("2023-01-31")));
+        assert!(!range.contains(date("2023-02-01")));
+        assert!(DateRange::new(date("2023-02-01"), date("2023-01-01")).is_err());
+    }
+
+    #[test]
+    fn range_is_limited_and_safe_near_the_last_date() {
+        assert!(DateRange::new(date("2013-01-02"), date("2023-01-01")).is_ok());
+        assert!(DateRange::new(date("2013-01-01"), date("2023-01-01")).is_err());
+
+        let last = NaiveDate::MAX;
+        let range = DateRange::new(last.with_day(1).unwrap(), last).unwrap();
+        assert_eq!(range.months(), vec![range]);
+    }
+
+    #[test]
+    fn months_are_cut_off_at_the_range() {
+        let range = DateRange::new(date("2022-12-15"), date("2023-02-10")).unwrap();
+        let months: Vec<(NaiveDate, NaiveDate)> = range.months().iter().map(|m| (m.from, m.to)).collect();
+        assert_eq!(months, vec![
+            (date("2022-12-15"), date("2022-12-31")),
+            (date("2023-01-01"), date("2023-01-31")),
+            (date("2023-02-01"), date("2023-02-10")),
+        ]);
+    }
+
+    #[test]
+    fn counts_frozen_withdrawn_and_expired() {
+        let range = DateRange::new(date("2023-01-01"), date("2023-12-31")).unwrap();
+        let stats = Stats::of(&records(), &range);
+
+        assert_eq!(stats.frozen_items, 2);
+        assert_eq!(stats.frozen_quantity, BTreeMap::from([(Unit::Grams, 1000.0)]));
+        assert_eq!(stats.withdrawn_items, 2);
+        assert_eq!(stats.withdrawn_quantity, BTreeMap::from([(Unit::Grams, 700.0)]));
+        // 50 days and 406 days.
+        assert_eq!(stats.average_dwell_days, Some(228.0));
+        assert_eq!(stats.expired_items, 1);
+        assert_eq!(stats.expired_share, Some(0.5));
+        assert_eq!(stats.discarded_items, 1);
+        assert_eq!(stats.discard_rate, Some(0.5));
+    }
+
+    #[test]
+    fn no_withdrawals_has_no_averages() {
+        let range = DateRange::new(date("2023-01-01"), date("2023-01-31")).unwrap();
+        let stats = Stats::of(&records(), &range);
+
+        assert_eq!(stats.frozen_items, 1);
+        assert_eq!(stats.average_dwell_days, None);
+        assert_eq!(stats.expired_share, None);
+        assert_eq!(stats.discard_rate, None);
+    }
+
+    #[test]
+    fn groups_per_product_and_month() {
+        let range = DateRange::new(date("2023-01-01"), date("2023-03-31")).unwrap();
+
+        let products = per_product(&records(), &range);
+        assert_eq!(products.iter().map(|p| p.product_id).collect::<Vec<i32>>(), vec![1, 2]);
+        assert_eq!(products[1].stats.expired_items, 1);
+
+        let freezers = per_freezer(&records(), &range);
+        assert_eq!(freezers.iter().map(|f| f.freezer_name.as_str()).collect::<Vec<&str>>(), vec!["Freezer 1", "Freezer 2"]);
+        assert_eq!(freezers[1].stats.discard_rate, Some(1.0));
+
+        let months = per_month(&records(), &range);
+        assert_eq!(months.iter().map(|m| m.month.as_str()).collect::<Vec<&str>>(), vec!["2023-01", "2023-02", "2023-03"]);
+        assert_eq!(months[1].stats.frozen_items, 1);
+        assert_eq!(months[1].stats.withdrawn_items, 1);
+        assert_eq!(months[2].stats.withdrawn_items, 1);
+    }
+}
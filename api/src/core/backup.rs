@@ -0,0 +1,346 @@
+//! Export of all data as a versioned dump, and restore of such a dump into an empty database.
+//!
+//! A [Dump] holds the rows of every table in [TABLES] as JSON objects keyed by column name, ids
+//! included. Restoring inserts them as they are, preserving ids and relationships, and then moves the
+//! id sequences past the restored ids. Columns missing from a row get their default, so a dump taken
+//! before a column was added can still be restored. Idempotency keys are not part of a dump.
+//!
+//! A dump can also be exported as a ZIP of CSV files, one per table, e.g. to open it in a spreadsheet.
+//! Only JSON dumps can be restored.
+//!
+//! Used by `GET /api/export` and `POST /api/restore`, see [crate::routes::backup], and by the
+//! `inventory export` and `inventory restore` commands.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Cursor, Write};
+use std::str::FromStr;
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Text};
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::{internal_error, TransactionError};
+
+/// Version of the dump format, increased whenever a dump of the previous version can't be restored as is.
+pub const DUMP_VERSION: u32 = 1;
+
+/// A table in a dump.
+#[derive(Debug, Clone, Copy)]
+pub struct DumpTable {
+    /// Name of the table.
+    pub name: &'static str,
+    /// Columns sorting the rows.
+    pub order_by: &'static str,
+    /// Id column backed by a sequence, if any.
+    pub serial: Option<&'static str>,
+}
+
+/// Tables in a dump, ordered so that rows are only restored after the rows they refer to.
+pub const TABLES: [DumpTable; 13] = [
+    DumpTable { name: "categories", order_by: "category_id", serial: Some("category_id") },
+    DumpTable { name: "tags", order_by: "tag_id", serial: Some("tag_id") },
+    DumpTable { name: "freezer_types", order_by: "freezer_type_id", serial: Some("freezer_type_id") },
+    DumpTable { name: "products", order_by: "product_id", serial: Some("product_id") },
+    DumpTable { name: "product_tags", order_by: "product_id, tag_id", serial: None },
+    DumpTable { name: "product_shelf_lives", order_by: "product_id, freezer_type_id", serial: None },
+    DumpTable { name: "product_barcodes", order_by: "barcode", serial: None },
+    DumpTable { name: "freezers", order_by: "freezer_id", serial: Some("freezer_id") },
+    DumpTable { name: "drawers", order_by: "drawer_id", serial: Some("drawer_id") },
+    DumpTable { name: "storage", order_by: "storage_id", serial: Some("storage_id") },
+    DumpTable { name: "shopping_list_items", order_by: "item_id", serial: Some("item_id") },
+    DumpTable { name: "stocktakes", order_by: "stocktake_id", serial: Some("stocktake_id") },
+    DumpTable { name: "stocktake_items", order_by: "stocktake_id, storage_id", serial: None },
+];
+
+/// Format of an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// A [Dump], the default.
+    #[default]
+    Json,
+    /// A ZIP with a CSV file per table and a `manifest.json` with the version.
+    Csv,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Json => f.write_str("json"),
+            ExportFormat::Csv => f.write_str("csv"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("Unknown export format {}, expected json or csv", format)),
+        }
+    }
+}
+
+/// All data, as exported by [export].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dump {
+    /// Format version, see [DUMP_VERSION].
+    pub version: u32,
+    /// Moment of the export.
+    pub exported_at: DateTime<Utc>,
+    /// Rows per table name, as JSON objects keyed by column name.
+    pub tables: BTreeMap<String, Vec<serde_json::Value>>,
+}
+
+/// Outcome of [restore].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    /// Version of the restored dump.
+    pub version: u32,
+    /// Amount of restored rows per table name.
+    pub rows: BTreeMap<String, usize>,
+}
+
+#[derive(QueryableByName)]
+struct JsonRows {
+    #[diesel(sql_type = Text)]
+    rows: String,
+}
+
+#[derive(QueryableByName)]
+struct Column {
+    #[diesel(sql_type = Text)]
+    column_name: String,
+    /// Whether a value is required: the column is not nullable and has no default.
+    #[diesel(sql_type = Bool)]
+    required: bool,
+}
+
+#[derive(QueryableByName)]
+struct RowCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Columns of a table that can be written, in their order in the table.
+fn columns(conn: &mut PgConnection, table: &str) -> QueryResult<Vec<Column>> {
+    diesel::sql_query(
+        "SELECT column_name::text AS column_name, (is_nullable = 'NO' AND column_default IS NULL) AS required \
+         FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND table_name = $1 AND is_generated = 'NEVER' \
+         ORDER BY ordinal_position",
+    )
+        .bind::<Text, _>(table)
+        .load::<Column>(conn)
+}
+
+/// Checks a row of `table` in a dump against the columns of the table.
+fn check_row(table: &str, row: &serde_json::Value, columns: &[Column]) -> Result<(), (StatusCode, String)> {
+    let row = row
+        .as_object()
+        .filter(|row| !row.is_empty())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Rows of table {} must be objects keyed by column name", table)))?;
+    if let Some(unknown) = row.keys().find(|key| !columns.iter().any(|column| &column.column_name == *key)) {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown column {} in table {}", unknown, table)));
+    }
+    if let Some(missing) = columns.iter().find(|column| column.required && !row.contains_key(&column.column_name)) {
+        return Err((StatusCode::BAD_REQUEST, format!("Missing column {} in table {}", missing.column_name, table)));
+    }
+
+    Ok(())
+}
+
+/// Whether two rows, as checked by [check_row], have the same columns.
+fn same_columns(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    match (a.as_object(), b.as_object()) {
+        (Some(a), Some(b)) => a.len() == b.len() && a.keys().all(|key| b.contains_key(key)),
+        _ => false,
+    }
+}
+
+/// Exports all rows of all [TABLES].
+pub fn export(conn: &mut PgConnection) -> QueryResult<Dump> {
+    conn.build_transaction().read_only().repeatable_read().run(|conn| {
+        let mut tables = BTreeMap::new();
+        for table in TABLES {
+            let rows = diesel::sql_query(format!(
+                "SELECT coalesce(json_agg(t ORDER BY {}), '[]'::json)::text AS rows FROM {} t",
+                table.order_by, table.name,
+            ))
+                .get_result::<JsonRows>(conn)?;
+            let rows = serde_json::from_str::<Vec<serde_json::Value>>(&rows.rows)
+                .map_err(|err| diesel::result::Error::DeserializationError(Box::new(err)))?;
+            tables.insert(String::from(table.name), rows);
+        }
+
+        Ok(Dump { version: DUMP_VERSION, exported_at: Utc::now(), tables })
+    })
+}
+
+/// Text of a value in a CSV file, empty for `null`.
+fn csv_field(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// Exports all rows of all [TABLES] as a ZIP of CSV files, see [ExportFormat::Csv].
+pub fn export_csv_zip(conn: &mut PgConnection) -> Result<Vec<u8>, (StatusCode, String)> {
+    let dump = export(conn).map_err(internal_error)?;
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+
+    zip.start_file("manifest.json", options).map_err(internal_error)?;
+    let manifest = serde_json::json!({ "version": dump.version, "exportedAt": dump.exported_at });
+    zip.write_all(manifest.to_string().as_bytes()).map_err(internal_error)?;
+    for table in TABLES {
+        let columns: Vec<String> = columns(conn, table.name)
+            .map_err(internal_error)?
+            .into_iter()
+            .map(|column| column.column_name)
+            .collect();
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&columns).map_err(internal_error)?;
+        for row in dump.tables.get(table.name).into_iter().flatten() {
+            writer
+                .write_record(columns.iter().map(|column| csv_field(row.get(column))))
+                .map_err(internal_error)?;
+        }
+        let csv = writer.into_inner().map_err(internal_error)?;
+
+        zip.start_file(format!("{}.csv", table.name), options).map_err(internal_error)?;
+        zip.write_all(&csv).map_err(internal_error)?;
+    }
+
+    Ok(zip.finish().map_err(internal_error)?.into_inner())
+}
+
+/// Restores a [Dump] into an empty database, in a single transaction which locks all [TABLES] against writes.
+///
+/// # Errors
+///
+/// * `BadRequest` => "Unsupported dump version <u32>, expected <u32>", "Unknown table <String> in dump",
+///   "Unknown column <String> in table <String>", "Missing column <String> in table <String>" or
+///   "Invalid rows in table <String>: <String>" when the database rejects them.
+/// * `Conflict` => "Restoring requires an empty database".
+pub fn restore(conn: &mut PgConnection, dump: &Dump) -> Result<RestoreReport, (StatusCode, String)> {
+    if dump.version != DUMP_VERSION {
+        return Err((StatusCode::BAD_REQUEST, format!("Unsupported dump version {}, expected {}", dump.version, DUMP_VERSION)));
+    }
+    if let Some(unknown) = dump.tables.keys().find(|name| !TABLES.iter().any(|table| table.name == name.as_str())) {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown table {} in dump", unknown)));
+    }
+
+    let rows = conn.transaction::<_, TransactionError, _>(|conn| {
+        // Blocks writes, and so a concurrent restore, until this one is committed. The other restore
+        // then finds the database no longer empty.
+        let names = TABLES.iter().map(|table| table.name).collect::<Vec<&str>>().join(", ");
+        diesel::sql_query(format!("LOCK TABLE {} IN EXCLUSIVE MODE", names)).execute(conn)?;
+        for table in TABLES {
+            let existing = diesel::sql_query(format!("SELECT count(*) AS count FROM {}", table.name))
+                .get_result::<RowCount>(conn)?;
+            if existing.count > 0 {
+                return Err((StatusCode::CONFLICT, String::from("Restoring requires an empty database")).into());
+            }
+        }
+
+        let mut restored = BTreeMap::new();
+        for table in TABLES {
+            let rows = dump.tables.get(table.name).map(Vec::as_slice).unwrap_or_default();
+            let table_columns = columns(conn, table.name)?;
+            for row in rows {
+                check_row(table.name, row, &table_columns)?;
+            }
+            // Only the columns present in the rows are inserted, the others get their default.
+            for run in rows.chunk_by(same_columns) {
+                let columns = run[0]
+                    .as_object()
+                    .into_iter()
+                    .flat_map(|row| row.keys())
+                    .map(|column| format!("\"{}\"", column))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let rows_json = serde_json::to_string(run)
+                    .map_err(|err| diesel::result::Error::SerializationError(Box::new(err)))?;
+                diesel::sql_query(format!(
+                    "INSERT INTO {table} ({columns}) SELECT {columns} FROM json_populate_recordset(NULL::{table}, $1::json)",
+                    table = table.name, columns = columns,
+                ))
+                    .bind::<Text, _>(rows_json)
+                    .execute(conn)
+                    .map_err(|err| match err {
+                        diesel::result::Error::DatabaseError(_, info) => TransactionError::from((
+                            StatusCode::BAD_REQUEST,
+                            format!("Invalid rows in table {}: {}", table.name, info.message()),
+                        )),
+                        err => err.into(),
+                    })?;
+            }
+            if let Some(serial) = table.serial {
+                diesel::sql_query(format!(
+                    "SELECT setval(pg_get_serial_sequence('{table}', '{serial}'), coalesce(max({serial}), 0) + 1, false) FROM {table}",
+                    table = table.name, serial = serial,
+                ))
+                    .execute(conn)?;
+            }
+            restored.insert(String::from(table.name), rows.len());
+        }
+
+        Ok(restored)
+    })?;
+
+    Ok(RestoreReport { version: dump.version, rows })
+}
+
+#[cfg(test)]
+mod dumps {
+    use super::*;
+
+    #[test]
+    fn export_formats_are_case_insensitive() {
+        assert_eq!("CSV".parse::<ExportFormat>(), Ok(ExportFormat::Csv));
+        assert_eq!("json".parse::<ExportFormat>(), Ok(ExportFormat::Json));
+        assert!("xml".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn csv_fields_are_plain_text() {
+        assert_eq!(csv_field(None), "");
+        assert_eq!(csv_field(Some(&serde_json::Value::Null)), "");
+        assert_eq!(csv_field(Some(&serde_json::json!("Brocoli"))), "Brocoli");
+        assert_eq!(csv_field(Some(&serde_json::json!(643.3))), "643.3");
+        assert_eq!(csv_field(Some(&serde_json::json!(true))), "true");
+    }
+
+    #[test]
+    fn rows_with_the_same_keys_have_the_same_columns() {
+        let row = serde_json::json!({ "product_id": 1, "name": "Brocoli" });
+
+        assert!(same_columns(&row, &serde_json::json!({ "name": "Erwten", "product_id": 2 })));
+        assert!(!same_columns(&row, &serde_json::json!({ "product_id": 2 })));
+        assert!(!same_columns(&row, &serde_json::json!({ "product_id": 2, "expiration_months": 6 })));
+        assert!(!same_columns(&row, &serde_json::json!([1, "Brocoli"])));
+    }
+
+    #[test]
+    fn tables_refer_to_earlier_tables_only() {
+        let position = |name: &str| TABLES.iter().position(|table| table.name == name).unwrap();
+
+        assert!(position("categories") < position("products"));
+        assert!(position("freezer_types") < position("freezers"));
+        assert!(position("freezers") < position("drawers"));
+        assert!(position("drawers") < position("storage"));
+        assert!(position("storage") < position("stocktake_items"));
+        assert!(position("stocktakes") < position("stocktake_items"));
+    }
+}
@@ -0,0 +1,64 @@
+//! Validation of product barcodes.
+//!
+//! Accepts EAN-13 and UPC-A codes. A UPC-A code is an EAN-13 code starting with `0`, so barcodes are
+//! stored and compared as EAN-13: scanning either form of the same code finds the same product.
+
+/// Normalizes a scanned barcode to EAN-13. Surrounding whitespace is removed and UPC-A codes get a
+/// leading zero.
+///
+/// # Errors
+///
+/// When the code is not 12 or 13 digits long, or its check digit does not match.
+pub fn normalize(code: &str) -> Result<String, String> {
+    let code = code.trim();
+    if !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Barcode {} must only contain digits", code));
+    }
+    let ean = match code.len() {
+        12 => format!("0{}", code),
+        13 => code.to_owned(),
+        _ => return Err(format!("Barcode {} must be an EAN-13 or UPC-A code", code)),
+    };
+
+    let digits: Vec<u32> = ean.chars().filter_map(|c| c.to_digit(10)).collect();
+    if check_digit(&digits[..12]) != digits[12] {
+        return Err(format!("Barcode {} has an invalid check digit", code));
+    }
+
+    Ok(ean)
+}
+
+/// Check digit of the first 12 digits of an EAN-13 code: digits are weighted 1 and 3 alternately.
+fn check_digit(digits: &[u32]) -> u32 {
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { *digit } else { digit * 3 })
+        .sum();
+
+    (10 - sum % 10) % 10
+}
+
+#[cfg(test)]
+mod barcodes {
+    use super::*;
+
+    #[test]
+    fn accepts_ean_13() {
+        assert_eq!(normalize("5410000000000"), Err(String::from("Barcode 5410000000000 has an invalid check digit")));
+        assert_eq!(normalize(" 4006381333931 "), Ok(String::from("4006381333931")));
+    }
+
+    #[test]
+    fn stores_upc_a_as_ean_13() {
+        assert_eq!(normalize("036000291452"), Ok(String::from("0036000291452")));
+        assert_eq!(normalize("036000291452"), normalize("0036000291452"));
+    }
+
+    #[test]
+    fn rejects_invalid_codes() {
+        assert!(normalize("036000291453").is_err());
+        assert!(normalize("12345").is_err());
+        assert!(normalize("40063813339A1").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Capacity and fill level of freezers and drawers.
+//!
+//! A capacity is a maximum weight in grams or a volume in millilitres. The fill level only counts the
+//! available items in the unit of the capacity, items in other units can't be compared to it and are
+//! only listed in the quantities per unit, see [crate::models::Unit].
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Unit;
+
+/// Checks that `unit` can be used for a capacity: grams or millilitres.
+pub fn check_unit(unit: Unit) -> Result<(), String> {
+    match unit {
+        Unit::Grams | Unit::Millilitres => Ok(()),
+        _ => Err(String::from("capacity unit must be grams or millilitres")),
+    }
+}
+
+/// How full a freezer or drawer is.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FillLevel {
+    /// Declared capacity, in [Self::unit]. `None` when not declared.
+    pub capacity: Option<f32>,
+    /// Unit of [Self::capacity].
+    pub unit: Option<Unit>,
+    /// Amount of available items.
+    pub items: i64,
+    /// Quantity of the available items, per unit.
+    pub quantity: BTreeMap<Unit, f32>,
+    /// Share of the capacity in use, 1 when full. Above 1 when over capacity, `None` without capacity.
+    pub fill_level: Option<f64>,
+}
+
+impl FillLevel {
+    /// Fill level of a freezer or drawer with the given capacity, holding `items` as quantity and unit.
+    pub fn new(capacity: Option<(f32, Unit)>, items: impl IntoIterator<Item = (f32, Unit)>) -> Self {
+        let mut level = FillLevel {
+            capacity: capacity.map(|(quantity, _)| quantity),
+            unit: capacity.map(|(_, unit)| unit),
+            ..Default::default()
+        };
+        for (quantity, unit) in items {
+            level.items += 1;
+            *level.quantity.entry(unit).or_insert(0.0) += quantity;
+        }
+        level.fill_level = capacity
+            .map(|(capacity, unit)| level.used(unit) as f64 / capacity as f64);
+
+        level
+    }
+
+    /// Quantity in use in `unit`.
+    pub fn used(&self, unit: Unit) -> f32 {
+        self.quantity.get(&unit).copied().unwrap_or(0.0)
+    }
+
+    /// Whether more is stored than the capacity allows.
+    pub fn is_over_capacity(&self) -> bool {
+        self.fill_level.is_some_and(|level| level > 1.0)
+    }
+}
+
+#[cfg(test)]
+mod fill_levels {
+    use super::*;
+
+    #[test]
+    fn only_grams_and_millilitres_are_capacities() {
+        assert!(check_unit(Unit::Grams).is_ok());
+        assert!(check_unit(Unit::Millilitres).is_ok());
+        assert!(check_unit(Unit::Pieces).is_err());
+        assert!(check_unit(Unit::Portions).is_err());
+    }
+
+    #[test]
+    fn counts_items_in_the_capacity_unit() {
+        let level = FillLevel::new(
+            Some((1000.0, Unit::Grams)),
+            [(400.0, Unit::Grams), (350.0, Unit::Grams), (6.0, Unit::Pieces)],
+        );
+
+        assert_eq!(level.items, 3);
+        assert_eq!(level.used(Unit::Grams), 750.0);
+        assert_eq!(level.used(Unit::Pieces), 6.0);
+        assert_eq!(level.fill_level, Some(0.75));
+        assert!(!level.is_over_capacity());
+    }
+
+    #[test]
+    fn without_capacity_there_is_no_fill_level() {
+        let level = FillLevel::new(None, [(400.0, Unit::Grams)]);
+
+        assert_eq!(level.fill_level, None);
+        assert!(!level.is_over_capacity());
+    }
+
+    #[test]
+    fn over_capacity() {
+        let level = FillLevel::new(Some((500.0, Unit::Millilitres)), [(600.0, Unit::Millilitres)]);
+
+        assert!(level.is_over_capacity());
+    }
+}
@@ -37,3 +37,49 @@ pub fn internal_error<E>(err: E) -> (StatusCode, String)
     (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
 }
 
+
+/// Error type for database transactions, allowing to roll back with an API error as well as with a
+/// database connector error. Converts into the usual `(StatusCode, String)` handler error.
+///
+/// ```no_run
+///     # use api::core::error::TransactionError;
+///     # use axum::http::StatusCode;
+///     # use diesel::prelude::*;
+///     # fn example(conn: &mut PgConnection) -> Result<(), (StatusCode, String)> {
+///     conn.transaction::<_, TransactionError, _>(|conn| {
+///         // Database errors are converted using `?`.
+///         diesel::sql_query("SELECT 1").execute(conn)?;
+///
+///         Err((StatusCode::CONFLICT, String::from("Rolled back")).into())
+///     })?;
+///     # Ok(())
+///     # }
+/// ```
+#[derive(Debug)]
+pub enum TransactionError {
+    /// Error returned by the database connector.
+    Database(diesel::result::Error),
+    /// Error to be returned by the handler as is.
+    Api(StatusCode, String),
+}
+
+impl From<diesel::result::Error> for TransactionError {
+    fn from(err: diesel::result::Error) -> Self {
+        Self::Database(err)
+    }
+}
+
+impl From<(StatusCode, String)> for TransactionError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        Self::Api(status, message)
+    }
+}
+
+impl From<TransactionError> for (StatusCode, String) {
+    fn from(err: TransactionError) -> Self {
+        match err {
+            TransactionError::Database(err) => internal_error(err),
+            TransactionError::Api(status, message) => (status, message),
+        }
+    }
+}
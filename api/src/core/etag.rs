@@ -0,0 +1,157 @@
+//! Entity tags and conditional requests, used for optimistic concurrency.
+//!
+//! Every row in `products`, `freezers`, `drawers` and `storage` carries a `version` column which is
+//! incremented on each update. Single entity responses expose this version as a strong `ETag`, which
+//! the frontend is expected to send back in an `If-Match` header on `PATCH` and `DELETE` requests.
+//! When the entity was changed in the meantime, the request is refused with
+//! [StatusCode::PRECONDITION_FAILED] instead of silently overwriting the other change.
+//!
+//! List responses carry a weak `ETag` derived from the response body. Sending it back in an
+//! `If-None-Match` header returns [StatusCode::NOT_MODIFIED] without a body when nothing changed.
+//!
+//! Requests without conditional headers are handled as before.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// Strong ETag of an entity at the given row version, e.g. `"3"`.
+pub fn version_etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Weak ETag of a response body, e.g. `W/"9f86d081884c7d65"`.
+pub fn content_etag<T: Serialize>(body: &T) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(body).unwrap_or_default().hash(&mut hasher);
+
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+/// [HeaderMap] containing only the `ETag` header.
+pub fn etag_header(etag: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, value);
+    }
+
+    headers
+}
+
+/// [HeaderMap] containing the strong `ETag` of an entity at the given row version.
+pub fn version_header(version: i32) -> HeaderMap {
+    etag_header(&version_etag(version))
+}
+
+/// Error returned when the entity was modified since the client last fetched it.
+pub fn precondition_failed() -> (StatusCode, String) {
+    (StatusCode::PRECONDITION_FAILED, String::from("This item was modified by another request"))
+}
+
+/// Checks the `If-Match` request header against the current row version of an entity.
+///
+/// Passes when the header is absent, equals `*` or lists the current version. Weak tags never match,
+/// as `If-Match` requires a strong comparison.
+///
+/// # Errors
+///
+/// * 400: [StatusCode::BAD_REQUEST] when the header is not valid ASCII.
+/// * 412: [StatusCode::PRECONDITION_FAILED] when none of the listed tags match.
+pub fn check_if_match(headers: &HeaderMap, version: i32) -> Result<(), (StatusCode, String)> {
+    let Some(if_match) = headers.get(header::IF_MATCH) else {
+        return Ok(());
+    };
+    let if_match = if_match
+        .to_str()
+        .map_err(|_| (StatusCode::BAD_REQUEST, String::from("Invalid If-Match header")))?;
+
+    let current = version_etag(version);
+    let matches = if_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag == current);
+
+    if matches {
+        Ok(())
+    } else {
+        Err(precondition_failed())
+    }
+}
+
+/// Whether the `If-None-Match` request header matches the given ETag, using weak comparison.
+pub fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(Ok(if_none_match)) = headers.get(header::IF_NONE_MATCH).map(|value| value.to_str()) else {
+        return false;
+    };
+    let etag = etag.trim_start_matches("W/");
+
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Json response carrying a weak `ETag`, or an empty [StatusCode::NOT_MODIFIED] when the client
+/// already holds the same representation.
+pub fn conditional_json<T: Serialize>(headers: &HeaderMap, body: T) -> Response {
+    let etag = content_etag(&body);
+
+    if is_not_modified(headers, &etag) {
+        (StatusCode::NOT_MODIFIED, etag_header(&etag)).into_response()
+    } else {
+        (etag_header(&etag), Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod conditional_headers {
+    use super::*;
+
+    fn headers_with(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn if_match_absent_passes() {
+        assert!(check_if_match(&HeaderMap::new(), 4).is_ok());
+    }
+
+    #[test]
+    fn if_match_current_version_passes() {
+        assert!(check_if_match(&headers_with(header::IF_MATCH, "\"4\""), 4).is_ok());
+        assert!(check_if_match(&headers_with(header::IF_MATCH, "\"2\", \"4\""), 4).is_ok());
+        assert!(check_if_match(&headers_with(header::IF_MATCH, "*"), 4).is_ok());
+    }
+
+    #[test]
+    fn if_match_stale_version_fails() {
+        let result = check_if_match(&headers_with(header::IF_MATCH, "\"3\""), 4);
+
+        assert_eq!(result.err(), Some(precondition_failed()));
+    }
+
+    #[test]
+    fn if_match_weak_tag_fails() {
+        let result = check_if_match(&headers_with(header::IF_MATCH, "W/\"4\""), 4);
+
+        assert_eq!(result.err(), Some(precondition_failed()));
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let etag = content_etag(&vec![1, 2, 3]);
+
+        assert!(is_not_modified(&headers_with(header::IF_NONE_MATCH, &etag), &etag));
+        assert!(is_not_modified(&headers_with(header::IF_NONE_MATCH, etag.trim_start_matches("W/")), &etag));
+        assert!(!is_not_modified(&headers_with(header::IF_NONE_MATCH, &content_etag(&vec![1, 2])), &etag));
+        assert!(!is_not_modified(&HeaderMap::new(), &etag));
+    }
+}
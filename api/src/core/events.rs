@@ -0,0 +1,197 @@
+//! Change feed broadcasting modifications to all connected clients, see [crate::routes::events].
+//!
+//! Route handlers [EventBus::publish] a [ChangeEvent] after their changes were committed. Every event
+//! gets an increasing id, and the last [HISTORY_SIZE] events are kept in memory so a reconnecting
+//! client can pick up where it left off by sending the id of the last event it received.
+//!
+//! Events only carry the changed entity and its id, clients are expected to refetch what they
+//! display. Cascading changes are not broadcast separately: deleting a freezer also trashes its
+//! drawers and storage items, but only the freezer deletion is sent.
+//!
+//! Ids restart at 1 when the API restarts. When the requested history is no longer available, the
+//! client is told to reload everything instead, see [Subscription::missed_events].
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Amount of past events kept for reconnecting clients.
+pub const HISTORY_SIZE: usize = 256;
+
+/// Kind of entity a [ChangeEvent] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Entity {
+    /// [crate::models::Product].
+    Product,
+    /// [crate::models::Freezer].
+    Freezer,
+    /// [crate::models::Drawer].
+    Drawer,
+    /// [crate::models::Storage].
+    Storage,
+    /// [crate::models::Category].
+    Category,
+    /// [crate::models::Tag].
+    Tag,
+    /// [crate::models::FreezerType].
+    FreezerType,
+    /// [crate::models::ShoppingListItem].
+    ShoppingListItem,
+    /// [crate::models::Stocktake].
+    Stocktake,
+}
+
+/// Kind of change a [ChangeEvent] reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeAction {
+    /// The entity was created.
+    Created,
+    /// The entity was updated.
+    Updated,
+    /// The storage item was withdrawn.
+    Withdrawn,
+    /// The storage item was put back into storage.
+    ReEntered,
+    /// The entity was deleted, or moved to the trash for entities supporting it.
+    Deleted,
+    /// The entity was restored from the trash.
+    Restored,
+}
+
+/// A single change, as sent to the clients.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEvent {
+    /// Increasing id of the event.
+    pub id: u64,
+    /// Kind of the changed entity.
+    pub entity: Entity,
+    /// Kind of the change.
+    pub action: ChangeAction,
+    /// Id of the changed entity.
+    pub entity_id: i32,
+}
+
+/// Subscription to the change feed, as returned by [EventBus::subscribe].
+pub struct Subscription {
+    /// Events published after the requested id, still in the history.
+    pub replay: Vec<ChangeEvent>,
+    /// Whether events after the requested id are no longer available.
+    pub missed_events: bool,
+    /// Receiver of all events published after subscribing.
+    pub receiver: broadcast::Receiver<ChangeEvent>,
+}
+
+struct History {
+    last_id: u64,
+    events: VecDeque<ChangeEvent>,
+}
+
+/// Broadcasts [ChangeEvent]'s to all subscribers. Cheap to clone, clones share the same feed.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ChangeEvent>,
+    history: Arc<Mutex<History>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    /// Creates a feed without any events.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(HISTORY_SIZE);
+
+        Self {
+            sender,
+            history: Arc::new(Mutex::new(History {
+                last_id: 0,
+                events: VecDeque::with_capacity(HISTORY_SIZE),
+            })),
+        }
+    }
+
+    /// Sends a change to all subscribers, returning the published event.
+    pub fn publish(&self, entity: Entity, action: ChangeAction, entity_id: i32) -> ChangeEvent {
+        let mut history = self.history.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        history.last_id += 1;
+
+        let event = ChangeEvent { id: history.last_id, entity, action, entity_id };
+        if history.events.len() == HISTORY_SIZE {
+            history.events.pop_front();
+        }
+        history.events.push_back(event.clone());
+        // Sending fails when nobody is listening, which is fine.
+        let _ = self.sender.send(event.clone());
+
+        event
+    }
+
+    /// Subscribes to the feed, replaying the events published after `last_event_id` when given.
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> Subscription {
+        // Holding the lock guarantees no event is missed or sent twice between replay and receiver.
+        let history = self.history.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let receiver = self.sender.subscribe();
+
+        let Some(last_event_id) = last_event_id else {
+            return Subscription { replay: Vec::new(), missed_events: false, receiver };
+        };
+        let oldest_id = history.events.front().map_or(history.last_id + 1, |event| event.id);
+        let missed_events = last_event_id > history.last_id || last_event_id + 1 < oldest_id;
+        let replay = history.events
+            .iter()
+            .filter(|event| event.id > last_event_id)
+            .cloned()
+            .collect();
+
+        Subscription { replay, missed_events, receiver }
+    }
+}
+
+#[cfg(test)]
+mod event_bus {
+    use super::*;
+
+    #[test]
+    fn replays_events_after_last_event_id() {
+        let bus = EventBus::new();
+        bus.publish(Entity::Product, ChangeAction::Created, 1);
+        bus.publish(Entity::Storage, ChangeAction::Withdrawn, 4);
+        bus.publish(Entity::Drawer, ChangeAction::Deleted, 2);
+
+        let subscription = bus.subscribe(Some(1));
+
+        assert!(!subscription.missed_events);
+        assert_eq!(subscription.replay.iter().map(|event| event.id).collect::<Vec<u64>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn reports_missed_events_outside_history() {
+        let bus = EventBus::new();
+        for id in 0..(HISTORY_SIZE as i32 + 2) {
+            bus.publish(Entity::Storage, ChangeAction::Created, id);
+        }
+
+        assert!(bus.subscribe(Some(1)).missed_events);
+        assert!(!bus.subscribe(Some(2)).missed_events);
+        // Ids from before a restart of the API.
+        assert!(EventBus::new().subscribe(Some(5)).missed_events);
+    }
+
+    #[tokio::test]
+    async fn receives_events_published_after_subscribing() {
+        let bus = EventBus::new();
+        let mut subscription = bus.subscribe(None);
+        let event = bus.publish(Entity::Freezer, ChangeAction::Updated, 3);
+
+        assert!(subscription.replay.is_empty());
+        assert_eq!(subscription.receiver.recv().await.unwrap(), event);
+    }
+}
@@ -0,0 +1,242 @@
+//! Idempotency keys for mutating requests.
+//!
+//! Clients on an unreliable connection may retry a `POST` or `PATCH` without knowing whether the first
+//! attempt reached the API. When every attempt carries the same `Idempotency-Key` header, the request
+//! is only executed once: retries within the configured window get the stored response replayed,
+//! marked with an `Idempotent-Replayed: true` header.
+//!
+//! Reusing a key for a different request (other method, uri or body) returns [StatusCode::CONFLICT],
+//! as does a retry arriving while the original request is still being processed. Only successful
+//! responses are stored, so a failed request can be retried with the same key.
+//!
+//! The window defaults to [DEFAULT_WINDOW_SECONDS] and can be changed through the
+//! `IDEMPOTENCY_WINDOW_SECONDS` environment variable. A key still being processed after
+//! [IN_PROGRESS_TIMEOUT_SECONDS], e.g. because the request was dropped, is released.
+
+use std::env;
+
+use axum::{
+    body::{boxed, Body, Full},
+    extract::State,
+    http::{header, HeaderValue, Method, Request, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::core::{connection::establish_connection, error::internal_error};
+use crate::models::{IdempotencyRecord, NewIdempotencyRecord};
+use crate::AppState;
+
+/// Request header carrying the idempotency key.
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Response header set on replayed responses.
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+/// Default time during which a key is remembered, in seconds (24 hours).
+pub const DEFAULT_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+/// Time after which a key without a stored response is released, in seconds.
+pub const IN_PROGRESS_TIMEOUT_SECONDS: i64 = 60;
+
+/// Time during which a key is remembered, read from `IDEMPOTENCY_WINDOW_SECONDS`.
+pub fn window_from_env() -> Duration {
+    let seconds = env::var("IDEMPOTENCY_WINDOW_SECONDS")
+        .ok()
+        .and_then(|seconds| seconds.parse::<i64>().ok())
+        .filter(|seconds| *seconds > 0)
+        .unwrap_or(DEFAULT_WINDOW_SECONDS);
+
+    Duration::seconds(seconds)
+}
+
+/// SHA-256 identifying a request by its method, uri and body, as 64 hexadecimal characters.
+/// Stored with the key, so it must stay the same across releases.
+pub fn fingerprint_request(method: &Method, uri: &Uri, body: &[u8]) -> String {
+    let uri = uri.to_string();
+    let mut hasher = Sha256::new();
+    for part in [method.as_str().as_bytes(), uri.as_bytes(), body] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Middleware honoring the `Idempotency-Key` header on `POST` and `PATCH` requests.
+/// Requests without the header, or using other methods, are passed through untouched.
+pub async fn idempotency(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    if !matches!(*request.method(), Method::POST | Method::PATCH) {
+        return next.run(request).await;
+    }
+    let key = match request.headers().get(IDEMPOTENCY_KEY).map(|key| key.to_str()) {
+        None => return next.run(request).await,
+        Some(Ok(key)) if !key.is_empty() && key.len() <= 255 => key.to_owned(),
+        Some(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                String::from("Idempotency-Key must contain 1 to 255 visible ASCII characters"),
+            )
+                .into_response()
+        }
+    };
+
+    handle_idempotent(state, key, request, next)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+async fn handle_idempotent(
+    state: AppState,
+    key: String,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, (StatusCode, String)> {
+    use crate::schema::idempotency_keys::dsl::*;
+
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let fingerprint = fingerprint_request(&parts.method, &parts.uri, &body);
+
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    // Forget keys that are older than the window, and keys whose request never completed.
+    let now = Utc::now();
+    diesel::delete(idempotency_keys)
+        .filter(
+            created_at.lt(now - state.idempotency_window)
+                .or(response_status.is_null().and(created_at.lt(now - Duration::seconds(IN_PROGRESS_TIMEOUT_SECONDS))))
+        )
+        .execute(conn)
+        .map_err(internal_error)?;
+
+    // Reserve the key. Nothing is inserted when the key was seen before.
+    let reserved = diesel::insert_into(idempotency_keys)
+        .values(NewIdempotencyRecord {
+            idempotency_key: key.clone(),
+            request_fingerprint: fingerprint.clone(),
+        })
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map_err(internal_error)?;
+    if reserved == 0 {
+        let record = idempotency_keys
+            .find(&key)
+            .select(IdempotencyRecord::as_select())
+            .first(conn)
+            .optional()
+            .map_err(internal_error)?;
+
+        return replay(record, &fingerprint);
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    if !response.status().is_success() {
+        // Release the key so the client can retry.
+        diesel::delete(idempotency_keys.find(&key))
+            .execute(conn)
+            .map_err(internal_error)?;
+
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body).await.map_err(internal_error)?;
+    let header_string = |name| {
+        parts.headers
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .map(String::from)
+    };
+
+    diesel::update(idempotency_keys.find(&key))
+        .set((
+            response_status.eq(parts.status.as_u16() as i32),
+            response_body.eq(body.to_vec()),
+            response_content_type.eq(header_string(header::CONTENT_TYPE)),
+            response_etag.eq(header_string(header::ETAG)),
+        ))
+        .execute(conn)
+        .map_err(internal_error)?;
+
+    Ok(Response::from_parts(parts, boxed(Full::from(body))))
+}
+
+/// Rebuilds the stored response of a previously seen key.
+fn replay(record: Option<IdempotencyRecord>, fingerprint: &str) -> Result<Response, (StatusCode, String)> {
+    let in_progress = || {
+        (
+            StatusCode::CONFLICT,
+            String::from("A request with this Idempotency-Key is still being processed"),
+        )
+    };
+    let record = record.ok_or_else(in_progress)?;
+
+    if record.request_fingerprint != fingerprint {
+        return Err((
+            StatusCode::CONFLICT,
+            String::from("This Idempotency-Key was already used for a different request"),
+        ));
+    }
+    let status = record
+        .response_status
+        .and_then(|status| StatusCode::from_u16(status as u16).ok())
+        .ok_or_else(in_progress)?;
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(IDEMPOTENT_REPLAYED, "true");
+    if let Some(content_type) = record.response_content_type {
+        response = response.header(header::CONTENT_TYPE, content_type);
+    }
+    if let Some(etag) = record.response_etag {
+        response = response.header(header::ETAG, etag);
+    }
+
+    response
+        .body(boxed(Full::from(record.response_body.unwrap_or_default())))
+        .map_err(internal_error)
+}
+
+#[cfg(test)]
+mod fingerprint {
+    use super::*;
+
+    #[test]
+    fn equal_requests_have_equal_fingerprints() {
+        let uri = Uri::from_static("/api/storage");
+        let first = fingerprint_request(&Method::POST, &uri, b"{\"productId\":1}");
+        let second = fingerprint_request(&Method::POST, &uri, b"{\"productId\":1}");
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn fingerprints_are_stable_sha256_digests() {
+        let fingerprint = fingerprint_request(&Method::POST, &Uri::from_static("/api/storage"), b"{}");
+
+        assert_eq!(fingerprint.len(), 64);
+        assert_eq!(fingerprint, "fa37c21a368689da287b043561299bab7a6b47e652410e70f8efc8562e422ef3");
+    }
+
+    #[test]
+    fn different_requests_have_different_fingerprints() {
+        let uri = Uri::from_static("/api/storage");
+        let reference = fingerprint_request(&Method::POST, &uri, b"{\"productId\":1}");
+
+        assert_ne!(reference, fingerprint_request(&Method::POST, &uri, b"{\"productId\":2}"));
+        assert_ne!(reference, fingerprint_request(&Method::PATCH, &uri, b"{\"productId\":1}"));
+        assert_ne!(reference, fingerprint_request(&Method::POST, &Uri::from_static("/api/products"), b"{\"productId\":1}"));
+    }
+}
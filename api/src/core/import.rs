@@ -0,0 +1,542 @@
+//! Import of storage items from CSV or JSON, e.g. years of freezer history kept in a spreadsheet.
+//!
+//! Every row names its product, freezer and drawer instead of referring to ids, see [ImportRow].
+//! Names are matched regardless of case, see [crate::core::name]. Unknown names are errors, unless
+//! [ImportOptions::create_missing] is set, in which case the products, freezers and drawers are created
+//! along with the items.
+//!
+//! All rows are validated before anything is written. A single invalid row cancels the import, the
+//! [ImportReport] then lists the errors of every row. With [ImportOptions::dry_run] the rows are only
+//! validated, without locking anything. Otherwise the existing products, freezers and drawers the rows
+//! refer to are locked while the items are inserted, rows referring to ones removed in the meantime are
+//! errors after all.
+//!
+//! Used by `POST /api/import`, see [crate::routes::import], and by the `inventory import` command.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::events::{ChangeAction, Entity, EventBus};
+use crate::core::name::normalize_name;
+use crate::models::{NewDrawer, NewFreezer, NewProduct, Unit, WithdrawalReason};
+use crate::schema::{drawers, freezers, products, storage};
+
+/// Maximum length of product, freezer and drawer names.
+const MAX_NAME_LENGTH: usize = 50;
+/// Amount of storage items inserted per statement, keeping below the limit of bind parameters.
+const INSERT_CHUNK_SIZE: usize = 1000;
+
+/// Format of the imported file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Comma separated values with a header row, the columns named as the fields of [ImportRow].
+    Csv,
+    /// Array of [ImportRow] objects, the default.
+    #[default]
+    Json,
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportFormat::Csv => f.write_str("csv"),
+            ImportFormat::Json => f.write_str("json"),
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(ImportFormat::Csv),
+            "json" => Ok(ImportFormat::Json),
+            _ => Err(format!("Unknown import format {}, expected csv or json", format)),
+        }
+    }
+}
+
+/// A single storage item to import.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRow {
+    /// **Required**: Name of the product.
+    pub product_name: String,
+    /// **Required**: Name of the freezer.
+    pub freezer_name: String,
+    /// **Required**: Name of the drawer, within the freezer.
+    pub drawer_name: String,
+    /// **Required**: Quantity, in [Self::unit]. Also accepted as `weightGrams`.
+    #[serde(alias = "weightGrams")]
+    pub quantity: f32,
+    /// **Optional**: Unit of the quantity. Defaults to the default unit of the product.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+    /// **Required**: Date the item was frozen.
+    pub date_in: NaiveDate,
+    /// **Optional**: Date the item was taken out, for items no longer in the freezer.
+    #[serde(default)]
+    pub date_out: Option<NaiveDate>,
+    /// **Optional**: Explicit expiration date.
+    #[serde(default)]
+    pub expiration_date: Option<NaiveDate>,
+    /// **Optional**: Why the item was taken out, requires [Self::date_out].
+    #[serde(default)]
+    pub withdrawal_reason: Option<WithdrawalReason>,
+}
+
+impl ImportRow {
+    /// Row with normalized names, or all the reasons it can't be imported.
+    pub fn checked(mut self) -> Result<ImportRow, Vec<String>> {
+        let mut errors = Vec::new();
+        for (field, name) in [
+            ("productName", &mut self.product_name),
+            ("freezerName", &mut self.freezer_name),
+            ("drawerName", &mut self.drawer_name),
+        ] {
+            *name = normalize_name(name);
+            if name.is_empty() {
+                errors.push(format!("{} cannot be empty", field));
+            } else if name.chars().count() > MAX_NAME_LENGTH {
+                errors.push(format!("{} cannot be longer than {} characters", field, MAX_NAME_LENGTH));
+            }
+        }
+        if !self.quantity.is_finite() || self.quantity <= 0.0 {
+            errors.push(String::from("quantity must be positive"));
+        }
+        if self.date_out.is_some_and(|date_out| date_out < self.date_in) {
+            errors.push(String::from("dateOut cannot be earlier than dateIn"));
+        }
+        if self.expiration_date.is_some_and(|expiration_date| expiration_date < self.date_in) {
+            errors.push(String::from("expirationDate cannot be earlier than dateIn"));
+        }
+        if self.withdrawal_reason.is_some() && self.date_out.is_none() {
+            errors.push(String::from("withdrawalReason requires dateOut"));
+        }
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Parses the rows of a file. A row that can't be read is returned as its error, so it is reported
+/// along with the other rows.
+///
+/// # Errors
+///
+/// When the file as a whole can't be read, e.g. JSON that is not an array.
+pub fn parse_rows(input: &str, format: ImportFormat) -> Result<Vec<Result<ImportRow, String>>, String> {
+    match format {
+        ImportFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(input.as_bytes());
+            reader.headers().map_err(|err| format!("Invalid CSV header: {}", err))?;
+
+            Ok(reader
+                .deserialize::<ImportRow>()
+                .map(|row| row.map_err(|err| match err.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+                    _ => err.to_string(),
+                }))
+                .collect())
+        }
+        ImportFormat::Json => {
+            let values = serde_json::from_str::<Vec<serde_json::Value>>(input)
+                .map_err(|err| format!("Invalid JSON, expected an array of rows: {}", err))?;
+
+            Ok(values
+                .into_iter()
+                .map(|value| serde_json::from_value::<ImportRow>(value).map_err(|err| err.to_string()))
+                .collect())
+        }
+    }
+}
+
+/// Options of [import_rows].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// Only validate the rows, without writing anything.
+    pub dry_run: bool,
+    /// Create the products, freezers and drawers that don't exist, instead of rejecting their rows.
+    pub create_missing: bool,
+}
+
+/// Errors of a single row.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    /// Number of the row, the first item being row 1. The header of a CSV file is not counted.
+    pub row: usize,
+    /// Why the row can't be imported.
+    pub errors: Vec<String>,
+}
+
+/// Outcome of an import.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Whether the rows were only validated.
+    pub dry_run: bool,
+    /// Amount of rows read.
+    pub rows: usize,
+    /// Amount of storage items imported, 0 for a dry run or when any row is invalid.
+    pub imported: usize,
+    /// Products created, or to be created for a dry run.
+    pub created_products: Vec<String>,
+    /// Freezers created, or to be created for a dry run.
+    pub created_freezers: Vec<String>,
+    /// Drawers created, or to be created for a dry run, as "freezer / drawer".
+    pub created_drawers: Vec<String>,
+    /// Rows that can't be imported, by row number.
+    pub errors: Vec<RowError>,
+}
+
+impl ImportReport {
+    /// Whether all rows can be imported.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Names still to be created, in order of appearance, deduplicated on their lowercase key.
+struct Missing<K> {
+    seen: HashSet<K>,
+    names: Vec<String>,
+}
+
+impl<K> Default for Missing<K> {
+    fn default() -> Self {
+        Missing { seen: HashSet::new(), names: Vec::new() }
+    }
+}
+
+impl<K: Eq + Hash> Missing<K> {
+    fn add(&mut self, key: K, name: String) {
+        if self.seen.insert(key) {
+            self.names.push(name);
+        }
+    }
+}
+
+/// Validates and imports `rows`, as parsed by [parse_rows]. See the [module](self) documentation.
+///
+/// Created entities and storage items are published on `events`.
+pub fn import_rows(
+    conn: &mut PgConnection,
+    rows: Vec<Result<ImportRow, String>>,
+    options: ImportOptions,
+    events: &EventBus,
+) -> QueryResult<ImportReport> {
+    let mut product_ids: HashMap<String, (i32, Unit)> = products::table
+        .filter(products::deleted_at.is_null())
+        .select((products::name, products::product_id, products::default_unit))
+        .load::<(String, i32, Unit)>(conn)?
+        .into_iter()
+        .map(|(name, id, unit)| (name.to_lowercase(), (id, unit)))
+        .collect();
+    let mut freezer_ids: HashMap<String, i32> = freezers::table
+        .filter(freezers::deleted_at.is_null())
+        .select((freezers::name, freezers::freezer_id))
+        .load::<(String, i32)>(conn)?
+        .into_iter()
+        .map(|(name, id)| (name.to_lowercase(), id))
+        .collect();
+    let mut drawer_ids: HashMap<(String, String), i32> = drawers::table
+        .inner_join(freezers::table)
+        .filter(drawers::deleted_at.is_null())
+        .filter(freezers::deleted_at.is_null())
+        .select((freezers::name, drawers::name, drawers::drawer_id))
+        .load::<(String, String, i32)>(conn)?
+        .into_iter()
+        .map(|(freezer, drawer, id)| ((freezer.to_lowercase(), drawer.to_lowercase()), id))
+        .collect();
+
+    let mut report = ImportReport { dry_run: options.dry_run, rows: rows.len(), ..Default::default() };
+    let mut missing_products = Missing::default();
+    let mut missing_freezers = Missing::default();
+    let mut missing_drawers = Missing::default();
+    let mut valid_rows = Vec::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        let row = match row.map_err(|err| vec![err]).and_then(ImportRow::checked) {
+            Ok(row) => row,
+            Err(errors) => {
+                report.errors.push(RowError { row: index + 1, errors });
+                continue;
+            }
+        };
+        let mut errors = Vec::new();
+        let product = row.product_name.to_lowercase();
+        let freezer = row.freezer_name.to_lowercase();
+        let drawer = (freezer.clone(), row.drawer_name.to_lowercase());
+        if !product_ids.contains_key(&product) {
+            if options.create_missing {
+                missing_products.add(product, row.product_name.clone());
+            } else {
+                errors.push(format!("Product {} not found", row.product_name));
+            }
+        }
+        let freezer_found = freezer_ids.contains_key(&freezer);
+        if !freezer_found {
+            if options.create_missing {
+                missing_freezers.add(freezer, row.freezer_name.clone());
+            } else {
+                errors.push(format!("Freezer {} not found", row.freezer_name));
+            }
+        }
+        if !drawer_ids.contains_key(&drawer) {
+            if options.create_missing {
+                missing_drawers.add(drawer, format!("{} / {}", row.freezer_name, row.drawer_name));
+            } else if freezer_found {
+                errors.push(format!("Drawer {} not found in freezer {}", row.drawer_name, row.freezer_name));
+            }
+        }
+
+        if errors.is_empty() {
+            valid_rows.push((index + 1, row));
+        } else {
+            report.errors.push(RowError { row: index + 1, errors });
+        }
+    }
+    report.created_products = missing_products.names;
+    report.created_freezers = missing_freezers.names;
+    report.created_drawers = missing_drawers.names;
+    if options.dry_run || !report.is_valid() {
+        return Ok(report);
+    }
+
+    // The existing products, freezers and drawers the rows refer to are locked until the items are
+    // inserted, those removed since the lookup make their rows invalid after all.
+    let (created, storage_ids) = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let referred_products: Vec<i32> = valid_rows
+            .iter()
+            .filter_map(|(_, row)| product_ids.get(&row.product_name.to_lowercase()).map(|(id, _)| *id))
+            .collect();
+        let referred_freezers: Vec<i32> = valid_rows
+            .iter()
+            .filter_map(|(_, row)| freezer_ids.get(&row.freezer_name.to_lowercase()).copied())
+            .collect();
+        let referred_drawers: Vec<i32> = valid_rows
+            .iter()
+            .filter_map(|(_, row)| drawer_ids.get(&(row.freezer_name.to_lowercase(), row.drawer_name.to_lowercase())).copied())
+            .collect();
+        let locked_products: HashSet<i32> = products::table
+            .filter(products::product_id.eq_any(&referred_products))
+            .filter(products::deleted_at.is_null())
+            .select(products::product_id)
+            .for_share()
+            .load::<i32>(conn)?
+            .into_iter()
+            .collect();
+        let locked_freezers: HashSet<i32> = freezers::table
+            .filter(freezers::freezer_id.eq_any(&referred_freezers))
+            .filter(freezers::deleted_at.is_null())
+            .select(freezers::freezer_id)
+            .for_share()
+            .load::<i32>(conn)?
+            .into_iter()
+            .collect();
+        let locked_drawers: HashSet<i32> = drawers::table
+            .filter(drawers::drawer_id.eq_any(&referred_drawers))
+            .filter(drawers::deleted_at.is_null())
+            .select(drawers::drawer_id)
+            .for_share()
+            .load::<i32>(conn)?
+            .into_iter()
+            .collect();
+
+        for (row_number, row) in &valid_rows {
+            let mut errors = Vec::new();
+            if matches!(product_ids.get(&row.product_name.to_lowercase()), Some((id, _)) if !locked_products.contains(id)) {
+                errors.push(format!("Product {} not found", row.product_name));
+            }
+            if matches!(freezer_ids.get(&row.freezer_name.to_lowercase()), Some(id) if !locked_freezers.contains(id)) {
+                errors.push(format!("Freezer {} not found", row.freezer_name));
+            }
+            let drawer = (row.freezer_name.to_lowercase(), row.drawer_name.to_lowercase());
+            if matches!(drawer_ids.get(&drawer), Some(id) if !locked_drawers.contains(id)) {
+                errors.push(format!("Drawer {} not found in freezer {}", row.drawer_name, row.freezer_name));
+            }
+            if !errors.is_empty() {
+                report.errors.push(RowError { row: *row_number, errors });
+            }
+        }
+        if !report.is_valid() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+
+        let mut created = Vec::new();
+        for name in &report.created_products {
+            let (id, unit) = diesel::insert_into(products::table)
+                .values(NewProduct { name: name.clone(), expiration_months: None, default_unit: None })
+                .returning((products::product_id, products::default_unit))
+                .get_result::<(i32, Unit)>(conn)?;
+            product_ids.insert(name.to_lowercase(), (id, unit));
+            created.push((Entity::Product, id));
+        }
+        for name in &report.created_freezers {
+            let id = diesel::insert_into(freezers::table)
+                .values(NewFreezer { name: name.clone() })
+                .returning(freezers::freezer_id)
+                .get_result::<i32>(conn)?;
+            freezer_ids.insert(name.to_lowercase(), id);
+            created.push((Entity::Freezer, id));
+        }
+        for (_, row) in &valid_rows {
+            let key = (row.freezer_name.to_lowercase(), row.drawer_name.to_lowercase());
+            if drawer_ids.contains_key(&key) {
+                continue;
+            }
+            let id = diesel::insert_into(drawers::table)
+                .values(NewDrawer { name: row.drawer_name.clone(), freezer_id: freezer_ids[&key.0] })
+                .returning(drawers::drawer_id)
+                .get_result::<i32>(conn)?;
+            drawer_ids.insert(key, id);
+            created.push((Entity::Drawer, id));
+        }
+
+        let items: Vec<_> = valid_rows
+            .iter()
+            .map(|(_, row)| {
+                let (product_id, default_unit) = product_ids[&row.product_name.to_lowercase()];
+                let drawer_id = drawer_ids[&(row.freezer_name.to_lowercase(), row.drawer_name.to_lowercase())];
+                (
+                    storage::product_id.eq(product_id),
+                    storage::drawer_id.eq(drawer_id),
+                    storage::quantity.eq(row.quantity),
+                    storage::unit.eq(row.unit.unwrap_or(default_unit)),
+                    storage::date_in.eq(row.date_in),
+                    storage::date_out.eq(row.date_out),
+                    storage::expiration_date.eq(row.expiration_date),
+                    storage::withdrawal_reason.eq(row.withdrawal_reason),
+                )
+            })
+            .collect();
+        let mut storage_ids = Vec::with_capacity(items.len());
+        for chunk in items.chunks(INSERT_CHUNK_SIZE) {
+            storage_ids.extend(
+                diesel::insert_into(storage::table)
+                    .values(chunk)
+                    .returning(storage::storage_id)
+                    .get_results::<i32>(conn)?,
+            );
+        }
+
+        Ok((created, storage_ids))
+    })?;
+
+    for (entity, id) in created {
+        events.publish(entity, ChangeAction::Created, id);
+    }
+    for id in &storage_ids {
+        events.publish(Entity::Storage, ChangeAction::Created, *id);
+    }
+    report.imported = storage_ids.len();
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod imports {
+    use super::*;
+
+    const CSV: &str = "productName,freezerName,drawerName,weightGrams,unit,dateIn,dateOut,expirationDate,withdrawalReason
+Brocoli , Garage,Schuif 1,400,,2021-03-01,2021-06-01,,eaten
+Erwten,Garage,Schuif 2,250,grams,2021-04-12,,,
+Erwten,Garage,Schuif 2,veel,grams,2021-04-12,,,
+";
+
+    fn row(product_name: &str, quantity: f32) -> ImportRow {
+        ImportRow {
+            product_name: String::from(product_name),
+            freezer_name: String::from("Garage"),
+            drawer_name: String::from("Schuif 1"),
+            quantity,
+            unit: None,
+            date_in: NaiveDate::from_ymd_opt(2021, 3, 1).unwrap(),
+            date_out: None,
+            expiration_date: None,
+            withdrawal_reason: None,
+        }
+    }
+
+    #[test]
+    fn parses_csv_with_optional_columns() {
+        let rows = parse_rows(CSV, ImportFormat::Csv).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        let first = rows[0].clone().unwrap();
+        assert_eq!(first.product_name, "Brocoli");
+        assert_eq!(first.quantity, 400.0);
+        assert_eq!(first.unit, None);
+        assert_eq!(first.date_out, NaiveDate::from_ymd_opt(2021, 6, 1));
+        assert_eq!(first.withdrawal_reason, Some(WithdrawalReason::Eaten));
+        assert_eq!(rows[1].clone().unwrap().unit, Some(Unit::Grams));
+        assert!(rows[2].is_err());
+    }
+
+    #[test]
+    fn parses_json_rows_one_by_one() {
+        let rows = parse_rows(r#"[
+            {"productName": "Brocoli", "freezerName": "Garage", "drawerName": "Schuif 1", "quantity": 400, "dateIn": "2021-03-01"},
+            {"productName": "Brocoli"}
+        ]"#, ImportFormat::Json).unwrap();
+
+        assert_eq!(rows[0], Ok(row("Brocoli", 400.0)));
+        assert!(rows[1].is_err());
+        assert!(parse_rows(r#"{"productName": "Brocoli"}"#, ImportFormat::Json).is_err());
+    }
+
+    #[test]
+    fn checked_rows_have_normalized_names() {
+        let checked = row("  Rode   kool ", 300.0).checked().unwrap();
+
+        assert_eq!(checked.product_name, "Rode kool");
+    }
+
+    #[test]
+    fn checked_rows_report_all_errors() {
+        let mut invalid = row(" ", -1.0);
+        invalid.date_out = NaiveDate::from_ymd_opt(2020, 1, 1);
+        invalid.expiration_date = NaiveDate::from_ymd_opt(2020, 1, 1);
+
+        assert_eq!(invalid.checked(), Err(vec![
+            String::from("productName cannot be empty"),
+            String::from("quantity must be positive"),
+            String::from("dateOut cannot be earlier than dateIn"),
+            String::from("expirationDate cannot be earlier than dateIn"),
+        ]));
+
+        let mut reason_only = row("Brocoli", 1.0);
+        reason_only.withdrawal_reason = Some(WithdrawalReason::Discarded);
+        assert_eq!(reason_only.checked(), Err(vec![String::from("withdrawalReason requires dateOut")]));
+    }
+
+    #[test]
+    fn formats_are_case_insensitive() {
+        assert_eq!("CSV".parse::<ImportFormat>(), Ok(ImportFormat::Csv));
+        assert_eq!("json".parse::<ImportFormat>(), Ok(ImportFormat::Json));
+        assert!("xlsx".parse::<ImportFormat>().is_err());
+    }
+
+    #[test]
+    fn missing_drawers_are_keyed_on_freezer_and_drawer() {
+        let mut missing = Missing::default();
+        missing.add((String::from("a/b"), String::from("c")), String::from("a/b / c"));
+        missing.add((String::from("a"), String::from("b/c")), String::from("a / b/c"));
+        missing.add((String::from("a"), String::from("b/c")), String::from("A / B/C"));
+
+        assert_eq!(missing.names, vec!["a/b / c", "a / b/c"]);
+    }
+}
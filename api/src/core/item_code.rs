@@ -0,0 +1,52 @@
+//! Short codes identifying storage items, e.g. printed on labels or typed in by hand.
+//!
+//! Codes are generated by the database on insert (`generate_storage_code()`), as [CODE_LENGTH]
+//! characters from [ALPHABET]. The alphabet leaves out `0`, `1`, `I`, `L` and `O`, which are easily
+//! confused when read from a label. Codes are case insensitive.
+
+/// Characters used in codes, must match the `generate_storage_code()` database function.
+pub const ALPHABET: &str = "23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+/// Number of characters of a code.
+pub const CODE_LENGTH: usize = 6;
+
+/// Normalizes a code as given by a user: surrounding whitespace is removed and letters are uppercased.
+///
+/// # Errors
+///
+/// When the code does not have [CODE_LENGTH] characters from [ALPHABET].
+pub fn normalize(code: &str) -> Result<String, String> {
+    let code = code.trim().to_uppercase();
+    if code.chars().count() != CODE_LENGTH || !code.chars().all(|c| ALPHABET.contains(c)) {
+        return Err(format!("code must be {} characters from {}", CODE_LENGTH, ALPHABET));
+    }
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod item_codes {
+    use super::*;
+
+    #[test]
+    fn normalizes_case_and_whitespace() {
+        assert_eq!(normalize(" ab3x9k\n"), Ok(String::from("AB3X9K")));
+    }
+
+    #[test]
+    fn rejects_ambiguous_characters_and_wrong_lengths() {
+        assert!(normalize("AB0X9K").is_err());
+        assert!(normalize("ABIX9K").is_err());
+        assert!(normalize("AB3X9").is_err());
+        assert!(normalize("AB3X9KM").is_err());
+    }
+
+    #[test]
+    fn alphabet_has_no_duplicates() {
+        let mut chars = ALPHABET.chars().collect::<Vec<char>>();
+        chars.sort();
+        chars.dedup();
+
+        assert_eq!(chars.len(), ALPHABET.len());
+        assert_eq!(ALPHABET.len(), 31);
+    }
+}
@@ -0,0 +1,597 @@
+//! Printable labels for storage items, see [crate::routes::labels].
+//!
+//! A label shows the product name, dates of entry and expiration, location and quantity next to a QR
+//! code encoding the item code. Labels are laid out once as shapes measured in millimetres, each
+//! [LabelFormat] only draws those shapes:
+//!
+//! * SVG and PDF are written directly, PDF uses the built-in Helvetica fonts so nothing is embedded.
+//! * PNG is rasterized at [PNG_DPI] using bitmap fonts, so no fonts need to be installed.
+//!
+//! A sheet places many labels on A4 pages, with a thin frame to cut along. PDF sheets get as many
+//! pages as needed. PNG and SVG hold a single page, so a sheet of several pages comes as a ZIP with a
+//! file per page, see [Sheet].
+
+use std::convert::Infallible;
+use std::fmt;
+use std::io::{Cursor, Write};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use embedded_graphics::{
+    mono_font::{iso_8859_1::FONT_10X20, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use qrcode::{Color, QrCode};
+use serde::Deserialize;
+
+/// Default label width, matching common 62 mm label printer rolls.
+pub const DEFAULT_WIDTH_MM: f32 = 62.0;
+/// Default label height.
+pub const DEFAULT_HEIGHT_MM: f32 = 29.0;
+/// Smallest width or height of a label.
+pub const MIN_SIZE_MM: f32 = 20.0;
+/// Largest width or height of a label.
+pub const MAX_SIZE_MM: f32 = 200.0;
+/// Resolution of PNG labels.
+pub const PNG_DPI: f32 = 300.0;
+
+const SHEET_WIDTH_MM: f32 = 210.0;
+const SHEET_HEIGHT_MM: f32 = 297.0;
+const SHEET_MARGIN_MM: f32 = 10.0;
+const PADDING_MM: f32 = 2.0;
+const FRAME_WIDTH_MM: f32 = 0.2;
+const TEXT_LINES: usize = 5;
+/// Width of a character relative to the font size, as for Helvetica digits.
+const CHAR_WIDTH: f32 = 0.56;
+/// Modules of white space around the QR code.
+const QR_QUIET_ZONE: usize = 2;
+
+/// File format of a rendered label.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelFormat {
+    /// Raster image at [PNG_DPI], the default.
+    #[default]
+    Png,
+    /// Vector image, sized in millimetres.
+    Svg,
+    /// Printable document.
+    Pdf,
+}
+
+impl LabelFormat {
+    /// MIME type of the format.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            LabelFormat::Png => "image/png",
+            LabelFormat::Svg => "image/svg+xml",
+            LabelFormat::Pdf => "application/pdf",
+        }
+    }
+
+    /// File extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LabelFormat::Png => "png",
+            LabelFormat::Svg => "svg",
+            LabelFormat::Pdf => "pdf",
+        }
+    }
+}
+
+impl FromStr for LabelFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "png" => Ok(LabelFormat::Png),
+            "svg" => Ok(LabelFormat::Svg),
+            "pdf" => Ok(LabelFormat::Pdf),
+            _ => Err(format!("Unknown label format {}, expected png, svg or pdf", format)),
+        }
+    }
+}
+
+impl fmt::Display for LabelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// Size of a single label.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabelSize {
+    /// Width in millimetres.
+    pub width_mm: f32,
+    /// Height in millimetres.
+    pub height_mm: f32,
+}
+
+impl Default for LabelSize {
+    fn default() -> Self {
+        Self { width_mm: DEFAULT_WIDTH_MM, height_mm: DEFAULT_HEIGHT_MM }
+    }
+}
+
+impl LabelSize {
+    /// Label size, defaulting to [DEFAULT_WIDTH_MM] by [DEFAULT_HEIGHT_MM].
+    ///
+    /// Fails when a dimension is not between [MIN_SIZE_MM] and [MAX_SIZE_MM].
+    pub fn new(width_mm: Option<f32>, height_mm: Option<f32>) -> Result<Self, String> {
+        let size = Self {
+            width_mm: width_mm.unwrap_or(DEFAULT_WIDTH_MM),
+            height_mm: height_mm.unwrap_or(DEFAULT_HEIGHT_MM),
+        };
+        let valid = |dimension: f32| (MIN_SIZE_MM..=MAX_SIZE_MM).contains(&dimension);
+        if !valid(size.width_mm) || !valid(size.height_mm) {
+            return Err(format!("width and height must be between {} and {} mm", MIN_SIZE_MM, MAX_SIZE_MM));
+        }
+
+        Ok(size)
+    }
+}
+
+/// Content of a label.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    /// Code identifying the storage item, encoded in the QR code.
+    pub code: String,
+    /// Name of the product.
+    pub product_name: String,
+    /// Date of entry.
+    pub date_in: NaiveDate,
+    /// Expiration date.
+    pub expiration_date: NaiveDate,
+    /// Freezer and drawer.
+    pub location: String,
+    /// Quantity with its unit.
+    pub quantity: String,
+}
+
+/// Shapes labels are made of, positioned in millimetres from the top left corner.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    /// Black filled rectangle.
+    Rect { x: f32, y: f32, width: f32, height: f32 },
+    /// Thin rectangle outline to cut along.
+    Frame { x: f32, y: f32, width: f32, height: f32 },
+    /// Single line of text, `y` is the baseline.
+    Text { x: f32, y: f32, size: f32, bold: bool, text: String },
+}
+
+/// Page of shapes, sized in millimetres.
+struct Page {
+    width: f32,
+    height: f32,
+    shapes: Vec<Shape>,
+}
+
+impl Label {
+    /// Adds the shapes of the label, with its top left corner at `x`, `y`.
+    fn layout(&self, size: LabelSize, x: f32, y: f32, shapes: &mut Vec<Shape>) -> Result<(), String> {
+        let inner_height = size.height_mm - 2.0 * PADDING_MM;
+        let qr_side = inner_height.min(size.width_mm * 0.45);
+        let qr_top = y + (size.height_mm - qr_side) / 2.0;
+        layout_qr_code(&self.code, x + PADDING_MM, qr_top, qr_side, shapes)?;
+
+        let text_x = x + 2.0 * PADDING_MM + qr_side;
+        let text_width = x + size.width_mm - PADDING_MM - text_x;
+        let line_height = inner_height / TEXT_LINES as f32;
+        let font_size = line_height * 0.7;
+        let lines = [
+            self.product_name.clone(),
+            format!("In: {}", self.date_in),
+            format!("Exp: {}", self.expiration_date),
+            self.location.clone(),
+            self.quantity.clone(),
+        ];
+        for (index, line) in lines.into_iter().enumerate() {
+            shapes.push(Shape::Text {
+                x: text_x,
+                y: y + PADDING_MM + line_height * (index as f32 + 1.0) - line_height * 0.2,
+                size: font_size,
+                bold: index == 0,
+                text: truncate(&line, (text_width / (font_size * CHAR_WIDTH)) as usize),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds the dark modules of the QR code encoding `code` as rectangles, merging horizontal runs.
+fn layout_qr_code(code: &str, x: f32, y: f32, side: f32, shapes: &mut Vec<Shape>) -> Result<(), String> {
+    let qr_code = QrCode::new(code.as_bytes()).map_err(|err| err.to_string())?;
+    let modules = qr_code.width();
+    let colors = qr_code.to_colors();
+    let module_size = side / (modules + 2 * QR_QUIET_ZONE) as f32;
+
+    for row in 0..modules {
+        let mut column = 0;
+        while column < modules {
+            if colors[row * modules + column] != Color::Dark {
+                column += 1;
+                continue;
+            }
+            let start = column;
+            while column < modules && colors[row * modules + column] == Color::Dark {
+                column += 1;
+            }
+            shapes.push(Shape::Rect {
+                x: x + (start + QR_QUIET_ZONE) as f32 * module_size,
+                y: y + (row + QR_QUIET_ZONE) as f32 * module_size,
+                width: (column - start) as f32 * module_size,
+                height: module_size,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Shortens a line to at most `max_chars` characters, ending in "..." when cut.
+fn truncate(line: &str, max_chars: usize) -> String {
+    if line.chars().count() <= max_chars {
+        return line.to_owned();
+    }
+    if max_chars < 4 {
+        return line.chars().take(max_chars).collect();
+    }
+
+    line.chars().take(max_chars - 3).chain("...".chars()).collect()
+}
+
+/// Renders a single label, on a page of the label size.
+pub fn render_label(label: &Label, size: LabelSize, format: LabelFormat) -> Result<Vec<u8>, String> {
+    let mut shapes = Vec::new();
+    label.layout(size, 0.0, 0.0, &mut shapes)?;
+
+    render(vec![Page { width: size.width_mm, height: size.height_mm, shapes }], format)
+}
+
+/// A rendered sheet of labels, see [render_sheet].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sheet {
+    /// The sheet in the requested format, or a ZIP of its pages when [Self::zipped].
+    pub body: Vec<u8>,
+    /// Whether [Self::body] is a ZIP with a file per page, for PNG and SVG sheets of several pages.
+    pub zipped: bool,
+}
+
+/// Renders a sheet of labels, placed in rows on A4 pages.
+pub fn render_sheet(labels: &[Label], size: LabelSize, format: LabelFormat) -> Result<Sheet, String> {
+    let page_width = SHEET_WIDTH_MM.max(size.width_mm + 2.0 * SHEET_MARGIN_MM);
+    let page_height = SHEET_HEIGHT_MM.max(size.height_mm + 2.0 * SHEET_MARGIN_MM);
+    let columns = (((page_width - 2.0 * SHEET_MARGIN_MM) / size.width_mm) as usize).max(1);
+    let rows_per_page = (((page_height - 2.0 * SHEET_MARGIN_MM) / size.height_mm) as usize).max(1);
+
+    let mut pages = Vec::new();
+    for page_labels in labels.chunks(columns * rows_per_page) {
+        let mut shapes = Vec::new();
+        for (index, label) in page_labels.iter().enumerate() {
+            let x = SHEET_MARGIN_MM + (index % columns) as f32 * size.width_mm;
+            let y = SHEET_MARGIN_MM + (index / columns) as f32 * size.height_mm;
+            shapes.push(Shape::Frame { x, y, width: size.width_mm, height: size.height_mm });
+            label.layout(size, x, y, &mut shapes)?;
+        }
+        pages.push(Page { width: page_width, height: page_height, shapes });
+    }
+    if pages.is_empty() {
+        pages.push(Page { width: page_width, height: page_height, shapes: Vec::new() });
+    }
+    if format == LabelFormat::Pdf || pages.len() == 1 {
+        return Ok(Sheet { body: render(pages, format)?, zipped: false });
+    }
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    for (index, page) in pages.into_iter().enumerate() {
+        let body = render(vec![page], format)?;
+        zip.start_file(format!("labels-{}.{}", index + 1, format.extension()), options).map_err(|err| err.to_string())?;
+        zip.write_all(&body).map_err(|err| err.to_string())?;
+    }
+    let body = zip.finish().map_err(|err| err.to_string())?.into_inner();
+
+    Ok(Sheet { body, zipped: true })
+}
+
+fn render(pages: Vec<Page>, format: LabelFormat) -> Result<Vec<u8>, String> {
+    match format {
+        LabelFormat::Svg => Ok(render_svg(&pages[0]).into_bytes()),
+        LabelFormat::Png => render_png(&pages[0]),
+        LabelFormat::Pdf => Ok(render_pdf(&pages)),
+    }
+}
+
+fn render_svg(page: &Page) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"white\"/>\n",
+        w = page.width,
+        h = page.height,
+    );
+    for shape in &page.shapes {
+        let element = match shape {
+            Shape::Rect { x, y, width, height } => {
+                format!("<rect x=\"{:.3}\" y=\"{:.3}\" width=\"{:.3}\" height=\"{:.3}\"/>\n", x, y, width, height)
+            }
+            Shape::Frame { x, y, width, height } => format!(
+                "<rect x=\"{:.3}\" y=\"{:.3}\" width=\"{:.3}\" height=\"{:.3}\" fill=\"none\" stroke=\"black\" stroke-width=\"{}\"/>\n",
+                x, y, width, height, FRAME_WIDTH_MM,
+            ),
+            Shape::Text { x, y, size, bold, text } => format!(
+                "<text x=\"{:.3}\" y=\"{:.3}\" font-family=\"Helvetica, Arial, sans-serif\" font-size=\"{:.3}\"{}>{}</text>\n",
+                x, y, size, if *bold { " font-weight=\"bold\"" } else { "" }, escape_xml(text),
+            ),
+        };
+        svg.push_str(&element);
+    }
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes a PDF document by hand, which only takes rectangles, text and the standard fonts.
+fn render_pdf(pages: &[Page]) -> Vec<u8> {
+    const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+    // Objects 1 and 2 are the catalog and page tree, 3 and 4 the fonts, followed by page and
+    // content stream per page.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|index| 5 + 2 * index).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<String>>().join(" "),
+            pages.len(),
+        ).into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+    for (page, page_id) in pages.iter().zip(&page_ids) {
+        let height = page.height * POINTS_PER_MM;
+        let mut content = Vec::new();
+        for shape in &page.shapes {
+            match shape {
+                Shape::Rect { x, y, width, height: rect_height } => content.extend(format!(
+                    "{:.3} {:.3} {:.3} {:.3} re f\n",
+                    x * POINTS_PER_MM, height - (y + rect_height) * POINTS_PER_MM, width * POINTS_PER_MM, rect_height * POINTS_PER_MM,
+                ).into_bytes()),
+                Shape::Frame { x, y, width, height: rect_height } => content.extend(format!(
+                    "{:.3} w {:.3} {:.3} {:.3} {:.3} re S\n",
+                    FRAME_WIDTH_MM * POINTS_PER_MM, x * POINTS_PER_MM, height - (y + rect_height) * POINTS_PER_MM,
+                    width * POINTS_PER_MM, rect_height * POINTS_PER_MM,
+                ).into_bytes()),
+                Shape::Text { x, y, size, bold, text } => {
+                    content.extend(format!(
+                        "BT /{} {:.3} Tf {:.3} {:.3} Td (",
+                        if *bold { "F2" } else { "F1" }, size * POINTS_PER_MM, x * POINTS_PER_MM, height - y * POINTS_PER_MM,
+                    ).into_bytes());
+                    content.extend(pdf_string(text));
+                    content.extend(b") Tj ET\n");
+                }
+            }
+        }
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            page.width * POINTS_PER_MM, height, page_id + 1,
+        ).into_bytes());
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", index + 1).into_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref_offset = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1, xref_offset,
+    ).into_bytes());
+
+    pdf
+}
+
+/// Encodes text as a PDF string in WinAnsiEncoding, replacing characters it can't represent.
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for character in text.chars() {
+        match character {
+            '(' | ')' | '\\' => bytes.extend([b'\\', character as u8]),
+            ' '..='~' | '\u{a0}'..='\u{ff}' => bytes.push(character as u32 as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+
+    bytes
+}
+
+/// Grayscale image, white on creation.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self { width, height, pixels: vec![255; (width * height) as usize] }
+    }
+
+    /// Blackens a rectangle in pixels, clipped to the image.
+    fn fill(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        let (x_start, x_end) = (x.max(0), (x + width).min(self.width as i32));
+        let (y_start, y_end) = (y.max(0), (y + height).min(self.height as i32));
+        for row in y_start..y_end {
+            let offset = (row * self.width as i32) as usize;
+            self.pixels[offset + x_start as usize..offset + x_end.max(x_start) as usize].fill(0);
+        }
+    }
+}
+
+/// Draws on a [Canvas] with every pixel enlarged to a square of `scale` pixels, from `origin`.
+struct ScaledCanvas<'a> {
+    canvas: &'a mut Canvas,
+    origin: Point,
+    scale: i32,
+}
+
+impl OriginDimensions for ScaledCanvas<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.canvas.width, self.canvas.height)
+    }
+}
+
+impl DrawTarget for ScaledCanvas<'_> {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if color.is_on() {
+                let x = self.origin.x + point.x * self.scale;
+                let y = self.origin.y + point.y * self.scale;
+                self.canvas.fill(x, y, self.scale, self.scale);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn render_png(page: &Page) -> Result<Vec<u8>, String> {
+    let pixels_per_mm = PNG_DPI / 25.4;
+    let to_pixels = |mm: f32| (mm * pixels_per_mm).round() as i32;
+    let mut canvas = Canvas::new(to_pixels(page.width) as u32, to_pixels(page.height) as u32);
+
+    for shape in &page.shapes {
+        match shape {
+            Shape::Rect { x, y, width, height } => {
+                let (left, top) = (to_pixels(*x), to_pixels(*y));
+                canvas.fill(left, top, to_pixels(x + width) - left, to_pixels(y + height) - top);
+            }
+            Shape::Frame { x, y, width, height } => {
+                let (left, top, right, bottom) = (to_pixels(*x), to_pixels(*y), to_pixels(x + width), to_pixels(y + height));
+                let line = to_pixels(FRAME_WIDTH_MM).max(1);
+                canvas.fill(left, top, right - left, line);
+                canvas.fill(left, bottom - line, right - left, line);
+                canvas.fill(left, top, line, bottom - top);
+                canvas.fill(right - line, top, line, bottom - top);
+            }
+            Shape::Text { x, y, size, bold, text } => {
+                let scale = ((size * pixels_per_mm) / FONT_10X20.character_size.height as f32).round().max(1.0) as i32;
+                let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
+                // Bold text is drawn twice, shifted by one enlarged pixel.
+                for shift in 0..=(*bold as i32) {
+                    let mut target = ScaledCanvas {
+                        canvas: &mut canvas,
+                        origin: Point::new(to_pixels(*x) + shift * scale, to_pixels(*y)),
+                        scale,
+                    };
+                    Text::with_baseline(text, Point::zero(), style, Baseline::Alphabetic)
+                        .draw(&mut target)
+                        .unwrap_or_else(|never| match never {});
+                }
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, canvas.width, canvas.height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer.write_image_data(&canvas.pixels).map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod labels {
+    use super::*;
+
+    fn label() -> Label {
+        Label {
+            code: String::from("12"),
+            product_name: String::from("Spaghettisaus (huisgemaakt)"),
+            date_in: NaiveDate::from_ymd_opt(2023, 8, 10).unwrap(),
+            expiration_date: NaiveDate::from_ymd_opt(2024, 8, 10).unwrap(),
+            location: String::from("Berging / Schuif 2"),
+            quantity: String::from("643.3 grams"),
+        }
+    }
+
+    #[test]
+    fn truncates_long_lines() {
+        assert_eq!(truncate("Groentensoep", 20), "Groentensoep");
+        assert_eq!(truncate("Groentensoep", 8), "Groen...");
+    }
+
+    #[test]
+    fn rejects_sizes_out_of_bounds() {
+        assert_eq!(LabelSize::new(None, None).unwrap(), LabelSize::default());
+        assert!(LabelSize::new(Some(10.0), None).is_err());
+        assert!(LabelSize::new(None, Some(250.0)).is_err());
+    }
+
+    #[test]
+    fn renders_all_formats() {
+        let size = LabelSize::default();
+
+        let png = render_label(&label(), size, LabelFormat::Png).unwrap();
+        let svg = String::from_utf8(render_label(&label(), size, LabelFormat::Svg).unwrap()).unwrap();
+        let pdf = render_label(&label(), size, LabelFormat::Pdf).unwrap();
+
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(svg.contains("In: 2023-08-10"));
+        assert!(pdf.starts_with(b"%PDF-1.4") && pdf.ends_with(b"%%EOF\n"));
+    }
+
+    #[test]
+    fn paginates_pdf_sheets() {
+        // 3 columns of 62 mm and 9 rows of 29 mm fit on an A4 page.
+        let labels = vec![label(); 28];
+
+        let pdf = render_sheet(&labels, LabelSize::default(), LabelFormat::Pdf).unwrap();
+        let pdf = String::from_utf8_lossy(&pdf.body);
+
+        assert!(pdf.contains("/Count 2"));
+    }
+
+    #[test]
+    fn zips_png_and_svg_sheets_of_several_pages() {
+        let single = render_sheet(&vec![label(); 27], LabelSize::default(), LabelFormat::Png).unwrap();
+        assert!(!single.zipped && single.body.starts_with(b"\x89PNG"));
+
+        let sheet = render_sheet(&vec![label(); 28], LabelSize::default(), LabelFormat::Svg).unwrap();
+        assert!(sheet.zipped);
+        let zip = zip::ZipArchive::new(Cursor::new(sheet.body)).unwrap();
+        assert_eq!(zip.file_names().count(), 2);
+        assert!(zip.file_names().any(|name| name == "labels-2.svg"));
+    }
+}
@@ -0,0 +1,50 @@
+//! Names of products, freezers and drawers.
+//!
+//! Names are normalized on write: surrounding whitespace is removed and inner whitespace collapsed
+//! to a single space. They are unique regardless of case, which the database enforces with unique
+//! indexes on `lower(name)`. Lookups by name therefore compare [lower] on both sides, so "brocoli "
+//! finds "Brocoli".
+
+use axum::http::StatusCode;
+use diesel::sql_types::Text;
+
+diesel::sql_function! {
+    /// Lowercase version of a text, used to compare names regardless of case.
+    fn lower(x: Text) -> Text;
+}
+
+/// Name with surrounding whitespace removed and inner whitespace collapsed to single spaces.
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// [normalize_name]s a name to be written.
+///
+/// # Errors
+///
+/// * `BadRequest` => "name cannot be empty".
+pub fn normalize_required_name(name: &str) -> Result<String, (StatusCode, String)> {
+    let name = normalize_name(name);
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, String::from("name cannot be empty")));
+    }
+
+    Ok(name)
+}
+
+#[cfg(test)]
+mod names {
+    use super::*;
+
+    #[test]
+    fn trims_and_collapses_whitespace() {
+        assert_eq!(normalize_name("  Rode \t kool\n"), "Rode kool");
+        assert_eq!(normalize_name("Brocoli"), "Brocoli");
+    }
+
+    #[test]
+    fn rejects_blank_names() {
+        assert_eq!(normalize_required_name(" \t "), Err((StatusCode::BAD_REQUEST, String::from("name cannot be empty"))));
+        assert_eq!(normalize_required_name(" Garage "), Ok(String::from("Garage")));
+    }
+}
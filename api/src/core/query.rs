@@ -2,7 +2,7 @@
 
 use std::fmt;
 use std::str::FromStr;
-use chrono::{Local, Months, NaiveDate};
+use chrono::{Days, Local, Months, NaiveDate};
 use serde::{de, Deserializer, Deserialize};
 
 /// Handler to capture empty query parameters as None.
@@ -19,8 +19,56 @@ pub fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
     }
 }
 
+/// Allowed query parameters when deleting a freezer or drawer that still contains available items.
+///
+/// Without either of them, the deletion is refused. Both can't be given at once.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteOptions {
+    /// Move the available items to the trash along with the freezer or drawer.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub force: Option<bool>,
+    /// Id of the drawer receiving the available items before deletion.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub reassign_to: Option<i32>,
+}
+
+/// Time a product keeps in the freezer, see [crate::core::shelf_life] for how it is determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelfLife {
+    /// Whole calendar months, as defined by [crate::models::Product] `expiration_months`.
+    Months(u32),
+    /// Days, for products keeping less than a month or adjusted for the freezer type.
+    Days(u32),
+}
+
+impl ShelfLife {
+    /// Expiration date of an item put in storage on `date_in`, [NaiveDate::MAX] when it lies beyond.
+    pub fn expiration_date(&self, date_in: NaiveDate) -> NaiveDate {
+        match self {
+            ShelfLife::Months(months) => date_in.checked_add_months(Months::new(*months)),
+            ShelfLife::Days(days) => date_in.checked_add_days(Days::new(*days as u64)),
+        }
+            .unwrap_or(NaiveDate::MAX)
+    }
+
+    /// Shelf life multiplied by `multiplier`, counted in days from `date_in` unless it is 1.
+    ///
+    /// Months differ in length, so the amount of days depends on the date of entry. The result is
+    /// rounded and at least one day.
+    pub fn scaled(self, date_in: NaiveDate, multiplier: f32) -> Self {
+        if multiplier == 1.0 {
+            return self;
+        }
+        let days = self.expiration_date(date_in).signed_duration_since(date_in).num_days();
+        let scaled_days = (days as f32 * multiplier).round().max(1.0);
+
+        ShelfLife::Days(scaled_days as u32)
+    }
+}
+
 /// Struct containing all relevant datetime information.
-/// Allows parsing from the NaiveDate as stored in the database as well as the expiration time.
+/// Allows parsing from the NaiveDate as stored in the database as well as the shelf life.
 ///
 /// All dates returned are in [NaiveDate].
 #[derive(Debug)]
@@ -29,8 +77,8 @@ pub struct ExpirationData {
     pub date_in: NaiveDate,
     /// Expiration date of [crate::models::Storage] item, [Local] Tz.
     pub date_expires: NaiveDate,
-    /// Expiration time of [crate::models::Product], in [Months]
-    pub expiration_months: Months,
+    /// Effective shelf life of the [crate::models::Storage] item.
+    pub shelf_life: ShelfLife,
     /// Time until expiration. Value < 0 if the product has already expired.
     pub expires_in_days: i64,
 }
@@ -38,21 +86,26 @@ pub struct ExpirationData {
 impl ExpirationData {
     /// Takes input date stamp in UTC from database and returns object with useful data for storage calculations.
     pub fn new(date_in: NaiveDate, expiration_months: i32) -> Self {
-        // Work in UTC to avoid errors.
+        Self::from_shelf_life(date_in, ShelfLife::Months(expiration_months as u32))
+    }
+
+    /// Same as [Self::new], for an explicit expiration date instead of a shelf life.
+    pub fn from_date(date_in: NaiveDate, date_expires: NaiveDate) -> Self {
+        let days = date_expires.signed_duration_since(date_in).num_days().max(0);
+
+        Self::from_shelf_life(date_in, ShelfLife::Days(days as u32))
+    }
+
+    /// Same as [Self::new], for any [ShelfLife].
+    pub fn from_shelf_life(date_in: NaiveDate, shelf_life: ShelfLife) -> Self {
         let today = Local::now().date_naive();
-        // let date_in = DateTime::<Utc>::from_naive_utc_and_offset(
-        //     date_in.and_hms_opt(0, 0, 0).unwrap(),
-        //     Utc
-        // );
-        let expiration_months = Months::new(expiration_months as u32);
-        let date_expires = date_in.checked_add_months(expiration_months).unwrap();
+        let date_expires = shelf_life.expiration_date(date_in);
         let expires_in_days = date_expires.signed_duration_since(today.to_owned()).num_days();
 
-        // Convert to local timezone for output to frontend.
         Self {
             date_in,
             date_expires,
-            expiration_months,
+            shelf_life,
             expires_in_days,
         }
     }
@@ -69,7 +122,7 @@ mod expiration_data {
         let expiration_data = ExpirationData::new(date_in, expiration_months);
 
         let expected_date_expires = NaiveDate::parse_from_str("2024-01-01", "%Y-%m-%d").unwrap();
-        assert_eq!(expiration_data.expiration_months, Months::new(expiration_months as u32));
+        assert_eq!(expiration_data.shelf_life, ShelfLife::Months(expiration_months as u32));
         assert_eq!(expiration_data.date_expires, expected_date_expires);
         assert!(expiration_data.expires_in_days < 0);
     }
@@ -82,8 +135,34 @@ mod expiration_data {
 
         let expected_date_expires = date_in.checked_add_months(Months::new(expiration_months as u32)).unwrap();
 
-        assert_eq!(expiration_data.expiration_months, Months::new(expiration_months as u32));
+        assert_eq!(expiration_data.shelf_life, ShelfLife::Months(expiration_months as u32));
         assert_eq!(expiration_data.date_expires, expected_date_expires);
         assert!(expiration_data.expires_in_days > 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn counts_shelf_life_in_days() {
+        let date_in = NaiveDate::parse_from_str("2024-02-20", "%Y-%m-%d").unwrap();
+        let expiration_data = ExpirationData::from_shelf_life(date_in, ShelfLife::Days(14));
+
+        assert_eq!(expiration_data.date_expires, NaiveDate::parse_from_str("2024-03-05", "%Y-%m-%d").unwrap());
+    }
+
+    #[test]
+    fn saturates_expiration_dates_out_of_range() {
+        let date_in = NaiveDate::parse_from_str("2023-01-01", "%Y-%m-%d").unwrap();
+
+        assert_eq!(ShelfLife::Days(u32::MAX).expiration_date(date_in), NaiveDate::MAX);
+        assert_eq!(ShelfLife::Months(u32::MAX).expiration_date(date_in), NaiveDate::MAX);
+        assert_eq!(ShelfLife::Months(12).scaled(date_in, 1e30), ShelfLife::Days(u32::MAX));
+    }
+
+    #[test]
+    fn scales_shelf_life_in_days_of_the_months() {
+        let date_in = NaiveDate::parse_from_str("2023-01-01", "%Y-%m-%d").unwrap();
+
+        assert_eq!(ShelfLife::Months(12).scaled(date_in, 1.0), ShelfLife::Months(12));
+        assert_eq!(ShelfLife::Months(12).scaled(date_in, 0.5), ShelfLife::Days(183));
+        assert_eq!(ShelfLife::Days(3).scaled(date_in, 0.1), ShelfLife::Days(1));
+    }
+}
@@ -0,0 +1,95 @@
+//! Ranking of storage items to eat next.
+//!
+//! Every available item gets a score from its days until expiration and its days in storage, the
+//! highest score is eaten first. How both are weighed depends on the [Strategy]:
+//!
+//! * [Strategy::Fefo]: first expired, first out. The score is minus the days until expiration.
+//! * [Strategy::Fifo]: first in, first out. The score is the days in storage.
+//! * [Strategy::Mixed]: the FEFO score plus [MIXED_AGE_WEIGHT] times the days in storage, so an item
+//!   that has been forgotten for a long time moves up even when it doesn't expire soon.
+
+use serde::{Deserialize, Serialize};
+
+/// Weight of one day in storage relative to one day less until expiration, for [Strategy::Mixed].
+pub const MIXED_AGE_WEIGHT: f64 = 0.25;
+
+/// How storage items are ranked, see the [module](self) docs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Strategy {
+    /// First in, first out.
+    Fifo,
+    /// First expired, first out.
+    Fefo,
+    /// Expiration first, weighed with time in storage.
+    #[default]
+    Mixed,
+}
+
+/// Score of a storage item expiring in `expires_in_days` and stored `days_in_storage` days ago.
+/// Items with a higher score should be eaten first.
+pub fn score(strategy: Strategy, expires_in_days: i64, days_in_storage: i64) -> f64 {
+    match strategy {
+        Strategy::Fifo => days_in_storage as f64,
+        Strategy::Fefo => -(expires_in_days as f64),
+        Strategy::Mixed => -(expires_in_days as f64) + MIXED_AGE_WEIGHT * days_in_storage as f64,
+    }
+}
+
+/// Explanation of the [score] of a storage item, e.g. "Expires in 3 days, in storage for 200 days".
+pub fn reason(strategy: Strategy, expires_in_days: i64, days_in_storage: i64) -> String {
+    match strategy {
+        Strategy::Fifo => format!("In storage for {}", days(days_in_storage)),
+        Strategy::Fefo => expiration(expires_in_days),
+        Strategy::Mixed => format!("{}, in storage for {}", expiration(expires_in_days), days(days_in_storage)),
+    }
+}
+
+fn expiration(expires_in_days: i64) -> String {
+    match expires_in_days {
+        0 => String::from("Expires today"),
+        1 => String::from("Expires tomorrow"),
+        d if d < 0 => format!("Expired {} ago", days(-d)),
+        d => format!("Expires in {}", days(d)),
+    }
+}
+
+fn days(amount: i64) -> String {
+    match amount {
+        1 => String::from("1 day"),
+        _ => format!("{} days", amount),
+    }
+}
+
+#[cfg(test)]
+mod recommendations {
+    use super::*;
+
+    #[test]
+    fn fefo_prefers_items_expiring_sooner() {
+        assert!(score(Strategy::Fefo, 3, 10) > score(Strategy::Fefo, 30, 400));
+        assert!(score(Strategy::Fefo, -5, 10) > score(Strategy::Fefo, 0, 10));
+    }
+
+    #[test]
+    fn fifo_prefers_items_stored_longer() {
+        assert!(score(Strategy::Fifo, 30, 400) > score(Strategy::Fifo, 3, 10));
+    }
+
+    #[test]
+    fn mixed_weighs_time_in_storage() {
+        // Close expirations: the long forgotten item goes first.
+        assert!(score(Strategy::Mixed, 20, 400) > score(Strategy::Mixed, 10, 5));
+        // Expiring soon still beats a bit of time in storage.
+        assert!(score(Strategy::Mixed, 2, 5) > score(Strategy::Mixed, 30, 60));
+    }
+
+    #[test]
+    fn reasons_describe_the_strategy() {
+        assert_eq!(reason(Strategy::Fifo, 3, 1), "In storage for 1 day");
+        assert_eq!(reason(Strategy::Fefo, 0, 10), "Expires today");
+        assert_eq!(reason(Strategy::Fefo, 1, 10), "Expires tomorrow");
+        assert_eq!(reason(Strategy::Fefo, -1, 10), "Expired 1 day ago");
+        assert_eq!(reason(Strategy::Mixed, 12, 200), "Expires in 12 days, in storage for 200 days");
+    }
+}
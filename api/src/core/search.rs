@@ -0,0 +1,84 @@
+//! Fuzzy matching of product names, used by [crate::routes::products::search_products] and the
+//! `fuzzy` option of [crate::routes::storage::StorageFilter].
+//!
+//! A name matches a search text when it starts with it, contains it or is similar to it according to
+//! the trigram similarity of the `pg_trgm` extension, which tolerates typos. Matches are ranked in
+//! that order, similar names by their similarity.
+
+use std::cmp::Ordering;
+
+use diesel::sql_types::Text;
+
+/// Lowest `word_similarity` of a name to be matched despite not containing the search text.
+pub const SIMILARITY_THRESHOLD: f32 = 0.5;
+
+diesel::sql_function! {
+    /// Greatest similarity, between 0 and 1, of the first text to any part of the second (`pg_trgm`).
+    fn word_similarity(search: Text, name: Text) -> Float4;
+}
+
+/// Search text as compared to names: normalized and lowercased, see [crate::core::name].
+pub fn search_text(text: &str) -> String {
+    super::name::normalize_name(text).to_lowercase()
+}
+
+/// `LIKE` pattern matching names containing the search text, with `%`, `_` and `\` escaped.
+pub fn contains_pattern(search: &str) -> String {
+    let escaped = search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+
+    format!("%{}%", escaped)
+}
+
+/// How well a name matches the search text, higher is better.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum MatchRank {
+    /// Similar according to the trigram similarity.
+    Similar(f32),
+    /// Contains the search text.
+    Contains,
+    /// Starts with the search text.
+    Prefix,
+}
+
+impl MatchRank {
+    /// Rank of a name for a search text, as returned by [search_text], given their `word_similarity`.
+    pub fn of(name: &str, search: &str, similarity: f32) -> Self {
+        let name = name.to_lowercase();
+        if name.starts_with(search) {
+            Self::Prefix
+        } else if name.contains(search) {
+            Self::Contains
+        } else {
+            Self::Similar(similarity)
+        }
+    }
+
+    /// Orders best matches first.
+    pub fn best_first(a: &Self, b: &Self) -> Ordering {
+        b.partial_cmp(a).unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod product_search {
+    use super::*;
+
+    #[test]
+    fn ranks_prefix_before_substring_before_similar() {
+        let prefix = MatchRank::of("Spaghetti", "spag", 0.4);
+        let contains = MatchRank::of("Saus voor spaghetti", "spag", 0.4);
+        let similar = MatchRank::of("Spagetti", "spagh", 0.6);
+
+        assert_eq!(prefix, MatchRank::Prefix);
+        assert_eq!(contains, MatchRank::Contains);
+        assert_eq!(similar, MatchRank::Similar(0.6));
+        assert!(prefix > contains && contains > similar);
+        assert!(MatchRank::Similar(0.7) > MatchRank::Similar(0.5));
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(contains_pattern("50%_a\\b"), "%50\\%\\_a\\\\b%");
+        assert_eq!(search_text("  Rode   KOOL "), "rode kool");
+    }
+}
@@ -0,0 +1,136 @@
+//! Effective shelf life of storage items.
+//!
+//! The shelf life of a [Product] is its `shelf_life_days` when set, its `expiration_months`
+//! otherwise. Freezers can have a [crate::models::FreezerType], scaling that shelf life by its
+//! multiplier. A [ShelfLifeOverride] for the product and freezer type replaces the result entirely.
+//!
+//! An explicit expiration date on a [Storage] item takes precedence over all of the above.
+//!
+//! [ShelfLifeRules] loads all of this at once, so expirations of many items can be calculated
+//! without querying per item. Expiration filters are calculated on the result, so they always
+//! honor the effective shelf life.
+//!
+//! The `storage_expirations` database view applies the same rules in SQL for aggregate queries, see
+//! [crate::routes::summary]. Changes to the rules have to be made in both.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+use crate::core::query::{ExpirationData, ShelfLife};
+use crate::models::{Product, ShelfLifeOverride, Storage};
+use crate::schema::{freezer_types, freezers, product_shelf_lives, products};
+
+/// Everything needed to calculate the effective shelf life of storage items.
+///
+/// The default has no rules at all, every product keeps its `expiration_months`.
+#[derive(Debug, Clone, Default)]
+pub struct ShelfLifeRules {
+    /// `shelf_life_days` per product id, for the products defining it.
+    product_days: HashMap<i32, i32>,
+    /// Freezer type id and multiplier per freezer id, for the freezers having a type.
+    freezer_types: HashMap<i32, (i32, f32)>,
+    /// Shelf life in days per product id and freezer type id.
+    overrides: HashMap<(i32, i32), i32>,
+}
+
+impl ShelfLifeRules {
+    /// Loads the rules of all products and freezers.
+    pub fn load(conn: &mut PgConnection) -> QueryResult<Self> {
+        let product_days = products::table
+            .filter(products::shelf_life_days.is_not_null())
+            .select((products::product_id, products::shelf_life_days.assume_not_null()))
+            .load::<(i32, i32)>(conn)?
+            .into_iter()
+            .collect();
+        let freezer_types = freezers::table
+            .inner_join(freezer_types::table)
+            .select((freezers::freezer_id, freezer_types::freezer_type_id, freezer_types::shelf_life_multiplier))
+            .load::<(i32, i32, f32)>(conn)?
+            .into_iter()
+            .map(|(freezer_id, freezer_type_id, multiplier)| (freezer_id, (freezer_type_id, multiplier)))
+            .collect();
+        let overrides = product_shelf_lives::table
+            .select(ShelfLifeOverride::as_select())
+            .load::<ShelfLifeOverride>(conn)?
+            .into_iter()
+            .map(|rule| ((rule.product_id, rule.freezer_type_id), rule.shelf_life_days))
+            .collect();
+
+        Ok(Self { product_days, freezer_types, overrides })
+    }
+
+    /// Shelf life of a product stored in a freezer on `date_in`.
+    pub fn shelf_life(&self, product: &Product, freezer_id: i32, date_in: NaiveDate) -> ShelfLife {
+        let base = match self.product_days.get(&product.product_id) {
+            Some(days) => ShelfLife::Days(*days as u32),
+            None => ShelfLife::Months(product.expiration_months as u32),
+        };
+        let Some((freezer_type_id, multiplier)) = self.freezer_types.get(&freezer_id) else {
+            return base;
+        };
+
+        match self.overrides.get(&(product.product_id, *freezer_type_id)) {
+            Some(days) => ShelfLife::Days(*days as u32),
+            None => base.scaled(date_in, *multiplier),
+        }
+    }
+
+    /// [ExpirationData] of a product stored in a freezer on `date_in`.
+    pub fn expiration(&self, product: &Product, freezer_id: i32, date_in: NaiveDate) -> ExpirationData {
+        ExpirationData::from_shelf_life(date_in, self.shelf_life(product, freezer_id, date_in))
+    }
+
+    /// [ExpirationData] of a storage item of a product in a freezer, honoring its explicit expiration date.
+    pub fn storage_expiration(&self, item: &Storage, product: &Product, freezer_id: i32) -> ExpirationData {
+        match item.expiration_date {
+            Some(date_expires) => ExpirationData::from_date(item.date_in, date_expires),
+            None => self.expiration(product, freezer_id, item.date_in),
+        }
+    }
+}
+
+#[cfg(test)]
+mod shelf_life_rules {
+    use super::*;
+
+    fn product() -> Product {
+        Product::from_tuple((1, "Brocoli", 12))
+    }
+
+    fn date_in() -> NaiveDate {
+        NaiveDate::parse_from_str("2023-01-01", "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn defaults_to_expiration_months() {
+        let rules = ShelfLifeRules::default();
+
+        assert_eq!(rules.shelf_life(&product(), 1, date_in()), ShelfLife::Months(12));
+    }
+
+    #[test]
+    fn applies_product_days_and_freezer_type_multiplier() {
+        let rules = ShelfLifeRules {
+            product_days: HashMap::from([(1, 10)]),
+            freezer_types: HashMap::from([(2, (1, 3.0))]),
+            overrides: HashMap::new(),
+        };
+
+        assert_eq!(rules.shelf_life(&product(), 1, date_in()), ShelfLife::Days(10));
+        assert_eq!(rules.shelf_life(&product(), 2, date_in()), ShelfLife::Days(30));
+    }
+
+    #[test]
+    fn prefers_override_of_freezer_type() {
+        let rules = ShelfLifeRules {
+            product_days: HashMap::from([(1, 10)]),
+            freezer_types: HashMap::from([(2, (1, 3.0)), (3, (2, 3.0))]),
+            overrides: HashMap::from([((1, 1), 5)]),
+        };
+
+        assert_eq!(rules.shelf_life(&product(), 2, date_in()), ShelfLife::Days(5));
+        assert_eq!(rules.shelf_life(&product(), 3, date_in()), ShelfLife::Days(30));
+    }
+}
@@ -0,0 +1,99 @@
+//! Shopping list suggestions from minimum stock levels.
+//!
+//! A product with a minimum stock is suggested when the available quantity of it, in the unit of the
+//! minimum, is lower. Only storage items still in the freezer count, and quantities in other units
+//! are ignored as they can't be compared, see [crate::models::Unit].
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Unit;
+
+/// Minimum stock of a product.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinStock {
+    /// Id of the product.
+    pub product_id: i32,
+    /// Name of the product.
+    pub product_name: String,
+    /// Quantity to keep in stock, in [Self::unit].
+    pub min_quantity: f32,
+    /// Unit of [Self::min_quantity].
+    pub unit: Unit,
+}
+
+/// Product to buy because its stock is below its minimum.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShoppingSuggestion {
+    /// Id of the product.
+    pub product_id: i32,
+    /// Name of the product.
+    pub product_name: String,
+    /// Unit of the quantities.
+    pub unit: Unit,
+    /// Quantity to keep in stock.
+    pub min_quantity: f32,
+    /// Quantity available in the freezers.
+    pub available_quantity: f32,
+    /// Quantity to buy to reach the minimum.
+    pub missing_quantity: f32,
+}
+
+/// Suggestions for all products whose `available` quantity, per product id and unit, is below their
+/// minimum stock. Sorted by product name.
+pub fn suggestions(min_stocks: &[MinStock], available: &HashMap<(i32, Unit), f32>) -> Vec<ShoppingSuggestion> {
+    let mut result: Vec<ShoppingSuggestion> = min_stocks
+        .iter()
+        .filter_map(|min_stock| {
+            let available_quantity = available.get(&(min_stock.product_id, min_stock.unit)).copied().unwrap_or(0.0);
+            (available_quantity < min_stock.min_quantity).then(|| ShoppingSuggestion {
+                product_id: min_stock.product_id,
+                product_name: min_stock.product_name.clone(),
+                unit: min_stock.unit,
+                min_quantity: min_stock.min_quantity,
+                available_quantity,
+                missing_quantity: min_stock.min_quantity - available_quantity,
+            })
+        })
+        .collect();
+    result.sort_by(|a, b| a.product_name.cmp(&b.product_name));
+
+    result
+}
+
+#[cfg(test)]
+mod shopping_suggestions {
+    use super::*;
+
+    fn min_stock(product_id: i32, product_name: &str, min_quantity: f32, unit: Unit) -> MinStock {
+        MinStock { product_id, product_name: String::from(product_name), min_quantity, unit }
+    }
+
+    #[test]
+    fn suggests_products_below_minimum() {
+        let min_stocks = [
+            min_stock(2, "Groentensoep", 4.0, Unit::Portions),
+            min_stock(1, "Brocoli", 500.0, Unit::Grams),
+        ];
+        let available = HashMap::from([((2, Unit::Portions), 1.0), ((1, Unit::Grams), 600.0)]);
+
+        let result = suggestions(&min_stocks, &available);
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].product_name, "Groentensoep");
+        assert_eq!(result[0].missing_quantity, 3.0);
+    }
+
+    #[test]
+    fn ignores_quantities_in_other_units() {
+        let min_stocks = [min_stock(2, "Groentensoep", 2.0, Unit::Portions)];
+        let available = HashMap::from([((2, Unit::Grams), 2000.0)]);
+
+        let result = suggestions(&min_stocks, &available);
+
+        assert_eq!(result[0].available_quantity, 0.0);
+        assert_eq!(result[0].missing_quantity, 2.0);
+    }
+}
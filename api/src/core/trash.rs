@@ -0,0 +1,78 @@
+//! Soft deletion and purging of trashed rows.
+//!
+//! Deleting a product, freezer, drawer or storage item only sets its `deleted_at` timestamp, moving
+//! it to the trash. Rows deleted along with their parent (e.g. the storage items inside a deleted
+//! drawer) get the exact same timestamp, which allows restoring them together with the parent. See
+//! [crate::routes::trash] for the trash endpoints.
+//!
+//! Trashed rows are permanently deleted by [purge_trash] once they are older than the retention
+//! period, which defaults to [DEFAULT_RETENTION_DAYS] and can be changed through the
+//! `TRASH_RETENTION_DAYS` environment variable.
+
+use std::env;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+use crate::core::connection::establish_connection;
+
+/// Default time trashed rows are kept before being purged, in days.
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+/// Time between two purges of the trash, in seconds.
+pub const PURGE_INTERVAL_SECONDS: u64 = 60 * 60;
+
+/// Time trashed rows are kept, read from `TRASH_RETENTION_DAYS`.
+pub fn retention_from_env() -> Duration {
+    let days = env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+    Duration::days(days)
+}
+
+/// Permanently deletes all rows that have been in the trash for longer than `retention`.
+///
+/// # Returns
+///
+/// The amount of purged rows, over all tables.
+pub fn purge_trash(conn: &mut PgConnection, retention: Duration) -> QueryResult<usize> {
+    use crate::schema::{drawers, freezers, products, storage};
+
+    let deleted_before = Utc::now() - retention;
+
+    conn.transaction(|conn| {
+        let purged_storage = diesel::delete(storage::table)
+            .filter(storage::deleted_at.lt(deleted_before))
+            .execute(conn)?;
+        let purged_drawers = diesel::delete(drawers::table)
+            .filter(drawers::deleted_at.lt(deleted_before))
+            .execute(conn)?;
+        let purged_freezers = diesel::delete(freezers::table)
+            .filter(freezers::deleted_at.lt(deleted_before))
+            .execute(conn)?;
+        let purged_products = diesel::delete(products::table)
+            .filter(products::deleted_at.lt(deleted_before))
+            .execute(conn)?;
+
+        Ok(purged_storage + purged_drawers + purged_freezers + purged_products)
+    })
+}
+
+/// Background job purging the trash every [PURGE_INTERVAL_SECONDS]. Never returns.
+pub async fn purge_job(db_url: Option<String>) {
+    let retention = retention_from_env();
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(PURGE_INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        let conn = &mut establish_connection(db_url.clone());
+        match purge_trash(conn, retention) {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!(target: "trash_purge", "Purged {} rows from the trash", purged),
+            Err(err) => tracing::error!(target: "trash_purge", "Failed to purge the trash: {}", err),
+        }
+    }
+}
@@ -10,6 +10,8 @@ pub mod schema;
 use std::time::Duration;
 
 use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
     routing::{get, post, patch, delete},
     response::Response,
     body::Body,
@@ -23,61 +25,159 @@ use tower_http::{
 };
 use tracing::Span;
 
-use crate::routes::{root, products, freezers, drawers, storage};
+use crate::core::{events::EventBus, idempotency};
+use crate::routes::{root, products, freezers, drawers, storage, trash, events, categories, tags, freezer_types, labels, barcodes, shopping_list, recommendations, analytics, capacities, summary, stocktakes, import, backup};
 
 /// Contains application state variables.
 #[derive(Clone)]
 pub struct AppState {
     db_url: Option<String>,
+    /// Time during which idempotency keys are remembered, see [core::idempotency].
+    idempotency_window: chrono::Duration,
+    /// Change feed shared by all handlers, see [core::events].
+    events: EventBus,
 }
 
 /// App factory with possibility to define non-.env database url.
 pub async fn app(db_url: Option<String>) -> Router {
     let state = AppState {
         db_url,
+        idempotency_window: idempotency::window_from_env(),
+        events: EventBus::new(),
     };
 
     let products_subroutes = Router::new()
         .route("/", get(products::get_all_products))
         .route("/", patch(products::update_product))
         .route("/create", post(products::create_product))
+        .route("/search", get(products::search_products))
         .route("/id=:id", get(products::get_product_by_id))
         .route("/id=:id", delete(products::delete_product))
         .route("/name=:name", get(products::get_product_by_name))
-        .route("/expiration=:expiration", get(products::get_products_by_expiration));
+        .route("/expiration=:expiration", get(products::get_products_by_expiration))
+        .route("/id=:id/category", get(categories::get_product_category))
+        .route("/id=:id/category", patch(categories::set_product_category))
+        .route("/id=:id/tags", get(tags::get_product_tags))
+        .route("/id=:id/tags", patch(tags::set_product_tags))
+        .route("/id=:id/merge", post(products::merge_products))
+        .route("/id=:id/min-stock", get(shopping_list::get_product_min_stock))
+        .route("/id=:id/min-stock", patch(shopping_list::set_product_min_stock))
+        .route("/id=:id/barcodes", get(barcodes::get_product_barcodes))
+        .route("/id=:id/barcodes", patch(barcodes::set_product_barcodes))
+        .route("/barcode/:code", get(barcodes::get_product_by_barcode))
+        .route("/id=:id/shelf-life", get(freezer_types::get_product_shelf_life))
+        .route("/id=:id/shelf-life", patch(freezer_types::set_product_shelf_life));
 
     let freezer_subroutes = Router::new()
         .route("/", get(freezers::get_all_freezers))
         .route("/", patch(freezers::update_freezer))
         .route("/create", post(freezers::create_freezer))
+        .route("/fill-levels", get(capacities::get_fill_levels))
         .route("/id=:id", get(freezers::get_freezer_by_id))
         .route("/id=:id", delete(freezers::delete_freezer))
-        .route("/name=:name", get(freezers::get_freezer_by_name));
+        .route("/name=:name", get(freezers::get_freezer_by_name))
+        .route("/id=:id/type", get(freezer_types::get_freezer_type))
+        .route("/id=:id/type", patch(freezer_types::set_freezer_type))
+        .route("/id=:id/capacity", get(capacities::get_freezer_capacity))
+        .route("/id=:id/capacity", patch(capacities::set_freezer_capacity));
 
     let drawer_subroutes = Router::new()
         .route("/", get(drawers::get_drawers))
         .route("/", post(drawers::create_drawer))
         .route("/", patch(drawers::update_drawer))
-        .route("/:id", delete(drawers::delete_drawer));
+        .route("/:id", delete(drawers::delete_drawer))
+        .route("/:id/capacity", get(capacities::get_drawer_capacity))
+        .route("/:id/capacity", patch(capacities::set_drawer_capacity));
 
     let storage_subroutes = Router::new()
         .route("/", get(storage::get_storage))
+        .route("/totals", get(storage::get_storage_totals))
+        .route("/labels", get(labels::get_storage_labels))
+        .route("/batch", post(storage::create_storage_batch))
+        .route("/scan", post(barcodes::scan_storage))
         .route("/:id", get(storage::get_storage_by_id))
+        .route("/code/:code", get(storage::get_storage_by_code))
+        .route("/code/:code/withdraw", patch(storage::withdraw_storage_by_code))
+        .route("/code/:code/re-enter", patch(storage::re_enter_storage_by_code))
         .route("/", post(storage::create_storage))
         .route("/", patch(storage::update_storage))
         .route("/:id/withdraw", patch(storage::withdraw_storage))
         .route("/:id/re-enter", patch(storage::re_enter_storage))
+        .route("/:id/label", get(labels::get_storage_label))
         .route("/:id", delete(storage::delete_storage));
 
+    let category_subroutes = Router::new()
+        .route("/", get(categories::get_categories))
+        .route("/", post(categories::create_category))
+        .route("/", patch(categories::update_category))
+        .route("/:id", delete(categories::delete_category));
+
+    let tag_subroutes = Router::new()
+        .route("/", get(tags::get_tags))
+        .route("/", post(tags::create_tag))
+        .route("/", patch(tags::update_tag))
+        .route("/:id", delete(tags::delete_tag));
+
+    let freezer_type_subroutes = Router::new()
+        .route("/", get(freezer_types::get_freezer_types))
+        .route("/", post(freezer_types::create_freezer_type))
+        .route("/", patch(freezer_types::update_freezer_type))
+        .route("/:id", delete(freezer_types::delete_freezer_type));
+
+    let shopping_list_subroutes = Router::new()
+        .route("/", get(shopping_list::get_shopping_list))
+        .route("/items", post(shopping_list::create_shopping_list_item))
+        .route("/items/:id", delete(shopping_list::delete_shopping_list_item))
+        .route("/items/:id/check", patch(shopping_list::check_shopping_list_item))
+        .route("/items/:id/uncheck", patch(shopping_list::uncheck_shopping_list_item));
+
+    let stocktake_subroutes = Router::new()
+        .route("/", get(stocktakes::get_stocktakes))
+        .route("/", post(stocktakes::create_stocktake))
+        .route("/:id", get(stocktakes::get_stocktake))
+        .route("/:id", delete(stocktakes::delete_stocktake))
+        .route("/:id/unseen", get(stocktakes::get_unseen_items))
+        .route("/:id/items/:storage_id", patch(stocktakes::mark_item_seen))
+        .route("/:id/items/:storage_id", delete(stocktakes::unmark_item_seen))
+        .route("/:id/code/:code", patch(stocktakes::mark_code_seen))
+        .route("/:id/complete", post(stocktakes::complete_stocktake));
+
+    let analytics_subroutes = Router::new()
+        .route("/", get(analytics::get_analytics))
+        .route("/products", get(analytics::get_product_analytics))
+        .route("/freezers", get(analytics::get_freezer_analytics))
+        .route("/trends", get(analytics::get_analytics_trends));
+
+    let trash_subroutes = Router::new()
+        .route("/", get(trash::get_trash))
+        .route("/products/:id/restore", patch(trash::restore_product))
+        .route("/freezers/:id/restore", patch(trash::restore_freezer))
+        .route("/drawers/:id/restore", patch(trash::restore_drawer))
+        .route("/storage/:id/restore", patch(trash::restore_storage));
+
     let api_subroutes = Router::new()
         .route("/", get(|| async { "API active" }))
         .route("/info", get(root::info))
         .route("/authors", get(root::authors))
         .route("/version", get(root::version))
+        .route("/events", get(events::get_events))
+        .route("/recommendations", get(recommendations::get_recommendations))
+        .route("/summary", get(summary::get_summary))
+        .route("/import", post(import::import_storage).layer(DefaultBodyLimit::max(import::MAX_IMPORT_SIZE)))
+        .route("/export", get(backup::get_export))
+        .route("/restore", post(backup::restore_dump).layer(DefaultBodyLimit::max(backup::MAX_DUMP_SIZE)))
         .nest("/products", products_subroutes)
         .nest("/freezers", freezer_subroutes)
         .nest("/drawers", drawer_subroutes)
-        .nest("/storage", storage_subroutes);
+        .nest("/storage", storage_subroutes)
+        .nest("/categories", category_subroutes)
+        .nest("/tags", tag_subroutes)
+        .nest("/freezer-types", freezer_type_subroutes)
+        .nest("/shopping-list", shopping_list_subroutes)
+        .nest("/stocktakes", stocktake_subroutes)
+        .nest("/analytics", analytics_subroutes)
+        .nest("/trash", trash_subroutes)
+        .layer(middleware::from_fn_with_state(state.clone(), idempotency::idempotency));
 
     Router::new()
         .nest("/api", api_subroutes)
@@ -8,6 +8,7 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use api::app;
 use api::core::connection::{establish_connection, MIGRATIONS};
+use api::core::trash;
 
 #[tokio::main]
 async fn main() {
@@ -28,6 +29,9 @@ async fn main() {
             panic!("Failed migrations.")
         });
 
+    // Permanently delete items that stayed in the trash for too long.
+    tokio::spawn(trash::purge_job(None));
+
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
     tracing::debug!("listening on {} at port {}", addr.ip(), addr.port());
 
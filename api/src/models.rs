@@ -1,14 +1,140 @@
 //! [diesel.rs](http://diesel.rs) models.
 
-use chrono::NaiveDate;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
 use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
 use serde::{Serialize, Deserialize};
 use typeshare::typeshare;
 
-use crate::schema::{products, freezers, drawers, storage};
+use crate::schema::{products, freezers, drawers, storage, idempotency_keys, categories, tags, product_tags, freezer_types, product_shelf_lives, product_barcodes, shopping_list_items, stocktakes, stocktake_items};
 
 // Query | Select
 
+/// Unit in which the quantity of a [Storage] item is expressed, stored as text.
+///
+/// Quantities in different units can't be compared or added up.
+#[typeshare]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "camelCase")]
+pub enum Unit {
+    /// Weight in grams, the unit of all items stored before units were introduced.
+    #[default]
+    Grams,
+    /// Volume in millilitres.
+    Millilitres,
+    /// Countable pieces, e.g. 6 hamburgers.
+    Pieces,
+    /// Portions, e.g. 2 portions of soup.
+    Portions,
+}
+impl Unit {
+    /// Name of the unit, as stored in the database and used in query parameters.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unit::Grams => "grams",
+            Unit::Millilitres => "millilitres",
+            Unit::Pieces => "pieces",
+            Unit::Portions => "portions",
+        }
+    }
+}
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit {
+            "grams" => Ok(Unit::Grams),
+            "millilitres" => Ok(Unit::Millilitres),
+            "pieces" => Ok(Unit::Pieces),
+            "portions" => Ok(Unit::Portions),
+            _ => Err(format!("Unknown unit {}, expected grams, millilitres, pieces or portions", unit)),
+        }
+    }
+}
+impl ToSql<Text, Pg> for Unit {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+impl FromSql<Text, Pg> for Unit {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let unit = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(unit.parse::<Unit>()?)
+    }
+}
+
+/// Why a [Storage] item was withdrawn from the freezer.
+#[typeshare]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "camelCase")]
+pub enum WithdrawalReason {
+    /// Eaten, the purpose of the freezer.
+    Eaten,
+    /// Thrown out, e.g. because of freezer burn.
+    Discarded,
+    /// Given to someone else.
+    GivenAway,
+    /// Not found during a stocktake, see [crate::routes::stocktakes].
+    Missing,
+}
+impl WithdrawalReason {
+    /// Name of the reason, as stored in the database and used in query parameters.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WithdrawalReason::Eaten => "eaten",
+            WithdrawalReason::Discarded => "discarded",
+            WithdrawalReason::GivenAway => "givenAway",
+            WithdrawalReason::Missing => "missing",
+        }
+    }
+}
+impl fmt::Display for WithdrawalReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl FromStr for WithdrawalReason {
+    type Err = String;
+
+    fn from_str(reason: &str) -> Result<Self, Self::Err> {
+        match reason {
+            "eaten" => Ok(WithdrawalReason::Eaten),
+            "discarded" => Ok(WithdrawalReason::Discarded),
+            "givenAway" => Ok(WithdrawalReason::GivenAway),
+            "missing" => Ok(WithdrawalReason::Missing),
+            _ => Err(format!("Unknown withdrawal reason {}, expected eaten, discarded, givenAway or missing", reason)),
+        }
+    }
+}
+impl ToSql<Text, Pg> for WithdrawalReason {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+impl FromSql<Text, Pg> for WithdrawalReason {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let reason = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(reason.parse::<WithdrawalReason>()?)
+    }
+}
+
 /// **For testing purposes.** Type representing a [Product] database entry as a tuple.
 pub type ProductTuple = (i32, &'static str, i32);
 
@@ -30,6 +156,9 @@ pub struct Product {
     pub name: String,
     /// Time until product expires, defined in whole months. Defaults to 6 months if not given.
     pub expiration_months: i32,
+    /// Unit used for new storage items of this product when none is given. Defaults to grams.
+    #[serde(default)]
+    pub default_unit: Unit,
 }
 impl Product {
     /// **For testing purposes.** Creates a product from a single tuple (statically
@@ -41,6 +170,7 @@ impl Product {
             product_id,
             name: name.into(),
             expiration_months,
+            default_unit: Unit::Grams,
         }
     }
     /// **For testing purposes.** Creates a vector of products from a vector of tuples (statically
@@ -50,7 +180,8 @@ impl Product {
             Product {
                 product_id,
                 name: name.into(),
-                expiration_months
+                expiration_months,
+                default_unit: Unit::Grams,
             }
         }).collect()
     }
@@ -148,6 +279,105 @@ impl Drawer {
     }
 }
 
+/// Product category database model, matching [crate::schema::categories].
+///
+/// Categories form a tree: a category without [Self::parent_id] is a top level category, e.g.
+/// "Meat" containing "Poultry". Products belong to at most one category.
+#[typeshare]
+#[derive(Debug, Clone, Serialize, Deserialize, Identifiable, Queryable, Selectable, AsChangeset, Eq, PartialEq)]
+#[diesel(primary_key(category_id))]
+#[diesel(table_name = categories)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+#[serde(rename_all = "camelCase")]
+pub struct Category {
+    /// Category id.
+    pub category_id: i32,
+    /// Category name, must be unique and not longer than 50 characters.
+    pub name: String,
+    /// Id of the parent category, `None` for top level categories.
+    pub parent_id: Option<i32>,
+}
+
+/// Tag database model, matching [crate::schema::tags].
+///
+/// Tags are free-form labels, a product can carry any number of them.
+#[typeshare]
+#[derive(Debug, Clone, Serialize, Deserialize, Identifiable, Queryable, Selectable, AsChangeset, Eq, PartialEq)]
+#[diesel(primary_key(tag_id))]
+#[diesel(table_name = tags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    /// Tag id.
+    pub tag_id: i32,
+    /// Tag name, must be unique and not longer than 50 characters.
+    pub name: String,
+}
+
+/// Freezer type database model, matching [crate::schema::freezer_types].
+///
+/// Freezer types, e.g. star ratings or chest and upright freezers, scale the shelf life of the
+/// products stored in freezers of that type, see [crate::core::shelf_life].
+#[typeshare]
+#[derive(Debug, Clone, Serialize, Deserialize, Identifiable, Queryable, Selectable, AsChangeset, PartialEq)]
+#[diesel(primary_key(freezer_type_id))]
+#[diesel(table_name = freezer_types)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct FreezerType {
+    /// Freezer type id.
+    pub freezer_type_id: i32,
+    /// Freezer type name, must be unique and not longer than 50 characters.
+    pub name: String,
+    /// Factor applied to the shelf life of products, e.g. 0.5 for a freezer keeping food half as long.
+    pub shelf_life_multiplier: f32,
+}
+
+/// Item added to the shopping list by hand, matching [crate::schema::shopping_list_items].
+///
+/// Products below their minimum stock are suggested without being added, see
+/// [crate::core::shopping_list].
+#[typeshare]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Identifiable, Queryable, Selectable)]
+#[diesel(primary_key(item_id))]
+#[diesel(table_name = shopping_list_items)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct ShoppingListItem {
+    /// Shopping list item id, serial number.
+    pub item_id: i32,
+    /// What to buy, the product name when linked to a product.
+    pub name: String,
+    /// Product bought, required to store the item once checked off.
+    pub product_id: Option<i32>,
+    /// Quantity to buy, in [Self::unit].
+    pub quantity: Option<f32>,
+    /// Unit of [Self::quantity].
+    pub unit: Option<Unit>,
+    /// Moment the item was added.
+    pub created_at: DateTime<Utc>,
+    /// Moment the item was checked off, `None` while still to buy.
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+/// Stocktake of a freezer, matching [crate::schema::stocktakes]. See [crate::routes::stocktakes].
+#[typeshare]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Identifiable, Queryable, Selectable)]
+#[diesel(primary_key(stocktake_id))]
+#[diesel(table_name = stocktakes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct Stocktake {
+    /// Stocktake id, serial number.
+    pub stocktake_id: i32,
+    /// Freezer being counted.
+    pub freezer_id: i32,
+    /// Moment the stocktake was started.
+    pub started_at: DateTime<Utc>,
+    /// Moment the stocktake was completed, `None` while still open.
+    pub completed_at: Option<DateTime<Utc>>,
+}
 
 /// **For testing purposes.** Type representing a [Storage] database entry as a tuple.
 pub type StorageTuple<'a> = (i32, i32, f32, &'a str, &'a str, i32);
@@ -173,18 +403,34 @@ pub struct Storage {
     pub product_id: i32,
     /// Location of the product in the storage.
     pub drawer_id: i32,
-    /// Weight of the product being stored, expressed in grams.
-    pub weight_grams: f32,
+    /// Quantity of the product being stored, expressed in [Self::unit]. Also accepted as `weightGrams`.
+    #[serde(alias = "weightGrams")]
+    pub quantity: f32,
+    /// Unit of [Self::quantity].
+    pub unit: Unit,
     /// Date of storage, defaults to the current date. Derived from `DateTime<Local>` and parsed into Date string.
     pub date_in: NaiveDate,
     /// Date taken out of storage.
     pub date_out: Option<NaiveDate>,
+    /// Explicit expiration date, e.g. a printed best-before date. Takes precedence over the date
+    /// computed from the shelf life, see [crate::core::shelf_life].
+    #[serde(default)]
+    pub expiration_date: Option<NaiveDate>,
+    /// Short unique code, generated by the database on insert, see [crate::core::item_code].
+    #[serde(default)]
+    pub code: String,
+    /// Why the item was withdrawn, `None` while in storage or when not given.
+    #[serde(default)]
+    pub withdrawal_reason: Option<WithdrawalReason>,
+    /// Free text note given when withdrawing, e.g. "freezer burn".
+    #[serde(default)]
+    pub withdrawal_note: Option<String>,
 }
 impl Storage {
     /// **For testing purposes.** Creates a storage item from a single tuple (statically
     /// defined in `tests/common/db_data.rs`).
     pub fn from_tuple(storage: StorageTuple) -> Storage {
-        let (storage_id, product_id, weight_grams, date_in, date_out, drawer_id) = storage;
+        let (storage_id, product_id, quantity, date_in, date_out, drawer_id) = storage;
         let date_in = NaiveDate::parse_from_str(date_in, "%Y-%m-%d").unwrap();
         // let date_in = DateTime::parse_from_str(format!("{} 12:00:00 +0200", date_in).as_str(), "%Y-%m-%d %H:%M:%S %z").unwrap().naive_utc().date();
         let date_out = match date_out {
@@ -196,16 +442,21 @@ impl Storage {
         Storage {
             storage_id,
             product_id,
-            weight_grams,
+            quantity,
+            unit: Unit::Grams,
             date_in,
             date_out,
             drawer_id,
+            expiration_date: None,
+            code: String::new(),
+            withdrawal_reason: None,
+            withdrawal_note: None,
         }
     }
     /// **For testing purposes.** Creates a vector of storage items from a vector of tuples (statically
     /// defined in `tests/common/db_data.rs`).
     pub fn from_vec(storages: Vec<StorageTuple>) -> Vec<Storage> {
-        storages.into_iter().map(|(storage_id, product_id, weight_grams, date_in, date_out,drawer_id)| {
+        storages.into_iter().map(|(storage_id, product_id, quantity, date_in, date_out,drawer_id)| {
             let date_in = NaiveDate::parse_from_str(date_in, "%Y-%m-%d").unwrap();
             // let date_in = DateTime::parse_from_str(format!("{} 12:00:00 +0200", date_in).as_str(), "%Y-%m-%d %H:%M:%S %z").unwrap().naive_utc().date();
             let date_out = match date_out {
@@ -217,22 +468,32 @@ impl Storage {
             Storage {
                 storage_id,
                 product_id,
-                weight_grams,
+                quantity,
+                unit: Unit::Grams,
                 date_in,
                 date_out,
                 drawer_id,
+                expiration_date: None,
+                code: String::new(),
+                withdrawal_reason: None,
+                withdrawal_note: None,
             }
         }).collect()
     }
 }
+// Codes are random, so they are left out.
 impl PartialEq for Storage {
     fn eq(&self, other: &Self) -> bool {
-        self.weight_grams - other.weight_grams < 1e-6 &&
+        self.quantity - other.quantity < 1e-6 &&
+            self.unit == other.unit &&
             self.storage_id == other.storage_id &&
             self.product_id == other.product_id &&
             self.drawer_id == other.drawer_id &&
             self.date_in == other.date_in &&
-            self.date_out == other.date_out
+            self.date_out == other.date_out &&
+            self.expiration_date == other.expiration_date &&
+            self.withdrawal_reason == other.withdrawal_reason &&
+            self.withdrawal_note == other.withdrawal_note
     }
 }
 
@@ -248,6 +509,9 @@ pub struct NewProduct {
     pub name: String,
     /// **Optional**: The time until expiration in months. Defaults to 6 months.
     pub expiration_months: Option<i32>,
+    /// **Optional**: Default unit of its storage items. Defaults to grams.
+    #[serde(default)]
+    pub default_unit: Option<Unit>,
 }
 
 /// Insertable storage item containing the required fields.
@@ -260,25 +524,36 @@ pub struct NewStorageItem {
     pub product_id: i32,
     /// **Required**: ID of the drawer in which the product will be stored.
     pub drawer_id: i32,
-    /// **Required**: The storage item weight, expressed in grams.
-    pub weight_grams: f32,
+    /// **Required**: The storage item quantity, expressed in [Self::unit]. Also accepted as
+    /// `weightGrams` from older clients.
+    #[serde(alias = "weightGrams")]
+    pub quantity: f32,
+    /// **Optional**: Unit of the quantity. Defaults to the [Product::default_unit].
+    #[serde(default)]
+    pub unit: Option<Unit>,
     /// **Required**: Date in
     pub date_in: NaiveDate,
+    /// **Optional**: Explicit expiration date, e.g. a printed best-before date. Computed from the
+    /// shelf life when not given.
+    #[serde(default)]
+    pub expiration_date: Option<NaiveDate>,
 }
 impl NewStorageItem {
-    /// Create new storage item. `date_in` is accepted as [Local] [DateTime].
+    /// Create new storage item, weighed in grams. `date_in` is accepted as [Local] [DateTime].
     pub fn from(product_id: i32, drawer_id: i32, weight_grams: f32, date_in: NaiveDate) -> Self {
-        let date_in = date_in;
         NewStorageItem {
             product_id,
             drawer_id,
-            weight_grams,
+            quantity: weight_grams,
+            unit: Some(Unit::Grams),
             date_in,
+            expiration_date: None,
         }
     }
 }
 
-/// Allows storage availability update. Required to be able to set date_out to `NULL`.
+/// Allows storage availability update. Required to be able to set date_out, and the withdrawal reason
+/// and note with it, to `NULL`.
 #[derive(Debug, Clone, Deserialize, Serialize, AsChangeset)]
 #[diesel(table_name = storage)]
 #[diesel(treat_none_as_null = true)]
@@ -286,6 +561,10 @@ impl NewStorageItem {
 pub struct UpdateStorageAvailability {
     /// [Storage] : field `date_out`
     pub date_out: Option<NaiveDate>,
+    /// [Storage] : field `withdrawal_reason`
+    pub withdrawal_reason: Option<WithdrawalReason>,
+    /// [Storage] : field `withdrawal_note`
+    pub withdrawal_note: Option<String>,
 }
 
 /// Insertable freezer containing the required fields.
@@ -308,4 +587,166 @@ pub struct NewDrawer {
     pub name: String,
     /// **Required**: Freezer id to which the drawer should be assigned to.
     pub freezer_id: i32,
-}
\ No newline at end of file
+}
+
+/// Insertable product category containing the required fields.
+#[typeshare]
+#[derive(Debug, Clone, Deserialize, Serialize, Insertable)]
+#[diesel(table_name = categories)]
+#[serde(rename_all = "camelCase")]
+pub struct NewCategory {
+    /// **Required, Unique**: Category name.
+    pub name: String,
+    /// **Optional**: Id of the parent category.
+    pub parent_id: Option<i32>,
+}
+
+/// Insertable tag containing the required fields.
+#[typeshare]
+#[derive(Debug, Clone, Deserialize, Serialize, Insertable)]
+#[diesel(table_name = tags)]
+#[serde(rename_all = "camelCase")]
+pub struct NewTag {
+    /// **Required, Unique**: Tag name.
+    pub name: String,
+}
+
+/// Insertable freezer type containing the required fields.
+#[typeshare]
+#[derive(Debug, Clone, Deserialize, Serialize, Insertable)]
+#[diesel(table_name = freezer_types)]
+#[serde(rename_all = "camelCase")]
+pub struct NewFreezerType {
+    /// **Required, Unique**: Freezer type name.
+    pub name: String,
+    /// **Optional**: Factor applied to the shelf life of products. Defaults to 1.
+    #[serde(default)]
+    pub shelf_life_multiplier: Option<f32>,
+}
+
+/// Insertable shopping list item containing the required fields.
+#[typeshare]
+#[derive(Debug, Clone, Deserialize, Serialize, Insertable)]
+#[diesel(table_name = shopping_list_items)]
+#[serde(rename_all = "camelCase")]
+pub struct NewShoppingListItem {
+    /// **Required** unless a product is given: what to buy. Defaults to the product name.
+    #[serde(default)]
+    pub name: String,
+    /// **Optional**: Product bought.
+    #[serde(default)]
+    pub product_id: Option<i32>,
+    /// **Optional**: Quantity to buy.
+    #[serde(default)]
+    pub quantity: Option<f32>,
+    /// **Optional**: Unit of the quantity.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+}
+
+/// Insertable stocktake containing the required fields.
+#[typeshare]
+#[derive(Debug, Clone, Deserialize, Serialize, Insertable)]
+#[diesel(table_name = stocktakes)]
+#[serde(rename_all = "camelCase")]
+pub struct NewStocktake {
+    /// **Required**: ID of the freezer to count.
+    pub freezer_id: i32,
+}
+
+/// Storage item found, or withdrawn as missing, during a stocktake, matching [crate::schema::stocktake_items].
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = stocktake_items)]
+pub struct NewStocktakeItem {
+    /// [Stocktake] the item belongs to.
+    pub stocktake_id: i32,
+    /// [Storage] item.
+    pub storage_id: i32,
+    /// Moment the item was found, `None` for items withdrawn as missing.
+    pub seen_at: Option<DateTime<Utc>>,
+}
+
+/// Link between a [Product] and a [Tag], matching [crate::schema::product_tags].
+#[derive(Debug, Clone, Identifiable, Queryable, Selectable, Insertable, Associations)]
+#[diesel(primary_key(product_id, tag_id))]
+#[diesel(belongs_to(Product, foreign_key = product_id))]
+#[diesel(belongs_to(Tag, foreign_key = tag_id))]
+#[diesel(table_name = product_tags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProductTag {
+    /// Id of the tagged product.
+    pub product_id: i32,
+    /// Id of the tag.
+    pub tag_id: i32,
+}
+
+/// Barcode of a [Product], matching [crate::schema::product_barcodes].
+///
+/// Barcodes are stored as EAN-13, see [crate::core::barcode].
+#[derive(Debug, Clone, Identifiable, Queryable, Selectable, Insertable, Associations)]
+#[diesel(primary_key(barcode))]
+#[diesel(belongs_to(Product, foreign_key = product_id))]
+#[diesel(table_name = product_barcodes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProductBarcode {
+    /// EAN-13 barcode.
+    pub barcode: String,
+    /// Id of the product.
+    pub product_id: i32,
+}
+
+/// Shelf life of a [Product] in freezers of a [FreezerType], matching [crate::schema::product_shelf_lives].
+///
+/// Replaces the product shelf life multiplied by [FreezerType::shelf_life_multiplier].
+#[typeshare]
+#[derive(Debug, Clone, Serialize, Deserialize, Identifiable, Queryable, Selectable, Insertable, Associations, PartialEq, Eq)]
+#[diesel(primary_key(product_id, freezer_type_id))]
+#[diesel(belongs_to(Product, foreign_key = product_id))]
+#[diesel(belongs_to(FreezerType, foreign_key = freezer_type_id))]
+#[diesel(table_name = product_shelf_lives)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct ShelfLifeOverride {
+    /// Product id. Ignored when sent by the frontend, the id in the path is used.
+    #[serde(default)]
+    pub product_id: i32,
+    /// Id of the freezer type.
+    pub freezer_type_id: i32,
+    /// Shelf life of the product in this freezer type, in days.
+    pub shelf_life_days: i32,
+}
+
+/// Stored idempotent request, matching [crate::schema::idempotency_keys].
+///
+/// The response fields stay empty while the original request is still being processed.
+/// See [crate::core::idempotency].
+#[derive(Debug, Clone, Identifiable, Queryable, Selectable)]
+#[diesel(primary_key(idempotency_key))]
+#[diesel(table_name = idempotency_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IdempotencyRecord {
+    /// Key as sent by the client in the `Idempotency-Key` header.
+    pub idempotency_key: String,
+    /// Hash of the request method, uri and body.
+    pub request_fingerprint: String,
+    /// Status code of the stored response.
+    pub response_status: Option<i32>,
+    /// Body of the stored response.
+    pub response_body: Option<Vec<u8>>,
+    /// `Content-Type` header of the stored response.
+    pub response_content_type: Option<String>,
+    /// `ETag` header of the stored response.
+    pub response_etag: Option<String>,
+    /// Moment the key was first received.
+    pub created_at: DateTime<Utc>,
+}
+
+/// Insertable idempotency key, reserving the key while the request is processed.
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = idempotency_keys)]
+pub struct NewIdempotencyRecord {
+    /// **Required, Unique**: Key as sent by the client.
+    pub idempotency_key: String,
+    /// **Required**: Hash of the request method, uri and body.
+    pub request_fingerprint: String,
+}
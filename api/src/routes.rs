@@ -4,3 +4,18 @@ pub mod freezers;
 pub mod drawers;
 pub mod products;
 pub mod storage;
+pub mod trash;
+pub mod events;
+pub mod categories;
+pub mod tags;
+pub mod freezer_types;
+pub mod labels;
+pub mod barcodes;
+pub mod shopping_list;
+pub mod recommendations;
+pub mod analytics;
+pub mod capacities;
+pub mod summary;
+pub mod stocktakes;
+pub mod import;
+pub mod backup;
@@ -0,0 +1,177 @@
+//! Endpoint `/api/analytics`, implements `GET` on the consumption and waste statistics.
+//!
+//! See [crate::core::analytics] for how the statistics are computed.
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use chrono::{Local, Months, NaiveDate};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    core::{
+        analytics::{per_freezer, per_month, per_product, DateRange, ItemRecord, Stats},
+        connection::establish_connection,
+        error::internal_error,
+        etag::conditional_json,
+        name::{lower, normalize_name},
+        query::empty_string_as_none,
+        shelf_life::ShelfLifeRules,
+    },
+    models::{Freezer, Product, Storage},
+    routes::{categories::category_ids_with_descendants, storage::split_list},
+    schema::{drawers, freezers, products, storage},
+    AppState,
+};
+
+/// Query parameters of the analytics endpoints.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsQuery {
+    /// First day of the range, `<YYYY>-<MM>-<DD>`. Defaults to twelve months before [Self::to].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub from: Option<NaiveDate>,
+    /// Last day of the range, `<YYYY>-<MM>-<DD>`. Defaults to today.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub to: Option<NaiveDate>,
+    /// Name of the product, only its items are counted.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub product_name: Option<String>,
+    /// Comma separated category names. Only products in any of these categories or their
+    /// subcategories are counted.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub category: Option<String>,
+}
+
+impl AnalyticsQuery {
+    /// Date range of the query, filling in the defaults.
+    pub fn range(&self) -> Result<DateRange, (StatusCode, String)> {
+        let to = self.to.unwrap_or_else(|| Local::now().date_naive());
+        let from = match self.from {
+            Some(from) => from,
+            None => to
+                .checked_sub_months(Months::new(12))
+                .ok_or_else(|| (StatusCode::BAD_REQUEST, String::from("to is out of range")))?,
+        };
+        DateRange::new(from, to).map_err(|e| (StatusCode::BAD_REQUEST, e))
+    }
+}
+
+/// Loads the storage items that entered the freezer before the end of `range`, with the filters of `params`.
+fn load_records(conn: &mut PgConnection, params: &AnalyticsQuery, range: &DateRange) -> Result<Vec<ItemRecord>, (StatusCode, String)> {
+    let mut query = storage::table
+        .inner_join(products::table)
+        .inner_join(drawers::table.inner_join(freezers::table))
+        .filter(storage::deleted_at.is_null())
+        .filter(storage::date_in.le(range.to))
+        .select((Storage::as_select(), Product::as_select(), Freezer::as_select()))
+        .into_boxed();
+    if let Some(product_name) = &params.product_name {
+        query = query.filter(lower(products::name).eq(lower(normalize_name(product_name))));
+    }
+    if let Some(category) = &params.category {
+        let category_ids = category_ids_with_descendants(conn, &split_list(category)).map_err(internal_error)?;
+        query = query.filter(products::category_id.eq_any(category_ids));
+    }
+    let rows = query
+        .load::<(Storage, Product, Freezer)>(conn)
+        .map_err(internal_error)?;
+
+    let rules = ShelfLifeRules::load(conn).map_err(internal_error)?;
+    Ok(rows
+        .into_iter()
+        .map(|(item, product, freezer)| {
+            let expiration = rules.storage_expiration(&item, &product, freezer.freezer_id);
+            ItemRecord {
+                product_id: product.product_id,
+                product_name: product.name,
+                freezer_id: freezer.freezer_id,
+                freezer_name: freezer.name,
+                quantity: item.quantity,
+                unit: item.unit,
+                date_in: item.date_in,
+                date_out: item.date_out,
+                expiration_date: expiration.date_expires,
+                withdrawal_reason: item.withdrawal_reason,
+            }
+        })
+        .collect())
+}
+
+/// Get the statistics of all items over a date range: `GET /api/analytics`.
+///
+/// # Accepted query parameters
+///
+/// * `from=<YYYY-MM-DD>` **(defaults to twelve months before `to`)**: first day of the range.
+/// * `to=<YYYY-MM-DD>` **(defaults to today)**: last day of the range.
+/// * `productName=<String>`: Name of the product.
+/// * `category=<String>[,<String>]`: Products in any of the categories, subcategories included.
+///
+/// # Returns
+///
+/// [Stats]. Honors `If-None-Match`, see [crate::core::etag].
+///
+/// # Errors
+///
+/// * `BadRequest` => "from cannot be later than to" or "The range cannot span more than 10 years".
+pub async fn get_analytics(State(state): State<AppState>, headers: HeaderMap, params: Query<AnalyticsQuery>) -> Result<Response, (StatusCode, String)> {
+    let range = params.range()?;
+    let conn = &mut establish_connection(state.db_url);
+
+    let records = load_records(conn, &params, &range)?;
+
+    Ok(conditional_json(&headers, Stats::of(&records, &range)))
+}
+
+/// Get the statistics per product: `GET /api/analytics/products`.
+///
+/// Accepts the same query parameters as [get_analytics]. Products without items frozen or withdrawn
+/// in the range are left out.
+///
+/// # Returns
+///
+/// Vec<[crate::core::analytics::ProductStats]>, sorted by product name. Honors `If-None-Match`.
+pub async fn get_product_analytics(State(state): State<AppState>, headers: HeaderMap, params: Query<AnalyticsQuery>) -> Result<Response, (StatusCode, String)> {
+    let range = params.range()?;
+    let conn = &mut establish_connection(state.db_url);
+
+    let records = load_records(conn, &params, &range)?;
+
+    Ok(conditional_json(&headers, per_product(&records, &range)))
+}
+
+/// Get the statistics per freezer: `GET /api/analytics/freezers`.
+///
+/// Accepts the same query parameters as [get_analytics]. Items count for the freezer they are in now,
+/// freezers without items frozen or withdrawn in the range are left out.
+///
+/// # Returns
+///
+/// Vec<[crate::core::analytics::FreezerStats]>, sorted by freezer name. Honors `If-None-Match`.
+pub async fn get_freezer_analytics(State(state): State<AppState>, headers: HeaderMap, params: Query<AnalyticsQuery>) -> Result<Response, (StatusCode, String)> {
+    let range = params.range()?;
+    let conn = &mut establish_connection(state.db_url);
+
+    let records = load_records(conn, &params, &range)?;
+
+    Ok(conditional_json(&headers, per_freezer(&records, &range)))
+}
+
+/// Get the statistics per calendar month: `GET /api/analytics/trends`.
+///
+/// Accepts the same query parameters as [get_analytics]. The first and last month only count the
+/// days inside the range.
+///
+/// # Returns
+///
+/// Vec<[crate::core::analytics::MonthStats]>, one per month, oldest first. Honors `If-None-Match`.
+pub async fn get_analytics_trends(State(state): State<AppState>, headers: HeaderMap, params: Query<AnalyticsQuery>) -> Result<Response, (StatusCode, String)> {
+    let range = params.range()?;
+    let conn = &mut establish_connection(state.db_url);
+
+    let records = load_records(conn, &params, &range)?;
+
+    Ok(conditional_json(&headers, per_month(&records, &range)))
+}
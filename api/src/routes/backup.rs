@@ -0,0 +1,92 @@
+//! Endpoints `/api/export` and `/api/restore`, to back up all data and restore it.
+//!
+//! See [crate::core::backup] for the format of a dump.
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::{
+    core::{
+        backup::{export, export_csv_zip, restore, Dump, ExportFormat, RestoreReport},
+        connection::establish_connection,
+        error::internal_error,
+        query::empty_string_as_none,
+    },
+    AppState,
+};
+
+/// Maximum size of a dump sent to [restore_dump], in bytes.
+pub const MAX_DUMP_SIZE: usize = 256 * 1024 * 1024;
+
+/// Allowed query parameters when exporting.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportQuery {
+    /// Format of the export, defaults to [ExportFormat::Json].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub format: Option<ExportFormat>,
+}
+
+/// Headers of a downloaded export, named after the current date.
+fn download_headers(content_type: &'static str, extension: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    let file_name = format!("attachment; filename=\"freezit-{}.{}\"", Local::now().date_naive(), extension);
+    if let Ok(disposition) = HeaderValue::from_str(&file_name) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+
+    headers
+}
+
+/// Export all data: `GET /api/export`.
+///
+/// # Accepted query parameters
+///
+/// * `format=<json|csv>` **(defaults to json)**.
+///
+/// # Returns
+///
+/// A [Dump] in `application/json`, or a ZIP with a CSV file per table for `csv`. Both are sent as a
+/// download named after the current date.
+pub async fn get_export(State(state): State<AppState>, params: Query<ExportQuery>) -> Result<Response, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    match params.format.unwrap_or_default() {
+        ExportFormat::Json => {
+            let dump = export(conn).map_err(internal_error)?;
+            Ok((download_headers("application/json", "json"), Json(dump)).into_response())
+        }
+        ExportFormat::Csv => {
+            let zip = export_csv_zip(conn)?;
+            Ok((download_headers("application/zip", "zip"), zip).into_response())
+        }
+    }
+}
+
+/// Restore all data from a JSON export: `POST /api/restore`.
+///
+/// The database must be empty, e.g. freshly created. Ids are preserved. Connected clients are not
+/// notified, they should reload.
+///
+/// # Required body
+///
+/// [Dump] in `application/json`, as returned by [get_export]. At most [MAX_DUMP_SIZE] bytes.
+///
+/// # Returns
+///
+/// [RestoreReport] with the amount of rows restored per table.
+///
+/// # Errors
+///
+/// * `BadRequest` => "Unsupported dump version <u32>, expected <u32>" or "Unknown table <String> in dump".
+/// * `Conflict` => "Restoring requires an empty database".
+pub async fn restore_dump(State(state): State<AppState>, dump: Json<Dump>) -> Result<Json<RestoreReport>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    Ok(Json(restore(conn, &dump)?))
+}
@@ -0,0 +1,211 @@
+//! Barcodes of products: `GET` and `PATCH` on `/api/products/id=<i32>/barcodes`, lookup on
+//! `/api/products/barcode/<String>` and scanning items into storage on `POST /api/storage/scan`.
+//!
+//! Barcodes are validated and stored as EAN-13, see [crate::core::barcode].
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{Local, NaiveDate};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    core::{
+        barcode,
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+    },
+    models::{NewStorageItem, Product, ProductBarcode, Unit},
+    routes::{
+        storage::{create_storage, StorageResponse},
+        tags::check_product_active,
+    },
+    schema::{product_barcodes, products},
+    AppState,
+};
+
+/// Storage item to create from a scanned barcode: `POST /api/storage/scan`.
+#[typeshare]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanStorageItem {
+    /// **Required**: Scanned EAN-13 or UPC-A barcode.
+    pub barcode: String,
+    /// **Required**: ID of the drawer in which the product will be stored.
+    pub drawer_id: i32,
+    /// **Optional**: Quantity, defaults to 1. A single item is counted in [Unit::Pieces] when no unit is given either.
+    #[serde(default)]
+    pub quantity: Option<f32>,
+    /// **Optional**: Unit of the quantity, defaults to the [Product::default_unit] when a quantity is given.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+    /// **Optional**: Date in, defaults to the current date.
+    #[serde(default)]
+    pub date_in: Option<NaiveDate>,
+    /// **Optional**: Explicit expiration date, e.g. the printed best-before date.
+    #[serde(default)]
+    pub expiration_date: Option<NaiveDate>,
+}
+
+/// Get the product with a barcode: `GET /api/products/barcode/<String>`.
+///
+/// # Returns
+///
+/// The [Product], in format `application/json`.
+///
+/// # Errors
+///
+/// * `BadRequest`: not a valid EAN-13 or UPC-A code.
+/// * `NotFound` => "No product with barcode <String>".
+pub async fn get_product_by_barcode(State(state): State<AppState>, Path(code): Path<String>) -> Result<Json<Product>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    Ok(Json(product_by_barcode(conn, &code)?))
+}
+
+/// Get the barcodes of a product: `GET /api/products/id=<i32>/barcodes`.
+///
+/// # Returns
+///
+/// The EAN-13 barcodes as a json array of strings, sorted.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+pub async fn get_product_barcodes(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    check_product_active(conn, id)?;
+
+    let result = product_barcodes::table
+        .filter(product_barcodes::product_id.eq(id))
+        .select(product_barcodes::barcode)
+        .order_by(product_barcodes::barcode)
+        .load::<String>(conn)
+        .map_err(internal_error)?;
+
+    Ok(Json(result))
+}
+
+/// Replaces the barcodes of a product: `PATCH /api/products/id=<i32>/barcodes`.
+///
+/// # Required body
+///
+/// The barcodes as a json array of strings, e.g. `["4006381333931", "036000291452"]`. UPC-A codes are
+/// stored as EAN-13. Barcodes of products in the trash move to this product.
+///
+/// # Returns
+///
+/// The new EAN-13 barcodes of the product, sorted.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+/// * `BadRequest`: a barcode is not a valid EAN-13 or UPC-A code.
+/// * `Conflict` => "Barcode <String> already belongs to another product".
+pub async fn set_product_barcodes(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    codes: Json<Vec<String>>,
+) -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let mut codes = codes
+        .iter()
+        .map(|code| barcode::normalize(code))
+        .collect::<Result<Vec<String>, String>>()
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    codes.sort();
+    codes.dedup();
+
+    conn.transaction::<_, TransactionError, _>(|conn| {
+        check_product_active(conn, id)?;
+
+        let taken = product_barcodes::table
+            .inner_join(products::table)
+            .filter(product_barcodes::barcode.eq_any(&codes))
+            .filter(product_barcodes::product_id.ne(id))
+            .filter(products::deleted_at.is_null())
+            .select(product_barcodes::barcode)
+            .first::<String>(conn)
+            .optional()?;
+        if let Some(code) = taken {
+            return Err((StatusCode::CONFLICT, format!("Barcode {} already belongs to another product", code)).into());
+        }
+
+        diesel::delete(
+            product_barcodes::table.filter(product_barcodes::product_id.eq(id).or(product_barcodes::barcode.eq_any(&codes)))
+        ).execute(conn)?;
+        let links: Vec<ProductBarcode> = codes
+            .iter()
+            .map(|code| ProductBarcode { barcode: code.clone(), product_id: id })
+            .collect();
+        diesel::insert_into(product_barcodes::table)
+            .values(&links)
+            .execute(conn)?;
+
+        Ok(())
+    })?;
+
+    state.events.publish(Entity::Product, ChangeAction::Updated, id);
+
+    Ok(Json(codes))
+}
+
+/// Create a storage item from a scanned barcode: `POST /api/storage/scan`.
+///
+/// # Required body
+///
+/// [ScanStorageItem]
+///
+/// # Returns
+///
+/// Same as [create_storage].
+///
+/// # Errors
+///
+/// * `BadRequest`: not a valid EAN-13 or UPC-A code.
+/// * `NotFound` => "No product with barcode <String>".
+/// * Same as [create_storage].
+pub async fn scan_storage(
+    State(state): State<AppState>,
+    scanned: Json<ScanStorageItem>,
+) -> Result<(HeaderMap, Json<Vec<StorageResponse>>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let product = product_by_barcode(conn, &scanned.barcode)?;
+
+    let (quantity, unit) = match (scanned.quantity, scanned.unit) {
+        (None, None) => (1.0, Some(Unit::Pieces)),
+        (quantity, unit) => (quantity.unwrap_or(1.0), unit),
+    };
+    let new_storage_item = NewStorageItem {
+        product_id: product.product_id,
+        drawer_id: scanned.drawer_id,
+        quantity,
+        unit,
+        date_in: scanned.date_in.unwrap_or_else(|| Local::now().date_naive()),
+        expiration_date: scanned.expiration_date,
+    };
+
+    create_storage(State(state), Json(new_storage_item)).await
+}
+
+/// Product with a barcode, not in the trash.
+fn product_by_barcode(conn: &mut PgConnection, code: &str) -> Result<Product, (StatusCode, String)> {
+    let code = barcode::normalize(code).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+
+    product_barcodes::table
+        .inner_join(products::table)
+        .filter(product_barcodes::barcode.eq(&code))
+        .filter(products::deleted_at.is_null())
+        .select(Product::as_select())
+        .first::<Product>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No product with barcode {}", code)))
+}
+
@@ -0,0 +1,341 @@
+//! Capacity of freezers and drawers: `GET` and `PATCH` on `/api/freezers/id=<i32>/capacity` and
+//! `/api/drawers/<i32>/capacity`, and their fill levels: `GET /api/freezers/fill-levels`.
+//!
+//! See [crate::core::capacity] for how fill levels are computed.
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        capacity::{check_unit, FillLevel},
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+        etag::{check_if_match, conditional_json, version_header},
+    },
+    models::Unit,
+    schema::{drawers, freezers, storage},
+    AppState,
+};
+
+/// Capacity of a single freezer, used to read and change it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezerCapacity {
+    /// Freezer id. Ignored when sent by the frontend, the id in the path is used.
+    #[serde(default)]
+    pub freezer_id: i32,
+    /// Maximum quantity the freezer holds, `None` when not declared.
+    pub capacity: Option<f32>,
+    /// Unit of the capacity, grams or millilitres. Defaults to grams when changing it.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+}
+
+/// Capacity of a single drawer, used to read and change it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DrawerCapacity {
+    /// Drawer id. Ignored when sent by the frontend, the id in the path is used.
+    #[serde(default)]
+    pub drawer_id: i32,
+    /// Maximum quantity the drawer holds, `None` when not declared.
+    pub capacity: Option<f32>,
+    /// Unit of the capacity, grams or millilitres. Defaults to grams when changing it.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+}
+
+/// Fill level of a drawer, see [get_fill_levels].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DrawerFillLevel {
+    /// Id of the drawer.
+    pub drawer_id: i32,
+    /// Name of the drawer.
+    pub drawer_name: String,
+    /// Fill level of the drawer.
+    #[serde(flatten)]
+    pub level: FillLevel,
+}
+
+/// Fill level of a freezer and its drawers, see [get_fill_levels].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezerFillLevel {
+    /// Id of the freezer.
+    pub freezer_id: i32,
+    /// Name of the freezer.
+    pub freezer_name: String,
+    /// Fill level of the freezer, all its drawers together.
+    #[serde(flatten)]
+    pub level: FillLevel,
+    /// Fill levels of the drawers of the freezer, sorted by name.
+    pub drawers: Vec<DrawerFillLevel>,
+}
+
+/// Drawer as loaded for [get_fill_levels]: id, freezer id, name, capacity and capacity unit.
+type DrawerRow = (i32, i32, String, Option<f32>, Option<Unit>);
+
+/// Validates a capacity to be set and fills in its unit.
+fn checked_capacity(capacity: Option<f32>, unit: Option<Unit>) -> Result<Option<Unit>, (StatusCode, String)> {
+    let Some(capacity) = capacity else {
+        return Ok(None);
+    };
+    if !capacity.is_finite() || capacity <= 0.0 {
+        return Err((StatusCode::BAD_REQUEST, String::from("capacity must be positive")));
+    }
+    let unit = unit.unwrap_or(Unit::Grams);
+    check_unit(unit).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(Some(unit))
+}
+
+fn freezer_not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, String::from("Freezer not found"))
+}
+
+fn drawer_not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, String::from("Drawer not found"))
+}
+
+/// Get the capacity of a freezer: `GET /api/freezers/id=<i32>/capacity`.
+///
+/// # Returns
+///
+/// [FreezerCapacity], in format `application/json`, with the version of the freezer as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer not found".
+pub async fn get_freezer_capacity(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<FreezerCapacity>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let (capacity, unit, freezer_version) = freezers::table
+        .find(id)
+        .filter(freezers::deleted_at.is_null())
+        .select((freezers::capacity, freezers::capacity_unit, freezers::version))
+        .first::<(Option<f32>, Option<Unit>, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(freezer_not_found)?;
+
+    Ok((version_header(freezer_version), Json(FreezerCapacity { freezer_id: id, capacity, unit })))
+}
+
+/// Changes the capacity of a freezer: `PATCH /api/freezers/id=<i32>/capacity`.
+///
+/// # Required body
+///
+/// [FreezerCapacity] in `application/json`, a `capacity` of `null` removes the capacity.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the freezer as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The updated [FreezerCapacity], with the new version of the freezer as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer not found".
+/// * `BadRequest` => "capacity must be positive" or "capacity unit must be grams or millilitres".
+/// * `PreconditionFailed` => "This item was modified by another request".
+pub async fn set_freezer_capacity(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    capacity: Json<FreezerCapacity>,
+) -> Result<(HeaderMap, Json<FreezerCapacity>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let unit = checked_capacity(capacity.capacity, capacity.unit)?;
+
+    let update_version = conn.transaction::<_, TransactionError, _>(|conn| {
+        let current_version = freezers::table
+            .find(id)
+            .filter(freezers::deleted_at.is_null())
+            .select(freezers::version)
+            .for_update()
+            .first::<i32>(conn)
+            .optional()?
+            .ok_or_else(freezer_not_found)?;
+        check_if_match(&headers, current_version)?;
+
+        Ok(diesel::update(freezers::table.find(id))
+            .set((
+                freezers::capacity.eq(capacity.capacity),
+                freezers::capacity_unit.eq(unit),
+                freezers::version.eq(freezers::version + 1),
+            ))
+            .returning(freezers::version)
+            .get_result::<i32>(conn)?)
+    })?;
+
+    state.events.publish(Entity::Freezer, ChangeAction::Updated, id);
+
+    Ok((version_header(update_version), Json(FreezerCapacity { freezer_id: id, capacity: capacity.capacity, unit })))
+}
+
+/// Get the capacity of a drawer: `GET /api/drawers/<i32>/capacity`.
+///
+/// # Returns
+///
+/// [DrawerCapacity], in format `application/json`, with the version of the drawer as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Drawer not found".
+pub async fn get_drawer_capacity(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<DrawerCapacity>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let (capacity, unit, drawer_version) = drawers::table
+        .find(id)
+        .filter(drawers::deleted_at.is_null())
+        .select((drawers::capacity, drawers::capacity_unit, drawers::version))
+        .first::<(Option<f32>, Option<Unit>, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(drawer_not_found)?;
+
+    Ok((version_header(drawer_version), Json(DrawerCapacity { drawer_id: id, capacity, unit })))
+}
+
+/// Changes the capacity of a drawer: `PATCH /api/drawers/<i32>/capacity`.
+///
+/// # Required body
+///
+/// [DrawerCapacity] in `application/json`, a `capacity` of `null` removes the capacity.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the drawer as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The updated [DrawerCapacity], with the new version of the drawer as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Drawer not found".
+/// * `BadRequest` => "capacity must be positive" or "capacity unit must be grams or millilitres".
+/// * `PreconditionFailed` => "This item was modified by another request".
+pub async fn set_drawer_capacity(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    capacity: Json<DrawerCapacity>,
+) -> Result<(HeaderMap, Json<DrawerCapacity>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let unit = checked_capacity(capacity.capacity, capacity.unit)?;
+
+    let update_version = conn.transaction::<_, TransactionError, _>(|conn| {
+        let current_version = drawers::table
+            .find(id)
+            .filter(drawers::deleted_at.is_null())
+            .select(drawers::version)
+            .for_update()
+            .first::<i32>(conn)
+            .optional()?
+            .ok_or_else(drawer_not_found)?;
+        check_if_match(&headers, current_version)?;
+
+        Ok(diesel::update(drawers::table.find(id))
+            .set((
+                drawers::capacity.eq(capacity.capacity),
+                drawers::capacity_unit.eq(unit),
+                drawers::version.eq(drawers::version + 1),
+            ))
+            .returning(drawers::version)
+            .get_result::<i32>(conn)?)
+    })?;
+
+    state.events.publish(Entity::Drawer, ChangeAction::Updated, id);
+
+    Ok((version_header(update_version), Json(DrawerCapacity { drawer_id: id, capacity: capacity.capacity, unit })))
+}
+
+/// Fill level of a drawer, with the quantity and unit of its available items.
+pub fn drawer_fill_level(conn: &mut PgConnection, id: i32) -> QueryResult<FillLevel> {
+    let (capacity, unit) = drawers::table
+        .find(id)
+        .select((drawers::capacity, drawers::capacity_unit))
+        .first::<(Option<f32>, Option<Unit>)>(conn)?;
+    let items = storage::table
+        .filter(storage::drawer_id.eq(id))
+        .filter(storage::date_out.is_null())
+        .filter(storage::deleted_at.is_null())
+        .select((storage::quantity, storage::unit))
+        .load::<(f32, Unit)>(conn)?;
+
+    Ok(FillLevel::new(capacity.zip(unit), items))
+}
+
+/// Get the fill levels of all freezers and their drawers: `GET /api/freezers/fill-levels`.
+///
+/// Only available items count, see [crate::core::capacity].
+///
+/// # Returns
+///
+/// Vec<[FreezerFillLevel]>, sorted by freezer name. Honors `If-None-Match`, see [crate::core::etag].
+pub async fn get_fill_levels(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let all_freezers = freezers::table
+        .filter(freezers::deleted_at.is_null())
+        .order_by(freezers::name)
+        .select((freezers::freezer_id, freezers::name, freezers::capacity, freezers::capacity_unit))
+        .load::<(i32, String, Option<f32>, Option<Unit>)>(conn)
+        .map_err(internal_error)?;
+    let all_drawers = drawers::table
+        .filter(drawers::deleted_at.is_null())
+        .order_by(drawers::name)
+        .select((drawers::drawer_id, drawers::freezer_id, drawers::name, drawers::capacity, drawers::capacity_unit))
+        .load::<DrawerRow>(conn)
+        .map_err(internal_error)?;
+    let mut items: HashMap<i32, Vec<(f32, Unit)>> = HashMap::new();
+    for (drawer_id, quantity, unit) in storage::table
+        .filter(storage::date_out.is_null())
+        .filter(storage::deleted_at.is_null())
+        .select((storage::drawer_id, storage::quantity, storage::unit))
+        .load::<(i32, f32, Unit)>(conn)
+        .map_err(internal_error)?
+    {
+        items.entry(drawer_id).or_default().push((quantity, unit));
+    }
+
+    let fill_levels: Vec<FreezerFillLevel> = all_freezers
+        .into_iter()
+        .map(|(freezer_id, freezer_name, capacity, unit)| {
+            let freezer_drawers: Vec<&DrawerRow> = all_drawers
+                .iter()
+                .filter(|drawer| drawer.1 == freezer_id)
+                .collect();
+            let freezer_items = freezer_drawers
+                .iter()
+                .flat_map(|drawer| items.get(&drawer.0).cloned().unwrap_or_default());
+            FreezerFillLevel {
+                freezer_id,
+                freezer_name,
+                level: FillLevel::new(capacity.zip(unit), freezer_items),
+                drawers: freezer_drawers
+                    .iter()
+                    .map(|(drawer_id, _, drawer_name, capacity, unit)| DrawerFillLevel {
+                        drawer_id: *drawer_id,
+                        drawer_name: drawer_name.clone(),
+                        level: FillLevel::new(capacity.zip(*unit), items.get(drawer_id).cloned().unwrap_or_default()),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(conditional_json(&headers, fill_levels))
+}
@@ -0,0 +1,309 @@
+//! Endpoint `/api/categories`, implements `GET`, `POST`, `PATCH`, `DELETE`.
+//!
+//! Also implements assigning a category to a product: `GET` and `PATCH` on
+//! `/api/products/id=<i32>/category`.
+use std::collections::HashSet;
+use std::ops::Deref;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+        etag::{check_if_match, conditional_json, version_header},
+    },
+    models::{Category, NewCategory},
+    schema::{categories, products},
+    AppState,
+};
+
+/// Category of a single product, used to read and change it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductCategory {
+    /// Product id. Ignored when sent by the frontend, the id in the path is used.
+    #[serde(default)]
+    pub product_id: i32,
+    /// Id of the assigned category, `None` when the product is uncategorized.
+    pub category_id: Option<i32>,
+}
+
+/// Ids of the categories with the given names, along with all their subcategories.
+///
+/// Unknown names are ignored.
+pub fn category_ids_with_descendants(conn: &mut PgConnection, names: &[String]) -> QueryResult<Vec<i32>> {
+    let all_categories = categories::table
+        .select(Category::as_select())
+        .load::<Category>(conn)?;
+
+    let mut ids: HashSet<i32> = all_categories
+        .iter()
+        .filter(|category| names.contains(&category.name))
+        .map(|category| category.category_id)
+        .collect();
+    // Add children until no new ones are found, the tree is small.
+    loop {
+        let children: Vec<i32> = all_categories
+            .iter()
+            .filter(|category| !ids.contains(&category.category_id))
+            .filter(|category| category.parent_id.is_some_and(|parent| ids.contains(&parent)))
+            .map(|category| category.category_id)
+            .collect();
+        if children.is_empty() {
+            break;
+        }
+        ids.extend(children);
+    }
+
+    Ok(ids.into_iter().collect())
+}
+
+/// Checks that the parent of a category exists and does not create a cycle.
+fn check_parent(conn: &mut PgConnection, id: Option<i32>, parent_id: Option<i32>) -> Result<(), (StatusCode, String)> {
+    let mut ancestor = parent_id;
+
+    while let Some(ancestor_id) = ancestor {
+        if Some(ancestor_id) == id {
+            return Err((StatusCode::BAD_REQUEST, String::from("A category cannot be its own subcategory")));
+        }
+        ancestor = categories::table
+            .find(ancestor_id)
+            .select(categories::parent_id)
+            .first::<Option<i32>>(conn)
+            .optional()
+            .map_err(internal_error)?
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, String::from("Parent category not found")))?;
+    }
+
+    Ok(())
+}
+
+/// Get all categories: `GET /api/categories`.
+///
+/// # Returns
+///
+/// Vec<[Category]>, in format `application/json`. The tree is built from [Category::parent_id].
+/// Honors `If-None-Match`, see [crate::core::etag].
+pub async fn get_categories(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let result = categories::table
+        .select(Category::as_select())
+        .order_by(categories::category_id)
+        .load::<Category>(conn)
+        .map_err(internal_error)?;
+
+    Ok(conditional_json(&headers, result))
+}
+
+/// Create a new category: `POST /api/categories`.
+///
+/// # Required body
+///
+/// [NewCategory] model in `application/json`.
+///
+/// # Returns
+///
+/// The new [Category].
+///
+/// # Errors
+///
+/// * `Duplicate` => "This category name already exists".
+/// * `BadRequest` => "Parent category not found".
+pub async fn create_category(State(state): State<AppState>, new_category: Json<NewCategory>) -> Result<Json<Category>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let new_category = new_category.deref().to_owned();
+
+    check_parent(conn, None, new_category.parent_id)?;
+
+    let name_count = categories::table
+        .filter(categories::name.eq(&new_category.name))
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if name_count > 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("This category name already exists")));
+    }
+
+    let result = diesel::insert_into(categories::table)
+        .values(new_category)
+        .returning(Category::as_returning())
+        .get_result(conn)
+        .map_err(internal_error)?;
+
+    state.events.publish(Entity::Category, ChangeAction::Created, result.category_id);
+
+    Ok(Json(result))
+}
+
+/// Updates a category: `PATCH /api/categories`.
+///
+/// # Required body
+///
+/// [Category] model in `application/json`. Setting `parentId` to `null` makes it a top level category.
+///
+/// # Returns
+///
+/// The updated [Category].
+///
+/// # Errors
+///
+/// * `NotFound` => "Category not found".
+/// * `Duplicate` => "This category name already exists".
+/// * `BadRequest` => "Parent category not found" or "A category cannot be its own subcategory".
+pub async fn update_category(State(state): State<AppState>, updated_category: Json<Category>) -> Result<Json<Category>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let updated_category = updated_category.deref().to_owned();
+
+    check_parent(conn, Some(updated_category.category_id), updated_category.parent_id)?;
+
+    let name_count = categories::table
+        .filter(categories::category_id.ne(updated_category.category_id))
+        .filter(categories::name.eq(&updated_category.name))
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if name_count > 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("This category name already exists")));
+    }
+
+    let result = diesel::update(categories::table.find(updated_category.category_id))
+        .set(&updated_category)
+        .returning(Category::as_returning())
+        .get_result(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Category not found")))?;
+
+    state.events.publish(Entity::Category, ChangeAction::Updated, result.category_id);
+
+    Ok(Json(result))
+}
+
+/// Deletes a category: `DELETE /api/categories/<i32>`.
+///
+/// Subcategories move up to the parent of the deleted category, its products become uncategorized.
+///
+/// # Returns
+///
+/// The id of the deleted [Category].
+///
+/// # Errors
+///
+/// * `NotFound` => "Category not found".
+pub async fn delete_category(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<i32>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    conn.transaction::<_, TransactionError, _>(|conn| {
+        let parent_id = categories::table
+            .find(id)
+            .select(categories::parent_id)
+            .first::<Option<i32>>(conn)
+            .optional()?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Category not found")))?;
+
+        diesel::update(categories::table)
+            .filter(categories::parent_id.eq(id))
+            .set(categories::parent_id.eq(parent_id))
+            .execute(conn)?;
+        diesel::delete(categories::table.find(id)).execute(conn)?;
+
+        Ok(())
+    })?;
+
+    state.events.publish(Entity::Category, ChangeAction::Deleted, id);
+
+    Ok(Json(id))
+}
+
+/// Get the category of a product: `GET /api/products/id=<i32>/category`.
+///
+/// # Returns
+///
+/// [ProductCategory], in format `application/json`, with the version of the product as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+pub async fn get_product_category(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<ProductCategory>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let (category_id, product_version) = products::table
+        .find(id)
+        .filter(products::deleted_at.is_null())
+        .select((products::category_id, products::version))
+        .first::<(Option<i32>, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Product not found")))?;
+
+    Ok((version_header(product_version), Json(ProductCategory { product_id: id, category_id })))
+}
+
+/// Assigns a category to a product: `PATCH /api/products/id=<i32>/category`.
+///
+/// # Required body
+///
+/// [ProductCategory] in `application/json`. A `null` `categoryId` makes the product uncategorized.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the product as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The updated [ProductCategory], with the new version of the product as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+/// * `BadRequest` => "Category not found".
+/// * `PreconditionFailed` => "This item was modified by another request".
+pub async fn set_product_category(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    product_category: Json<ProductCategory>,
+) -> Result<(HeaderMap, Json<ProductCategory>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let category_id = product_category.category_id;
+
+    let update_version = conn.transaction::<_, TransactionError, _>(|conn| {
+        let current_version = products::table
+            .find(id)
+            .filter(products::deleted_at.is_null())
+            .select(products::version)
+            .for_update()
+            .first::<i32>(conn)
+            .optional()?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Product not found")))?;
+        check_if_match(&headers, current_version)?;
+
+        if let Some(category_id) = category_id {
+            let category_count = categories::table
+                .find(category_id)
+                .count()
+                .get_result::<i64>(conn)?;
+            if category_count == 0 {
+                return Err((StatusCode::BAD_REQUEST, String::from("Category not found")).into());
+            }
+        }
+
+        Ok(diesel::update(products::table.find(id))
+            .set((products::category_id.eq(category_id), products::version.eq(products::version + 1)))
+            .returning(products::version)
+            .get_result::<i32>(conn)?)
+    })?;
+
+    state.events.publish(Entity::Product, ChangeAction::Updated, id);
+
+    Ok((version_header(update_version), Json(ProductCategory { product_id: id, category_id })))
+}
@@ -1,6 +1,7 @@
 //! Endpoint `/api/drawers`, implements `GET`, `POST`, `PATCH`, `DELETE`.
 
-use axum::{extract::{Path, Query, State, Json}, http::StatusCode};
+use axum::{extract::{Path, Query, State, Json}, http::{HeaderMap, StatusCode}, response::Response};
+use chrono::Utc;
 use diesel::{QueryDsl, RunQueryDsl};
 use diesel::prelude::*;
 use serde::Deserialize;
@@ -9,11 +10,15 @@ use std::ops::Deref;
 use crate::AppState;
 use crate::core::{
     connection::establish_connection,
-    error::internal_error,
-    query::empty_string_as_none,
+    error::{internal_error, TransactionError},
+    events::{ChangeAction, Entity},
+    etag::{check_if_match, conditional_json, precondition_failed, version_header},
+    name::{lower, normalize_name, normalize_required_name},
+    query::{empty_string_as_none, DeleteOptions},
 };
 
 use crate::models::{Drawer, NewDrawer};
+use crate::schema::{drawers, freezers, storage};
 
 /// Allowed query parameters to `GET` drawers. Any query parameters not in this struct will default to query all drawers.
 #[derive(Debug, Deserialize)]
@@ -45,7 +50,7 @@ pub struct DrawerQueryOptions {
 ///
 /// ## Result
 ///
-/// A Vec of [Drawer]'s. Is empty when no matches are found.
+/// A Vec of [Drawer]'s. Is empty when no matches are found. Honors `If-None-Match`, see [crate::core::etag].
 ///
 /// ## Default
 ///
@@ -55,12 +60,12 @@ pub struct DrawerQueryOptions {
 ///
 /// * 400: [StatusCode::BAD_REQUEST] when incorrect combinations of parameters are given.
 /// * 500: [StatusCode::INTERNAL_SERVER_ERROR] when a database error occurs.
-pub async fn get_drawers(State(state): State<AppState>, params: Query<DrawerQueryOptions>) -> Result<Json<Vec<Drawer>>, (StatusCode, String)>
+pub async fn get_drawers(State(state): State<AppState>, headers: HeaderMap, params: Query<DrawerQueryOptions>) -> Result<Response, (StatusCode, String)>
 {
     use crate::schema::drawers::dsl::*;
     let conn = &mut establish_connection(state.db_url);
     // Set up boxed query to add pieces depending on query parameters.
-    let mut query = drawers.into_boxed();
+    let mut query = drawers.filter(deleted_at.is_null()).into_boxed();
     let DrawerQueryOptions { drawer_id: d_id, drawer_name: d_name, freezer_id: f_id } = params.deref();
 
     match (d_id, d_name, f_id) {
@@ -77,26 +82,31 @@ pub async fn get_drawers(State(state): State<AppState>, params: Query<DrawerQuer
             query = query.filter(drawer_id.eq(id));
         }
         (None, Some(d_name), Some(f_id)) => {
-            query = query.filter(name.eq(d_name))
+            query = query.filter(lower(name).eq(lower(normalize_name(d_name))))
                 .filter(freezer_id.eq(f_id));
         }
         (None, Some(d_name), None) => {
-            query = query.filter(name.eq(d_name));
+            query = query.filter(lower(name).eq(lower(normalize_name(d_name))));
         }
         (None, None, Some(f_id)) => {
             query = query.filter(freezer_id.eq(f_id));
         }
         _ => {
-            let res = drawers.load::<Drawer>(conn).map_err(internal_error)?;
-            return Ok(Json(res));
+            let res = drawers
+                .filter(deleted_at.is_null())
+                .select(Drawer::as_select())
+                .load::<Drawer>(conn)
+                .map_err(internal_error)?;
+            return Ok(conditional_json(&headers, res));
         }
     }
 
     let res = query
+        .select(Drawer::as_select())
         .load::<Drawer>(conn)
         .map_err(internal_error)?;
 
-    Ok(Json(res))
+    Ok(conditional_json(&headers, res))
 }
 
 /// Create a new product in the database: `POST /api/drawers`.
@@ -104,7 +114,8 @@ pub async fn get_drawers(State(state): State<AppState>, params: Query<DrawerQuer
 /// # Required body
 ///
 /// [NewDrawer] model in `application/json'.
-/// The drawer name must be unique within the same freezer.
+/// The drawer name must be unique within the same freezer regardless of case, it is normalized
+/// first, see [crate::core::name].
 ///
 /// # Returns
 ///
@@ -113,14 +124,20 @@ pub async fn get_drawers(State(state): State<AppState>, params: Query<DrawerQuer
 /// # Errors
 ///
 /// * `Duplicate` => "This drawer name already exists within this freezer".
+/// * `BadRequest` => "name cannot be empty".
 pub async fn create_drawer(State(state): State<AppState>, new_drawer: Json<NewDrawer>) -> Result<Json<Drawer>, (StatusCode, String)> {
     use crate::schema::drawers::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
-    let new_drawer = new_drawer.deref().to_owned();
+    let conn = &mut establish_connection(state.db_url.clone());
+    let mut new_drawer = new_drawer.deref().to_owned();
+    new_drawer.name = normalize_required_name(&new_drawer.name)?;
+
+    check_freezer_active(conn, new_drawer.freezer_id)?;
 
     let name_query = drawers
-        .filter(name.eq(&new_drawer.name))
+        .filter(lower(name).eq(lower(&new_drawer.name)))
         .filter(freezer_id.eq(&new_drawer.freezer_id))
+        .filter(deleted_at.is_null())
+        .select(Drawer::as_select())
         .get_results::<Drawer>(conn)
         .map_err(internal_error)?;
 
@@ -134,6 +151,8 @@ pub async fn create_drawer(State(state): State<AppState>, new_drawer: Json<NewDr
         .get_result(conn)
         .map_err(internal_error)?;
 
+    state.events.publish(Entity::Drawer, ChangeAction::Created, create_result.drawer_id);
+
     Ok(Json(create_result))
 }
 
@@ -143,26 +162,45 @@ pub async fn create_drawer(State(state): State<AppState>, new_drawer: Json<NewDr
 /// # Required body
 ///
 /// [Drawer] model in `application/json'.
-/// The drawer name must be unique within the same freezer.
+/// The drawer name must be unique within the same freezer regardless of case, it is normalized
+/// first, see [crate::core::name].
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the drawer as last fetched, see [crate::core::etag].
 ///
 /// # Returns
 ///
-/// The updated [Drawer].
+/// The updated [Drawer], with its new version as `ETag` header.
 ///
 /// # Errors
 ///
 /// * `Duplicate` => "This drawer name already exists within this freezer".
+/// * `BadRequest` => "name cannot be empty".
 /// * `NotFound` => "Drawer not found". Returned when a wrong product_id was entered.
+/// * `PreconditionFailed` => "This item was modified by another request".
 ///
-pub async fn update_drawer(State(state): State<AppState>, updated_drawer: Json<Drawer>) -> Result<Json<Drawer>, (StatusCode, String)> {
+pub async fn update_drawer(State(state): State<AppState>, headers: HeaderMap, updated_drawer: Json<Drawer>) -> Result<(HeaderMap, Json<Drawer>), (StatusCode, String)> {
     use crate::schema::drawers::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
-    let updated_drawer = updated_drawer.deref().to_owned();
+    let conn = &mut establish_connection(state.db_url.clone());
+    let mut updated_drawer = updated_drawer.deref().to_owned();
+    updated_drawer.name = normalize_required_name(&updated_drawer.name)?;
+
+    let current_version = drawers
+        .find(&updated_drawer.drawer_id)
+        .filter(deleted_at.is_null())
+        .select(version)
+        .first::<i32>(conn)
+        .map_err(internal_error)?;
+    check_if_match(&headers, current_version)?;
+    check_freezer_active(conn, updated_drawer.freezer_id)?;
 
     let name_query = drawers
-        .filter(name.eq(&updated_drawer.name))
+        .filter(lower(name).eq(lower(&updated_drawer.name)))
         .filter(freezer_id.eq(&updated_drawer.freezer_id))
+        .filter(deleted_at.is_null())
         .filter(drawer_id.ne(&updated_drawer.drawer_id))
+        .select(Drawer::as_select())
         .get_results::<Drawer>(conn)
         .map_err(internal_error)?;
 
@@ -170,21 +208,39 @@ pub async fn update_drawer(State(state): State<AppState>, updated_drawer: Json<D
         return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("This drawer name already exists within this freezer")));
     }
 
-    let update_result = diesel::update(drawers)
+    let (update_result, update_version) = diesel::update(drawers)
         .filter(drawer_id.eq(updated_drawer.drawer_id))
-        .set(updated_drawer)
-        .get_result(conn)
-        .map_err(internal_error)?;
+        .filter(version.eq(current_version))
+        .set((updated_drawer, version.eq(version + 1)))
+        .returning((Drawer::as_returning(), version))
+        .get_result::<(Drawer, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(precondition_failed)?;
 
-    Ok(Json(update_result))
+    state.events.publish(Entity::Drawer, ChangeAction::Updated, update_result.drawer_id);
+
+    Ok((version_header(update_version), Json(update_result)))
 }
 
-/// Deletes a drawer in the database based on its `product_id`: `DELETE /api/drawers/id=<i32>`.
+/// Moves a drawer to the trash based on its `drawer_id`: `DELETE /api/drawers/id=<i32>`.
+/// All storage items in the drawer are moved to the trash along with it, see [crate::core::trash].
 ///
 /// # Requires
 ///
-/// A valid drawer ID to be given. It's recommended to implement delete protection in the frontend
-/// as it may remove a lot of data linked to a drawer.
+/// A valid drawer ID to be given. A drawer still holding available items is only deleted when
+/// either `force` or `reassignTo` is given.
+///
+/// # Accepted query parameters
+///
+/// See [DeleteOptions].
+///
+/// * `force=true`: the available items are moved to the trash along with the drawer.
+/// * `reassignTo=<i32>`: the available items are moved to the given drawer first.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the drawer as last fetched, see [crate::core::etag].
 ///
 /// # Returns
 ///
@@ -193,22 +249,131 @@ pub async fn update_drawer(State(state): State<AppState>, updated_drawer: Json<D
 /// # Errors
 ///
 /// * `NotFound` => "Drawer not found".
-pub async fn delete_drawer(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<i32>, (StatusCode, String)> {
+/// * `BadRequest` => invalid combination of [DeleteOptions] or an invalid target drawer.
+/// * `Conflict` => "This drawer contains <n> available items, [...]".
+/// * `PreconditionFailed` => "This item was modified by another request".
+pub async fn delete_drawer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    params: Query<DeleteOptions>,
+) -> Result<Json<i32>, (StatusCode, String)> {
     use crate::schema::drawers::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
+    let conn = &mut establish_connection(state.db_url.clone());
 
     let id_query = drawers
         .filter(drawer_id.eq(&id))
-        .get_results::<Drawer>(conn)
+        .filter(deleted_at.is_null())
+        .select(version)
+        .get_results::<i32>(conn)
         .map_err(internal_error)?;
     if id_query.is_empty() {
         return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Drawer not found")));
     }
+    check_if_match(&headers, id_query[0])?;
 
-    diesel::delete(drawers)
-        .filter(drawer_id.eq(id))
-        .execute(conn)
-        .map_err(internal_error)?;
+    let trashed_at = Utc::now();
+    conn.transaction::<_, TransactionError, _>(|conn| {
+        let deleted = diesel::update(drawers)
+            .filter(drawer_id.eq(id))
+            .filter(version.eq(id_query[0]))
+            .set((deleted_at.eq(trashed_at), version.eq(version + 1)))
+            .execute(conn)?;
+        if deleted == 0 {
+            return Err(precondition_failed().into());
+        }
+
+        empty_drawers(conn, &[id], "drawer", params.deref())?;
+
+        diesel::update(storage::table)
+            .filter(storage::drawer_id.eq(id))
+            .filter(storage::deleted_at.is_null())
+            .set((storage::deleted_at.eq(trashed_at), storage::version.eq(storage::version + 1)))
+            .execute(conn)?;
+
+        Ok(())
+    })?;
+
+    state.events.publish(Entity::Drawer, ChangeAction::Deleted, id);
 
     Ok(Json(id))
 }
+
+/// Handles the available storage items in drawers about to be deleted, according to [DeleteOptions].
+///
+/// Refuses with [StatusCode::CONFLICT] when available items are found and neither option was given.
+/// With `reassign_to`, the available items are moved to the target drawer, which must be active and
+/// not one of the deleted drawers. Must be run inside the transaction deleting the drawers.
+pub(crate) fn empty_drawers(
+    conn: &mut PgConnection,
+    drawer_ids: &[i32],
+    deleted: &str,
+    options: &DeleteOptions,
+) -> Result<(), TransactionError> {
+    match (options.force.unwrap_or(false), options.reassign_to) {
+        (true, Some(_)) => Err((
+            StatusCode::BAD_REQUEST,
+            String::from("Either force or reassignTo can be given, not both"),
+        ).into()),
+        (true, None) => Ok(()),
+        (false, Some(target)) => {
+            if drawer_ids.contains(&target) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Cannot reassign items to a drawer of the deleted {}", deleted),
+                ).into());
+            }
+            let target_count = drawers::table
+                .find(target)
+                .filter(drawers::deleted_at.is_null())
+                .count()
+                .get_result::<i64>(conn)?;
+            if target_count == 0 {
+                return Err((StatusCode::BAD_REQUEST, String::from("Target drawer not found")).into());
+            }
+
+            diesel::update(storage::table)
+                .filter(storage::drawer_id.eq_any(drawer_ids))
+                .filter(storage::date_out.is_null())
+                .filter(storage::deleted_at.is_null())
+                .set((storage::drawer_id.eq(target), storage::version.eq(storage::version + 1)))
+                .execute(conn)?;
+
+            Ok(())
+        }
+        (false, None) => {
+            let count = storage::table
+                .filter(storage::drawer_id.eq_any(drawer_ids))
+                .filter(storage::date_out.is_null())
+                .filter(storage::deleted_at.is_null())
+                .count()
+                .get_result::<i64>(conn)?;
+            if count > 0 {
+                return Err((
+                    StatusCode::CONFLICT,
+                    format!(
+                        "This {} contains {} available items, delete it with force=true or move them with reassignTo=<drawerId>",
+                        deleted, count
+                    ),
+                ).into());
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Checks that the freezer a drawer is assigned to exists and is not in the trash.
+fn check_freezer_active(conn: &mut PgConnection, id: i32) -> Result<(), (StatusCode, String)> {
+    let freezer_count = freezers::table
+        .filter(freezers::freezer_id.eq(id))
+        .filter(freezers::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if freezer_count == 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Freezer not found")));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Endpoint `/api/events`, implements `GET` as a stream of Server-Sent Events.
+//!
+//! Every change is sent as a `change` event with a [ChangeEvent] as json data and its id as event id,
+//! see [crate::core::events]. Browsers' `EventSource` reconnects automatically and sends the
+//! `Last-Event-ID` header, after which the missed events are replayed first.
+//!
+//! When the missed events are no longer available, a `reset` event is sent instead: the client
+//! should reload everything it displays.
+//!
+//! The API has no notion of users, all clients receive all events.
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    core::events::ChangeEvent,
+    AppState,
+};
+
+/// Request header sent by reconnecting clients.
+pub const LAST_EVENT_ID: &str = "last-event-id";
+
+fn change_event(event: &ChangeEvent) -> Event {
+    Event::default()
+        .id(event.id.to_string())
+        .event("change")
+        .json_data(event)
+        .unwrap_or_else(|_| Event::default().event("reset").data("reload"))
+}
+
+fn reset_event() -> Event {
+    Event::default().event("reset").data("reload")
+}
+
+/// Subscribes to the change feed: `GET /api/events`.
+///
+/// # Optional headers
+///
+/// `Last-Event-ID` with the id of the last received event, to replay the events since.
+///
+/// # Returns
+///
+/// A `text/event-stream` response that stays open, with regular keep-alive comments.
+pub async fn get_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_event_id = headers
+        .get(LAST_EVENT_ID)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+
+    let subscription = state.events.subscribe(last_event_id);
+
+    let mut replay: Vec<Event> = Vec::new();
+    if subscription.missed_events {
+        replay.push(reset_event());
+    }
+    replay.extend(subscription.replay.iter().map(change_event));
+
+    let live = stream::unfold(subscription.receiver, |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(event) => change_event(&event),
+            // This client fell behind, the skipped events are lost for it.
+            Err(RecvError::Lagged(_)) => reset_event(),
+            Err(RecvError::Closed) => return None,
+        };
+
+        Some((event, receiver))
+    });
+
+    let events = stream::iter(replay).chain(live).map(Ok);
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,392 @@
+//! Endpoint `/api/freezer-types`, implements `GET`, `POST`, `PATCH`, `DELETE`.
+//!
+//! Also implements assigning a type to a freezer: `GET` and `PATCH` on `/api/freezers/id=<i32>/type`,
+//! and the shelf life of a product: `GET` and `PATCH` on `/api/products/id=<i32>/shelf-life`.
+//! See [crate::core::shelf_life] for how these determine the expiration of storage items.
+use std::ops::Deref;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+        etag::{check_if_match, conditional_json, version_header},
+    },
+    models::{FreezerType, NewFreezerType, ShelfLifeOverride},
+    schema::{freezer_types, freezers, product_shelf_lives, products},
+    AppState,
+};
+
+/// Type of a single freezer, used to read and change it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezerTypeAssignment {
+    /// Freezer id. Ignored when sent by the frontend, the id in the path is used.
+    #[serde(default)]
+    pub freezer_id: i32,
+    /// Id of the assigned freezer type, `None` when the freezer has no type.
+    pub freezer_type_id: Option<i32>,
+}
+
+/// Shelf life of a single product, used to read and change it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductShelfLife {
+    /// Product id. Ignored when sent by the frontend, the id in the path is used.
+    #[serde(default)]
+    pub product_id: i32,
+    /// Shelf life in days, e.g. 14 for two weeks. `None` to use the product `expiration_months`.
+    pub shelf_life_days: Option<i32>,
+    /// Shelf life per freezer type, replacing the multiplied shelf life in freezers of that type.
+    #[serde(default)]
+    pub overrides: Vec<ShelfLifeOverride>,
+}
+
+/// Largest shelf life multiplier of a freezer type.
+pub const MAX_SHELF_LIFE_MULTIPLIER: f32 = 10.0;
+/// Largest shelf life in days, a hundred years.
+pub const MAX_SHELF_LIFE_DAYS: i32 = 36500;
+
+/// Checks that a shelf life multiplier is a positive number of at most [MAX_SHELF_LIFE_MULTIPLIER].
+fn check_multiplier(multiplier: f32) -> Result<(), (StatusCode, String)> {
+    if !multiplier.is_finite() || multiplier <= 0.0 || multiplier > MAX_SHELF_LIFE_MULTIPLIER {
+        return Err((StatusCode::BAD_REQUEST, format!("shelfLifeMultiplier must be positive and at most {}", MAX_SHELF_LIFE_MULTIPLIER)));
+    }
+
+    Ok(())
+}
+
+/// Get all freezer types: `GET /api/freezer-types`.
+///
+/// # Returns
+///
+/// Vec<[FreezerType]>, in format `application/json`, sorted by name. Honors `If-None-Match`, see
+/// [crate::core::etag].
+pub async fn get_freezer_types(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let result = freezer_types::table
+        .select(FreezerType::as_select())
+        .order_by(freezer_types::name)
+        .load::<FreezerType>(conn)
+        .map_err(internal_error)?;
+
+    Ok(conditional_json(&headers, result))
+}
+
+/// Create a new freezer type: `POST /api/freezer-types`.
+///
+/// # Required body
+///
+/// [NewFreezerType] model in `application/json`.
+///
+/// # Returns
+///
+/// The new [FreezerType].
+///
+/// # Errors
+///
+/// * `Duplicate` => "This freezer type name already exists".
+/// * `BadRequest` => "shelfLifeMultiplier must be positive and at most 10".
+pub async fn create_freezer_type(State(state): State<AppState>, new_freezer_type: Json<NewFreezerType>) -> Result<Json<FreezerType>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let new_freezer_type = new_freezer_type.deref().to_owned();
+
+    if let Some(multiplier) = new_freezer_type.shelf_life_multiplier {
+        check_multiplier(multiplier)?;
+    }
+
+    let name_count = freezer_types::table
+        .filter(freezer_types::name.eq(&new_freezer_type.name))
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if name_count > 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("This freezer type name already exists")));
+    }
+
+    let result = diesel::insert_into(freezer_types::table)
+        .values(new_freezer_type)
+        .returning(FreezerType::as_returning())
+        .get_result(conn)
+        .map_err(internal_error)?;
+
+    state.events.publish(Entity::FreezerType, ChangeAction::Created, result.freezer_type_id);
+
+    Ok(Json(result))
+}
+
+/// Updates a freezer type: `PATCH /api/freezer-types`.
+///
+/// # Required body
+///
+/// [FreezerType] model in `application/json`.
+///
+/// # Returns
+///
+/// The updated [FreezerType].
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer type not found".
+/// * `Duplicate` => "This freezer type name already exists".
+/// * `BadRequest` => "shelfLifeMultiplier must be positive and at most 10".
+pub async fn update_freezer_type(State(state): State<AppState>, updated_freezer_type: Json<FreezerType>) -> Result<Json<FreezerType>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let updated_freezer_type = updated_freezer_type.deref().to_owned();
+
+    check_multiplier(updated_freezer_type.shelf_life_multiplier)?;
+
+    let name_count = freezer_types::table
+        .filter(freezer_types::freezer_type_id.ne(updated_freezer_type.freezer_type_id))
+        .filter(freezer_types::name.eq(&updated_freezer_type.name))
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if name_count > 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("This freezer type name already exists")));
+    }
+
+    let result = diesel::update(freezer_types::table.find(updated_freezer_type.freezer_type_id))
+        .set(&updated_freezer_type)
+        .returning(FreezerType::as_returning())
+        .get_result(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Freezer type not found")))?;
+
+    state.events.publish(Entity::FreezerType, ChangeAction::Updated, result.freezer_type_id);
+
+    Ok(Json(result))
+}
+
+/// Deletes a freezer type: `DELETE /api/freezer-types/<i32>`.
+///
+/// Freezers of this type lose their type, shelf life overrides for it are removed.
+///
+/// # Returns
+///
+/// The id of the deleted [FreezerType].
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer type not found".
+pub async fn delete_freezer_type(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<i32>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let deleted = diesel::delete(freezer_types::table.find(id))
+        .execute(conn)
+        .map_err(internal_error)?;
+    if deleted == 0 {
+        return Err((StatusCode::NOT_FOUND, String::from("Freezer type not found")));
+    }
+
+    state.events.publish(Entity::FreezerType, ChangeAction::Deleted, id);
+
+    Ok(Json(id))
+}
+
+/// Get the type of a freezer: `GET /api/freezers/id=<i32>/type`.
+///
+/// # Returns
+///
+/// [FreezerTypeAssignment], in format `application/json`, with the version of the freezer as `ETag`
+/// header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer not found".
+pub async fn get_freezer_type(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<FreezerTypeAssignment>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let (freezer_type_id, freezer_version) = freezers::table
+        .find(id)
+        .filter(freezers::deleted_at.is_null())
+        .select((freezers::freezer_type_id, freezers::version))
+        .first::<(Option<i32>, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Freezer not found")))?;
+
+    Ok((version_header(freezer_version), Json(FreezerTypeAssignment { freezer_id: id, freezer_type_id })))
+}
+
+/// Assigns a type to a freezer: `PATCH /api/freezers/id=<i32>/type`.
+///
+/// # Required body
+///
+/// [FreezerTypeAssignment] in `application/json`. A `null` `freezerTypeId` removes the type.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the freezer as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The updated [FreezerTypeAssignment], with the new version of the freezer as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer not found".
+/// * `BadRequest` => "Freezer type not found".
+/// * `PreconditionFailed` => "This item was modified by another request".
+pub async fn set_freezer_type(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    assignment: Json<FreezerTypeAssignment>,
+) -> Result<(HeaderMap, Json<FreezerTypeAssignment>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let freezer_type_id = assignment.freezer_type_id;
+
+    let update_version = conn.transaction::<_, TransactionError, _>(|conn| {
+        let current_version = freezers::table
+            .find(id)
+            .filter(freezers::deleted_at.is_null())
+            .select(freezers::version)
+            .for_update()
+            .first::<i32>(conn)
+            .optional()?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Freezer not found")))?;
+        check_if_match(&headers, current_version)?;
+
+        if let Some(freezer_type_id) = freezer_type_id {
+            let freezer_type_count = freezer_types::table
+                .find(freezer_type_id)
+                .count()
+                .get_result::<i64>(conn)?;
+            if freezer_type_count == 0 {
+                return Err((StatusCode::BAD_REQUEST, String::from("Freezer type not found")).into());
+            }
+        }
+
+        Ok(diesel::update(freezers::table.find(id))
+            .set((freezers::freezer_type_id.eq(freezer_type_id), freezers::version.eq(freezers::version + 1)))
+            .returning(freezers::version)
+            .get_result::<i32>(conn)?)
+    })?;
+
+    state.events.publish(Entity::Freezer, ChangeAction::Updated, id);
+
+    Ok((version_header(update_version), Json(FreezerTypeAssignment { freezer_id: id, freezer_type_id })))
+}
+
+/// Get the shelf life of a product: `GET /api/products/id=<i32>/shelf-life`.
+///
+/// # Returns
+///
+/// [ProductShelfLife], in format `application/json`, overrides sorted by freezer type id. The version
+/// of the product is the `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+pub async fn get_product_shelf_life(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<ProductShelfLife>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let (shelf_life_days, product_version) = products::table
+        .find(id)
+        .filter(products::deleted_at.is_null())
+        .select((products::shelf_life_days, products::version))
+        .first::<(Option<i32>, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Product not found")))?;
+    let overrides = product_shelf_lives::table
+        .filter(product_shelf_lives::product_id.eq(id))
+        .select(ShelfLifeOverride::as_select())
+        .order_by(product_shelf_lives::freezer_type_id)
+        .load::<ShelfLifeOverride>(conn)
+        .map_err(internal_error)?;
+
+    Ok((version_header(product_version), Json(ProductShelfLife { product_id: id, shelf_life_days, overrides })))
+}
+
+/// Replaces the shelf life of a product: `PATCH /api/products/id=<i32>/shelf-life`.
+///
+/// # Required body
+///
+/// [ProductShelfLife] in `application/json`. The overrides replace all existing ones of the product.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the product as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The updated [ProductShelfLife], overrides sorted by freezer type id, with the new version of the
+/// product as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+/// * `BadRequest` => "shelfLifeDays must be between 1 and 36500", "Freezer type not found" or "Duplicate
+///   override for freezer type <id>".
+/// * `PreconditionFailed` => "This item was modified by another request".
+pub async fn set_product_shelf_life(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    shelf_life: Json<ProductShelfLife>,
+) -> Result<(HeaderMap, Json<ProductShelfLife>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let shelf_life_days = shelf_life.shelf_life_days;
+    let mut overrides: Vec<ShelfLifeOverride> = shelf_life.overrides
+        .iter()
+        .map(|rule| ShelfLifeOverride { product_id: id, ..rule.clone() })
+        .collect();
+    overrides.sort_by_key(|rule| rule.freezer_type_id);
+
+    let mut all_days = shelf_life_days.into_iter().chain(overrides.iter().map(|rule| rule.shelf_life_days));
+    if all_days.any(|days| !(1..=MAX_SHELF_LIFE_DAYS).contains(&days)) {
+        return Err((StatusCode::BAD_REQUEST, format!("shelfLifeDays must be between 1 and {}", MAX_SHELF_LIFE_DAYS)));
+    }
+    if let Some(duplicate) = overrides.windows(2).find(|pair| pair[0].freezer_type_id == pair[1].freezer_type_id) {
+        return Err((StatusCode::BAD_REQUEST, format!("Duplicate override for freezer type {}", duplicate[0].freezer_type_id)));
+    }
+
+    let update_version = conn.transaction::<_, TransactionError, _>(|conn| {
+        let current_version = products::table
+            .find(id)
+            .filter(products::deleted_at.is_null())
+            .select(products::version)
+            .for_update()
+            .first::<i32>(conn)
+            .optional()?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Product not found")))?;
+        check_if_match(&headers, current_version)?;
+        let update_version = diesel::update(products::table.find(id))
+            .set((products::shelf_life_days.eq(shelf_life_days), products::version.eq(products::version + 1)))
+            .returning(products::version)
+            .get_result::<i32>(conn)?;
+
+        let freezer_type_ids: Vec<i32> = overrides.iter().map(|rule| rule.freezer_type_id).collect();
+        let known_types = freezer_types::table
+            .filter(freezer_types::freezer_type_id.eq_any(&freezer_type_ids))
+            .count()
+            .get_result::<i64>(conn)?;
+        if known_types != freezer_type_ids.len() as i64 {
+            return Err((StatusCode::BAD_REQUEST, String::from("Freezer type not found")).into());
+        }
+
+        diesel::delete(product_shelf_lives::table.filter(product_shelf_lives::product_id.eq(id))).execute(conn)?;
+        diesel::insert_into(product_shelf_lives::table)
+            .values(&overrides)
+            .execute(conn)?;
+
+        Ok(update_version)
+    })?;
+
+    state.events.publish(Entity::Product, ChangeAction::Updated, id);
+
+    Ok((version_header(update_version), Json(ProductShelfLife { product_id: id, shelf_life_days, overrides })))
+}
+
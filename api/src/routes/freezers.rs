@@ -1,9 +1,10 @@
 //! Endpoint `/api/freezers`, implements `GET`, `POST`, `PATCH`, `DELETE`.
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
 };
+use chrono::Utc;
 use diesel::prelude::*;
 use diesel::QueryDsl;
 use std::ops::Deref;
@@ -11,9 +12,15 @@ use std::ops::Deref;
 use crate::{
     core::{
         connection::establish_connection,
-        error::internal_error,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+        etag::{check_if_match, conditional_json, precondition_failed, version_header},
+        name::{lower, normalize_name, normalize_required_name},
+        query::DeleteOptions,
     },
     models::{Freezer, NewFreezer},
+    routes::drawers::empty_drawers,
+    schema::{drawers, storage},
     AppState,
 };
 
@@ -21,24 +28,29 @@ use crate::{
 ///
 /// # Returns
 ///
-/// Vec<[Freezer]>, in format `application/json`
+/// Vec<[Freezer]>, in format `application/json`. Honors `If-None-Match`, see [crate::core::etag].
 pub async fn get_all_freezers(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Freezer>>, (StatusCode, String)> {
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
     use crate::schema::freezers::dsl::*;
 
     let conn = &mut establish_connection(state.db_url);
 
-    let result = freezers.load::<Freezer>(conn).map_err(internal_error)?;
+    let result = freezers
+        .filter(deleted_at.is_null())
+        .select(Freezer::as_select())
+        .load::<Freezer>(conn)
+        .map_err(internal_error)?;
 
-    Ok(Json(result))
+    Ok(conditional_json(&headers, result))
 }
 
 /// Get a freezer entry by its id: `GET /api/freezers/id=<i32>`.
 ///
 /// # Returns
 ///
-/// [Freezer], in format `application/json`
+/// [Freezer], in format `application/json`, with its version as `ETag` header.
 ///
 /// # Errors
 ///
@@ -46,24 +58,27 @@ pub async fn get_all_freezers(
 pub async fn get_freezer_by_id(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<Json<Freezer>, (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Freezer>), (StatusCode, String)> {
     use crate::schema::freezers::dsl::*;
 
     let conn = &mut establish_connection(state.db_url);
 
-    let result = freezers
+    let (result, result_version) = freezers
         .filter(freezer_id.eq(id))
-        .get_result(conn)
+        .filter(deleted_at.is_null())
+        .select((Freezer::as_select(), version))
+        .get_result::<(Freezer, i32)>(conn)
         .map_err(internal_error)?;
 
-    Ok(Json(result))
+    Ok((version_header(result_version), Json(result)))
 }
 
 /// Get a freezer entry by its name: `GET /api/freezers/name=<String>`.
+/// Names match regardless of case and surrounding whitespace, see [crate::core::name].
 ///
 /// # Returns
 ///
-/// [Freezer], in format `application/json`
+/// [Freezer], in format `application/json`, with its version as `ETag` header.
 ///
 /// # Errors
 ///
@@ -71,45 +86,65 @@ pub async fn get_freezer_by_id(
 pub async fn get_freezer_by_name(
     State(state): State<AppState>,
     Path(query_name): Path<String>,
-) -> Result<Json<Freezer>, (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Freezer>), (StatusCode, String)> {
     use crate::schema::freezers::dsl::*;
 
     let conn = &mut establish_connection(state.db_url);
 
-    let result = freezers
-        .filter(name.eq(query_name))
-        .get_result(conn)
+    let (result, result_version) = freezers
+        .filter(lower(name).eq(lower(normalize_name(&query_name))))
+        .filter(deleted_at.is_null())
+        .select((Freezer::as_select(), version))
+        .get_result::<(Freezer, i32)>(conn)
         .map_err(internal_error)?;
 
-    Ok(Json(result))
+    Ok((version_header(result_version), Json(result)))
 }
 
 /// Update a freezer entry: `PATCH /api/freezers/name=<String>`.
 ///
 /// # Required body
 ///
-/// [Freezer] with a unique name.
+/// [Freezer] with a name unique regardless of case, it is normalized first, see [crate::core::name].
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the freezer as last fetched, see [crate::core::etag].
 ///
 /// # Returns
 ///
-/// [Freezer], in format `application/json`
+/// [Freezer], in format `application/json`, with its new version as `ETag` header.
 ///
 /// # Errors
 ///
 /// * `NotFound`: Freezer name does not exist.
 /// * `DuplicateError`: Freezer name already exists.
+/// * `BadRequest` => "name cannot be empty".
+/// * `PreconditionFailed`: Freezer was modified since it was fetched.
 pub async fn update_freezer(
     State(state): State<AppState>,
+    headers: HeaderMap,
     updated_freezer: Json<Freezer>,
-) -> Result<Json<Freezer>, (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Freezer>), (StatusCode, String)> {
     use crate::schema::freezers::dsl::*;
 
-    let conn = &mut establish_connection(state.db_url);
-    let updated_freezer = updated_freezer.deref().to_owned();
+    let conn = &mut establish_connection(state.db_url.clone());
+    let mut updated_freezer = updated_freezer.deref().to_owned();
+    updated_freezer.name = normalize_required_name(&updated_freezer.name)?;
+
+    let current_version = freezers
+        .find(&updated_freezer.freezer_id)
+        .filter(deleted_at.is_null())
+        .select(version)
+        .first::<i32>(conn)
+        .map_err(internal_error)?;
+    check_if_match(&headers, current_version)?;
 
     let name_lookup = freezers
         .filter(freezer_id.ne(&updated_freezer.freezer_id))
-        .filter(name.eq(&updated_freezer.name))
+        .filter(lower(name).eq(lower(&updated_freezer.name)))
+        .filter(deleted_at.is_null())
+        .select(Freezer::as_select())
         .get_results::<Freezer>(conn)
         .map_err(internal_error)?;
 
@@ -120,21 +155,26 @@ pub async fn update_freezer(
         ));
     }
 
-    let update_result = diesel::update(freezers)
+    let (update_result, update_version) = diesel::update(freezers)
         .filter(freezer_id.eq(&updated_freezer.freezer_id))
-        .set(&updated_freezer)
-        .returning(Freezer::as_returning())
-        .get_result(conn)
-        .map_err(internal_error)?;
+        .filter(version.eq(current_version))
+        .set((&updated_freezer, version.eq(version + 1)))
+        .returning((Freezer::as_returning(), version))
+        .get_result::<(Freezer, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(precondition_failed)?;
+
+    state.events.publish(Entity::Freezer, ChangeAction::Updated, update_result.freezer_id);
 
-    Ok(Json(update_result))
+    Ok((version_header(update_version), Json(update_result)))
 }
 
 /// Create a new freezer entry: `POST /api/freezers`.
 ///
 /// # Required body
 ///
-/// [NewFreezer]: Name must be unique.
+/// [NewFreezer]: Name must be unique regardless of case, it is normalized first, see [crate::core::name].
 ///
 /// # Returns
 ///
@@ -143,16 +183,20 @@ pub async fn update_freezer(
 /// # Errors
 ///
 /// * `DuplicateError`: freezer name already taken.
+/// * `BadRequest` => "name cannot be empty".
 pub async fn create_freezer(
     State(state): State<AppState>,
     new_freezer: Json<NewFreezer>,
 ) -> Result<Json<Freezer>, (StatusCode, String)> {
     use crate::schema::freezers::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
-    let new_freezer = new_freezer.deref().to_owned();
+    let conn = &mut establish_connection(state.db_url.clone());
+    let mut new_freezer = new_freezer.deref().to_owned();
+    new_freezer.name = normalize_required_name(&new_freezer.name)?;
 
     let name_query = freezers
-        .filter(name.eq(&new_freezer.name))
+        .filter(lower(name).eq(lower(&new_freezer.name)))
+        .filter(deleted_at.is_null())
+        .select(Freezer::as_select())
         .get_results::<Freezer>(conn)
         .map_err(internal_error)?;
 
@@ -171,21 +215,47 @@ pub async fn create_freezer(
           (StatusCode::INTERNAL_SERVER_ERROR, format!("Error while inserting freezer: {}", err));
         }).unwrap();
 
+    state.events.publish(Entity::Freezer, ChangeAction::Created, create_result.freezer_id);
+
     Ok(Json(create_result))
 }
 
-/// Deletes a freezer entry: `DELETE /api/freezers/id=<i32>`.
+/// Moves a freezer entry to the trash: `DELETE /api/freezers/id=<i32>`.
+/// All drawers of the freezer and their storage items are moved to the trash along with it, see
+/// [crate::core::trash].
+///
+/// A freezer still holding available items is only deleted when either `force` or `reassignTo`
+/// is given, see [DeleteOptions].
+///
+/// # Accepted query parameters
+///
+/// * `force=true`: the available items are moved to the trash along with the freezer.
+/// * `reassignTo=<i32>`: the available items are moved to the given drawer, in another freezer, first.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the freezer as last fetched, see [crate::core::etag].
 ///
 /// # Errors
 ///
 /// * `NotFound`: freezer id not found.
-pub async fn delete_freezer(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<i32>, (StatusCode, String)> {
+/// * `BadRequest`: invalid combination of [DeleteOptions] or an invalid target drawer.
+/// * `Conflict`: the freezer holds available items and no [DeleteOptions] were given.
+/// * `PreconditionFailed`: Freezer was modified since it was fetched.
+pub async fn delete_freezer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    params: Query<DeleteOptions>,
+) -> Result<Json<i32>, (StatusCode, String)> {
     use crate::schema::freezers::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
+    let conn = &mut establish_connection(state.db_url.clone());
 
     let id_query = freezers
         .find(id)
-        .get_results::<Freezer>(conn)
+        .filter(deleted_at.is_null())
+        .select(version)
+        .get_results::<i32>(conn)
         .map_err(internal_error)?;
     if id_query.is_empty() {
         return Err((
@@ -193,11 +263,37 @@ pub async fn delete_freezer(State(state): State<AppState>, Path(id): Path<i32>)
             String::from("This freezer id does not exist"),
         ));
     }
+    check_if_match(&headers, id_query[0])?;
 
-    diesel::delete(freezers)
-        .filter(freezer_id.eq(id))
-        .execute(conn)
-        .map_err(internal_error)?;
+    let trashed_at = Utc::now();
+    conn.transaction::<_, TransactionError, _>(|conn| {
+        let deleted = diesel::update(freezers)
+            .filter(freezer_id.eq(id))
+            .filter(version.eq(id_query[0]))
+            .set((deleted_at.eq(trashed_at), version.eq(version + 1)))
+            .execute(conn)?;
+        if deleted == 0 {
+            return Err(precondition_failed().into());
+        }
+
+        let drawer_ids = diesel::update(drawers::table)
+            .filter(drawers::freezer_id.eq(id))
+            .filter(drawers::deleted_at.is_null())
+            .set((drawers::deleted_at.eq(trashed_at), drawers::version.eq(drawers::version + 1)))
+            .returning(drawers::drawer_id)
+            .get_results::<i32>(conn)?;
+        empty_drawers(conn, &drawer_ids, "freezer", params.deref())?;
+
+        diesel::update(storage::table)
+            .filter(storage::drawer_id.eq_any(drawer_ids))
+            .filter(storage::deleted_at.is_null())
+            .set((storage::deleted_at.eq(trashed_at), storage::version.eq(storage::version + 1)))
+            .execute(conn)?;
+
+        Ok(())
+    })?;
+
+    state.events.publish(Entity::Freezer, ChangeAction::Deleted, id);
 
     Ok(Json(id))
 }
@@ -0,0 +1,82 @@
+//! Endpoint `/api/import`, implements `POST` to import storage items from CSV or JSON.
+//!
+//! See [crate::core::import] for the format of the rows and how they are validated.
+use axum::{
+    extract::{Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::internal_error,
+        import::{import_rows, parse_rows, ImportFormat, ImportOptions, ImportReport},
+        query::empty_string_as_none,
+    },
+    AppState,
+};
+
+/// Maximum size of a file sent to [import_storage], in bytes.
+pub const MAX_IMPORT_SIZE: usize = 64 * 1024 * 1024;
+
+/// Allowed query parameters when importing.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQuery {
+    /// Format of the body. Defaults to CSV for a `text/csv` content type, JSON otherwise.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub format: Option<ImportFormat>,
+    /// Only validate the rows, see [ImportOptions::dry_run].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub dry_run: Option<bool>,
+    /// Create missing products, freezers and drawers, see [ImportOptions::create_missing].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub create_missing: Option<bool>,
+}
+
+/// Import storage items: `POST /api/import`.
+///
+/// # Required body
+///
+/// Rows as described in [crate::core::import::ImportRow], either CSV with a header row or a JSON array.
+/// At most [MAX_IMPORT_SIZE] bytes.
+///
+/// # Optional query parameters
+///
+/// [ImportQuery], e.g. `?dryRun=true&createMissing=true`.
+///
+/// # Returns
+///
+/// [ImportReport]. When any row is invalid nothing is imported and the report, listing the errors per
+/// row, comes with status `UnprocessableEntity`.
+///
+/// # Errors
+///
+/// * `BadRequest`: the body can't be read at all, e.g. JSON that is not an array.
+pub async fn import_storage(
+    State(state): State<AppState>,
+    params: Query<ImportQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<(StatusCode, Json<ImportReport>), (StatusCode, String)> {
+    let format = params.format.unwrap_or_else(|| {
+        let is_csv = headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/csv"));
+        if is_csv { ImportFormat::Csv } else { ImportFormat::Json }
+    });
+    let rows = parse_rows(&body, format).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    let options = ImportOptions {
+        dry_run: params.dry_run.unwrap_or(false),
+        create_missing: params.create_missing.unwrap_or(false),
+    };
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let report = import_rows(conn, rows, options, &state.events).map_err(internal_error)?;
+    let status = if report.is_valid() { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+
+    Ok((status, Json(report)))
+}
@@ -0,0 +1,168 @@
+//! Printable labels for storage items: `GET /api/storage/<i32>/label` and `GET /api/storage/labels`.
+//!
+//! See [crate::core::label] for the layout and formats.
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::internal_error,
+        label::{render_label, render_sheet, Label, LabelFormat, LabelSize},
+        query::empty_string_as_none,
+    },
+    routes::storage::{storage_items_by_ids, StorageResponse, MAX_BATCH_SIZE},
+    AppState,
+};
+
+/// Allowed query parameters when rendering labels.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelQuery {
+    /// File format, defaults to [LabelFormat::Png].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub format: Option<LabelFormat>,
+    /// Label width in millimetres, defaults to [crate::core::label::DEFAULT_WIDTH_MM].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub width: Option<f32>,
+    /// Label height in millimetres, defaults to [crate::core::label::DEFAULT_HEIGHT_MM].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub height: Option<f32>,
+    /// Comma separated storage ids, only used for sheets.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub ids: Option<String>,
+}
+
+impl LabelQuery {
+    fn size(&self) -> Result<LabelSize, (StatusCode, String)> {
+        LabelSize::new(self.width, self.height).map_err(|err| (StatusCode::BAD_REQUEST, err))
+    }
+
+    fn format(&self) -> LabelFormat {
+        self.format.unwrap_or_default()
+    }
+}
+
+/// Content of the label of a storage item.
+fn label_from_item(item: &StorageResponse) -> Label {
+    Label {
+        code: item.code.clone(),
+        product_name: item.product_name.clone(),
+        date_in: item.in_storage_since,
+        expiration_date: item.expiration_date,
+        location: format!("{} / {}", item.freezer_name, item.drawer_name),
+        quantity: format!("{} {}", item.quantity, item.unit),
+    }
+}
+
+/// Response carrying a rendered label, to be shown inline or saved as `file_name`.`extension`.
+fn label_response(body: Vec<u8>, content_type: &'static str, file_name: &str, extension: &str) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    if let Ok(disposition) = HeaderValue::from_str(&format!("inline; filename=\"{}.{}\"", file_name, extension)) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+
+    (headers, body).into_response()
+}
+
+/// Get the label of a storage item: `GET /api/storage/<i32>/label`.
+///
+/// # Accepted query parameters
+///
+/// * `format=<png|svg|pdf>` **(defaults to png)**.
+/// * `width=<f32>`, `height=<f32>`: label size in millimetres, 62 by 29 mm when not given.
+///
+/// # Returns
+///
+/// The label in the requested format, with its `Content-Type`.
+///
+/// # Errors
+///
+/// * `NotFound` => "Storage item not found".
+/// * `BadRequest` => "width and height must be between 20 and 200 mm".
+pub async fn get_storage_label(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    params: Query<LabelQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let size = params.size()?;
+    let conn = &mut establish_connection(state.db_url);
+
+    let items = storage_items_by_ids(conn, &[id])?;
+    let item = items.first().ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Storage item not found")))?;
+    let label = label_from_item(item);
+    let format = params.format();
+    let body = tokio::task::spawn_blocking(move || render_label(&label, size, format))
+        .await
+        .map_err(internal_error)?
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+    Ok(label_response(body, format.content_type(), &format!("label-{}", id), format.extension()))
+}
+
+/// Get a sheet with the labels of multiple storage items: `GET /api/storage/labels?ids=<i32>[,<i32>]`.
+///
+/// Labels are placed in rows on A4 pages, in the order of the ids. Accepts the same query
+/// parameters as [get_storage_label]. At most [MAX_BATCH_SIZE] ids, repeated ids included.
+///
+/// # Returns
+///
+/// The sheet in the requested format, with its `Content-Type`. PNG and SVG sheets of several pages
+/// come as `application/zip` with a file per page.
+///
+/// # Errors
+///
+/// * `BadRequest` => "ids must be a comma separated list of storage ids", "A sheet holds at most 100
+///   labels" or "width and height must be between 20 and 200 mm".
+/// * `NotFound` => "Storage item <id> not found".
+pub async fn get_storage_labels(
+    State(state): State<AppState>,
+    params: Query<LabelQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let ids = params.ids
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|id| id.trim().parse::<i32>())
+        .collect::<Result<Vec<i32>, _>>()
+        .map_err(|_| (StatusCode::BAD_REQUEST, String::from("ids must be a comma separated list of storage ids")))?;
+    if ids.len() > MAX_BATCH_SIZE {
+        return Err((StatusCode::BAD_REQUEST, format!("A sheet holds at most {} labels", MAX_BATCH_SIZE)));
+    }
+    let conn = &mut establish_connection(state.db_url);
+
+    let items = storage_items_by_ids(conn, &ids)?;
+    let items = ids
+        .iter()
+        .map(|id| {
+            items.iter()
+                .find(|item| item.storage_id == *id)
+                .cloned()
+                .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Storage item {} not found", id)))
+        })
+        .collect::<Result<Vec<StorageResponse>, (StatusCode, String)>>()?;
+
+    labels_sheet(&items, &params).await
+}
+
+/// Sheet with the labels of `items`, in their order, rendered following `params`.
+pub(crate) async fn labels_sheet(items: &[StorageResponse], params: &LabelQuery) -> Result<Response, (StatusCode, String)> {
+    let labels: Vec<Label> = items.iter().map(label_from_item).collect();
+    let size = params.size()?;
+    let format = params.format();
+    let sheet = tokio::task::spawn_blocking(move || render_sheet(&labels, size, format))
+        .await
+        .map_err(internal_error)?
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+    Ok(if sheet.zipped {
+        label_response(sheet.body, "application/zip", "labels", "zip")
+    } else {
+        label_response(sheet.body, format.content_type(), "labels", format.extension())
+    })
+}
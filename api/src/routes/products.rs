@@ -1,26 +1,34 @@
 //! Endpoint `/api/products`, implements `GET`, `POST`, `PATCH`, `DELETE`.
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
 };
+use chrono::Utc;
 use diesel::prelude::*;
 use diesel::QueryDsl;
+use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 
 use crate::core::{
     connection::establish_connection,
-    error::internal_error
+    error::{internal_error, TransactionError},
+    events::{ChangeAction, Entity},
+    etag::{check_if_match, conditional_json, precondition_failed, version_header},
+    name::{lower, normalize_name, normalize_required_name},
+    query::empty_string_as_none,
+    search::{contains_pattern, search_text, word_similarity, MatchRank, SIMILARITY_THRESHOLD},
 };
-use crate::models::{NewProduct, Product};
+use crate::models::{NewProduct, Product, Unit};
+use crate::schema::{product_barcodes, product_shelf_lives, product_tags, shopping_list_items, storage};
 use crate::AppState;
 
 /// Get a product entry by its ID, given as a path parameter: `GET /api/products/id=<i32>`.
 ///
 /// # Returns
 ///
-/// A single product entry (no duplicate names allowed)
+/// A single product entry (no duplicate names allowed), with its version as `ETag` header.
 ///
 /// # Errors
 ///
@@ -28,24 +36,26 @@ use crate::AppState;
 pub async fn get_product_by_id(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<Json<Product>, (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Product>), (StatusCode, String)> {
     use crate::schema::products::dsl::*;
     let conn = &mut establish_connection(state.db_url);
 
-    let res = products
+    let (res, res_version) = products
         .filter(product_id.eq(id))
-        .select(Product::as_select())
-        .first(conn)
+        .filter(deleted_at.is_null())
+        .select((Product::as_select(), version))
+        .first::<(Product, i32)>(conn)
         .map_err(internal_error)?;
 
-    Ok(Json(res))
+    Ok((version_header(res_version), Json(res)))
 }
 
 /// Get a product entry by its name, given as a path parameter: `GET /api/products/name=<String>`.
+/// Names match regardless of case and surrounding whitespace, see [crate::core::name].
 ///
 /// # Returns
 ///
-/// A single product entry (no duplicate names allowed)
+/// A single product entry (no duplicate names allowed), with its version as `ETag` header.
 ///
 /// # Errors
 ///
@@ -53,17 +63,87 @@ pub async fn get_product_by_id(
 pub async fn get_product_by_name(
     State(state): State<AppState>,
     Path(query_name): Path<String>,
-) -> Result<Json<Product>, (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Product>), (StatusCode, String)> {
     use crate::schema::products::dsl::*;
     let conn = &mut establish_connection(state.db_url);
 
-    let res = products
-        .filter(name.eq(query_name))
-        .select(Product::as_select())
-        .first(conn)
+    let (res, res_version) = products
+        .filter(lower(name).eq(lower(normalize_name(&query_name))))
+        .filter(deleted_at.is_null())
+        .select((Product::as_select(), version))
+        .first::<(Product, i32)>(conn)
         .map_err(internal_error)?;
 
-    Ok(Json(res))
+    Ok((version_header(res_version), Json(res)))
+}
+
+/// Allowed query parameters when searching products.
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchQuery {
+    /// Text to search for.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub q: Option<String>,
+    /// Maximum number of products returned, defaults to [DEFAULT_SEARCH_LIMIT].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub limit: Option<i64>,
+}
+
+/// Number of products returned by [search_products] when no limit is given.
+pub const DEFAULT_SEARCH_LIMIT: i64 = 10;
+
+/// Search products by name, e.g. for autocompletion: `GET /api/products/search?q=<String>`.
+///
+/// Names starting with `q` come first, then names containing it, then names similar to it so typos
+/// are tolerated, see [crate::core::search]. Case and surrounding whitespace are ignored.
+///
+/// # Accepted query parameters
+///
+/// * `q=<String>`: text to search for.
+/// * `limit=<i64>`: maximum number of products, defaults to [DEFAULT_SEARCH_LIMIT].
+///
+/// # Returns
+///
+/// A vector of products, best matches first.
+///
+/// # Errors
+///
+/// * `BadRequest` => "q cannot be empty" or "limit must be positive".
+pub async fn search_products(
+    State(state): State<AppState>,
+    params: Query<SearchQuery>,
+) -> Result<Json<Vec<Product>>, (StatusCode, String)> {
+    use crate::schema::products::dsl::*;
+
+    let search = search_text(params.q.as_deref().unwrap_or_default());
+    if search.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, String::from("q cannot be empty")));
+    }
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if limit < 1 {
+        return Err((StatusCode::BAD_REQUEST, String::from("limit must be positive")));
+    }
+    let conn = &mut establish_connection(state.db_url);
+
+    let similarity = word_similarity(&search, lower(name));
+    let candidates = products
+        .filter(deleted_at.is_null())
+        .filter(lower(name).like(contains_pattern(&search)).or(similarity.ge(SIMILARITY_THRESHOLD)))
+        .select((Product::as_select(), similarity))
+        .load::<(Product, f32)>(conn)
+        .map_err(internal_error)?;
+
+    let mut ranked: Vec<(MatchRank, Product)> = candidates
+        .into_iter()
+        .map(|(product, product_similarity)| (MatchRank::of(&product.name, &search, product_similarity), product))
+        .collect();
+    ranked.sort_by(|(a_rank, a), (b_rank, b)| MatchRank::best_first(a_rank, b_rank).then_with(|| a.name.cmp(&b.name)));
+    let result = ranked
+        .into_iter()
+        .take(limit as usize)
+        .map(|(_, product)| product)
+        .collect();
+
+    Ok(Json(result))
 }
 
 /// Get products based on their expiration time in months, given as a path parameter:
@@ -71,44 +151,52 @@ pub async fn get_product_by_name(
 ///
 /// # Returns
 ///
-/// A vector of products.
+/// A vector of products. Honors `If-None-Match`, see [crate::core::etag].
 ///
 /// # Errors
 ///
 /// * `ExpirationNotFound` => "No products defined with this expiration time".
 pub async fn get_products_by_expiration(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(query_expiration): Path<i32>,
-) -> Result<Json<Vec<Product>>, (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     use crate::schema::products::dsl::*;
     let conn = &mut establish_connection(state.db_url);
 
     let res: Vec<Product> = products
         .filter(expiration_months.eq(query_expiration))
+        .filter(deleted_at.is_null())
+        .select(Product::as_select())
         .get_results(conn)
         .map_err(internal_error)?;
 
-    Ok(Json(res))
+    Ok(conditional_json(&headers, res))
 }
 
 /// Get all products stored in the database: `GET /api/products`.
 ///
 /// # Returns
 ///
-/// A vector of products.
+/// A vector of products. Honors `If-None-Match`, see [crate::core::etag].
 ///
 /// # Errors
 ///
 /// * `NotFound` => "Product not found". Only returned on an empty database.
 pub async fn get_all_products(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Product>>, (StatusCode, String)> {
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
     use crate::schema::products::dsl::*;
     let conn = &mut establish_connection(state.db_url);
 
-    let res = products.load::<Product>(conn).map_err(internal_error)?;
+    let res = products
+        .filter(deleted_at.is_null())
+        .select(Product::as_select())
+        .load::<Product>(conn)
+        .map_err(internal_error)?;
 
-    Ok(Json(res))
+    Ok(conditional_json(&headers, res))
 }
 
 /// Create a new product in the database: `POST /api/products`.
@@ -116,7 +204,7 @@ pub async fn get_all_products(
 /// # Required body
 ///
 /// [NewProduct] model in `application/json'.
-/// The product name must be unique.
+/// The product name must be unique regardless of case, it is normalized first, see [crate::core::name].
 ///
 /// # Returns
 ///
@@ -125,16 +213,20 @@ pub async fn get_all_products(
 /// # Errors
 ///
 /// * `Duplicate` => "This product name already exists".
+/// * `BadRequest` => "name cannot be empty".
 pub async fn create_product(
     State(state): State<AppState>,
     new_product: Json<NewProduct>,
 ) -> Result<Json<Product>, (StatusCode, String)> {
     use crate::schema::products::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
-    let new_product = new_product.deref().to_owned();
+    let conn = &mut establish_connection(state.db_url.clone());
+    let mut new_product = new_product.deref().to_owned();
+    new_product.name = normalize_required_name(&new_product.name)?;
 
     let name_query = products
-        .filter(name.eq(&new_product.name))
+        .filter(lower(name).eq(lower(&new_product.name)))
+        .filter(deleted_at.is_null())
+        .select(Product::as_select())
         .get_results::<Product>(conn)
         .map_err(internal_error)?;
 
@@ -151,6 +243,8 @@ pub async fn create_product(
         .get_result(conn)
         .map_err(internal_error)?;
 
+    state.events.publish(Entity::Product, ChangeAction::Created, res.product_id);
+
     Ok(Json(res))
 }
 
@@ -160,28 +254,46 @@ pub async fn create_product(
 /// # Required body
 ///
 /// [Product] model in `application/json'.
-/// The product name must be unique.
+/// The product name must be unique regardless of case, it is normalized first, see [crate::core::name].
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the product as last fetched, see [crate::core::etag].
 ///
 /// # Returns
 ///
-/// The updated [Product].
+/// The updated [Product], with its new version as `ETag` header.
 ///
 /// # Errors
 ///
 /// * `Duplicate` => "This product name already exists".
+/// * `BadRequest` => "name cannot be empty".
 /// * `NotFound` => "Product not found". Returned when a wrong product_id was entered.
+/// * `PreconditionFailed` => "This item was modified by another request".
 ///
 pub async fn update_product(
     State(state): State<AppState>,
+    headers: HeaderMap,
     update_product: Json<Product>,
-) -> Result<Json<Product>, (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Product>), (StatusCode, String)> {
     use crate::schema::products::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
-    let updated_product = update_product.deref().to_owned();
+    let conn = &mut establish_connection(state.db_url.clone());
+    let mut updated_product = update_product.deref().to_owned();
+    updated_product.name = normalize_required_name(&updated_product.name)?;
+
+    let current_version = products
+        .find(&updated_product.product_id)
+        .filter(deleted_at.is_null())
+        .select(version)
+        .first::<i32>(conn)
+        .map_err(internal_error)?;
+    check_if_match(&headers, current_version)?;
 
     let name_lookup = products
-        .filter(product_id.ne(&update_product.product_id))
-        .filter(name.eq(&update_product.name))
+        .filter(product_id.ne(&updated_product.product_id))
+        .filter(lower(name).eq(lower(&updated_product.name)))
+        .filter(deleted_at.is_null())
+        .select(Product::as_select())
         .get_results::<Product>(conn)
         .map_err(internal_error)?;
 
@@ -192,23 +304,33 @@ pub async fn update_product(
         ));
     }
 
-    let res = diesel::update(products)
+    let (res, res_version) = diesel::update(products)
         .filter(product_id.eq(&updated_product.product_id))
-        .set(&updated_product)
-        .returning(Product::as_returning())
-        .get_result(conn)
-        .map_err(internal_error)?;
+        .filter(version.eq(current_version))
+        .set((&updated_product, version.eq(version + 1)))
+        .returning((Product::as_returning(), version))
+        .get_result::<(Product, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(precondition_failed)?;
 
-    Ok(Json(res))
+    state.events.publish(Entity::Product, ChangeAction::Updated, res.product_id);
+
+    Ok((version_header(res_version), Json(res)))
 }
 
-/// Deletes a product in the database based on its `product_id`: `DELETE /api/products/id=<i32>`.
+/// Moves a product to the trash based on its `product_id`: `DELETE /api/products/id=<i32>`.
+/// All storage items of the product are moved to the trash along with it, see [crate::core::trash].
 ///
 /// # Requires
 ///
 /// A valid product ID to be given. It's recommended to implement delete protection in the frontend
 /// as it may remove a lot of data linked to a product.
 ///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the product as last fetched, see [crate::core::etag].
+///
 /// # Returns
 ///
 /// The deleted [Product] id.
@@ -216,17 +338,21 @@ pub async fn update_product(
 /// # Errors
 ///
 /// * `NotFound` => "Product not found". Returned when a wrong product_id was entered.
+/// * `PreconditionFailed` => "This item was modified by another request".
 ///
 pub async fn delete_product(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(id): Path<i32>,
 ) -> Result<Json<i32>, (StatusCode, String)> {
     use crate::schema::products::dsl::*;
-    let conn = &mut establish_connection(state.db_url);
+    let conn = &mut establish_connection(state.db_url.clone());
 
     let id_query = products
         .find(id)
-        .get_results::<Product>(conn)
+        .filter(deleted_at.is_null())
+        .select(version)
+        .get_results::<i32>(conn)
         .map_err(internal_error)?;
     if id_query.is_empty() {
         return Err((
@@ -234,15 +360,192 @@ pub async fn delete_product(
             String::from("This product id does not exist")
         ));
     }
+    check_if_match(&headers, id_query[0])?;
 
-    diesel::delete(products)
-        .filter(product_id.eq(id))
-        .execute(conn)
-        .map_err(internal_error)?;
+    let trashed_at = Utc::now();
+    conn.transaction::<_, TransactionError, _>(|conn| {
+        let deleted = diesel::update(products)
+            .filter(product_id.eq(id))
+            .filter(version.eq(id_query[0]))
+            .set((deleted_at.eq(trashed_at), version.eq(version + 1)))
+            .execute(conn)?;
+        if deleted == 0 {
+            return Err(precondition_failed().into());
+        }
+
+        diesel::update(storage::table)
+            .filter(storage::product_id.eq(id))
+            .filter(storage::deleted_at.is_null())
+            .set((storage::deleted_at.eq(trashed_at), storage::version.eq(storage::version + 1)))
+            .execute(conn)?;
+
+        Ok(())
+    })?;
+
+    state.events.publish(Entity::Product, ChangeAction::Deleted, id);
 
     Ok(Json(id))
 }
 
+/// Products to merge into another one: `POST /api/products/id=<i32>/merge`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductMerge {
+    /// Ids of the duplicate products, moved to the trash once merged.
+    pub source_ids: Vec<i32>,
+}
+
+/// Outcome of [merge_products].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    /// Id of the product the others were merged into.
+    pub product_id: i32,
+    /// Ids of the merged products, now in the trash.
+    pub merged_ids: Vec<i32>,
+    /// Amount of storage items moved to the product, withdrawn and trashed items included.
+    pub moved_items: usize,
+}
+
+/// Merges duplicate products into one: `POST /api/products/id=<i32>/merge`.
+///
+/// In a single transaction, all storage items, shopping list items, barcodes and tags of the source
+/// products are moved to the product with the given id, as are shelf life overrides for freezer types
+/// the product has none for, and the minimum stock when the product has none. The source products are
+/// then moved to the trash, see [crate::core::trash].
+///
+/// # Required body
+///
+/// [ProductMerge] model in `application/json`.
+///
+/// # Returns
+///
+/// [MergeResult], reporting how many storage items moved.
+///
+/// # Errors
+///
+/// * `BadRequest` => "sourceIds cannot be empty" or "A product cannot be merged into itself".
+/// * `NotFound` => "Product <i32> not found", for the product or any of the sources.
+pub async fn merge_products(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    merge: Json<ProductMerge>,
+) -> Result<Json<MergeResult>, (StatusCode, String)> {
+    use crate::schema::products::dsl::*;
+
+    let mut source_ids = merge.source_ids.clone();
+    source_ids.sort();
+    source_ids.dedup();
+    if source_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, String::from("sourceIds cannot be empty")));
+    }
+    if source_ids.contains(&id) {
+        return Err((StatusCode::BAD_REQUEST, String::from("A product cannot be merged into itself")));
+    }
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let trashed_at = Utc::now();
+    let (moved_ids, moved_list_ids) = conn.transaction::<_, TransactionError, _>(|conn| {
+        let all_ids: Vec<i32> = source_ids.iter().copied().chain([id]).collect();
+        let active_ids = products
+            .filter(product_id.eq_any(&all_ids))
+            .filter(deleted_at.is_null())
+            .select(product_id)
+            .for_update()
+            .load::<i32>(conn)?;
+        if let Some(missing) = all_ids.iter().find(|product| !active_ids.contains(product)) {
+            return Err((StatusCode::NOT_FOUND, format!("Product {} not found", missing)).into());
+        }
+
+        let moved_ids = diesel::update(storage::table)
+            .filter(storage::product_id.eq_any(&source_ids))
+            .set((storage::product_id.eq(id), storage::version.eq(storage::version + 1)))
+            .returning(storage::storage_id)
+            .get_results::<i32>(conn)?;
+        diesel::update(product_barcodes::table)
+            .filter(product_barcodes::product_id.eq_any(&source_ids))
+            .set(product_barcodes::product_id.eq(id))
+            .execute(conn)?;
+
+        // Tags and overrides the product already has are kept, the duplicates of the sources dropped.
+        let source_tags = product_tags::table
+            .filter(product_tags::product_id.eq_any(&source_ids))
+            .select(product_tags::tag_id)
+            .load::<i32>(conn)?;
+        diesel::insert_into(product_tags::table)
+            .values(source_tags.iter().map(|tag| (product_tags::product_id.eq(id), product_tags::tag_id.eq(tag))).collect::<Vec<_>>())
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        diesel::delete(product_tags::table.filter(product_tags::product_id.eq_any(&source_ids))).execute(conn)?;
+        let source_overrides = product_shelf_lives::table
+            .filter(product_shelf_lives::product_id.eq_any(&source_ids))
+            .order_by((product_shelf_lives::product_id, product_shelf_lives::freezer_type_id))
+            .select((product_shelf_lives::freezer_type_id, product_shelf_lives::shelf_life_days))
+            .load::<(i32, i32)>(conn)?;
+        diesel::insert_into(product_shelf_lives::table)
+            .values(source_overrides.iter().map(|(freezer_type, days)| (
+                product_shelf_lives::product_id.eq(id),
+                product_shelf_lives::freezer_type_id.eq(freezer_type),
+                product_shelf_lives::shelf_life_days.eq(days),
+            )).collect::<Vec<_>>())
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        diesel::delete(product_shelf_lives::table.filter(product_shelf_lives::product_id.eq_any(&source_ids))).execute(conn)?;
+
+        let moved_list_ids = diesel::update(shopping_list_items::table)
+            .filter(shopping_list_items::product_id.eq_any(&source_ids))
+            .set(shopping_list_items::product_id.eq(id))
+            .returning(shopping_list_items::item_id)
+            .get_results::<i32>(conn)?;
+        let has_min_stock = products
+            .find(id)
+            .select(min_stock_quantity.is_not_null())
+            .first::<bool>(conn)?;
+        if !has_min_stock {
+            let source_min_stock = products
+                .filter(product_id.eq_any(&source_ids))
+                .filter(min_stock_quantity.is_not_null())
+                .order_by(product_id)
+                .select((min_stock_quantity, min_stock_unit))
+                .first::<(Option<f32>, Option<Unit>)>(conn)
+                .optional()?;
+            if let Some((source_quantity, source_unit)) = source_min_stock {
+                diesel::update(products.find(id))
+                    .set((min_stock_quantity.eq(source_quantity), min_stock_unit.eq(source_unit)))
+                    .execute(conn)?;
+            }
+        }
+
+        diesel::update(products)
+            .filter(product_id.eq_any(&source_ids))
+            .set((
+                deleted_at.eq(trashed_at),
+                min_stock_quantity.eq(None::<f32>),
+                min_stock_unit.eq(None::<Unit>),
+                version.eq(version + 1),
+            ))
+            .execute(conn)?;
+        diesel::update(products.find(id))
+            .set(version.eq(version + 1))
+            .execute(conn)?;
+
+        Ok((moved_ids, moved_list_ids))
+    })?;
+
+    for storage_id in &moved_ids {
+        state.events.publish(Entity::Storage, ChangeAction::Updated, *storage_id);
+    }
+    for item_id in &moved_list_ids {
+        state.events.publish(Entity::ShoppingListItem, ChangeAction::Updated, *item_id);
+    }
+    for source_id in &source_ids {
+        state.events.publish(Entity::Product, ChangeAction::Deleted, *source_id);
+    }
+    state.events.publish(Entity::Product, ChangeAction::Updated, id);
+
+    Ok(Json(MergeResult { product_id: id, merged_ids: source_ids, moved_items: moved_ids.len() }))
+}
+
 /// Counts the amount of occurrences of a product in the storage table:
 /// `GET /api/products/storage?id=<i32>`.
 ///
@@ -0,0 +1,121 @@
+//! Endpoint `/api/recommendations`, implements `GET`.
+//!
+//! Ranks the available storage items by what to eat next, see [crate::core::recommendation].
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        etag::conditional_json,
+        query::empty_string_as_none,
+        recommendation::{reason, score, Strategy},
+    },
+    routes::storage::{filter_storage, StorageFilter, StorageResponse},
+    AppState,
+};
+
+/// Query parameters of [get_recommendations].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendationQuery {
+    /// How items are ranked, defaults to [Strategy::Mixed].
+    #[serde(default)]
+    pub strategy: Strategy,
+    /// Name of the product, see [StorageFilter::product_name].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub product_name: Option<String>,
+    /// Match [Self::product_name] fuzzily, see [StorageFilter::fuzzy].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub fuzzy: Option<bool>,
+    /// Comma separated category names, see [StorageFilter::category].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub category: Option<String>,
+    /// Comma separated tag names, see [StorageFilter::tag].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub tag: Option<String>,
+    /// Name of the freezer, see [StorageFilter::freezer_name].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub freezer_name: Option<String>,
+    /// Maximum number of items returned, defaults to [DEFAULT_RECOMMENDATION_LIMIT].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub limit: Option<usize>,
+}
+
+/// Amount of items returned by [get_recommendations] when no `limit` is given.
+pub const DEFAULT_RECOMMENDATION_LIMIT: usize = 10;
+
+/// Storage item to eat next, as returned by [get_recommendations].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Recommendation {
+    /// The storage item, with the same fields as returned by [crate::routes::storage::get_storage].
+    #[serde(flatten)]
+    pub item: StorageResponse,
+    /// Score of the item, higher is eaten first, see [crate::core::recommendation::score].
+    pub score: f64,
+    /// Why the item is recommended, e.g. "Expires in 3 days, in storage for 200 days".
+    pub reason: String,
+}
+
+/// Get the available storage items to eat next: `GET /api/recommendations`.
+///
+/// # Accepted query parameters
+///
+/// * `strategy=<fifo|fefo|mixed>` **(defaults to mixed)**: how items are ranked, see [crate::core::recommendation].
+/// * `productName=<String>` and `fuzzy=<bool>`: Name of the product, as for [crate::routes::storage::get_storage].
+/// * `category=<String>[,<String>]`: Products in any of the categories, subcategories included.
+/// * `tag=<String>[,<String>]`: Products carrying any of the tags.
+/// * `freezerName=<String>`: Name of the freezer.
+/// * `limit=<usize>`: maximum number of items, defaults to [DEFAULT_RECOMMENDATION_LIMIT].
+///
+/// # Returns
+///
+/// Vec<[Recommendation]>, highest score first. Items with the same score are sorted by id.
+/// Honors `If-None-Match`, see [crate::core::etag].
+///
+/// # Errors
+///
+/// * `BadRequest` => "limit must be positive".
+pub async fn get_recommendations(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    params: Query<RecommendationQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let params = params.0;
+    let limit = params.limit.unwrap_or(DEFAULT_RECOMMENDATION_LIMIT);
+    if limit < 1 {
+        return Err((StatusCode::BAD_REQUEST, String::from("limit must be positive")));
+    }
+    let filter = StorageFilter {
+        product_name: params.product_name,
+        fuzzy: params.fuzzy,
+        freezer_name: params.freezer_name,
+        category: params.category,
+        tag: params.tag,
+        ..Default::default()
+    };
+    let conn = &mut establish_connection(state.db_url);
+
+    let today = Local::now().date_naive();
+    let mut recommendations: Vec<Recommendation> = filter_storage(conn, &filter)?
+        .into_iter()
+        .map(|item| {
+            let days_in_storage = (today - item.in_storage_since).num_days();
+            Recommendation {
+                score: score(params.strategy, item.expires_in_days, days_in_storage),
+                reason: reason(params.strategy, item.expires_in_days, days_in_storage),
+                item,
+            }
+        })
+        .collect();
+    recommendations.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.item.storage_id.cmp(&b.item.storage_id)));
+    recommendations.truncate(limit);
+
+    Ok(conditional_json(&headers, recommendations))
+}
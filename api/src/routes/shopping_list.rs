@@ -0,0 +1,406 @@
+//! Endpoint `/api/shopping-list`, implements `GET`, and `POST`, `PATCH`, `DELETE` on its items.
+//!
+//! Also implements the minimum stock of a product: `GET` and `PATCH` on `/api/products/id=<i32>/min-stock`.
+//! See [crate::core::shopping_list] for how products end up on the list.
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use chrono::{Local, NaiveDate, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+        etag::{check_if_match, conditional_json, precondition_failed, version_header},
+        name::normalize_name,
+        shopping_list::{suggestions, MinStock, ShoppingSuggestion},
+    },
+    models::{NewShoppingListItem, NewStorageItem, ShoppingListItem, Unit},
+    routes::storage::{checked_new_item, get_storage_by_id, StorageResponse},
+    schema::{products, shopping_list_items, storage},
+    AppState,
+};
+
+/// Minimum stock of a single product, used to read and change it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductMinStock {
+    /// Product id. Ignored when sent by the frontend, the id in the path is used.
+    #[serde(default)]
+    pub product_id: i32,
+    /// Quantity to keep in stock, `None` when the product has no minimum.
+    pub min_quantity: Option<f32>,
+    /// Unit of the minimum quantity. Defaults to the product `default_unit` when changing it.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+}
+
+/// The shopping list: `GET /api/shopping-list`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShoppingList {
+    /// Products below their minimum stock, sorted by product name.
+    pub suggestions: Vec<ShoppingSuggestion>,
+    /// Items added by hand and not checked off yet, oldest first.
+    pub items: Vec<ShoppingListItem>,
+}
+
+/// Optional body when checking off an item: `PATCH /api/shopping-list/items/<i32>/check`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckOff {
+    /// Store the bought item in a drawer right away.
+    #[serde(default)]
+    pub store: Option<CheckOffStorage>,
+}
+
+/// Storage item to create for a checked off item.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckOffStorage {
+    /// **Required**: ID of the drawer in which the item will be stored.
+    pub drawer_id: i32,
+    /// **Optional**: Quantity, defaults to the quantity of the shopping list item, or 1.
+    #[serde(default)]
+    pub quantity: Option<f32>,
+    /// **Optional**: Unit, defaults to the unit of the shopping list item, or the product default unit.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+    /// **Optional**: Date in, defaults to the current date.
+    #[serde(default)]
+    pub date_in: Option<NaiveDate>,
+    /// **Optional**: Explicit expiration date, e.g. the printed best-before date.
+    #[serde(default)]
+    pub expiration_date: Option<NaiveDate>,
+}
+
+/// Checked off shopping list item, with the storage item created for it if any.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckedItem {
+    /// The checked off item.
+    pub item: ShoppingListItem,
+    /// The created storage item, empty when the item was not stored.
+    pub storage: Vec<StorageResponse>,
+}
+
+/// Get the minimum stock of a product: `GET /api/products/id=<i32>/min-stock`.
+///
+/// # Returns
+///
+/// [ProductMinStock], in format `application/json`, with the version of the product as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+pub async fn get_product_min_stock(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<ProductMinStock>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let (min_quantity, unit, product_version) = products::table
+        .find(id)
+        .filter(products::deleted_at.is_null())
+        .select((products::min_stock_quantity, products::min_stock_unit, products::version))
+        .first::<(Option<f32>, Option<Unit>, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Product not found")))?;
+
+    Ok((version_header(product_version), Json(ProductMinStock { product_id: id, min_quantity, unit })))
+}
+
+/// Changes the minimum stock of a product: `PATCH /api/products/id=<i32>/min-stock`.
+///
+/// # Required body
+///
+/// [ProductMinStock] in `application/json`, a `minQuantity` of `null` removes the minimum.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the product as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The updated [ProductMinStock], with the new version of the product as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+/// * `BadRequest` => "minQuantity must be positive".
+/// * `PreconditionFailed` => "This item was modified by another request".
+pub async fn set_product_min_stock(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    min_stock: Json<ProductMinStock>,
+) -> Result<(HeaderMap, Json<ProductMinStock>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    if min_stock.min_quantity.is_some_and(|quantity| !quantity.is_finite() || quantity <= 0.0) {
+        return Err((StatusCode::BAD_REQUEST, String::from("minQuantity must be positive")));
+    }
+    let (default_unit, current_version) = products::table
+        .find(id)
+        .filter(products::deleted_at.is_null())
+        .select((products::default_unit, products::version))
+        .first::<(Unit, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Product not found")))?;
+    check_if_match(&headers, current_version)?;
+    let unit = min_stock.min_quantity.map(|_| min_stock.unit.unwrap_or(default_unit));
+
+    let update_version = diesel::update(products::table.find(id))
+        .filter(products::version.eq(current_version))
+        .set((
+            products::min_stock_quantity.eq(min_stock.min_quantity),
+            products::min_stock_unit.eq(unit),
+            products::version.eq(products::version + 1),
+        ))
+        .returning(products::version)
+        .get_result::<i32>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(precondition_failed)?;
+
+    state.events.publish(Entity::Product, ChangeAction::Updated, id);
+
+    Ok((version_header(update_version), Json(ProductMinStock { product_id: id, min_quantity: min_stock.min_quantity, unit })))
+}
+
+/// Get the shopping list: `GET /api/shopping-list`.
+///
+/// # Returns
+///
+/// [ShoppingList], with the products below their minimum stock and the items added by hand. Honors
+/// `If-None-Match`, see [crate::core::etag].
+pub async fn get_shopping_list(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let min_stocks: Vec<MinStock> = products::table
+        .filter(products::deleted_at.is_null())
+        .filter(products::min_stock_quantity.is_not_null())
+        .select((products::product_id, products::name, products::min_stock_quantity.assume_not_null(), products::min_stock_unit.assume_not_null()))
+        .load::<(i32, String, f32, Unit)>(conn)
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|(product_id, product_name, min_quantity, unit)| MinStock { product_id, product_name, min_quantity, unit })
+        .collect();
+    let product_ids: Vec<i32> = min_stocks.iter().map(|min_stock| min_stock.product_id).collect();
+    let mut available: HashMap<(i32, Unit), f32> = HashMap::new();
+    let available_items = storage::table
+        .filter(storage::product_id.eq_any(&product_ids))
+        .filter(storage::date_out.is_null())
+        .filter(storage::deleted_at.is_null())
+        .select((storage::product_id, storage::unit, storage::quantity))
+        .load::<(i32, Unit, f32)>(conn)
+        .map_err(internal_error)?;
+    for (product_id, unit, quantity) in available_items {
+        *available.entry((product_id, unit)).or_insert(0.0) += quantity;
+    }
+
+    let items = shopping_list_items::table
+        .filter(shopping_list_items::checked_at.is_null())
+        .select(ShoppingListItem::as_select())
+        .order_by(shopping_list_items::item_id)
+        .load::<ShoppingListItem>(conn)
+        .map_err(internal_error)?;
+
+    Ok(conditional_json(&headers, ShoppingList { suggestions: suggestions(&min_stocks, &available), items }))
+}
+
+/// Add an item to the shopping list: `POST /api/shopping-list/items`.
+///
+/// # Required body
+///
+/// [NewShoppingListItem] model in `application/json`.
+///
+/// # Returns
+///
+/// The new [ShoppingListItem].
+///
+/// # Errors
+///
+/// * `BadRequest` => "name or productId is required" or "quantity must be positive".
+/// * `NotFound` => "Product not found".
+pub async fn create_shopping_list_item(
+    State(state): State<AppState>,
+    new_item: Json<NewShoppingListItem>,
+) -> Result<Json<ShoppingListItem>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let mut new_item = new_item.deref().to_owned();
+
+    if new_item.quantity.is_some_and(|quantity| !quantity.is_finite() || quantity <= 0.0) {
+        return Err((StatusCode::BAD_REQUEST, String::from("quantity must be positive")));
+    }
+    new_item.name = normalize_name(&new_item.name);
+    if let Some(product_id) = new_item.product_id {
+        let product_name = products::table
+            .find(product_id)
+            .filter(products::deleted_at.is_null())
+            .select(products::name)
+            .first::<String>(conn)
+            .optional()
+            .map_err(internal_error)?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Product not found")))?;
+        if new_item.name.is_empty() {
+            new_item.name = product_name;
+        }
+    }
+    if new_item.name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, String::from("name or productId is required")));
+    }
+
+    let result = diesel::insert_into(shopping_list_items::table)
+        .values(&new_item)
+        .returning(ShoppingListItem::as_returning())
+        .get_result(conn)
+        .map_err(internal_error)?;
+
+    state.events.publish(Entity::ShoppingListItem, ChangeAction::Created, result.item_id);
+
+    Ok(Json(result))
+}
+
+/// Removes an item from the shopping list: `DELETE /api/shopping-list/items/<i32>`.
+///
+/// # Returns
+///
+/// The id of the deleted [ShoppingListItem].
+///
+/// # Errors
+///
+/// * `NotFound` => "Shopping list item not found".
+pub async fn delete_shopping_list_item(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<i32>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let deleted = diesel::delete(shopping_list_items::table.find(id))
+        .execute(conn)
+        .map_err(internal_error)?;
+    if deleted == 0 {
+        return Err(item_not_found());
+    }
+
+    state.events.publish(Entity::ShoppingListItem, ChangeAction::Deleted, id);
+
+    Ok(Json(id))
+}
+
+/// Checks off an item of the shopping list, once bought: `PATCH /api/shopping-list/items/<i32>/check`.
+///
+/// # Optional body
+///
+/// [CheckOff] in `application/json`, with `store` to put the bought item in the freezer right away,
+/// see [crate::routes::storage::create_storage].
+///
+/// # Returns
+///
+/// [CheckedItem], with the created storage item if stored.
+///
+/// # Errors
+///
+/// * `NotFound` => "Shopping list item not found".
+/// * `Conflict` => "Shopping list item is already checked off".
+/// * `BadRequest` => "Only items of a product can be stored".
+/// * Same as [crate::routes::storage::create_storage] when storing.
+pub async fn check_shopping_list_item(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    check_off: Option<Json<CheckOff>>,
+) -> Result<Json<CheckedItem>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let check_off = check_off.map(|check_off| check_off.0).unwrap_or_default();
+
+    // Checking off and storing happen together, so concurrent check-offs can't store the item twice.
+    let (item, storage_id) = conn.transaction::<_, TransactionError, _>(|conn| {
+        let item = diesel::update(shopping_list_items::table.find(id))
+            .filter(shopping_list_items::checked_at.is_null())
+            .set(shopping_list_items::checked_at.eq(Utc::now()))
+            .returning(ShoppingListItem::as_returning())
+            .get_result::<ShoppingListItem>(conn)
+            .optional()?;
+        let item = match item {
+            Some(item) => item,
+            None => {
+                let exists = diesel::select(diesel::dsl::exists(shopping_list_items::table.find(id))).get_result::<bool>(conn)?;
+                return Err(if exists {
+                    (StatusCode::CONFLICT, String::from("Shopping list item is already checked off")).into()
+                } else {
+                    item_not_found().into()
+                });
+            }
+        };
+
+        let storage_id = match check_off.store {
+            None => None,
+            Some(store) => {
+                let product_id = item.product_id
+                    .ok_or_else(|| (StatusCode::BAD_REQUEST, String::from("Only items of a product can be stored")))?;
+                let new_storage_item = checked_new_item(conn, NewStorageItem {
+                    product_id,
+                    drawer_id: store.drawer_id,
+                    quantity: store.quantity.or(item.quantity).unwrap_or(1.0),
+                    unit: store.unit.or(item.unit),
+                    date_in: store.date_in.unwrap_or_else(|| Local::now().date_naive()),
+                    expiration_date: store.expiration_date,
+                })?;
+                let storage_id = diesel::insert_into(storage::table)
+                    .values(&new_storage_item)
+                    .returning(storage::storage_id)
+                    .get_result::<i32>(conn)?;
+                Some(storage_id)
+            }
+        };
+
+        Ok((item, storage_id))
+    })?;
+
+    let storage = match storage_id {
+        None => Vec::new(),
+        Some(storage_id) => {
+            state.events.publish(Entity::Storage, ChangeAction::Created, storage_id);
+            let (_, Json(created)) = get_storage_by_id(State(state.clone()), Path(storage_id)).await?;
+            created
+        }
+    };
+    state.events.publish(Entity::ShoppingListItem, ChangeAction::Updated, id);
+
+    Ok(Json(CheckedItem { item, storage }))
+}
+
+/// Puts a checked off item back on the shopping list: `PATCH /api/shopping-list/items/<i32>/uncheck`.
+/// Storage items created when checking it off are kept.
+///
+/// # Returns
+///
+/// The updated [ShoppingListItem].
+///
+/// # Errors
+///
+/// * `NotFound` => "Shopping list item not found".
+pub async fn uncheck_shopping_list_item(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<ShoppingListItem>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let item = diesel::update(shopping_list_items::table.find(id))
+        .set(shopping_list_items::checked_at.eq(None::<chrono::DateTime<Utc>>))
+        .returning(ShoppingListItem::as_returning())
+        .get_result(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(item_not_found)?;
+
+    state.events.publish(Entity::ShoppingListItem, ChangeAction::Updated, id);
+
+    Ok(Json(item))
+}
+
+fn item_not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, String::from("Shopping list item not found"))
+}
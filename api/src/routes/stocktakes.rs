@@ -0,0 +1,434 @@
+//! Endpoint `/api/stocktakes`: stocktakes of a freezer, reconciling its contents with the database.
+//!
+//! A stocktake is started for a freezer, after which every item taken out of it is marked as found by
+//! its id or its code. Everything is stored as it happens, so a stocktake can be resumed at any time.
+//! Completing it withdraws the available items of the freezer that were not found, with reason
+//! [WithdrawalReason::Missing], and reports the discrepancies, see [StocktakeReport]. A freezer has
+//! at most one open stocktake.
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{Local, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+    },
+    models::{NewStocktake, NewStocktakeItem, Stocktake, WithdrawalReason},
+    routes::storage::{storage_id_by_code, storage_items_by_ids, StorageResponse},
+    schema::{drawers, freezers, stocktake_items, stocktakes, storage},
+    AppState,
+};
+
+/// Query parameters of [get_stocktakes].
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StocktakeQuery {
+    /// Only the stocktakes of this freezer.
+    pub freezer_id: Option<i32>,
+    /// Only open stocktakes when `true`, only completed ones when `false`.
+    pub open: Option<bool>,
+}
+
+/// State of a stocktake and its discrepancies, returned by all endpoints of a single stocktake.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StocktakeReport {
+    /// The stocktake.
+    #[serde(flatten)]
+    pub stocktake: Stocktake,
+    /// Name of the freezer being counted.
+    pub freezer_name: String,
+    /// Items found, sorted by id.
+    pub seen: Vec<StorageResponse>,
+    /// Available items of the freezer not found yet. Once completed, the items withdrawn as missing.
+    pub unseen: Vec<StorageResponse>,
+    /// Items found that are registered in the drawer of another freezer.
+    pub misplaced: Vec<StorageResponse>,
+    /// Items found that are registered as withdrawn.
+    pub found_withdrawn: Vec<StorageResponse>,
+}
+
+fn stocktake_not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, String::from("Stocktake not found"))
+}
+
+fn already_completed() -> (StatusCode, String) {
+    (StatusCode::CONFLICT, String::from("Stocktake is already completed"))
+}
+
+/// Loads a stocktake.
+fn load_stocktake(conn: &mut PgConnection, id: i32) -> Result<Stocktake, (StatusCode, String)> {
+    stocktakes::table
+        .find(id)
+        .select(Stocktake::as_select())
+        .first::<Stocktake>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(stocktake_not_found)
+}
+
+/// Loads a stocktake that is still open and locks it until the end of the transaction, so it can't
+/// be completed meanwhile.
+fn lock_open_stocktake(conn: &mut PgConnection, id: i32) -> Result<Stocktake, TransactionError> {
+    let stocktake = stocktakes::table
+        .find(id)
+        .select(Stocktake::as_select())
+        .for_update()
+        .first::<Stocktake>(conn)
+        .optional()?
+        .ok_or_else(stocktake_not_found)?;
+    if stocktake.completed_at.is_some() {
+        return Err(already_completed().into());
+    }
+
+    Ok(stocktake)
+}
+
+/// Ids of the available items of a freezer not found during the stocktake, sorted.
+fn unseen_ids(conn: &mut PgConnection, stocktake: &Stocktake) -> QueryResult<Vec<i32>> {
+    let seen = stocktake_items::table
+        .filter(stocktake_items::stocktake_id.eq(stocktake.stocktake_id))
+        .select(stocktake_items::storage_id);
+
+    storage::table
+        .inner_join(drawers::table)
+        .filter(drawers::freezer_id.eq(stocktake.freezer_id))
+        .filter(storage::date_out.is_null())
+        .filter(storage::deleted_at.is_null())
+        .filter(storage::storage_id.ne_all(seen))
+        .select(storage::storage_id)
+        .order_by(storage::storage_id)
+        .load::<i32>(conn)
+}
+
+/// Builds the report of a stocktake, see [StocktakeReport].
+fn report(conn: &mut PgConnection, stocktake: Stocktake) -> Result<StocktakeReport, (StatusCode, String)> {
+    let freezer_name = freezers::table
+        .find(stocktake.freezer_id)
+        .select(freezers::name)
+        .first::<String>(conn)
+        .map_err(internal_error)?;
+    // Storage id, freezer and withdrawal date of the items found, missing items have no `seen_at`.
+    let found = stocktake_items::table
+        .inner_join(storage::table.inner_join(drawers::table))
+        .filter(stocktake_items::stocktake_id.eq(stocktake.stocktake_id))
+        .filter(stocktake_items::seen_at.is_not_null())
+        .select((storage::storage_id, drawers::freezer_id, storage::date_out.is_not_null()))
+        .load::<(i32, i32, bool)>(conn)
+        .map_err(internal_error)?;
+    let missing = if stocktake.completed_at.is_some() {
+        stocktake_items::table
+            .filter(stocktake_items::stocktake_id.eq(stocktake.stocktake_id))
+            .filter(stocktake_items::seen_at.is_null())
+            .select(stocktake_items::storage_id)
+            .load::<i32>(conn)
+            .map_err(internal_error)?
+    } else {
+        unseen_ids(conn, &stocktake).map_err(internal_error)?
+    };
+
+    let seen_ids: Vec<i32> = found.iter().map(|(id, _, _)| *id).collect();
+    let misplaced: HashSet<i32> = found
+        .iter()
+        .filter(|(_, freezer_id, _)| *freezer_id != stocktake.freezer_id)
+        .map(|(id, _, _)| *id)
+        .collect();
+    let withdrawn: HashSet<i32> = found
+        .iter()
+        .filter(|(_, _, withdrawn)| *withdrawn)
+        .map(|(id, _, _)| *id)
+        .collect();
+    let seen = storage_items_by_ids(conn, &seen_ids)?;
+
+    Ok(StocktakeReport {
+        stocktake,
+        freezer_name,
+        misplaced: seen.iter().filter(|item| misplaced.contains(&item.storage_id)).cloned().collect(),
+        found_withdrawn: seen.iter().filter(|item| withdrawn.contains(&item.storage_id)).cloned().collect(),
+        unseen: storage_items_by_ids(conn, &missing)?,
+        seen,
+    })
+}
+
+/// Get the stocktakes: `GET /api/stocktakes`.
+///
+/// # Optional query parameters
+///
+/// See [StocktakeQuery], e.g. `?freezerId=1&open=true` to find the stocktake to resume.
+///
+/// # Returns
+///
+/// Vec<[Stocktake]>, most recent first.
+pub async fn get_stocktakes(State(state): State<AppState>, Query(query): Query<StocktakeQuery>) -> Result<Json<Vec<Stocktake>>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let mut stocktakes_query = stocktakes::table.select(Stocktake::as_select()).into_boxed();
+    if let Some(freezer_id) = query.freezer_id {
+        stocktakes_query = stocktakes_query.filter(stocktakes::freezer_id.eq(freezer_id));
+    }
+    match query.open {
+        Some(true) => stocktakes_query = stocktakes_query.filter(stocktakes::completed_at.is_null()),
+        Some(false) => stocktakes_query = stocktakes_query.filter(stocktakes::completed_at.is_not_null()),
+        None => {}
+    }
+
+    let result = stocktakes_query
+        .order_by(stocktakes::stocktake_id.desc())
+        .load::<Stocktake>(conn)
+        .map_err(internal_error)?;
+
+    Ok(Json(result))
+}
+
+/// Get a stocktake and its discrepancies so far: `GET /api/stocktakes/<i32>`.
+///
+/// # Returns
+///
+/// [StocktakeReport].
+///
+/// # Errors
+///
+/// * `NotFound` => "Stocktake not found".
+pub async fn get_stocktake(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<StocktakeReport>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let stocktake = load_stocktake(conn, id)?;
+
+    Ok(Json(report(conn, stocktake)?))
+}
+
+/// Get the items not found yet: `GET /api/stocktakes/<i32>/unseen`.
+///
+/// # Returns
+///
+/// Vec<[StorageResponse]>, the `unseen` items of the [StocktakeReport].
+///
+/// # Errors
+///
+/// * `NotFound` => "Stocktake not found".
+pub async fn get_unseen_items(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Vec<StorageResponse>>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let stocktake = load_stocktake(conn, id)?;
+
+    Ok(Json(report(conn, stocktake)?.unseen))
+}
+
+/// Start a stocktake: `POST /api/stocktakes`.
+///
+/// # Required body
+///
+/// [NewStocktake] model in `application/json`.
+///
+/// # Returns
+///
+/// [StocktakeReport] of the new stocktake, with all available items of the freezer unseen.
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer not found".
+/// * `Conflict` => "Freezer already has an open stocktake <id>", to be resumed or deleted first.
+pub async fn create_stocktake(State(state): State<AppState>, new_stocktake: Json<NewStocktake>) -> Result<Json<StocktakeReport>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let stocktake = conn.transaction::<_, TransactionError, _>(|conn| {
+        freezers::table
+            .find(new_stocktake.freezer_id)
+            .filter(freezers::deleted_at.is_null())
+            .select(freezers::freezer_id)
+            .for_update()
+            .first::<i32>(conn)
+            .optional()?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Freezer not found")))?;
+        let open = stocktakes::table
+            .filter(stocktakes::freezer_id.eq(new_stocktake.freezer_id))
+            .filter(stocktakes::completed_at.is_null())
+            .select(stocktakes::stocktake_id)
+            .first::<i32>(conn)
+            .optional()?;
+        if let Some(open) = open {
+            return Err((StatusCode::CONFLICT, format!("Freezer already has an open stocktake {}", open)).into());
+        }
+
+        Ok(diesel::insert_into(stocktakes::table)
+            .values(&*new_stocktake)
+            .returning(Stocktake::as_returning())
+            .get_result(conn)?)
+    })?;
+
+    state.events.publish(Entity::Stocktake, ChangeAction::Created, stocktake.stocktake_id);
+
+    Ok(Json(report(conn, stocktake)?))
+}
+
+/// Mark an item as found: `PATCH /api/stocktakes/<i32>/items/<i32>`, marking it again changes nothing.
+///
+/// Items of other freezers and withdrawn items can be marked as well, they are reported as
+/// discrepancies.
+///
+/// # Returns
+///
+/// The updated [StocktakeReport].
+///
+/// # Errors
+///
+/// * `NotFound` => "Stocktake not found" or "Storage item not found".
+/// * `Conflict` => "Stocktake is already completed".
+pub async fn mark_item_seen(State(state): State<AppState>, Path((id, storage_id)): Path<(i32, i32)>) -> Result<Json<StocktakeReport>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let stocktake = conn.transaction::<_, TransactionError, _>(|conn| {
+        let stocktake = lock_open_stocktake(conn, id)?;
+        let found = storage::table
+            .find(storage_id)
+            .filter(storage::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+        if found == 0 {
+            return Err((StatusCode::NOT_FOUND, String::from("Storage item not found")).into());
+        }
+
+        diesel::insert_into(stocktake_items::table)
+            .values(&NewStocktakeItem { stocktake_id: id, storage_id, seen_at: Some(Utc::now()) })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(stocktake)
+    })?;
+
+    state.events.publish(Entity::Stocktake, ChangeAction::Updated, id);
+
+    Ok(Json(report(conn, stocktake)?))
+}
+
+/// Mark an item as found by its code: `PATCH /api/stocktakes/<i32>/code/<String>`, e.g. after scanning
+/// its label.
+///
+/// Same as [mark_item_seen].
+///
+/// # Errors
+///
+/// * `BadRequest`: not a valid code.
+/// * Same as [mark_item_seen].
+pub async fn mark_code_seen(State(state): State<AppState>, Path((id, item_code)): Path<(i32, String)>) -> Result<Json<StocktakeReport>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let storage_id = storage_id_by_code(conn, &item_code)?;
+
+    mark_item_seen(State(state), Path((id, storage_id))).await
+}
+
+/// Undo marking an item as found: `DELETE /api/stocktakes/<i32>/items/<i32>`.
+///
+/// # Returns
+///
+/// The updated [StocktakeReport].
+///
+/// # Errors
+///
+/// * `NotFound` => "Stocktake not found".
+/// * `Conflict` => "Stocktake is already completed".
+pub async fn unmark_item_seen(State(state): State<AppState>, Path((id, storage_id)): Path<(i32, i32)>) -> Result<Json<StocktakeReport>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let stocktake = conn.transaction::<_, TransactionError, _>(|conn| {
+        let stocktake = lock_open_stocktake(conn, id)?;
+        diesel::delete(stocktake_items::table.find((id, storage_id))).execute(conn)?;
+
+        Ok(stocktake)
+    })?;
+
+    state.events.publish(Entity::Stocktake, ChangeAction::Updated, id);
+
+    Ok(Json(report(conn, stocktake)?))
+}
+
+/// Complete a stocktake: `POST /api/stocktakes/<i32>/complete`.
+///
+/// Withdraws the available items of the freezer that were not found, with reason
+/// [WithdrawalReason::Missing] and the stocktake in the note. Other discrepancies are only reported.
+///
+/// # Returns
+///
+/// The final [StocktakeReport], with the withdrawn items as `unseen`.
+///
+/// # Errors
+///
+/// * `NotFound` => "Stocktake not found".
+/// * `Conflict` => "Stocktake is already completed".
+pub async fn complete_stocktake(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<StocktakeReport>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let (stocktake, missing) = conn.transaction::<_, TransactionError, _>(|conn| {
+        let locked = lock_open_stocktake(conn, id)?;
+
+        // Items withdrawn or deleted since the lookup are left alone, only the ones updated count as missing.
+        let unseen = unseen_ids(conn, &locked)?;
+        let still_stored = storage::table
+            .filter(storage::storage_id.eq_any(&unseen))
+            .filter(storage::date_out.is_null())
+            .filter(storage::deleted_at.is_null());
+        let missing = diesel::update(still_stored)
+            .set((
+                storage::date_out.eq(Local::now().date_naive()),
+                storage::withdrawal_reason.eq(WithdrawalReason::Missing),
+                storage::withdrawal_note.eq(format!("Not found during stocktake {}", id)),
+                storage::version.eq(storage::version + 1),
+            ))
+            .returning(storage::storage_id)
+            .get_results::<i32>(conn)?;
+        let missing_items: Vec<NewStocktakeItem> = missing
+            .iter()
+            .map(|storage_id| NewStocktakeItem { stocktake_id: id, storage_id: *storage_id, seen_at: None })
+            .collect();
+        diesel::insert_into(stocktake_items::table)
+            .values(&missing_items)
+            .execute(conn)?;
+        let stocktake = diesel::update(stocktakes::table.find(id))
+            .set(stocktakes::completed_at.eq(Utc::now()))
+            .returning(Stocktake::as_returning())
+            .get_result(conn)?;
+
+        Ok((stocktake, missing))
+    })?;
+
+    for storage_id in missing {
+        state.events.publish(Entity::Storage, ChangeAction::Withdrawn, storage_id);
+    }
+    state.events.publish(Entity::Stocktake, ChangeAction::Updated, id);
+
+    Ok(Json(report(conn, stocktake)?))
+}
+
+/// Abandon an open stocktake: `DELETE /api/stocktakes/<i32>`. Completed stocktakes are kept.
+///
+/// # Returns
+///
+/// The id of the deleted [Stocktake].
+///
+/// # Errors
+///
+/// * `NotFound` => "Stocktake not found".
+/// * `Conflict` => "Stocktake is already completed".
+pub async fn delete_stocktake(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<i32>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    conn.transaction::<_, TransactionError, _>(|conn| {
+        lock_open_stocktake(conn, id)?;
+        diesel::delete(stocktakes::table.find(id)).execute(conn)?;
+
+        Ok(())
+    })?;
+
+    state.events.publish(Entity::Stocktake, ChangeAction::Deleted, id);
+
+    Ok(Json(id))
+}
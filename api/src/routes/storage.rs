@@ -4,19 +4,22 @@
 //!
 //! Any return by querying the /api/storage with or without additional filters will contain the following date, but formatted as Json and following [StorageResponse]:
 //!```bash
-//! storageId | productName | freezerName | drawerName | weightGrams | expirationDate | expiresInDays   | inStorageSince
-//! ----------|-------------|-------------|------------|-------------|----------------|-----------------|---------------
-//! 1         | Brocoli     | Garage      | Schuif 1   | 525.3       | 2024-08-01     | 7               | 2023-08-01
+//! storageId | code   | productName | freezerName | drawerName | quantity | unit  | expirationDate | expiresInDays   | inStorageSince
+//! ----------|--------|-------------|-------------|------------|----------|-------|----------------|-----------------|---------------
+//! 1         | AB3X9K | Brocoli     | Garage      | Schuif 1   | 525.3    | grams | 2024-08-01     | 7               | 2023-08-01
 //!```
 //! Which will give the following Json data:
 //! ```json
 //! {
 //!     "storageId": 1,
+//!     "code": "AB3X9K",
 //!     "productName": "Brocoli",
 //!     "freezerName": "Garage",
 //!     "drawerName": "Schuif 1",
-//!     "weightGrams": 525.3,
+//!     "quantity": 525.3,
+//!     "unit": "grams",
 //!     "expirationDate": "2024-08-01",
+//!     "expirationExplicit": false,
 //!     "expiresInDays": 128,
 //!     "inStorageSince": "2023-08-01",
 //!     "outStorageSince: "2024-08-01",
@@ -27,14 +30,19 @@
 //! Query by:
 //!
 //! * storage_id
+//! * code, as printed on labels: `/api/storage/code/AB3X9K`
 //! * storage in general, but filtered on possible filters given in [StorageFilter]. All are to be defined in a query parameter: `/api/storage?productName=Brocoli`.
 //!
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::ops::Deref;
 use std::sync::Arc;
 use axum::extract::{Path, Query, State};
+use axum::body::Bytes;
+use axum::http::{header::{CONTENT_TYPE, WARNING}, HeaderMap, HeaderValue};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
-use chrono::{NaiveDate, Local};
+use chrono::{NaiveDate, Local, Utc};
 use diesel::prelude::*;
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
@@ -42,12 +50,22 @@ use struct_iterable::Iterable;
 
 use crate::{AppState, schema};
 use crate::core::connection::establish_connection;
-use crate::core::error::internal_error;
-use crate::core::query::{empty_string_as_none, ExpirationData};
+use crate::core::error::{internal_error, TransactionError};
+use crate::core::events::{ChangeAction, Entity};
+use crate::core::item_code;
+use crate::core::etag::{check_if_match, conditional_json, precondition_failed, version_header};
+use crate::core::name::{lower, normalize_name};
+use crate::core::query::empty_string_as_none;
+use crate::core::search::{contains_pattern, search_text, word_similarity, SIMILARITY_THRESHOLD};
+use crate::core::shelf_life::ShelfLifeRules;
 use crate::models::*;
 use crate::schema::freezers::dsl as freezers_dsl;
 use crate::schema::drawers::dsl as drawers_dsl;
 use crate::schema::products::dsl as products_dsl;
+use crate::schema::{product_tags, tags};
+use crate::routes::capacities::drawer_fill_level;
+use crate::routes::categories::category_ids_with_descendants;
+use crate::routes::labels::{labels_sheet, LabelQuery};
 
 /// Struct containing the possible query parameters to query the storage table of the database.
 /// As the complexity of these  queries can increase pretty fast, some handlers and checks are built to parse the
@@ -62,8 +80,12 @@ pub struct StorageFilter {
     /// Name of the product to be queried, will return all products matching it.
     #[serde(default, deserialize_with = "empty_string_as_none")]
     pub product_name: Option<String>,
+    /// Match [Self::product_name] like the product search does, by prefix, substring or similarity,
+    /// see [crate::core::search]. Defaults to false, matching the name exactly.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub fuzzy: Option<bool>,
     /// ID of the drawer that is selected. Can be combined with product_id, freezer_id, freezer_name, drawer_name,
-    /// in_before, expires_in_months, expires_after_date, available, min_weight and max_weight.
+    /// in_before, expires_in_months, expires_after_date, available, min_quantity and max_quantity.
     // pub drawer_id: Option<i32>,
     // /// Name of the drawer to be queried. Must be accompanied with the freezer_id or freezer_name.
     #[serde(default, deserialize_with = "empty_string_as_none")]
@@ -85,16 +107,30 @@ pub struct StorageFilter {
     /// Selects all products that have a date_out specified (i.e. are withdrawn from the freezer). Defaults to false.
     #[serde(default = "is_withdrawn_default")]
     pub is_withdrawn: Option<bool>,
-    /// Minimum weight to be queried, defaults to 0.0 grams.
-    #[serde(default = "weight_min_default")]
-    pub min_weight: Option<f32>,
-    /// Maximum weight to be queries in grams, defaults to 10000.0 grams.
-    #[serde(default = "weight_max_default")]
-    pub max_weight: Option<f32>,
+    /// Reason the items were withdrawn for, see [WithdrawalReason]. Implies [Self::is_withdrawn].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub withdrawal_reason: Option<WithdrawalReason>,
+    /// Unit of the storage items. Defaults to grams when a quantity bound is given, as quantities in
+    /// different units can't be compared.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub unit: Option<Unit>,
+    /// Minimum quantity to be queried, in [Self::unit]. Also accepted as `minWeight`.
+    #[serde(default, alias = "minWeight")]
+    pub min_quantity: Option<f32>,
+    /// Maximum quantity to be queried, in [Self::unit]. Also accepted as `maxWeight`.
+    #[serde(default, alias = "maxWeight")]
+    pub max_quantity: Option<f32>,
+    /// Comma separated category names. Matches products in any of these categories or their subcategories.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub category: Option<String>,
+    /// Comma separated tag names. Matches products carrying any of these tags.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub tag: Option<String>,
 }
 
 impl StorageFilter {
     /// Checks if the query meets constraints to be respected. See [get_storage] docs for the constraints in place.
+    #[allow(clippy::unnecessary_unwrap)]
     pub fn parse(&self) -> Result<(), (StatusCode, String)> {
         if self.drawer_name.is_some() && self.freezer_name.is_none() {
             return Err((StatusCode::BAD_REQUEST, String::from("drawerName also requires freezerName as query parameters")));
@@ -119,14 +155,65 @@ impl StorageFilter {
                 return Err((StatusCode::BAD_REQUEST, String::from("expiresBeforeDate canot be equal or earlier than expiresAfterDate")));
             }
         }
-        let min_weight = self.min_weight.unwrap();
-        let max_weight = self.max_weight.unwrap();
-        if min_weight >= max_weight {
-            return Err((StatusCode::BAD_REQUEST, String::from("minWeight must be smaller than maxWeight")))
+        if let (Some(min_quantity), Some(max_quantity)) = (self.min_quantity, self.max_quantity) {
+            if min_quantity >= max_quantity {
+                return Err((StatusCode::BAD_REQUEST, String::from("minQuantity must be smaller than maxQuantity")))
+            }
         }
 
         Ok(())
     }
+
+    /// Category names given in the `category` parameter.
+    pub fn categories(&self) -> Option<Vec<String>> {
+        self.category.as_deref().map(split_list)
+    }
+
+    /// Unit to filter on: the given one, or grams when only quantity bounds are given.
+    pub fn filter_unit(&self) -> Option<Unit> {
+        match (self.unit, self.min_quantity, self.max_quantity) {
+            (None, None, None) => None,
+            (None, _, _) => Some(Unit::Grams),
+            (unit, _, _) => unit,
+        }
+    }
+
+    /// Tag names given in the `tag` parameter.
+    pub fn tags(&self) -> Option<Vec<String>> {
+        self.tag.as_deref().map(split_list)
+    }
+}
+
+impl Default for StorageFilter {
+    /// No filters, only available items, as `GET /api/storage` without query parameters.
+    fn default() -> Self {
+        StorageFilter {
+            product_name: None,
+            fuzzy: None,
+            drawer_name: None,
+            freezer_name: None,
+            in_before: None,
+            expires_in_days: None,
+            expires_after_date: None,
+            expires_before_date: None,
+            is_withdrawn: is_withdrawn_default(),
+            withdrawal_reason: None,
+            unit: None,
+            min_quantity: None,
+            max_quantity: None,
+            category: None,
+            tag: None,
+        }
+    }
+}
+
+/// Splits a comma separated query parameter into its trimmed, non-empty values.
+pub(crate) fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect()
 }
 
 /// Struct representing the returned object when querying the storage endpoint.
@@ -135,42 +222,66 @@ impl StorageFilter {
 pub struct StorageResponse {
     /// ID of the storage item.
     pub storage_id: i32,
+    /// Short unique code of the storage item, see [crate::core::item_code]. Ignored when updating.
+    #[serde(default)]
+    pub code: String,
     /// Name of the product linked to the [Storage] `product_id`.
     pub product_name: String,
     /// Name of the freezer linked to the [Storage] `drawer_id`.
     pub freezer_name: String,
     /// Name of the drawer linked to the [Storage] `drawer_id`.
     pub drawer_name: String,
-    /// Weight of the storage item, in grams.
-    pub weight_grams: f32,
+    /// Quantity of the storage item, in [Self::unit]. Also accepted as `weightGrams` from older clients.
+    #[serde(alias = "weightGrams")]
+    pub quantity: f32,
+    /// Unit of the quantity. Defaults to grams when updating, for older clients.
+    #[serde(default)]
+    pub unit: Unit,
     /// Time until the storage item expires, expressed in days.
-    /// Calculated from the [Storage] `date_in`, [Product] `expiration_months` and [Local] `now` time.
+    /// Calculated from the [Storage] `date_in`, the effective shelf life and [Local] `now` time.
     pub expires_in_days: i64,
-    /// Date of expiration of the storage item, calculated from [Storage] date of entry and [Product] expiration time.
+    /// Date of expiration of the storage item, calculated from [Storage] date of entry and the effective
+    /// shelf life, see [crate::core::shelf_life], unless it was given explicitly.
     pub expiration_date: NaiveDate,
+    /// Whether [Self::expiration_date] was given explicitly instead of computed, always set in responses.
+    /// When updating, `true` stores the given expiration date, `false` computes it again and leaving it
+    /// out keeps the stored one.
+    #[serde(default)]
+    pub expiration_explicit: Option<bool>,
     /// Date of entry, renamed for frontend readability: [Storage] `date_in`.
     pub in_storage_since: NaiveDate,
     /// Date of withdrawal. Is `None` when still in storage.
     pub out_storage_since: Option<NaiveDate>,
+    /// Why the item was withdrawn, see [withdraw_storage]. Ignored when updating.
+    #[serde(default)]
+    pub withdrawal_reason: Option<WithdrawalReason>,
+    /// Note given when withdrawing. Ignored when updating.
+    #[serde(default)]
+    pub withdrawal_note: Option<String>,
 }
 
 impl StorageResponse {
     /// Turns inner join query on all tables into a common [StorageResponse] to be consumed by the frontend.
-    pub fn from_query_result(query_result: Vec<(Storage, Product, Drawer, Freezer)>) -> Vec<Self> {
+    pub fn from_query_result(query_result: Vec<(Storage, Product, Drawer, Freezer)>, rules: &ShelfLifeRules) -> Vec<Self> {
         query_result
             .into_iter()
             .map(|(stor, prod, draw, freez)| {
-                let expiration_data = ExpirationData::new(stor.date_in, prod.expiration_months);
+                let expiration_data = rules.storage_expiration(&stor, &prod, freez.freezer_id);
                 StorageResponse {
                     storage_id: stor.storage_id,
+                    code: stor.code,
                     product_name: prod.name,
                     freezer_name: freez.name,
                     drawer_name: draw.name,
-                    weight_grams: stor.weight_grams,
+                    quantity: stor.quantity,
+                    unit: stor.unit,
                     expires_in_days: expiration_data.expires_in_days,
                     expiration_date: expiration_data.date_expires,
+                    expiration_explicit: Some(stor.expiration_date.is_some()),
                     in_storage_since: stor.date_in,
                     out_storage_since: stor.date_out,
+                    withdrawal_reason: stor.withdrawal_reason,
+                    withdrawal_note: stor.withdrawal_note,
                 }
             })
             .collect::<Vec<StorageResponse>>()
@@ -182,22 +293,18 @@ impl PartialEq for StorageResponse {
         && self.product_name == other.product_name
         && self.freezer_name == other.freezer_name
         && self.drawer_name == other.drawer_name
-        && (self.weight_grams - other.weight_grams).abs() <= 1e-6
+        && (self.quantity - other.quantity).abs() <= 1e-6
+        && self.unit == other.unit
         && self.expires_in_days == other.expires_in_days
         && self.expiration_date == other.expiration_date
+        && self.expiration_explicit == other.expiration_explicit
         && self.in_storage_since == other.in_storage_since
         && self.out_storage_since == other.out_storage_since
+        && self.withdrawal_reason == other.withdrawal_reason
+        && self.withdrawal_note == other.withdrawal_note
     }
 }
 
-fn weight_min_default() -> Option<f32> {
-    Some(0.0)
-}
-
-fn weight_max_default() -> Option<f32> {
-    Some(1000.0)
-}
-
 fn is_withdrawn_default() -> Option<bool> {
     Some(false)
 }
@@ -207,6 +314,8 @@ fn is_withdrawn_default() -> Option<bool> {
 /// # Accepted query parameters for filtering
 ///
 /// * `productName=<String>`: Name of the product.
+/// * `fuzzy=<bool>` **(defaults to false)**: Match `productName` by prefix, substring or similarity
+///   instead of exactly, see [crate::core::search].
 /// * `freezerName=<String>`: Name of the freezer.
 /// * `drawerName=<String>`: Name of the drawer.
 /// * `inBefore=<DateTime String>`: Products that have been put in storage before this date.
@@ -214,8 +323,18 @@ fn is_withdrawn_default() -> Option<bool> {
 /// * `expiresAfterDate=<DateTime String>`: Date after which products expire.
 /// * `expiresBeforeDate=<DateTime String>`: Date before which products expire.
 /// * `isWithdrawn=<bool>` **(defaults to false)**: Product has been withdrawn or not.
-/// * `minWeight=<f32>` **(defaults to 0.0)**: Minimum product weight filter, in grams.
-/// * `maxWeight=<f32>` **(defaults to 100000.0)**: Maximum product weight filter, in grams.
+/// * `withdrawalReason=<WithdrawalReason>`: Products withdrawn for this reason, see [WithdrawalReason].
+///   Implies `isWithdrawn=true`.
+/// * `unit=<Unit>`: Unit of the storage items, see [Unit].
+/// * `minQuantity=<f32>`: Minimum quantity, in `unit` or in grams when no unit is given.
+/// * `maxQuantity=<f32>`: Maximum quantity, in `unit` or in grams when no unit is given.
+///
+/// `minWeight` and `maxWeight` are accepted as aliases of the quantity bounds, in grams.
+/// * `category=<String>[,<String>]`: Products in any of the categories, subcategories included.
+/// * `tag=<String>[,<String>]`: Products carrying any of the tags.
+///
+/// Multiple values of one parameter are combined with OR, different parameters with AND. Expiration
+/// filters use the effective shelf life of each item, see [crate::core::shelf_life].
 ///
 /// # Query parameter constraints
 ///
@@ -223,29 +342,103 @@ fn is_withdrawn_default() -> Option<bool> {
 ///
 /// # Returns
 ///
-/// Vec<[Storage]>
-pub async fn get_storage(State(state): State<AppState>, params: Query<StorageFilter>) -> Result<Json<Vec<StorageResponse>>, (StatusCode, String)> {
+/// Vec<[Storage]>. Honors `If-None-Match`, see [crate::core::etag].
+pub async fn get_storage(State(state): State<AppState>, headers: HeaderMap, params: Query<StorageFilter>) -> Result<Response, (StatusCode, String)> {
     params.parse()?;
-    use schema::storage::dsl::*;
+    let conn = &mut establish_connection(state.db_url);
+
+    let storage_results = filter_storage(conn, &params)?;
 
+    Ok(conditional_json(&headers, storage_results))
+}
+
+/// Total quantity of a product in one unit, as returned by [get_storage_totals].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageTotal {
+    /// Name of the product.
+    pub product_name: String,
+    /// Unit of the total quantity.
+    pub unit: Unit,
+    /// Sum of the quantities of the storage items.
+    pub quantity: f32,
+    /// Amount of storage items.
+    pub items: i64,
+}
+
+/// Get the total quantity per product and unit of the storage items: `GET /api/storage/totals`.
+///
+/// Accepts the same query parameters as [get_storage]. Quantities in different units are never
+/// added up, a product stored in grams and in portions gets two totals.
+///
+/// # Returns
+///
+/// Vec<[StorageTotal]>, sorted by product name and unit. Honors `If-None-Match`, see [crate::core::etag].
+pub async fn get_storage_totals(State(state): State<AppState>, headers: HeaderMap, params: Query<StorageFilter>) -> Result<Response, (StatusCode, String)> {
+    params.parse()?;
     let conn = &mut establish_connection(state.db_url);
 
+    let mut totals: BTreeMap<(String, Unit), (f32, i64)> = BTreeMap::new();
+    for item in filter_storage(conn, &params)? {
+        let total = totals.entry((item.product_name, item.unit)).or_insert((0.0, 0));
+        total.0 += item.quantity;
+        total.1 += 1;
+    }
+    let totals: Vec<StorageTotal> = totals
+        .into_iter()
+        .map(|((product_name, unit), (quantity, items))| StorageTotal { product_name, unit, quantity, items })
+        .collect();
+
+    Ok(conditional_json(&headers, totals))
+}
+
+/// Loads the storage items matching the [StorageFilter], as used by [get_storage].
+#[allow(clippy::unnecessary_unwrap)]
+pub fn filter_storage(conn: &mut PgConnection, params: &StorageFilter) -> Result<Vec<StorageResponse>, (StatusCode, String)> {
+    use schema::storage::dsl::*;
+
     let mut query = storage
         .inner_join(products_dsl::products)
         .inner_join(drawers_dsl::drawers)
         .inner_join(freezers_dsl::freezers.on(freezers_dsl::freezer_id.eq(drawers_dsl::freezer_id)))
+        .filter(deleted_at.is_null())
+        .filter(products_dsl::deleted_at.is_null())
+        .filter(drawers_dsl::deleted_at.is_null())
+        .filter(freezers_dsl::deleted_at.is_null())
         .into_boxed();
 
     if params.product_name.is_some() {
-        query = query.filter(products_dsl::name.eq(params.product_name.as_ref().unwrap()));
+        let product_name = params.product_name.as_ref().unwrap();
+        if params.fuzzy.unwrap_or(false) {
+            let search = search_text(product_name);
+            query = query.filter(
+                lower(products_dsl::name).like(contains_pattern(&search))
+                    .or(word_similarity(search.clone(), lower(products_dsl::name)).ge(SIMILARITY_THRESHOLD))
+            );
+        } else {
+            query = query.filter(lower(products_dsl::name).eq(lower(normalize_name(product_name))));
+        }
     }
     if params.freezer_name.is_some() {
-        query = query.filter(freezers_dsl::name.eq(params.freezer_name.as_ref().unwrap()));
+        query = query.filter(lower(freezers_dsl::name).eq(lower(normalize_name(params.freezer_name.as_ref().unwrap()))));
 
         if params.drawer_name.is_some() {
-            query = query.filter(drawers_dsl::name.eq(params.drawer_name.as_ref().unwrap()));
+            query = query.filter(lower(drawers_dsl::name).eq(lower(normalize_name(params.drawer_name.as_ref().unwrap()))));
         }
     }
+    if let Some(category_names) = params.categories() {
+        let category_ids = category_ids_with_descendants(conn, &category_names).map_err(internal_error)?;
+        query = query.filter(products_dsl::category_id.eq_any(category_ids));
+    }
+    if let Some(tag_names) = params.tags() {
+        let tagged_products = product_tags::table
+            .inner_join(tags::table)
+            .filter(tags::name.eq_any(tag_names))
+            .select(product_tags::product_id)
+            .load::<i32>(conn)
+            .map_err(internal_error)?;
+        query = query.filter(product_id.eq_any(tagged_products));
+    }
     if params.in_before.is_some() {
         let date_max_naive = params.in_before.unwrap();
         query = query.filter(date_in.lt(date_max_naive))
@@ -255,15 +448,23 @@ pub async fn get_storage(State(state): State<AppState>, params: Query<StorageFil
 
     // Withdrawn means it's taken out -> Date_out is no longer NULL. If default (false), then we only
     // want rows where date_out is NULL.
-    if params.is_withdrawn.unwrap() {
+    if let Some(reason) = params.withdrawal_reason {
+        query = query.filter(withdrawal_reason.eq(reason));
+    } else if params.is_withdrawn.unwrap() {
         query = query.filter(date_out.is_not_null());
     } else {
         query = query.filter(date_out.is_null())
     }
 
-    query = query
-        .filter(weight_grams.le(params.max_weight.as_ref().unwrap()))
-        .filter(weight_grams.ge(params.min_weight.as_ref().unwrap()));
+    if let Some(filter_unit) = params.filter_unit() {
+        query = query.filter(unit.eq(filter_unit));
+    }
+    if let Some(min_quantity) = params.min_quantity {
+        query = query.filter(quantity.ge(min_quantity));
+    }
+    if let Some(max_quantity) = params.max_quantity {
+        query = query.filter(quantity.le(max_quantity));
+    }
 
     let storage_results = query
         .select((Storage::as_select(), Product::as_select(), Drawer::as_select(), Freezer::as_select()))
@@ -271,9 +472,10 @@ pub async fn get_storage(State(state): State<AppState>, params: Query<StorageFil
         .load::<(Storage, Product, Drawer, Freezer)>(conn)
         // .get_results::<Storage>(conn)
         .map_err(internal_error)?;
-    let zipped_result = StorageResponse::from_query_result(storage_results);
+    let rules = ShelfLifeRules::load(conn).map_err(internal_error)?;
+    let zipped_result = StorageResponse::from_query_result(storage_results, &rules);
 
-    // Expiration filters, calculated after search in database.
+    // Expiration filters, calculated after search in database as they depend on the effective shelf life.
     let zipped_result = match params.expires_in_days {
         Some(days) => {
             zipped_result
@@ -312,15 +514,34 @@ pub async fn get_storage(State(state): State<AppState>, params: Query<StorageFil
     };
 
 
-    Ok(Json(zipped_result))
+    Ok(zipped_result)
+}
+
+/// Loads the storage items with the given ids, skipping unknown ones and those in the trash.
+pub fn storage_items_by_ids(conn: &mut PgConnection, ids: &[i32]) -> Result<Vec<StorageResponse>, (StatusCode, String)> {
+    use schema::storage::dsl::*;
+
+    let storage_results = storage
+        .inner_join(products_dsl::products)
+        .inner_join(drawers_dsl::drawers)
+        .inner_join(freezers_dsl::freezers.on(freezers_dsl::freezer_id.eq(drawers_dsl::freezer_id)))
+        .filter(storage_id.eq_any(ids))
+        .filter(deleted_at.is_null())
+        .select((Storage::as_select(), Product::as_select(), Drawer::as_select(), Freezer::as_select()))
+        .order_by(storage_id)
+        .load::<(Storage, Product, Drawer, Freezer)>(conn)
+        .map_err(internal_error)?;
+    let rules = ShelfLifeRules::load(conn).map_err(internal_error)?;
+
+    Ok(StorageResponse::from_query_result(storage_results, &rules))
 }
 
 /// Get a storage entry by its id: `GET /api/storage/<i32>`.
 ///
 /// # Returns
 ///
-/// [Storage]
-pub async fn get_storage_by_id(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Vec<StorageResponse>>, (StatusCode, String)> {
+/// [Storage], with its version as `ETag` header.
+pub async fn get_storage_by_id(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<Vec<StorageResponse>>), (StatusCode, String)> {
     use crate::schema::storage::dsl::*;
 
     let conn = &mut establish_connection(state.db_url);
@@ -330,17 +551,24 @@ pub async fn get_storage_by_id(State(state): State<AppState>, Path(id): Path<i32
         .inner_join(drawers_dsl::drawers) // .on(drawers_dsl::drawer_id.eq(drawer_id))
         .inner_join(freezers_dsl::freezers.on(freezers_dsl::freezer_id.eq(drawers_dsl::freezer_id)))
         .filter(storage_id.eq(id))
-        .select((Storage::as_select(), Product::as_select(), Drawer::as_select(), Freezer::as_select()))
-        .load::<(Storage, Product, Drawer, Freezer)>(conn)
+        .filter(deleted_at.is_null())
+        .select((Storage::as_select(), Product::as_select(), Drawer::as_select(), Freezer::as_select(), version))
+        .load::<(Storage, Product, Drawer, Freezer, i32)>(conn)
         .map_err(internal_error)?;
 
     if storage_results.is_empty() {
         return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Storage item not found")));
     }
 
-    let result = StorageResponse::from_query_result(storage_results);
+    let item_version = storage_results[0].4;
+    let storage_results = storage_results
+        .into_iter()
+        .map(|(stor, prod, draw, freez, _)| (stor, prod, draw, freez))
+        .collect();
+    let rules = ShelfLifeRules::load(conn).map_err(internal_error)?;
+    let result = StorageResponse::from_query_result(storage_results, &rules);
 
-    Ok(Json(result))
+    Ok((version_header(item_version), Json(result)))
 }
 
 /// Create a new storage entry: `POST /api/storage`.
@@ -351,23 +579,179 @@ pub async fn get_storage_by_id(State(state): State<AppState>, Path(id): Path<i32
 ///
 /// # Returns
 ///
-/// The ID of the newly created storage item.
+/// The ID of the newly created storage item. When the drawer now holds more than its capacity, the item
+/// is still created and a `Warning` header is added, see [crate::core::capacity].
 ///
 /// # Errors
 ///
 /// * Can't have a duplicate error on this one.
-pub async fn create_storage(State(state): State<AppState>, new_storage_item: Json<NewStorageItem>) -> Result<Json<Vec<StorageResponse>>, (StatusCode, String)> {
+/// * `NotFound`: product or drawer does not exist or is in the trash.
+/// * `BadRequest` => "expirationDate cannot be earlier than dateIn".
+pub async fn create_storage(State(state): State<AppState>, new_storage_item: Json<NewStorageItem>) -> Result<(HeaderMap, Json<Vec<StorageResponse>>), (StatusCode, String)> {
     use crate::schema::storage::dsl::*;
 
     let conn = &mut establish_connection(state.db_url.clone());
-    let new_storage_item = new_storage_item.deref();
+    let new_storage_item = checked_new_item(conn, new_storage_item.deref().to_owned())?;
     let insert_result = diesel::insert_into(storage)
-        .values(new_storage_item)
+        .values(&new_storage_item)
         .returning(storage_id)
         .get_results::<i32>(conn)
         .map_err(internal_error)?;
 
-    get_storage_by_id(State(state), Path(insert_result[0])).await
+    let warning = capacity_warning(conn, new_storage_item.drawer_id, new_storage_item.unit.unwrap_or_default())
+        .map_err(internal_error)?;
+
+    state.events.publish(Entity::Storage, ChangeAction::Created, insert_result[0]);
+
+    let (mut headers, created) = get_storage_by_id(State(state), Path(insert_result[0])).await?;
+    if let Some(warning) = warning {
+        headers.append(WARNING, warning);
+    }
+    Ok((headers, created))
+}
+
+/// Validates a new storage item and fills in the unit of its product when not given.
+pub(crate) fn checked_new_item(conn: &mut PgConnection, mut new_storage_item: NewStorageItem) -> Result<NewStorageItem, (StatusCode, String)> {
+    check_expiration_date(new_storage_item.date_in, new_storage_item.expiration_date)?;
+    check_references_active(conn, new_storage_item.product_id, new_storage_item.drawer_id)?;
+    if new_storage_item.unit.is_none() {
+        let product_unit = products_dsl::products
+            .find(new_storage_item.product_id)
+            .select(products_dsl::default_unit)
+            .first::<Unit>(conn)
+            .map_err(internal_error)?;
+        new_storage_item.unit = Some(product_unit);
+    }
+
+    Ok(new_storage_item)
+}
+
+/// Maximum amount of storage items created at once by [create_storage_batch].
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// Body of [create_storage_batch]: a list of items, or a single item to create `count` times.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum NewStorageBatch {
+    /// Items to create, e.g. `[{"productId": 2, ...}, {"productId": 4, ...}]`.
+    Items(Vec<NewStorageItem>),
+    /// Identical items to create, e.g. `{"productId": 2, ..., "count": 8}`.
+    Template(StorageTemplate),
+}
+
+/// Storage item to create `count` times, see [NewStorageBatch].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageTemplate {
+    /// The item to create, as in [create_storage].
+    #[serde(flatten)]
+    pub item: NewStorageItem,
+    /// **Required**: How many identical items to create.
+    pub count: usize,
+}
+
+impl NewStorageBatch {
+    /// The items to create, checking the size of the batch.
+    fn into_items(self) -> Result<Vec<NewStorageItem>, (StatusCode, String)> {
+        let size = match &self {
+            NewStorageBatch::Items(items) => items.len(),
+            NewStorageBatch::Template(template) => template.count,
+        };
+        if size == 0 || size > MAX_BATCH_SIZE {
+            return Err((StatusCode::BAD_REQUEST, format!("A batch must contain between 1 and {} items", MAX_BATCH_SIZE)));
+        }
+
+        Ok(match self {
+            NewStorageBatch::Items(items) => items,
+            NewStorageBatch::Template(template) => vec![template.item; template.count],
+        })
+    }
+}
+
+/// Create multiple storage entries at once, e.g. after batch cooking: `POST /api/storage/batch`.
+///
+/// All items are created in a single transaction: when one of them is invalid, none are created.
+///
+/// # Required body
+///
+/// [NewStorageBatch]: an array of [NewStorageItem], or a single [NewStorageItem] with a `count`.
+///
+/// # Optional query parameters
+///
+/// [LabelQuery]: when `format` is given, the sheet with the labels of the new items is returned instead,
+/// see [crate::routes::labels::get_storage_labels]. `ids` is ignored.
+///
+/// # Returns
+///
+/// Vec<[StorageResponse]> of the new items, in the order they were given, or the sheet of labels. A
+/// `Warning` header is added for every drawer now holding more than its capacity, see [create_storage].
+///
+/// # Errors
+///
+/// * `BadRequest` => "A batch must contain between 1 and 100 items" or "width and height must be between
+///   20 and 200 mm".
+/// * Same as [create_storage], for any of the items.
+pub async fn create_storage_batch(
+    State(state): State<AppState>,
+    params: Query<LabelQuery>,
+    batch: Json<NewStorageBatch>,
+) -> Result<Response, (StatusCode, String)> {
+    use crate::schema::storage::dsl::*;
+
+    let conn = &mut establish_connection(state.db_url.clone());
+    let items = batch.deref().to_owned().into_items()?;
+
+    let (ids, filled_drawers) = conn.transaction::<_, TransactionError, _>(|conn| {
+        let items = items
+            .into_iter()
+            .map(|item| checked_new_item(conn, item))
+            .collect::<Result<Vec<NewStorageItem>, (StatusCode, String)>>()?;
+        let mut filled_drawers: Vec<(i32, Unit)> = Vec::new();
+        for item in &items {
+            let drawer = (item.drawer_id, item.unit.unwrap_or_default());
+            if !filled_drawers.contains(&drawer) {
+                filled_drawers.push(drawer);
+            }
+        }
+        let ids = diesel::insert_into(storage)
+            .values(&items)
+            .returning(storage_id)
+            .get_results::<i32>(conn)?;
+
+        Ok((ids, filled_drawers))
+    })?;
+
+    for id in &ids {
+        state.events.publish(Entity::Storage, ChangeAction::Created, *id);
+    }
+
+    let created = storage_items_by_ids(conn, &ids)?;
+    let mut headers = HeaderMap::new();
+    for (drawer, item_unit) in filled_drawers {
+        if let Some(warning) = capacity_warning(conn, drawer, item_unit).map_err(internal_error)? {
+            headers.append(WARNING, warning);
+        }
+    }
+    if params.format.is_some() {
+        let mut sheet = labels_sheet(&created, &params).await?;
+        sheet.headers_mut().extend(headers);
+        return Ok(sheet);
+    }
+
+    Ok((headers, Json(created)).into_response())
+}
+
+/// `Warning` header for a drawer holding more than its capacity, once an item in `item_unit` is added.
+/// Items in another unit than the capacity can't exceed it.
+fn capacity_warning(conn: &mut PgConnection, drawer: i32, item_unit: Unit) -> QueryResult<Option<HeaderValue>> {
+    let level = drawer_fill_level(conn, drawer)?;
+    match (level.capacity, level.unit) {
+        (Some(capacity), Some(capacity_unit)) if capacity_unit == item_unit && level.is_over_capacity() => {
+            let warning = format!("199 - \"Drawer {} is over capacity: {} of {} {}\"", drawer, level.used(capacity_unit), capacity, capacity_unit);
+            Ok(HeaderValue::from_str(&warning).ok())
+        }
+        _ => Ok(None),
+    }
 }
 
 /// Update an existing storage entry: `PATCH /api/storage`.
@@ -377,29 +761,38 @@ pub async fn create_storage(State(state): State<AppState>, new_storage_item: Jso
 /// [Storage]: updated storage item.
 /// Storage ID should not be changed and should be unique.
 ///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the storage item as last fetched, see [crate::core::etag].
+///
 /// # Returns
 ///
-/// [Storage] that was just updated, in format `application/json`
+/// [Storage] that was just updated, in format `application/json`, with its new version as `ETag` header.
 ///
 /// # Errors
 ///
 /// * `DuplicateError`: Storage ID already taken, usually caused by a database error.
-pub async fn update_storage(State(state): State<AppState>, updated_storage_frontend: Json<StorageResponse>) -> Result<Json<Vec<StorageResponse>>, (StatusCode, String)>{
+/// * `PreconditionFailed`: Storage item was modified since it was fetched.
+/// * `BadRequest` => "expirationDate cannot be earlier than dateIn".
+pub async fn update_storage(State(state): State<AppState>, headers: HeaderMap, updated_storage_frontend: Json<StorageResponse>) -> Result<(HeaderMap, Json<Vec<StorageResponse>>), (StatusCode, String)>{
     use crate::schema::storage::dsl::*;
 
     let conn = &mut establish_connection(state.db_url.clone());
 
     let storage_entry = storage
         .filter(storage_id.eq(&updated_storage_frontend.storage_id))
-        .select(Storage::as_select())
-        .get_results::<Storage>(conn)
+        .filter(deleted_at.is_null())
+        .select((Storage::as_select(), version))
+        .get_results::<(Storage, i32)>(conn)
         .map_err(internal_error)?;
     if storage_entry.is_empty() {
         return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Storage item not found")))
     }
-    let storage_entry = &storage_entry[0];
+    let (storage_entry, current_version) = &storage_entry[0];
+    check_if_match(&headers, *current_version)?;
     let product = products_dsl::products
-        .filter(products_dsl::name.eq(&updated_storage_frontend.product_name))
+        .filter(lower(products_dsl::name).eq(lower(normalize_name(&updated_storage_frontend.product_name))))
+        .filter(products_dsl::deleted_at.is_null())
         .select(Product::as_select())
         .load::<Product>(conn)
         .map_err(internal_error)?;
@@ -409,8 +802,10 @@ pub async fn update_storage(State(state): State<AppState>, updated_storage_front
     let product = &product[0];
     let drawer = drawers_dsl::drawers
         .inner_join(freezers_dsl::freezers)
-        .filter(drawers_dsl::name.eq(&updated_storage_frontend.drawer_name))
-        .filter(freezers_dsl::name.eq(&updated_storage_frontend.freezer_name))
+        .filter(lower(drawers_dsl::name).eq(lower(normalize_name(&updated_storage_frontend.drawer_name))))
+        .filter(lower(freezers_dsl::name).eq(lower(normalize_name(&updated_storage_frontend.freezer_name))))
+        .filter(drawers_dsl::deleted_at.is_null())
+        .filter(freezers_dsl::deleted_at.is_null())
         .select((Drawer::as_select(), Freezer::as_select()))
         .load::<(Drawer, Freezer)>(conn)
         .map_err(internal_error)?;
@@ -420,36 +815,70 @@ pub async fn update_storage(State(state): State<AppState>, updated_storage_front
     let (drawer, freezer) = &drawer[0];
 
     let updated_storage_frontend = updated_storage_frontend.deref();
+    let explicit_expiration_date = match updated_storage_frontend.expiration_explicit {
+        Some(true) => Some(updated_storage_frontend.expiration_date),
+        Some(false) => None,
+        None => storage_entry.expiration_date,
+    };
+    check_expiration_date(updated_storage_frontend.in_storage_since, explicit_expiration_date)?;
     let update_storage = Storage {
         storage_id: storage_entry.storage_id,
         product_id: product.product_id,
         drawer_id: drawer.drawer_id,
-        weight_grams: updated_storage_frontend.weight_grams,
+        quantity: updated_storage_frontend.quantity,
+        unit: updated_storage_frontend.unit,
         date_in: updated_storage_frontend.in_storage_since,
         date_out: storage_entry.date_out,
+        expiration_date: explicit_expiration_date,
+        code: storage_entry.code.clone(),
+        withdrawal_reason: storage_entry.withdrawal_reason,
+        withdrawal_note: storage_entry.withdrawal_note.clone(),
     };
 
-    let update_result = diesel::update(storage)
+    let (update_result, update_version) = diesel::update(storage)
         .filter(storage_id.eq(&update_storage.storage_id))
-        .set(&update_storage)
-        .returning(Storage::as_returning())
-        .get_result(conn)
-        .map_err(internal_error)?;
+        .filter(version.eq(current_version))
+        .set((&update_storage, version.eq(version + 1)))
+        .returning((Storage::as_returning(), version))
+        .get_result::<(Storage, i32)>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(precondition_failed)?;
 
-    let expiration = ExpirationData::new(update_result.date_in, product.expiration_months);
+    let rules = ShelfLifeRules::load(conn).map_err(internal_error)?;
+    let expiration = rules.storage_expiration(&update_result, product, freezer.freezer_id);
     let response = StorageResponse {
         storage_id: update_result.storage_id,
+        code: update_result.code,
         product_name: product.name.clone(),
         freezer_name: freezer.name.clone(),
         drawer_name: drawer.name.clone(),
-        weight_grams: update_result.weight_grams,
+        quantity: update_result.quantity,
+        unit: update_result.unit,
         in_storage_since: update_result.date_in,
         out_storage_since: update_result.date_out,
         expires_in_days: expiration.expires_in_days,
         expiration_date: expiration.date_expires,
+        expiration_explicit: Some(update_result.expiration_date.is_some()),
+        withdrawal_reason: update_result.withdrawal_reason,
+        withdrawal_note: update_result.withdrawal_note,
     };
 
-    Ok(Json(vec![response]))
+    state.events.publish(Entity::Storage, ChangeAction::Updated, response.storage_id);
+
+    Ok((version_header(update_version), Json(vec![response])))
+}
+
+/// Optional body of [withdraw_storage].
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    /// Why the item is withdrawn.
+    #[serde(default)]
+    pub reason: Option<WithdrawalReason>,
+    /// Free text note, e.g. "freezer burn". Blank notes are dropped.
+    #[serde(default)]
+    pub note: Option<String>,
 }
 
 /// Used when a product is removed from the storage (consumed/thrown away): `PATCH /api/storage/<i32>/withdraw`.
@@ -460,83 +889,284 @@ pub async fn update_storage(State(state): State<AppState>, updated_storage_front
 ///
 /// `storage_id` which does not have an availability set to `false`.
 ///
+/// # Optional body
+///
+/// [Withdrawal] with the reason and a note, stored on the item: `{"reason": "discarded", "note": "freezer burn"}`.
+/// Without a body, or without a JSON content type, the item is withdrawn without a reason.
+///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the storage item as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The new version of the storage item as `ETag` header.
+///
 /// # Errors
 ///
 /// * `AvailabilityError`: already not available.
 /// * `ExpirationError`: storage item has expired.
-pub async fn withdraw_storage(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), (StatusCode, String)> {
+/// * `PreconditionFailed`: storage item was modified since it was fetched.
+/// * `BadRequest`: the body is not valid JSON.
+/// * `UnprocessableEntity`: the body is not a valid [Withdrawal], e.g. an unknown reason.
+pub async fn withdraw_storage(State(state): State<AppState>, headers: HeaderMap, Path(id): Path<i32>, body: Bytes) -> Result<HeaderMap, (StatusCode, String)> {
     use crate::schema::storage::dsl::*;
 
-    let conn = &mut establish_connection(state.db_url);
+    let withdrawal = parse_withdrawal(&headers, &body)?;
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let current_version = storage_version(conn, id)?;
+    check_if_match(&headers, current_version)?;
 
     let today = Local::now().date_naive();
-    let update_result = diesel::update(storage)
+    let update_version = diesel::update(storage)
         .filter(storage_id.eq(id))
-        .set(date_out.eq(today))
-        .load::<Storage>(conn)
-        .map_err(internal_error)?;
-    if update_result.is_empty() {
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Storage id not found, update failed")))
+        .filter(version.eq(current_version))
+        .set((
+            &UpdateStorageAvailability {
+                date_out: Some(today),
+                withdrawal_reason: withdrawal.reason,
+                withdrawal_note: withdrawal.note.as_deref().map(str::trim).filter(|note| !note.is_empty()).map(String::from),
+            },
+            version.eq(version + 1),
+        ))
+        .returning(version)
+        .get_result::<i32>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(precondition_failed)?;
+
+    state.events.publish(Entity::Storage, ChangeAction::Withdrawn, id);
+
+    Ok(version_header(update_version))
+}
+
+/// The optional [Withdrawal] in the body of [withdraw_storage].
+fn parse_withdrawal(headers: &HeaderMap, body: &[u8]) -> Result<Withdrawal, (StatusCode, String)> {
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !is_json || body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Withdrawal::default());
     }
 
-    Ok(())
+    serde_json::from_slice(body).map_err(|err| {
+        let status = match err.classify() {
+            serde_json::error::Category::Data => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        (status, format!("Invalid withdrawal: {}", err))
+    })
 }
 
 /// Used when a product is re-entered in storage (mistakenly taken out): `PATCH /api/storage/<i32>/re-enter`.
-/// Sets the storage item availability to `true` and erases the withdrawn date (sets it to None).
+/// Sets the storage item availability to `true` and erases the withdrawn date (sets it to None), along
+/// with the withdrawal reason and note.
 ///
 /// # Requires
 ///
 /// `storage_id` which does not have an availability set to `false`.
 ///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the storage item as last fetched, see [crate::core::etag].
+///
+/// # Returns
+///
+/// The new version of the storage item as `ETag` header.
+///
 /// # Errors
 ///
 /// * `AvailabilityError`: already not available.
 /// * `ExpirationError`: storage item has expired.
-pub async fn re_enter_storage(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), (StatusCode, String)> {
+/// * `PreconditionFailed`: storage item was modified since it was fetched.
+pub async fn re_enter_storage(State(state): State<AppState>, headers: HeaderMap, Path(id): Path<i32>) -> Result<HeaderMap, (StatusCode, String)> {
     use crate::schema::storage::dsl::*;
 
-    let conn = &mut establish_connection(state.db_url);
+    let conn = &mut establish_connection(state.db_url.clone());
 
-    let update_result = diesel::update(storage)
+    let current_version = storage_version(conn, id)?;
+    check_if_match(&headers, current_version)?;
+
+    let update_version = diesel::update(storage)
         .filter(storage_id.eq(id))
-        .set(&UpdateStorageAvailability {
-            date_out: None,
-        })
-        .load::<Storage>(conn)
-        .map_err(internal_error)?;
-    if update_result.is_empty() {
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Storage id not found, update failed")))
-    }
+        .filter(version.eq(current_version))
+        .set((
+            &UpdateStorageAvailability {
+                date_out: None,
+                withdrawal_reason: None,
+                withdrawal_note: None,
+            },
+            version.eq(version + 1),
+        ))
+        .returning(version)
+        .get_result::<i32>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(precondition_failed)?;
 
-    Ok(())
+    state.events.publish(Entity::Storage, ChangeAction::ReEntered, id);
+
+    Ok(version_header(update_version))
 }
 
-/// Delete a storage item from the database: `DELETE /api/storage/id=<i32>`.
+/// Move a storage item to the trash: `DELETE /api/storage/id=<i32>`. See [crate::core::trash].
 ///
 /// # Requires
 ///
 /// A valid `storage_id`.
 ///
+/// # Optional headers
+///
+/// `If-Match` with the `ETag` of the storage item as last fetched, see [crate::core::etag].
+///
 /// # Errors
 ///
 /// * `NotFound`: `storage_id` does not exist.
-pub async fn delete_storage(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), (StatusCode, String)> {
+/// * `PreconditionFailed`: storage item was modified since it was fetched.
+pub async fn delete_storage(State(state): State<AppState>, headers: HeaderMap, Path(id): Path<i32>) -> Result<(), (StatusCode, String)> {
     use crate::schema::storage::dsl::*;
 
-    let conn = &mut establish_connection(state.db_url);
+    let conn = &mut establish_connection(state.db_url.clone());
 
     let id_check = storage
         .filter(storage_id.eq(&id))
-        .load::<Storage>(conn)
+        .filter(deleted_at.is_null())
+        .select(version)
+        .load::<i32>(conn)
         .map_err(internal_error)?;
     if id_check.is_empty() {
         return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Storage id not found, delete failed")));
     }
-    diesel::delete(storage)
+    check_if_match(&headers, id_check[0])?;
+
+    let deleted = diesel::update(storage)
         .filter(storage_id.eq(id))
+        .filter(version.eq(id_check[0]))
+        .set((deleted_at.eq(Utc::now()), version.eq(version + 1)))
         .execute(conn)
         .map_err(internal_error)?;
+    if deleted == 0 {
+        return Err(precondition_failed());
+    }
+
+    state.events.publish(Entity::Storage, ChangeAction::Deleted, id);
+
+    Ok(())
+}
+
+/// Get a storage entry by its code: `GET /api/storage/code/<String>`, e.g. after scanning its label.
+///
+/// Codes are case insensitive, see [crate::core::item_code].
+///
+/// # Returns
+///
+/// Same as [get_storage_by_id].
+///
+/// # Errors
+///
+/// * `BadRequest`: not a valid code.
+/// * `NotFound` => "Storage item not found".
+pub async fn get_storage_by_code(State(state): State<AppState>, Path(item_code): Path<String>) -> Result<(HeaderMap, Json<Vec<StorageResponse>>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let id = storage_id_by_code(conn, &item_code)?;
+
+    get_storage_by_id(State(state), Path(id)).await
+}
+
+/// Withdraw a storage item by its code: `PATCH /api/storage/code/<String>/withdraw`.
+///
+/// Same as [withdraw_storage], so scanning a label is enough to take an item out.
+///
+/// # Errors
+///
+/// * `BadRequest`: not a valid code.
+/// * `NotFound` => "Storage item not found".
+/// * Same as [withdraw_storage].
+pub async fn withdraw_storage_by_code(State(state): State<AppState>, headers: HeaderMap, Path(item_code): Path<String>, body: Bytes) -> Result<HeaderMap, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let id = storage_id_by_code(conn, &item_code)?;
+
+    withdraw_storage(State(state), headers, Path(id), body).await
+}
+
+/// Re-enter a storage item by its code: `PATCH /api/storage/code/<String>/re-enter`.
+///
+/// Same as [re_enter_storage].
+///
+/// # Errors
+///
+/// * `BadRequest`: not a valid code.
+/// * `NotFound` => "Storage item not found".
+/// * Same as [re_enter_storage].
+pub async fn re_enter_storage_by_code(State(state): State<AppState>, headers: HeaderMap, Path(item_code): Path<String>) -> Result<HeaderMap, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let id = storage_id_by_code(conn, &item_code)?;
+
+    re_enter_storage(State(state), headers, Path(id)).await
+}
+
+/// Id of the storage item with the given code, not in the trash.
+pub(crate) fn storage_id_by_code(conn: &mut PgConnection, item_code: &str) -> Result<i32, (StatusCode, String)> {
+    use crate::schema::storage::dsl::*;
+
+    let item_code = item_code::normalize(item_code).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+
+    storage
+        .filter(code.eq(item_code))
+        .filter(deleted_at.is_null())
+        .select(storage_id)
+        .first::<i32>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Storage item not found")))
+}
+
+/// Current version of a storage item, used to check `If-Match` before an update.
+fn storage_version(conn: &mut PgConnection, id: i32) -> Result<i32, (StatusCode, String)> {
+    use crate::schema::storage::dsl::*;
+
+    storage
+        .filter(storage_id.eq(id))
+        .filter(deleted_at.is_null())
+        .select(version)
+        .first::<i32>(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, String::from("Storage id not found, update failed")))
+}
+
+/// Checks that an explicit expiration date is not earlier than the date of entry.
+fn check_expiration_date(date_in: NaiveDate, expiration_date: Option<NaiveDate>) -> Result<(), (StatusCode, String)> {
+    if expiration_date.is_some_and(|expiration_date| expiration_date < date_in) {
+        return Err((StatusCode::BAD_REQUEST, String::from("expirationDate cannot be earlier than dateIn")));
+    }
+
+    Ok(())
+}
+
+/// Checks that the product and drawer of a new storage item exist and are not in the trash.
+fn check_references_active(conn: &mut PgConnection, product_id: i32, drawer_id: i32) -> Result<(), (StatusCode, String)> {
+    let product_count = products_dsl::products
+        .filter(products_dsl::product_id.eq(product_id))
+        .filter(products_dsl::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if product_count == 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Product not found")));
+    }
+
+    let drawer_count = drawers_dsl::drawers
+        .filter(drawers_dsl::drawer_id.eq(drawer_id))
+        .filter(drawers_dsl::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if drawer_count == 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("Drawer not found")));
+    }
 
     Ok(())
 }
@@ -553,6 +1183,7 @@ mod storage_filter {
         fn only_drawer_name_returns_error() {
             let storage_filter = StorageFilter {
                 product_name: None,
+                fuzzy: None,
                 drawer_name: Some(String::from("Drawer 1")),
                 freezer_name: None,
                 in_before: None,
@@ -560,8 +1191,12 @@ mod storage_filter {
                 expires_after_date: None,
                 expires_before_date: None,
                 is_withdrawn: None,
-                min_weight: None,
-                max_weight: None
+                withdrawal_reason: None,
+                unit: None,
+                min_quantity: None,
+                max_quantity: None,
+                category: None,
+                tag: None
             };
             let result = storage_filter.parse();
 
@@ -575,6 +1210,7 @@ mod storage_filter {
             let yesterday = today.checked_sub_days(Days::new(1)).unwrap();
             let storage_filter = StorageFilter {
                 product_name: None,
+                fuzzy: None,
                 drawer_name: None,
                 freezer_name: None,
                 in_before: Some(today),
@@ -582,8 +1218,12 @@ mod storage_filter {
                 expires_after_date: Some(yesterday),
                 expires_before_date: None,
                 is_withdrawn: None,
-                min_weight: None,
-                max_weight: None
+                withdrawal_reason: None,
+                unit: None,
+                min_quantity: None,
+                max_quantity: None,
+                category: None,
+                tag: None
             };
             let result = storage_filter.parse();
 
@@ -597,6 +1237,7 @@ mod storage_filter {
             let yesterday = today.checked_sub_days(Days::new(1)).unwrap();
             let storage_filter = StorageFilter {
                 product_name: None,
+                fuzzy: None,
                 drawer_name: None,
                 freezer_name: None,
                 in_before: None,
@@ -604,8 +1245,12 @@ mod storage_filter {
                 expires_after_date: Some(today),
                 expires_before_date: Some(yesterday),
                 is_withdrawn: None,
-                min_weight: None,
-                max_weight: None
+                withdrawal_reason: None,
+                unit: None,
+                min_quantity: None,
+                max_quantity: None,
+                category: None,
+                tag: None
             };
             let result = storage_filter.parse();
 
@@ -616,6 +1261,7 @@ mod storage_filter {
         fn min_weight_gt_max_weight_returns_error() {
             let storage_filter = StorageFilter {
                 product_name: None,
+                fuzzy: None,
                 drawer_name: None,
                 freezer_name: None,
                 in_before: None,
@@ -623,13 +1269,17 @@ mod storage_filter {
                 expires_after_date: None,
                 expires_before_date: None,
                 is_withdrawn: None,
-                min_weight: Some(500.),
-                max_weight: Some(100.)
+                withdrawal_reason: None,
+                unit: None,
+                min_quantity: Some(500.),
+                max_quantity: Some(100.),
+                category: None,
+                tag: None
             };
             let result = storage_filter.parse();
 
             assert!(result.is_err(), "Expected error");
-            assert_eq!(result.err(), Some((StatusCode::BAD_REQUEST, String::from("minWeight must be smaller than maxWeight"))))
+            assert_eq!(result.err(), Some((StatusCode::BAD_REQUEST, String::from("minQuantity must be smaller than maxQuantity"))))
         }
     }
 }
@@ -645,14 +1295,20 @@ mod storage_response {
                 storage_id: 1,
                 product_id: 2,
                 drawer_id: 3,
-                weight_grams: 4.0,
+                quantity: 4.0,
+                unit: Unit::Pieces,
                 date_in: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
                 date_out: None,
+                expiration_date: None,
+                code: String::from("AB3X9K"),
+                withdrawal_reason: None,
+                withdrawal_note: None,
             },
             Product {
                 product_id: 2,
                 name: String::from("product name"),
-                expiration_months: 12
+                expiration_months: 12,
+                default_unit: Unit::Grams,
             },
             Drawer {
                 drawer_id: 3,
@@ -664,26 +1320,33 @@ mod storage_response {
                 name: String::from("freezer name")
             }
         )];
-        let storage_response = StorageResponse::from_query_result(query_result);
+        let storage_response = StorageResponse::from_query_result(query_result, &ShelfLifeRules::default());
         let expected_storage_response = StorageResponse {
             storage_id: 1,
+            code: String::from("AB3X9K"),
             product_name: String::from("product name"),
             freezer_name: String::from("freezer name"),
             drawer_name: String::from("drawer name"),
-            weight_grams: 4.0,
+            quantity: 4.0,
+            unit: Unit::Pieces,
             expires_in_days: 0,
             expiration_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            expiration_explicit: Some(false),
             in_storage_since: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
             out_storage_since: None,
+            withdrawal_reason: None,
+            withdrawal_note: None,
         };
         let stor = &storage_response[0];
 
         assert_eq!(storage_response.len(), 1);
         assert_eq!(stor.storage_id, expected_storage_response.storage_id);
+        assert_eq!(stor.code, expected_storage_response.code);
         assert_eq!(stor.product_name, expected_storage_response.product_name);
         assert_eq!(stor.freezer_name, expected_storage_response.freezer_name);
         assert_eq!(stor.drawer_name, expected_storage_response.drawer_name);
-        assert_eq!(stor.weight_grams, expected_storage_response.weight_grams);
+        assert_eq!(stor.quantity, expected_storage_response.quantity);
+        assert_eq!(stor.unit, expected_storage_response.unit);
         assert_eq!(stor.in_storage_since, expected_storage_response.in_storage_since);
         assert_eq!(stor.expiration_date, expected_storage_response.expiration_date);
         assert_eq!(stor.out_storage_since, expected_storage_response.out_storage_since);
@@ -0,0 +1,314 @@
+//! Endpoint `/api/summary`, implements `GET`.
+//!
+//! Overview of the available storage items per freezer and drawer, for a dashboard. Computed with a
+//! few aggregate queries grouped by drawer, the freezer totals are added up from its drawers.
+//! Expirations come from the `storage_expirations` view, which applies the same rules as
+//! [crate::core::shelf_life].
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use chrono::{Local, NaiveDate};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Float4, Int4, Text};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::internal_error,
+        etag::conditional_json,
+        query::empty_string_as_none,
+    },
+    models::Unit,
+    schema::{drawers, freezers},
+    AppState,
+};
+
+/// Days ahead counted as expiring soon when no `expiresInDays` is given.
+pub const DEFAULT_EXPIRING_DAYS: i32 = 7;
+/// Amount of top products per freezer and drawer when no `top` is given.
+pub const DEFAULT_TOP_PRODUCTS: usize = 3;
+
+/// Query parameters of [get_summary].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryQuery {
+    /// Items expiring within this many days count as expiring, defaults to [DEFAULT_EXPIRING_DAYS].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub expires_in_days: Option<i32>,
+    /// Amount of top products, defaults to [DEFAULT_TOP_PRODUCTS].
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub top: Option<usize>,
+}
+
+/// Oldest available item of a freezer or drawer.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OldestItem {
+    /// ID of the storage item.
+    pub storage_id: i32,
+    /// Name of the product.
+    pub product_name: String,
+    /// Date of entry.
+    pub in_storage_since: NaiveDate,
+}
+
+/// Amount of available items of a product.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductCount {
+    /// ID of the product.
+    pub product_id: i32,
+    /// Name of the product.
+    pub product_name: String,
+    /// Amount of available items.
+    pub items: i64,
+}
+
+/// Aggregates of the available items of a freezer or drawer.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryTotals {
+    /// Amount of available items.
+    pub items: i64,
+    /// Total quantity, per unit.
+    pub quantity: BTreeMap<Unit, f32>,
+    /// Amount of items expiring today or within the requested days.
+    pub expiring: i64,
+    /// Amount of expired items.
+    pub expired: i64,
+    /// Item that went in first, `None` when empty.
+    pub oldest_item: Option<OldestItem>,
+    /// Products with the most items, most first.
+    pub top_products: Vec<ProductCount>,
+}
+
+/// Summary of a drawer.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DrawerSummary {
+    /// ID of the drawer.
+    pub drawer_id: i32,
+    /// Name of the drawer.
+    pub drawer_name: String,
+    /// Aggregates of the drawer.
+    #[serde(flatten)]
+    pub totals: SummaryTotals,
+}
+
+/// Summary of a freezer and its drawers.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezerSummary {
+    /// ID of the freezer.
+    pub freezer_id: i32,
+    /// Name of the freezer.
+    pub freezer_name: String,
+    /// Aggregates of all drawers of the freezer.
+    #[serde(flatten)]
+    pub totals: SummaryTotals,
+    /// Summaries of the drawers, sorted by name.
+    pub drawers: Vec<DrawerSummary>,
+}
+
+/// The summary: `GET /api/summary`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    /// Days ahead counted as expiring.
+    pub expires_in_days: i32,
+    /// Summaries of the freezers, sorted by name.
+    pub freezers: Vec<FreezerSummary>,
+}
+
+#[derive(QueryableByName)]
+struct UnitAggregate {
+    #[diesel(sql_type = Int4)]
+    drawer_id: i32,
+    #[diesel(sql_type = Text)]
+    unit: Unit,
+    #[diesel(sql_type = BigInt)]
+    items: i64,
+    #[diesel(sql_type = Float4)]
+    quantity: f32,
+    #[diesel(sql_type = BigInt)]
+    expiring: i64,
+    #[diesel(sql_type = BigInt)]
+    expired: i64,
+}
+
+#[derive(QueryableByName)]
+struct ProductAggregate {
+    #[diesel(sql_type = Int4)]
+    drawer_id: i32,
+    #[diesel(sql_type = Int4)]
+    product_id: i32,
+    #[diesel(sql_type = Text)]
+    product_name: String,
+    #[diesel(sql_type = BigInt)]
+    items: i64,
+}
+
+#[derive(QueryableByName)]
+struct OldestRow {
+    #[diesel(sql_type = Int4)]
+    drawer_id: i32,
+    #[diesel(sql_type = Int4)]
+    storage_id: i32,
+    #[diesel(sql_type = Text)]
+    product_name: String,
+    #[diesel(sql_type = Date)]
+    date_in: NaiveDate,
+}
+
+/// Available items of active products, drawers and freezers, with their expiration date.
+const AVAILABLE_ITEMS: &str = "
+    FROM storage s
+    JOIN storage_expirations e ON e.storage_id = s.storage_id
+    JOIN products p ON p.product_id = s.product_id AND p.deleted_at IS NULL
+    JOIN drawers d ON d.drawer_id = s.drawer_id AND d.deleted_at IS NULL
+    JOIN freezers f ON f.freezer_id = d.freezer_id AND f.deleted_at IS NULL
+    WHERE s.date_out IS NULL AND s.deleted_at IS NULL";
+
+fn load_unit_aggregates(conn: &mut PgConnection, today: NaiveDate, expires_in_days: i32) -> QueryResult<Vec<UnitAggregate>> {
+    diesel::sql_query(format!("
+        SELECT s.drawer_id, s.unit, count(*) AS items, sum(s.quantity) AS quantity,
+               count(*) FILTER (WHERE e.expiration_date BETWEEN $1 AND $1 + $2) AS expiring,
+               count(*) FILTER (WHERE e.expiration_date < $1) AS expired
+        {AVAILABLE_ITEMS}
+        GROUP BY s.drawer_id, s.unit"))
+        .bind::<Date, _>(today)
+        .bind::<Int4, _>(expires_in_days)
+        .load(conn)
+}
+
+fn load_product_aggregates(conn: &mut PgConnection) -> QueryResult<Vec<ProductAggregate>> {
+    diesel::sql_query(format!("
+        SELECT s.drawer_id, p.product_id, p.name AS product_name, count(*) AS items
+        {AVAILABLE_ITEMS}
+        GROUP BY s.drawer_id, p.product_id, p.name"))
+        .load(conn)
+}
+
+fn load_oldest_items(conn: &mut PgConnection) -> QueryResult<Vec<OldestRow>> {
+    diesel::sql_query(format!("
+        SELECT DISTINCT ON (s.drawer_id) s.drawer_id, s.storage_id, p.name AS product_name, s.date_in
+        {AVAILABLE_ITEMS}
+        ORDER BY s.drawer_id, s.date_in, s.storage_id"))
+        .load(conn)
+}
+
+/// Products with the most items, the first `top` of them, ties sorted by name.
+fn top_products(counts: HashMap<i32, ProductCount>, top: usize) -> Vec<ProductCount> {
+    let mut counts: Vec<ProductCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| b.items.cmp(&a.items).then_with(|| a.product_name.cmp(&b.product_name)));
+    counts.truncate(top);
+    counts
+}
+
+/// Aggregates of the drawers in `drawer_ids` together.
+fn totals(
+    drawer_ids: &[i32],
+    units: &[UnitAggregate],
+    products: &[ProductAggregate],
+    oldest: &HashMap<i32, OldestItem>,
+    top: usize,
+) -> SummaryTotals {
+    let mut totals = SummaryTotals::default();
+    for aggregate in units.iter().filter(|aggregate| drawer_ids.contains(&aggregate.drawer_id)) {
+        totals.items += aggregate.items;
+        *totals.quantity.entry(aggregate.unit).or_insert(0.0) += aggregate.quantity;
+        totals.expiring += aggregate.expiring;
+        totals.expired += aggregate.expired;
+    }
+    let mut counts: HashMap<i32, ProductCount> = HashMap::new();
+    for aggregate in products.iter().filter(|aggregate| drawer_ids.contains(&aggregate.drawer_id)) {
+        counts
+            .entry(aggregate.product_id)
+            .or_insert_with(|| ProductCount { product_id: aggregate.product_id, product_name: aggregate.product_name.clone(), items: 0 })
+            .items += aggregate.items;
+    }
+    totals.top_products = top_products(counts, top);
+    totals.oldest_item = drawer_ids
+        .iter()
+        .filter_map(|drawer_id| oldest.get(drawer_id))
+        .min_by(|a, b| a.in_storage_since.cmp(&b.in_storage_since).then(a.storage_id.cmp(&b.storage_id)))
+        .cloned();
+
+    totals
+}
+
+/// Get an overview of the available items per freezer and drawer: `GET /api/summary`.
+///
+/// # Accepted query parameters
+///
+/// * `expiresInDays=<i32>`: items expiring today or within this many days count as expiring, defaults
+///   to [DEFAULT_EXPIRING_DAYS].
+/// * `top=<usize>`: amount of top products, defaults to [DEFAULT_TOP_PRODUCTS].
+///
+/// # Returns
+///
+/// [Summary], freezers and drawers without items included. Honors `If-None-Match`, see [crate::core::etag].
+///
+/// # Errors
+///
+/// * `BadRequest` => "expiresInDays cannot be negative".
+pub async fn get_summary(State(state): State<AppState>, headers: HeaderMap, params: Query<SummaryQuery>) -> Result<Response, (StatusCode, String)> {
+    let expires_in_days = params.expires_in_days.unwrap_or(DEFAULT_EXPIRING_DAYS);
+    if expires_in_days < 0 {
+        return Err((StatusCode::BAD_REQUEST, String::from("expiresInDays cannot be negative")));
+    }
+    let top = params.top.unwrap_or(DEFAULT_TOP_PRODUCTS);
+    let conn = &mut establish_connection(state.db_url);
+    let today = Local::now().date_naive();
+
+    let all_freezers = freezers::table
+        .filter(freezers::deleted_at.is_null())
+        .order_by(freezers::name)
+        .select((freezers::freezer_id, freezers::name))
+        .load::<(i32, String)>(conn)
+        .map_err(internal_error)?;
+    let all_drawers = drawers::table
+        .filter(drawers::deleted_at.is_null())
+        .order_by(drawers::name)
+        .select((drawers::drawer_id, drawers::freezer_id, drawers::name))
+        .load::<(i32, i32, String)>(conn)
+        .map_err(internal_error)?;
+    let units = load_unit_aggregates(conn, today, expires_in_days).map_err(internal_error)?;
+    let products = load_product_aggregates(conn).map_err(internal_error)?;
+    let oldest: HashMap<i32, OldestItem> = load_oldest_items(conn)
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|row| (row.drawer_id, OldestItem { storage_id: row.storage_id, product_name: row.product_name, in_storage_since: row.date_in }))
+        .collect();
+
+    let freezers = all_freezers
+        .into_iter()
+        .map(|(freezer_id, freezer_name)| {
+            let freezer_drawers: Vec<&(i32, i32, String)> = all_drawers
+                .iter()
+                .filter(|(_, drawer_freezer_id, _)| *drawer_freezer_id == freezer_id)
+                .collect();
+            let drawer_ids: Vec<i32> = freezer_drawers.iter().map(|(drawer_id, _, _)| *drawer_id).collect();
+            FreezerSummary {
+                freezer_id,
+                freezer_name,
+                totals: totals(&drawer_ids, &units, &products, &oldest, top),
+                drawers: freezer_drawers
+                    .into_iter()
+                    .map(|(drawer_id, _, drawer_name)| DrawerSummary {
+                        drawer_id: *drawer_id,
+                        drawer_name: drawer_name.clone(),
+                        totals: totals(&[*drawer_id], &units, &products, &oldest, top),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(conditional_json(&headers, Summary { expires_in_days, freezers }))
+}
@@ -0,0 +1,246 @@
+//! Endpoint `/api/tags`, implements `GET`, `POST`, `PATCH`, `DELETE`.
+//!
+//! Also implements tagging products: `GET` and `PATCH` on `/api/products/id=<i32>/tags`.
+use std::ops::Deref;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use diesel::prelude::*;
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+        etag::conditional_json,
+    },
+    models::{NewTag, ProductTag, Tag},
+    schema::{product_tags, products, tags},
+    AppState,
+};
+
+/// Get all tags: `GET /api/tags`.
+///
+/// # Returns
+///
+/// Vec<[Tag]>, in format `application/json`, sorted by name. Honors `If-None-Match`, see
+/// [crate::core::etag].
+pub async fn get_tags(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let result = tags::table
+        .select(Tag::as_select())
+        .order_by(tags::name)
+        .load::<Tag>(conn)
+        .map_err(internal_error)?;
+
+    Ok(conditional_json(&headers, result))
+}
+
+/// Create a new tag: `POST /api/tags`.
+///
+/// # Required body
+///
+/// [NewTag] model in `application/json`.
+///
+/// # Returns
+///
+/// The new [Tag].
+///
+/// # Errors
+///
+/// * `Duplicate` => "This tag name already exists".
+pub async fn create_tag(State(state): State<AppState>, new_tag: Json<NewTag>) -> Result<Json<Tag>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let new_tag = new_tag.deref().to_owned();
+
+    let name_count = tags::table
+        .filter(tags::name.eq(&new_tag.name))
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if name_count > 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("This tag name already exists")));
+    }
+
+    let result = diesel::insert_into(tags::table)
+        .values(new_tag)
+        .returning(Tag::as_returning())
+        .get_result(conn)
+        .map_err(internal_error)?;
+
+    state.events.publish(Entity::Tag, ChangeAction::Created, result.tag_id);
+
+    Ok(Json(result))
+}
+
+/// Renames a tag: `PATCH /api/tags`.
+///
+/// # Required body
+///
+/// [Tag] model in `application/json`.
+///
+/// # Returns
+///
+/// The updated [Tag].
+///
+/// # Errors
+///
+/// * `NotFound` => "Tag not found".
+/// * `Duplicate` => "This tag name already exists".
+pub async fn update_tag(State(state): State<AppState>, updated_tag: Json<Tag>) -> Result<Json<Tag>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+    let updated_tag = updated_tag.deref().to_owned();
+
+    let name_count = tags::table
+        .filter(tags::tag_id.ne(updated_tag.tag_id))
+        .filter(tags::name.eq(&updated_tag.name))
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if name_count > 0 {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::from("This tag name already exists")));
+    }
+
+    let result = diesel::update(tags::table.find(updated_tag.tag_id))
+        .set(&updated_tag)
+        .returning(Tag::as_returning())
+        .get_result(conn)
+        .optional()
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, String::from("Tag not found")))?;
+
+    state.events.publish(Entity::Tag, ChangeAction::Updated, result.tag_id);
+
+    Ok(Json(result))
+}
+
+/// Deletes a tag and removes it from all products: `DELETE /api/tags/<i32>`.
+///
+/// # Returns
+///
+/// The id of the deleted [Tag].
+///
+/// # Errors
+///
+/// * `NotFound` => "Tag not found".
+pub async fn delete_tag(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<i32>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let deleted = diesel::delete(tags::table.find(id))
+        .execute(conn)
+        .map_err(internal_error)?;
+    if deleted == 0 {
+        return Err((StatusCode::NOT_FOUND, String::from("Tag not found")));
+    }
+
+    state.events.publish(Entity::Tag, ChangeAction::Deleted, id);
+
+    Ok(Json(id))
+}
+
+/// Get the tags of a product: `GET /api/products/id=<i32>/tags`.
+///
+/// # Returns
+///
+/// Vec<[Tag]>, in format `application/json`, sorted by name.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+pub async fn get_product_tags(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Vec<Tag>>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    check_product_active(conn, id)?;
+
+    let result = product_tags::table
+        .inner_join(tags::table)
+        .filter(product_tags::product_id.eq(id))
+        .select(Tag::as_select())
+        .order_by(tags::name)
+        .load::<Tag>(conn)
+        .map_err(internal_error)?;
+
+    Ok(Json(result))
+}
+
+/// Replaces the tags of a product: `PATCH /api/products/id=<i32>/tags`.
+///
+/// # Required body
+///
+/// The tag names as a json array of strings, e.g. `["soup", "vegetarian"]`. Names are trimmed, empty
+/// names are ignored and unknown tags are created.
+///
+/// # Returns
+///
+/// The new Vec<[Tag]> of the product, sorted by name.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found".
+pub async fn set_product_tags(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    tag_names: Json<Vec<String>>,
+) -> Result<Json<Vec<Tag>>, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let mut tag_names: Vec<String> = tag_names
+        .iter()
+        .map(|tag_name| tag_name.trim().to_owned())
+        .filter(|tag_name| !tag_name.is_empty())
+        .collect();
+    tag_names.sort();
+    tag_names.dedup();
+
+    let result = conn.transaction::<_, TransactionError, _>(|conn| {
+        check_product_active(conn, id)?;
+
+        let new_tags: Vec<NewTag> = tag_names
+            .iter()
+            .map(|tag_name| NewTag { name: tag_name.clone() })
+            .collect();
+        diesel::insert_into(tags::table)
+            .values(&new_tags)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        let product_tag_list = tags::table
+            .filter(tags::name.eq_any(&tag_names))
+            .select(Tag::as_select())
+            .order_by(tags::name)
+            .load::<Tag>(conn)?;
+
+        diesel::delete(product_tags::table.filter(product_tags::product_id.eq(id))).execute(conn)?;
+        let links: Vec<ProductTag> = product_tag_list
+            .iter()
+            .map(|tag| ProductTag { product_id: id, tag_id: tag.tag_id })
+            .collect();
+        diesel::insert_into(product_tags::table)
+            .values(&links)
+            .execute(conn)?;
+
+        Ok(product_tag_list)
+    })?;
+
+    state.events.publish(Entity::Product, ChangeAction::Updated, id);
+
+    Ok(Json(result))
+}
+
+/// Checks that a product exists and is not in the trash.
+pub(crate) fn check_product_active(conn: &mut PgConnection, id: i32) -> Result<(), (StatusCode, String)> {
+    let product_count = products::table
+        .find(id)
+        .filter(products::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(internal_error)?;
+    if product_count == 0 {
+        return Err((StatusCode::NOT_FOUND, String::from("Product not found")));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,334 @@
+//! Endpoint `/api/trash`, implements `GET` and restoring trashed items through `PATCH`.
+//!
+//! Deleted products, freezers, drawers and storage items are kept in the trash until they are purged,
+//! see [crate::core::trash]. Restoring an item also restores everything that was deleted along with
+//! it, e.g. restoring a freezer brings back its drawers and their storage items.
+//!
+//! An item can only be restored when its parents are not in the trash themselves, and when its name
+//! has not been taken by another item in the meantime.
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        connection::establish_connection,
+        error::{internal_error, TransactionError},
+        events::{ChangeAction, Entity},
+        etag::{conditional_json, version_header},
+        name::lower,
+    },
+    models::{Drawer, Freezer, Product, Storage},
+    routes::storage::{get_storage_by_id, StorageResponse},
+    schema::{drawers, freezers, products, storage},
+    AppState,
+};
+
+/// An item in the trash, along with the moment it was deleted.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedItem<T> {
+    /// The deleted item.
+    pub item: T,
+    /// Moment of deletion.
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Struct representing the returned object when querying the trash endpoint.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashResponse {
+    /// Trashed products.
+    pub products: Vec<TrashedItem<Product>>,
+    /// Trashed freezers.
+    pub freezers: Vec<TrashedItem<Freezer>>,
+    /// Trashed drawers.
+    pub drawers: Vec<TrashedItem<Drawer>>,
+    /// Trashed storage items.
+    pub storage: Vec<TrashedItem<Storage>>,
+}
+
+fn trashed_items<T>(rows: Vec<(T, Option<DateTime<Utc>>)>) -> Vec<TrashedItem<T>> {
+    rows.into_iter()
+        .filter_map(|(item, deleted_at)| deleted_at.map(|deleted_at| TrashedItem { item, deleted_at }))
+        .collect()
+}
+
+fn not_in_trash(item: &str) -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, format!("{} not found in the trash", item))
+}
+
+/// Get all items in the trash, most recently deleted first: `GET /api/trash`.
+///
+/// # Returns
+///
+/// [TrashResponse]. Honors `If-None-Match`, see [crate::core::etag].
+pub async fn get_trash(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url);
+
+    let trashed_products = products::table
+        .filter(products::deleted_at.is_not_null())
+        .order_by(products::deleted_at.desc())
+        .select((Product::as_select(), products::deleted_at))
+        .load::<(Product, Option<DateTime<Utc>>)>(conn)
+        .map_err(internal_error)?;
+    let trashed_freezers = freezers::table
+        .filter(freezers::deleted_at.is_not_null())
+        .order_by(freezers::deleted_at.desc())
+        .select((Freezer::as_select(), freezers::deleted_at))
+        .load::<(Freezer, Option<DateTime<Utc>>)>(conn)
+        .map_err(internal_error)?;
+    let trashed_drawers = drawers::table
+        .filter(drawers::deleted_at.is_not_null())
+        .order_by(drawers::deleted_at.desc())
+        .select((Drawer::as_select(), drawers::deleted_at))
+        .load::<(Drawer, Option<DateTime<Utc>>)>(conn)
+        .map_err(internal_error)?;
+    let trashed_storage = storage::table
+        .filter(storage::deleted_at.is_not_null())
+        .order_by((storage::deleted_at.desc(), storage::storage_id))
+        .select((Storage::as_select(), storage::deleted_at))
+        .load::<(Storage, Option<DateTime<Utc>>)>(conn)
+        .map_err(internal_error)?;
+
+    let trash = TrashResponse {
+        products: trashed_items(trashed_products),
+        freezers: trashed_items(trashed_freezers),
+        drawers: trashed_items(trashed_drawers),
+        storage: trashed_items(trashed_storage),
+    };
+
+    Ok(conditional_json(&headers, trash))
+}
+
+/// Restores a product from the trash, along with its storage items that were deleted with it:
+/// `PATCH /api/trash/products/<i32>/restore`.
+///
+/// # Returns
+///
+/// The restored [Product], with its new version as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Product not found in the trash".
+/// * `Conflict` => "This product name already exists".
+pub async fn restore_product(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<Product>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let (product, product_version) = conn.transaction::<_, TransactionError, _>(|conn| {
+        let (product, trashed_at) = products::table
+            .find(id)
+            .filter(products::deleted_at.is_not_null())
+            .select((Product::as_select(), products::deleted_at))
+            .first::<(Product, Option<DateTime<Utc>>)>(conn)
+            .optional()?
+            .ok_or_else(|| not_in_trash("Product"))?;
+
+        let name_taken = products::table
+            .filter(lower(products::name).eq(lower(&product.name)))
+            .filter(products::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+        if name_taken > 0 {
+            return Err((StatusCode::CONFLICT, String::from("This product name already exists")).into());
+        }
+
+        let restored = diesel::update(products::table.find(id))
+            .set((products::deleted_at.eq(None::<DateTime<Utc>>), products::version.eq(products::version + 1)))
+            .returning((Product::as_returning(), products::version))
+            .get_result::<(Product, i32)>(conn)?;
+
+        diesel::update(storage::table)
+            .filter(storage::product_id.eq(product.product_id))
+            .filter(storage::deleted_at.eq(trashed_at))
+            .filter(storage::drawer_id.eq_any(
+                drawers::table.filter(drawers::deleted_at.is_null()).select(drawers::drawer_id)
+            ))
+            .set((storage::deleted_at.eq(None::<DateTime<Utc>>), storage::version.eq(storage::version + 1)))
+            .execute(conn)?;
+
+        Ok(restored)
+    })?;
+
+    state.events.publish(Entity::Product, ChangeAction::Restored, product.product_id);
+
+    Ok((version_header(product_version), Json(product)))
+}
+
+/// Restores a freezer from the trash, along with its drawers and storage items that were deleted
+/// with it: `PATCH /api/trash/freezers/<i32>/restore`.
+///
+/// # Returns
+///
+/// The restored [Freezer], with its new version as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Freezer not found in the trash".
+/// * `Conflict` => "This freezer name already exists".
+pub async fn restore_freezer(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<Freezer>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let (freezer, freezer_version) = conn.transaction::<_, TransactionError, _>(|conn| {
+        let (freezer, trashed_at) = freezers::table
+            .find(id)
+            .filter(freezers::deleted_at.is_not_null())
+            .select((Freezer::as_select(), freezers::deleted_at))
+            .first::<(Freezer, Option<DateTime<Utc>>)>(conn)
+            .optional()?
+            .ok_or_else(|| not_in_trash("Freezer"))?;
+
+        let name_taken = freezers::table
+            .filter(lower(freezers::name).eq(lower(&freezer.name)))
+            .filter(freezers::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+        if name_taken > 0 {
+            return Err((StatusCode::CONFLICT, String::from("This freezer name already exists")).into());
+        }
+
+        let restored = diesel::update(freezers::table.find(id))
+            .set((freezers::deleted_at.eq(None::<DateTime<Utc>>), freezers::version.eq(freezers::version + 1)))
+            .returning((Freezer::as_returning(), freezers::version))
+            .get_result::<(Freezer, i32)>(conn)?;
+
+        let drawer_ids = diesel::update(drawers::table)
+            .filter(drawers::freezer_id.eq(id))
+            .filter(drawers::deleted_at.eq(trashed_at))
+            .set((drawers::deleted_at.eq(None::<DateTime<Utc>>), drawers::version.eq(drawers::version + 1)))
+            .returning(drawers::drawer_id)
+            .get_results::<i32>(conn)?;
+        diesel::update(storage::table)
+            .filter(storage::drawer_id.eq_any(drawer_ids))
+            .filter(storage::deleted_at.eq(trashed_at))
+            .filter(storage::product_id.eq_any(
+                products::table.filter(products::deleted_at.is_null()).select(products::product_id)
+            ))
+            .set((storage::deleted_at.eq(None::<DateTime<Utc>>), storage::version.eq(storage::version + 1)))
+            .execute(conn)?;
+
+        Ok(restored)
+    })?;
+
+    state.events.publish(Entity::Freezer, ChangeAction::Restored, freezer.freezer_id);
+
+    Ok((version_header(freezer_version), Json(freezer)))
+}
+
+/// Restores a drawer from the trash, along with its storage items that were deleted with it:
+/// `PATCH /api/trash/drawers/<i32>/restore`.
+///
+/// # Returns
+///
+/// The restored [Drawer], with its new version as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Drawer not found in the trash".
+/// * `Conflict` => "Restore the freezer of this drawer first".
+/// * `Conflict` => "This drawer name already exists within this freezer".
+pub async fn restore_drawer(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<Drawer>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    let (drawer, drawer_version) = conn.transaction::<_, TransactionError, _>(|conn| {
+        let (drawer, trashed_at) = drawers::table
+            .find(id)
+            .filter(drawers::deleted_at.is_not_null())
+            .select((Drawer::as_select(), drawers::deleted_at))
+            .first::<(Drawer, Option<DateTime<Utc>>)>(conn)
+            .optional()?
+            .ok_or_else(|| not_in_trash("Drawer"))?;
+
+        let freezer_active = freezers::table
+            .find(drawer.freezer_id)
+            .filter(freezers::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+        if freezer_active == 0 {
+            return Err((StatusCode::CONFLICT, String::from("Restore the freezer of this drawer first")).into());
+        }
+
+        let name_taken = drawers::table
+            .filter(lower(drawers::name).eq(lower(&drawer.name)))
+            .filter(drawers::freezer_id.eq(drawer.freezer_id))
+            .filter(drawers::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+        if name_taken > 0 {
+            return Err((StatusCode::CONFLICT, String::from("This drawer name already exists within this freezer")).into());
+        }
+
+        let restored = diesel::update(drawers::table.find(id))
+            .set((drawers::deleted_at.eq(None::<DateTime<Utc>>), drawers::version.eq(drawers::version + 1)))
+            .returning((Drawer::as_returning(), drawers::version))
+            .get_result::<(Drawer, i32)>(conn)?;
+
+        diesel::update(storage::table)
+            .filter(storage::drawer_id.eq(id))
+            .filter(storage::deleted_at.eq(trashed_at))
+            .filter(storage::product_id.eq_any(
+                products::table.filter(products::deleted_at.is_null()).select(products::product_id)
+            ))
+            .set((storage::deleted_at.eq(None::<DateTime<Utc>>), storage::version.eq(storage::version + 1)))
+            .execute(conn)?;
+
+        Ok(restored)
+    })?;
+
+    state.events.publish(Entity::Drawer, ChangeAction::Restored, drawer.drawer_id);
+
+    Ok((version_header(drawer_version), Json(drawer)))
+}
+
+/// Restores a storage item from the trash: `PATCH /api/trash/storage/<i32>/restore`.
+///
+/// # Returns
+///
+/// The restored item as [StorageResponse], with its new version as `ETag` header.
+///
+/// # Errors
+///
+/// * `NotFound` => "Storage item not found in the trash".
+/// * `Conflict` => "Restore the product and drawer of this storage item first".
+pub async fn restore_storage(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(HeaderMap, Json<Vec<StorageResponse>>), (StatusCode, String)> {
+    let conn = &mut establish_connection(state.db_url.clone());
+
+    conn.transaction::<_, TransactionError, _>(|conn| {
+        let item = storage::table
+            .find(id)
+            .filter(storage::deleted_at.is_not_null())
+            .select(Storage::as_select())
+            .first::<Storage>(conn)
+            .optional()?
+            .ok_or_else(|| not_in_trash("Storage item"))?;
+
+        let product_active = products::table
+            .find(item.product_id)
+            .filter(products::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+        let drawer_active = drawers::table
+            .find(item.drawer_id)
+            .filter(drawers::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+        if product_active == 0 || drawer_active == 0 {
+            return Err((StatusCode::CONFLICT, String::from("Restore the product and drawer of this storage item first")).into());
+        }
+
+        diesel::update(storage::table.find(id))
+            .set((storage::deleted_at.eq(None::<DateTime<Utc>>), storage::version.eq(storage::version + 1)))
+            .execute(conn)?;
+
+        Ok(())
+    })?;
+
+    state.events.publish(Entity::Storage, ChangeAction::Restored, id);
+
+    get_storage_by_id(State(state), Path(id)).await
+}
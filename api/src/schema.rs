@@ -1,11 +1,25 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    categories (category_id) {
+        category_id -> Int4,
+        #[max_length = 50]
+        name -> Varchar,
+        parent_id -> Nullable<Int4>,
+    }
+}
+
 diesel::table! {
     drawers (drawer_id) {
         drawer_id -> Int4,
         #[max_length = 50]
         name -> Varchar,
         freezer_id -> Int4,
+        version -> Int4,
+        deleted_at -> Nullable<Timestamptz>,
+        capacity -> Nullable<Float4>,
+        #[max_length = 20]
+        capacity_unit -> Nullable<Varchar>,
     }
 }
 
@@ -14,6 +28,60 @@ diesel::table! {
         freezer_id -> Int4,
         #[max_length = 50]
         name -> Varchar,
+        version -> Int4,
+        deleted_at -> Nullable<Timestamptz>,
+        freezer_type_id -> Nullable<Int4>,
+        capacity -> Nullable<Float4>,
+        #[max_length = 20]
+        capacity_unit -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    freezer_types (freezer_type_id) {
+        freezer_type_id -> Int4,
+        #[max_length = 50]
+        name -> Varchar,
+        shelf_life_multiplier -> Float4,
+    }
+}
+
+diesel::table! {
+    idempotency_keys (idempotency_key) {
+        #[max_length = 255]
+        idempotency_key -> Varchar,
+        #[max_length = 64]
+        request_fingerprint -> Varchar,
+        response_status -> Nullable<Int4>,
+        response_body -> Nullable<Bytea>,
+        #[max_length = 255]
+        response_content_type -> Nullable<Varchar>,
+        #[max_length = 255]
+        response_etag -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    product_barcodes (barcode) {
+        #[max_length = 13]
+        barcode -> Varchar,
+        product_id -> Int4,
+    }
+}
+
+diesel::table! {
+    product_shelf_lives (product_id, freezer_type_id) {
+        product_id -> Int4,
+        freezer_type_id -> Int4,
+        shelf_life_days -> Int4,
+    }
+}
+
+diesel::table! {
+    product_tags (product_id, tag_id) {
+        product_id -> Int4,
+        tag_id -> Int4,
     }
 }
 
@@ -23,6 +91,46 @@ diesel::table! {
         #[max_length = 50]
         name -> Varchar,
         expiration_months -> Int4,
+        version -> Int4,
+        deleted_at -> Nullable<Timestamptz>,
+        category_id -> Nullable<Int4>,
+        #[max_length = 20]
+        default_unit -> Varchar,
+        shelf_life_days -> Nullable<Int4>,
+        min_stock_quantity -> Nullable<Float4>,
+        #[max_length = 20]
+        min_stock_unit -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    shopping_list_items (item_id) {
+        item_id -> Int4,
+        #[max_length = 50]
+        name -> Varchar,
+        product_id -> Nullable<Int4>,
+        quantity -> Nullable<Float4>,
+        #[max_length = 20]
+        unit -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        checked_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    stocktake_items (stocktake_id, storage_id) {
+        stocktake_id -> Int4,
+        storage_id -> Int4,
+        seen_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    stocktakes (stocktake_id) {
+        stocktake_id -> Int4,
+        freezer_id -> Int4,
+        started_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -31,19 +139,58 @@ diesel::table! {
         storage_id -> Int4,
         product_id -> Int4,
         drawer_id -> Int4,
-        weight_grams -> Float4,
+        quantity -> Float4,
         date_in -> Date,
         date_out -> Nullable<Date>,
+        version -> Int4,
+        deleted_at -> Nullable<Timestamptz>,
+        #[max_length = 20]
+        unit -> Varchar,
+        expiration_date -> Nullable<Date>,
+        #[max_length = 6]
+        code -> Varchar,
+        #[max_length = 20]
+        withdrawal_reason -> Nullable<Varchar>,
+        withdrawal_note -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    tags (tag_id) {
+        tag_id -> Int4,
+        #[max_length = 50]
+        name -> Varchar,
     }
 }
 
 diesel::joinable!(drawers -> freezers (freezer_id));
+diesel::joinable!(freezers -> freezer_types (freezer_type_id));
+diesel::joinable!(product_barcodes -> products (product_id));
+diesel::joinable!(product_shelf_lives -> freezer_types (freezer_type_id));
+diesel::joinable!(product_shelf_lives -> products (product_id));
+diesel::joinable!(product_tags -> products (product_id));
+diesel::joinable!(product_tags -> tags (tag_id));
+diesel::joinable!(products -> categories (category_id));
+diesel::joinable!(shopping_list_items -> products (product_id));
+diesel::joinable!(stocktake_items -> stocktakes (stocktake_id));
+diesel::joinable!(stocktake_items -> storage (storage_id));
+diesel::joinable!(stocktakes -> freezers (freezer_id));
 diesel::joinable!(storage -> drawers (drawer_id));
 diesel::joinable!(storage -> products (product_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    categories,
     drawers,
+    freezer_types,
     freezers,
+    idempotency_keys,
+    product_barcodes,
+    product_shelf_lives,
+    product_tags,
     products,
+    shopping_list_items,
+    stocktake_items,
+    stocktakes,
     storage,
+    tags,
 );
@@ -25,6 +25,13 @@ pub struct Context {
 
 impl Context {
     pub fn new(ctx: &str) -> Context {
+        Self::create(ctx, true)
+    }
+    /// Context with a migrated database without any data.
+    pub fn empty(ctx: &str) -> Context {
+        Self::create(ctx, false)
+    }
+    fn create(ctx: &str, feed: bool) -> Context {
         info!(target: LOG_TARGET, "Setting up context for {ctx}");
         dotenv().ok();
 
@@ -75,8 +82,10 @@ impl Context {
                 panic!("Failed migrations in database '{}'", db_name)
             });
 
-        debug!("Priming database.");
-        Self::feed_database(conn, &db_name);
+        if feed {
+            debug!("Priming database.");
+            Self::feed_database(conn, &db_name);
+        }
 
         info!("Successfully created and primed database '{}'", db_name);
 
@@ -113,6 +122,7 @@ impl Context {
                 NewProduct {
                     name: String::from(name),
                     expiration_months: Some(expiration_months),
+                    default_unit: None,
                 }
             }).collect();
         let storage_feed: Vec<NewStorageItem> = db_data::STORAGE
@@ -120,9 +130,11 @@ impl Context {
             .map(|(_id, prod_id, wt_grams, dt_in, _, draw_id)| {
                 NewStorageItem {
                     product_id: prod_id,
-                    weight_grams: wt_grams,
+                    quantity: wt_grams,
+                    unit: None,
                     date_in: NaiveDate::parse_from_str(dt_in, "%Y-%m-%d").unwrap(),
                     drawer_id: draw_id,
+                    expiration_date: None,
                 }
             }).collect();
         let storage_withdrawn: Vec<(i32, &str)> = db_data::STORAGE
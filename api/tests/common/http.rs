@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+
+use axum::{
+    body::{Body, BoxBody},
+    http::{Request, Response, StatusCode},
+    Router,
+};
+use serde::de::DeserializeOwned;
+use tower::{Service, ServiceExt};
+
+/// Request without a body.
+pub fn request(method: &str, uri: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .method(method)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Request with a JSON body.
+pub fn json_request(method: &str, uri: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// Sends `request`, asserts the response has status `status` and returns its JSON body.
+pub async fn call_with_status<T: DeserializeOwned>(app: &mut Router, request: Request<Body>, status: StatusCode) -> T {
+    let response = ServiceExt::ready(app).await.unwrap()
+        .call(request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), status);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Sends `request`, asserts the response is `OK` and returns its JSON body.
+pub async fn call<T: DeserializeOwned>(app: &mut Router, request: Request<Body>) -> T {
+    call_with_status(app, request, StatusCode::OK).await
+}
+
+/// Sends `request` and returns the status of the response.
+pub async fn status(app: &mut Router, request: Request<Body>) -> StatusCode {
+    ServiceExt::ready(app).await.unwrap()
+        .call(request)
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Body of a response as text.
+pub async fn body_text(response: Response<BoxBody>) -> String {
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    String::from(std::str::from_utf8(&body[..]).unwrap())
+}
@@ -1,5 +1,6 @@
 pub mod db;
 pub mod db_data;
+pub mod http;
 
 use std::sync::atomic::AtomicU16;
 
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use api::{
+    app,
+    core::analytics::{FreezerStats, MonthStats, ProductStats, Stats},
+    models::Unit,
+};
+use crate::common::{db::Context, db_data::PRODUCTS, http::{call, json_request, request, status}};
+
+static MOD: &str = "router_analytics";
+
+#[tokio::test]
+async fn counts_frozen_and_withdrawn_items_of_a_product() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (_, product_name, _) = PRODUCTS[6];
+
+    let stats: Stats = call(&mut app, request("GET", &format!("/api/analytics?from=2023-01-01&to=2024-12-31&productName={}", product_name))).await;
+
+    assert_eq!(stats.frozen_items, 7);
+    assert_eq!(stats.withdrawn_items, 2);
+    assert_eq!(stats.withdrawn_quantity, BTreeMap::from([(Unit::Grams, 1316.6)]));
+    assert_eq!(stats.average_dwell_days, Some((295.0 + 104.0) / 2.0));
+    assert_eq!(stats.expired_share, Some(0.0));
+}
+
+#[tokio::test]
+async fn withdrawing_expired_items_counts_as_waste() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (_, product_name, _) = PRODUCTS[3];
+
+    // Item 14 went in in 2018 and is long expired.
+    assert_eq!(status(&mut app, request("PATCH", "/api/storage/14/withdraw")).await, StatusCode::OK);
+
+    let products: Vec<ProductStats> = call(&mut app, request("GET", "/api/analytics/products")).await;
+
+    assert_eq!(products.len(), 1);
+    assert_eq!(products[0].product_name, product_name);
+    assert_eq!(products[0].stats.frozen_items, 0);
+    assert_eq!(products[0].stats.expired_items, 1);
+    assert_eq!(products[0].stats.expired_share, Some(1.0));
+}
+
+#[tokio::test]
+async fn trends_have_a_row_per_month() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let months: Vec<MonthStats> = call(&mut app, request("GET", "/api/analytics/trends?from=2023-08-01&to=2023-12-31")).await;
+
+    let frozen: Vec<(&str, i64, i64)> = months.iter()
+        .map(|m| (m.month.as_str(), m.stats.frozen_items, m.stats.withdrawn_items))
+        .collect();
+    assert_eq!(frozen, vec![("2023-08", 10, 0), ("2023-09", 3, 0), ("2023-10", 5, 0), ("2023-11", 4, 0), ("2023-12", 0, 1)]);
+}
+
+#[tokio::test]
+async fn rejects_reversed_and_oversized_ranges() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    for uri in ["/api/analytics?from=2024-01-01&to=2023-01-01", "/api/analytics/trends?from=1900-01-01&to=2023-01-01"] {
+        assert_eq!(status(&mut app, request("GET", uri)).await, StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn discard_rates_per_product_and_freezer() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    // Items 1 and 2 are Brocoli in Berging, item 9 Groentensoep in Garage.
+    for (id, reason) in [(1, "discarded"), (2, "eaten"), (9, "discarded")] {
+        let withdraw = json_request("PATCH", &format!("/api/storage/{}/withdraw", id), json!({ "reason": reason }));
+        assert_eq!(status(&mut app, withdraw).await, StatusCode::OK);
+    }
+
+    let products: Vec<ProductStats> = call(&mut app, request("GET", "/api/analytics/products")).await;
+    let rates: Vec<(&str, i64, Option<f64>)> = products.iter()
+        .map(|p| (p.product_name.as_str(), p.stats.discarded_items, p.stats.discard_rate))
+        .collect();
+    assert_eq!(rates, vec![("Brocoli", 1, Some(0.5)), ("Groentensoep", 1, Some(1.0))]);
+
+    let freezers: Vec<FreezerStats> = call(&mut app, request("GET", "/api/analytics/freezers")).await;
+    let rates: Vec<(&str, Option<f64>)> = freezers.iter()
+        .map(|f| (f.freezer_name.as_str(), f.stats.discard_rate))
+        .collect();
+    assert_eq!(rates, vec![("Berging", Some(0.5)), ("Garage", Some(1.0))]);
+}
@@ -0,0 +1,133 @@
+use std::io::{Cursor, Read};
+
+use axum::http::{header::CONTENT_TYPE, StatusCode};
+use serde_json::json;
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    core::backup::{Dump, RestoreReport, DUMP_VERSION},
+    routes::storage::StorageResponse,
+};
+use crate::common::{db::Context, db_data::{DRAWERS, FREEZERS, PRODUCTS, STORAGE}, http::{call, json_request, request, status}};
+
+static MOD: &str = "router_backup";
+
+#[tokio::test]
+async fn export_restores_into_an_empty_database() {
+    let source_ctx = Context::new(MOD);
+    let mut source = app(Some(source_ctx.database_url())).await;
+    // Data beyond the seeded rows: a capacity, a minimum stock, a withdrawal reason, the trash and a stocktake.
+    let _: serde_json::Value = call(&mut source, json_request(
+        "PATCH", &format!("/api/freezers/id={}/capacity", FREEZERS[0].0), json!({ "capacity": 200000.0 }),
+    )).await;
+    let _: serde_json::Value = call(&mut source, json_request(
+        "PATCH", &format!("/api/products/id={}/min-stock", PRODUCTS[1].0), json!({ "minQuantity": 4.0, "unit": "portions" }),
+    )).await;
+    assert_eq!(status(&mut source, json_request("PATCH", "/api/storage/1/withdraw", json!({ "reason": "discarded" }))).await, StatusCode::OK);
+    assert_eq!(status(&mut source, request("DELETE", "/api/storage/2")).await, StatusCode::OK);
+    let stocktake: serde_json::Value = call(&mut source, json_request("POST", "/api/stocktakes", json!({ "freezerId": FREEZERS[1].0 }))).await;
+    let _: serde_json::Value = call(&mut source, request("PATCH", &format!("/api/stocktakes/{}/items/9", stocktake["stocktakeId"]))).await;
+
+    let dump: Dump = call(&mut source, request("GET", "/api/export")).await;
+    assert_eq!(dump.version, DUMP_VERSION);
+    assert_eq!(dump.tables["storage"].len(), STORAGE.len());
+
+    let target_ctx = Context::empty(MOD);
+    let mut target = app(Some(target_ctx.database_url())).await;
+    let report: RestoreReport = call(&mut target, json_request("POST", "/api/restore", serde_json::to_value(&dump).unwrap())).await;
+    assert_eq!(report.rows["products"], PRODUCTS.len());
+    assert_eq!(report.rows["drawers"], DRAWERS.len());
+    assert_eq!(report.rows["stocktake_items"], 1);
+
+    let restored: Dump = call(&mut target, request("GET", "/api/export")).await;
+    assert_eq!(restored.tables, dump.tables);
+    let source_items: Vec<StorageResponse> = call(&mut source, request("GET", "/api/storage")).await;
+    let target_items: Vec<StorageResponse> = call(&mut target, request("GET", "/api/storage")).await;
+    assert_eq!(target_items, source_items);
+
+    // New rows continue after the restored ids.
+    let created: Vec<StorageResponse> = call(&mut target, json_request("POST", "/api/storage", json!({
+        "productId": PRODUCTS[0].0, "drawerId": DRAWERS[0].0, "quantity": 400.0, "dateIn": "2023-10-01",
+    }))).await;
+    assert_eq!(created[0].storage_id, STORAGE.len() as i32 + 1);
+
+    assert_eq!(status(&mut target, json_request("POST", "/api/restore", serde_json::to_value(&dump).unwrap())).await, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn csv_export_is_a_zip_with_a_file_per_table() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("GET", "/api/export?format=csv"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/zip");
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    let mut zip = zip::ZipArchive::new(Cursor::new(body.to_vec())).unwrap();
+    assert!(zip.file_names().any(|name| name == "manifest.json"));
+    let mut storage_csv = String::new();
+    zip.by_name("storage.csv").unwrap().read_to_string(&mut storage_csv).unwrap();
+    let lines: Vec<&str> = storage_csv.lines().collect();
+    assert!(lines[0].starts_with("storage_id,product_id,drawer_id,quantity"));
+    assert_eq!(lines.len(), STORAGE.len() + 1);
+}
+
+#[tokio::test]
+async fn invalid_dumps_are_rejected() {
+    let ctx = Context::empty(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let unsupported = json!({ "version": DUMP_VERSION + 1, "exportedAt": "2026-10-18T12:00:00Z", "tables": {} });
+    assert_eq!(status(&mut app, json_request("POST", "/api/restore", unsupported)).await, StatusCode::BAD_REQUEST);
+    let unknown_table = json!({ "version": DUMP_VERSION, "exportedAt": "2026-10-18T12:00:00Z", "tables": { "users": [] } });
+    assert_eq!(status(&mut app, json_request("POST", "/api/restore", unknown_table)).await, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn columns_missing_from_a_dump_get_their_default() {
+    let source_ctx = Context::new(MOD);
+    let mut source = app(Some(source_ctx.database_url())).await;
+    let mut dump: Dump = call(&mut source, request("GET", "/api/export")).await;
+    // As exported before storage items had a unit and a version.
+    for row in dump.tables.get_mut("storage").unwrap() {
+        let row = row.as_object_mut().unwrap();
+        row.remove("unit");
+        row.remove("version");
+    }
+
+    let target_ctx = Context::empty(MOD);
+    let mut target = app(Some(target_ctx.database_url())).await;
+    let report: RestoreReport = call(&mut target, json_request("POST", "/api/restore", serde_json::to_value(&dump).unwrap())).await;
+    assert_eq!(report.rows["storage"], STORAGE.len());
+
+    let restored: Dump = call(&mut target, request("GET", "/api/export")).await;
+    assert!(restored.tables["storage"].iter().all(|row| row["unit"] == "grams" && row["version"] == 1));
+}
+
+#[tokio::test]
+async fn dumps_not_matching_the_schema_are_rejected() {
+    let source_ctx = Context::new(MOD);
+    let mut source = app(Some(source_ctx.database_url())).await;
+    let dump: Dump = call(&mut source, request("GET", "/api/export")).await;
+    let target_ctx = Context::empty(MOD);
+    let mut target = app(Some(target_ctx.database_url())).await;
+
+    let changes: [fn(&mut serde_json::Map<String, serde_json::Value>); 3] = [
+        |row| { row.insert(String::from("weight"), json!(400)); },
+        |row| { row.remove("product_id"); },
+        |row| { row.insert(String::from("quantity"), json!("veel")); },
+    ];
+    for change in changes {
+        let mut invalid = dump.clone();
+        change(invalid.tables.get_mut("storage").unwrap()[0].as_object_mut().unwrap());
+        assert_eq!(status(&mut target, json_request("POST", "/api/restore", serde_json::to_value(&invalid).unwrap())).await, StatusCode::BAD_REQUEST);
+    }
+
+    // Nothing was restored.
+    let _: RestoreReport = call(&mut target, json_request("POST", "/api/restore", serde_json::to_value(&dump).unwrap())).await;
+}
@@ -0,0 +1,129 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::Local;
+use serde_json::json;
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    models::{Product, Unit},
+    routes::storage::StorageResponse,
+};
+use crate::common::{db::Context, db_data::{DRAWERS, PRODUCTS}, http::json_request};
+
+static MOD: &str = "router_barcodes";
+
+async fn set_barcodes(app: &mut Router, product_id: i32, barcodes: serde_json::Value) -> (StatusCode, Vec<u8>) {
+    let response = ServiceExt::ready(app).await.unwrap()
+        .call(json_request("PATCH", &format!("/api/products/id={}/barcodes", product_id), barcodes))
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn sets_barcodes_and_finds_product_by_either_form() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let (status, body) = set_barcodes(&mut app, PRODUCTS[0].0, json!(["4006381333931", " 036000291452", "0036000291452"])).await;
+    assert_eq!(status, StatusCode::OK);
+    let barcodes: Vec<String> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(barcodes, vec!["0036000291452", "4006381333931"]);
+
+    for code in ["036000291452", "0036000291452", "4006381333931"] {
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri(format!("/api/products/barcode/{}", code)).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let product: Product = serde_json::from_slice(&body).unwrap();
+        assert_eq!(product.product_id, PRODUCTS[0].0);
+    }
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(format!("/api/products/id={}/barcodes", PRODUCTS[0].0)).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    assert_eq!(serde_json::from_slice::<Vec<String>>(&body).unwrap(), barcodes);
+}
+
+#[tokio::test]
+async fn rejects_invalid_and_taken_barcodes() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let (invalid_status, _) = set_barcodes(&mut app, PRODUCTS[0].0, json!(["4006381333932"])).await;
+    let (first_status, _) = set_barcodes(&mut app, PRODUCTS[0].0, json!(["4006381333931"])).await;
+    let (taken_status, _) = set_barcodes(&mut app, PRODUCTS[1].0, json!(["4006381333931"])).await;
+    let unknown_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/products/barcode/036000291452").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(invalid_status, StatusCode::BAD_REQUEST);
+    assert_eq!(first_status, StatusCode::OK);
+    assert_eq!(taken_status, StatusCode::CONFLICT);
+    assert_eq!(unknown_response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn scan_creates_storage_item() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let (status, _) = set_barcodes(&mut app, PRODUCTS[1].0, json!(["036000291452"])).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let scan_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("POST", "/api/storage/scan", json!({ "barcode": "036000291452", "drawerId": DRAWERS[0].0 })))
+        .await
+        .unwrap();
+    assert_eq!(scan_response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(scan_response.into_body()).await.unwrap();
+    let items: Vec<StorageResponse> = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(items[0].product_name, PRODUCTS[1].1);
+    assert_eq!(items[0].drawer_name, DRAWERS[0].1);
+    assert_eq!(items[0].unit, Unit::Pieces);
+    assert!((items[0].quantity - 1.0).abs() <= 1e-6);
+    assert_eq!(items[0].in_storage_since, Local::now().date_naive());
+
+    let bad_check_digit_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("POST", "/api/storage/scan", json!({ "barcode": "036000291453", "drawerId": DRAWERS[0].0 })))
+        .await
+        .unwrap();
+    assert_eq!(bad_check_digit_response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn barcodes_of_trashed_products_move_to_another_product() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let (status, _) = set_barcodes(&mut app, PRODUCTS[0].0, json!(["4006381333931"])).await;
+    assert_eq!(status, StatusCode::OK);
+    let delete_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(format!("/api/products/id={}", PRODUCTS[0].0)).method("DELETE").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(delete_response.status(), StatusCode::OK);
+
+    let (status, _) = set_barcodes(&mut app, PRODUCTS[1].0, json!(["4006381333931"])).await;
+    assert_eq!(status, StatusCode::OK);
+    let response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/products/barcode/4006381333931").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    assert_eq!(serde_json::from_slice::<Product>(&body).unwrap().product_id, PRODUCTS[1].0);
+}
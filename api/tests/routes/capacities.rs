@@ -0,0 +1,155 @@
+use axum::{
+    body::Body,
+    http::{header::{CONTENT_TYPE, WARNING}, Request, StatusCode},
+};
+use serde_json::json;
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    models::Unit,
+    routes::capacities::{DrawerCapacity, FreezerCapacity, FreezerFillLevel},
+};
+use crate::common::{db::Context, db_data::{DRAWERS, FREEZERS, PRODUCTS}, http::{call, json_request, status}};
+
+static MOD: &str = "router_capacities";
+
+#[tokio::test]
+async fn capacities_can_be_set_and_removed() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (freezer_id, _) = FREEZERS[0];
+    let (drawer_id, _, _) = DRAWERS[0];
+
+    let freezer: FreezerCapacity = call(&mut app, json_request(
+        "PATCH", &format!("/api/freezers/id={}/capacity", freezer_id), json!({ "capacity": 200000.0, "unit": "millilitres" }),
+    )).await;
+    assert_eq!(freezer, FreezerCapacity { freezer_id, capacity: Some(200000.0), unit: Some(Unit::Millilitres) });
+    let drawer: DrawerCapacity = call(&mut app, json_request(
+        "PATCH", &format!("/api/drawers/{}/capacity", drawer_id), json!({ "capacity": 4000.0 }),
+    )).await;
+    assert_eq!(drawer, DrawerCapacity { drawer_id, capacity: Some(4000.0), unit: Some(Unit::Grams) });
+    let fetched: DrawerCapacity = call(&mut app, Request::builder()
+        .uri(format!("/api/drawers/{}/capacity", drawer_id)).body(Body::empty()).unwrap()).await;
+    assert_eq!(fetched, drawer);
+
+    let removed: FreezerCapacity = call(&mut app, json_request(
+        "PATCH", &format!("/api/freezers/id={}/capacity", freezer_id), json!({ "capacity": null }),
+    )).await;
+    assert_eq!(removed, FreezerCapacity { freezer_id, capacity: None, unit: None });
+}
+
+#[tokio::test]
+async fn invalid_capacities_are_rejected() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let pieces = json_request("PATCH", "/api/drawers/1/capacity", json!({ "capacity": 10.0, "unit": "pieces" }));
+    assert_eq!(status(&mut app, pieces).await, StatusCode::BAD_REQUEST);
+    let negative = json_request("PATCH", "/api/freezers/id=1/capacity", json!({ "capacity": -1.0 }));
+    assert_eq!(status(&mut app, negative).await, StatusCode::BAD_REQUEST);
+    let unknown = json_request("PATCH", "/api/drawers/999/capacity", json!({ "capacity": 10.0 }));
+    assert_eq!(status(&mut app, unknown).await, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn fill_levels_per_freezer_and_drawer() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (freezer_id, freezer_name) = FREEZERS[0];
+    let (drawer_id, _, _) = DRAWERS[0];
+
+    let _: FreezerCapacity = call(&mut app, json_request(
+        "PATCH", &format!("/api/freezers/id={}/capacity", freezer_id), json!({ "capacity": 10000.0 }),
+    )).await;
+    let _: DrawerCapacity = call(&mut app, json_request(
+        "PATCH", &format!("/api/drawers/{}/capacity", drawer_id), json!({ "capacity": 4000.0 }),
+    )).await;
+
+    let levels: Vec<FreezerFillLevel> = call(&mut app, Request::builder()
+        .uri("/api/freezers/fill-levels").body(Body::empty()).unwrap()).await;
+
+    assert_eq!(levels.len(), FREEZERS.len());
+    let berging = &levels[0];
+    assert_eq!(berging.freezer_name, freezer_name);
+    // Drawers 1, 2 and 4 hold 3800, 3216.5 and 2350 grams, withdrawn items don't count.
+    assert_eq!(berging.level.items, 18);
+    assert!((berging.level.used(Unit::Grams) - 9366.5).abs() < 0.1);
+    assert!((berging.level.fill_level.unwrap() - 0.93665).abs() < 1e-4);
+    let schuif_1 = berging.drawers.iter().find(|drawer| drawer.drawer_id == drawer_id).unwrap();
+    assert_eq!(schuif_1.level.fill_level, Some(0.95));
+    assert!(levels[1].level.fill_level.is_none());
+}
+
+#[tokio::test]
+async fn storing_over_capacity_warns() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (drawer_id, _, _) = DRAWERS[0];
+    let (product_id, _, _) = PRODUCTS[0];
+
+    let _: DrawerCapacity = call(&mut app, json_request(
+        "PATCH", &format!("/api/drawers/{}/capacity", drawer_id), json!({ "capacity": 4000.0 }),
+    )).await;
+
+    let store = |quantity: f32, unit: &str| json_request("POST", "/api/storage", json!({
+        "productId": product_id, "drawerId": drawer_id, "quantity": quantity, "unit": unit, "dateIn": "2023-10-01",
+    }));
+    let fits = ServiceExt::ready(&mut app).await.unwrap().call(store(100.0, "grams")).await.unwrap();
+    assert_eq!(fits.status(), StatusCode::OK);
+    assert!(fits.headers().get(WARNING).is_none());
+    let other_unit = ServiceExt::ready(&mut app).await.unwrap().call(store(6.0, "pieces")).await.unwrap();
+    assert!(other_unit.headers().get(WARNING).is_none());
+
+    let over = ServiceExt::ready(&mut app).await.unwrap().call(store(300.0, "grams")).await.unwrap();
+    assert_eq!(over.status(), StatusCode::OK);
+    assert_eq!(
+        over.headers().get(WARNING).unwrap().to_str().unwrap(),
+        format!("199 - \"Drawer {} is over capacity: 4200 of 4000 grams\"", drawer_id),
+    );
+}
+
+#[tokio::test]
+async fn batches_over_capacity_warn_with_labels() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (drawer_id, _, _) = DRAWERS[0];
+    let (product_id, _, _) = PRODUCTS[0];
+
+    let _: DrawerCapacity = call(&mut app, json_request(
+        "PATCH", &format!("/api/drawers/{}/capacity", drawer_id), json!({ "capacity": 4000.0 }),
+    )).await;
+
+    let batch = json_request("POST", "/api/storage/batch?format=pdf", json!({
+        "productId": product_id, "drawerId": drawer_id, "quantity": 300.0, "unit": "grams", "dateIn": "2023-10-01", "count": 2,
+    }));
+    let labels = ServiceExt::ready(&mut app).await.unwrap().call(batch).await.unwrap();
+    assert_eq!(labels.status(), StatusCode::OK);
+    assert_eq!(labels.headers()[CONTENT_TYPE], "application/pdf");
+    assert_eq!(
+        labels.headers().get(WARNING).unwrap().to_str().unwrap(),
+        format!("199 - \"Drawer {} is over capacity: 4400 of 4000 grams\"", drawer_id),
+    );
+}
+
+#[tokio::test]
+async fn capacities_honor_if_match() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    for uri in [format!("/api/freezers/id={}/capacity", FREEZERS[0].0), format!("/api/drawers/{}/capacity", DRAWERS[0].0)] {
+        let get_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let etag = get_response.headers()["etag"].to_str().unwrap().to_owned();
+
+        let mut statuses = Vec::new();
+        for capacity in [1000.0, 2000.0] {
+            let mut request = json_request("PATCH", &uri, json!({ "capacity": capacity }));
+            request.headers_mut().insert("If-Match", etag.parse().unwrap());
+            statuses.push(status(&mut app, request).await);
+        }
+        assert_eq!(statuses, vec![StatusCode::OK, StatusCode::PRECONDITION_FAILED], "{}", uri);
+    }
+}
@@ -0,0 +1,124 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    models::{Category, NewCategory},
+    routes::{categories::ProductCategory, storage::StorageResponse},
+};
+use crate::common::{db::Context, db_data::{PRODUCTS, STORAGE}, http::{call, json_request, request, status}};
+
+static MOD: &str = "router_categories";
+
+async fn create_category(app: &mut axum::Router, name: &str, parent_id: Option<i32>) -> Category {
+    let response = ServiceExt::ready(app).await.unwrap()
+        .call(json_request("POST", "/api/categories", serde_json::to_value(NewCategory { name: String::from(name), parent_id }).unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn refuses_category_cycles() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let soups = create_category(&mut app, "Soups", None).await;
+    let vegetable_soups = create_category(&mut app, "Vegetable soups", Some(soups.category_id)).await;
+
+    let update_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("PATCH", "/api/categories", serde_json::to_value(Category { parent_id: Some(vegetable_soups.category_id), ..soups }).unwrap()))
+        .await
+        .unwrap();
+
+    assert_eq!(update_response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn delete_moves_subcategories_up() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let food = create_category(&mut app, "Food", None).await;
+    let meat = create_category(&mut app, "Meat", Some(food.category_id)).await;
+    let poultry = create_category(&mut app, "Poultry", Some(meat.category_id)).await;
+
+    let delete_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(format!("/api/categories/{}", meat.category_id)).method("DELETE").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(delete_response.status(), StatusCode::OK);
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/categories").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let categories: Vec<Category> = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(categories, vec![food.clone(), Category { parent_id: Some(food.category_id), ..poultry }]);
+}
+
+#[tokio::test]
+async fn filters_storage_by_category_including_subcategories() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let soups = create_category(&mut app, "Soups", None).await;
+    let vegetable_soups = create_category(&mut app, "Vegetable soups", Some(soups.category_id)).await;
+    // Groentensoep and Pastinaaksoep.
+    for (product_id, category_id) in [(PRODUCTS[1].0, soups.category_id), (PRODUCTS[3].0, vegetable_soups.category_id)] {
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("PATCH", &format!("/api/products/id={}/category", product_id), serde_json::to_value(ProductCategory { product_id, category_id: Some(category_id) }).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/storage?category=Soups").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(get_response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let items: Vec<StorageResponse> = serde_json::from_slice(&body).unwrap();
+    let expected = STORAGE.iter()
+        .filter(|item| item.4.is_empty() && (item.1 == PRODUCTS[1].0 || item.1 == PRODUCTS[3].0))
+        .count();
+
+    assert_eq!(items.len(), expected);
+    assert!(items.iter().all(|item| item.product_name == PRODUCTS[1].1 || item.product_name == PRODUCTS[3].1));
+}
+
+#[tokio::test]
+async fn product_category_honors_if_match() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let soups = create_category(&mut app, "Soups", None).await;
+    let meat = create_category(&mut app, "Meat", None).await;
+    let uri = format!("/api/products/id={}/category", PRODUCTS[0].0);
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("GET", &uri))
+        .await
+        .unwrap();
+    let etag = get_response.headers()["etag"].to_str().unwrap().to_owned();
+
+    let mut statuses = Vec::new();
+    for category_id in [soups.category_id, meat.category_id] {
+        let mut request = json_request("PATCH", &uri, serde_json::json!({ "categoryId": category_id }));
+        request.headers_mut().insert("If-Match", etag.parse().unwrap());
+        statuses.push(status(&mut app, request).await);
+    }
+
+    assert_eq!(statuses, vec![StatusCode::OK, StatusCode::PRECONDITION_FAILED]);
+    let product_category: ProductCategory = call(&mut app, request("GET", &uri)).await;
+    assert_eq!(product_category.category_id, Some(soups.category_id));
+}
@@ -9,7 +9,7 @@ use api::{
     models::{Drawer, NewDrawer},
 };
 use crate::common::db::Context;
-use crate::common::db_data::{FREEZERS, DRAWERS};
+use crate::common::db_data::{FREEZERS, DRAWERS, STORAGE};
 
 static MOD: &str = "router_drawers";
 
@@ -423,7 +423,7 @@ async fn deletes_drawer_correctly() {
         .unwrap()
         .call(
             Request::builder()
-                .uri("/api/drawers/1")
+                .uri("/api/drawers/1?force=true")
                 .method("DELETE")
                 .body(Body::empty())
                 .unwrap()
@@ -473,3 +473,111 @@ async fn delete_returns_error_on_nonexistent_drawer_id() {
 
     assert_eq!(error_text, "Drawer not found");
 }
+
+#[tokio::test]
+async fn delete_refuses_drawer_with_available_items() {
+    let ctx = Context::new(MOD);
+    let app = app(Some(ctx.database_url())).await;
+
+    let available_items = STORAGE.iter().filter(|item| item.5 == 1 && item.4.is_empty()).count();
+
+    let delete_response = app.oneshot(
+        Request::builder()
+            .uri("/api/drawers/1")
+            .method("DELETE")
+            .body(Body::empty())
+            .unwrap()
+    ).await.unwrap();
+
+    assert_eq!(delete_response.status(), StatusCode::CONFLICT);
+
+    let body = hyper::body::to_bytes(delete_response.into_body()).await.unwrap();
+    let error_text = std::str::from_utf8(&body).unwrap();
+
+    assert!(error_text.starts_with(&format!("This drawer contains {} available items", available_items)));
+}
+
+#[tokio::test]
+async fn delete_reassigns_available_items_to_target_drawer() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let moved_items = STORAGE.iter().filter(|item| item.5 == 1 && item.4.is_empty()).count();
+    let target_items = STORAGE.iter().filter(|item| item.5 == 3 && item.4.is_empty()).count();
+
+    let delete_response = ServiceExt::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .uri("/api/drawers/1?reassignTo=3")
+                .method("DELETE")
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(delete_response.status(), StatusCode::OK);
+
+    let storage_response = ServiceExt::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .uri("/api/storage?drawerName=Schuif%203&freezerName=Berging&isWithdrawn=false")
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(storage_response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(storage_response.into_body()).await.unwrap();
+    let items: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(items.len(), moved_items + target_items);
+}
+
+#[tokio::test]
+async fn delete_refuses_reassign_into_deleted_drawer() {
+    let ctx = Context::new(MOD);
+    let app = app(Some(ctx.database_url())).await;
+
+    let delete_response = app.oneshot(
+        Request::builder()
+            .uri("/api/drawers/1?reassignTo=1")
+            .method("DELETE")
+            .body(Body::empty())
+            .unwrap()
+    ).await.unwrap();
+
+    assert_eq!(delete_response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn returns_error_on_create_name_differing_in_case_within_freezer() {
+    let ctx = Context::new(MOD);
+    let app = app(Some(ctx.database_url())).await;
+
+    let Drawer {drawer_id: _, name, freezer_id } = Drawer::from_tuple(DRAWERS[10]);
+    let error_drawer = NewDrawer {
+        name: format!("{}  ", name.to_lowercase()),
+        freezer_id
+    };
+
+    let post_response = app.oneshot(
+        Request::builder()
+            .uri("/api/drawers")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_string(&error_drawer).unwrap()))
+            .unwrap()
+    ).await.unwrap();
+
+    let response_body = hyper::body::to_bytes(post_response.into_body()).await.unwrap();
+    let response_text = std::str::from_utf8(&response_body[..]).unwrap();
+
+    assert_eq!(response_text, "This drawer name already exists within this freezer")
+}
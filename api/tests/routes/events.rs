@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use axum::{
+    body::{Body, BoxBody, HttpBody},
+    http::{Request, StatusCode},
+    response::Response,
+};
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    models::{NewFreezer, NewProduct},
+};
+use crate::common::db::Context;
+
+static MOD: &str = "router_events";
+
+fn events_request(last_event_id: Option<&str>) -> Request<Body> {
+    let mut request = Request::builder().uri("/api/events");
+    if let Some(last_event_id) = last_event_id {
+        request = request.header("Last-Event-ID", last_event_id);
+    }
+
+    request.body(Body::empty()).unwrap()
+}
+
+async fn next_frame(response: &mut Response<BoxBody>) -> String {
+    let chunk = tokio::time::timeout(Duration::from_secs(5), response.body_mut().data())
+        .await
+        .expect("No event received in time")
+        .unwrap()
+        .unwrap();
+
+    String::from(std::str::from_utf8(&chunk).unwrap())
+}
+
+#[tokio::test]
+async fn streams_changes_after_subscribing() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let mut events_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(events_request(None))
+        .await
+        .unwrap();
+    assert_eq!(events_response.status(), StatusCode::OK);
+    assert_eq!(events_response.headers()["content-type"], "text/event-stream");
+
+    let create_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(
+            Request::builder()
+                .uri("/api/freezers/create")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::to_string(&NewFreezer { name: String::from("Bureau") }).unwrap()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(create_response.status(), StatusCode::OK);
+
+    let frame = next_frame(&mut events_response).await;
+
+    assert!(frame.contains("event:change"));
+    assert!(frame.contains("id:1"));
+    assert!(frame.contains("\"entity\":\"freezer\",\"action\":\"created\""));
+}
+
+#[tokio::test]
+async fn replays_changes_after_last_event_id() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    for name in ["Lasagne", "Ratatouille"] {
+        let new_product = NewProduct { name: String::from(name), expiration_months: Some(6), default_unit: None };
+        ServiceExt::ready(&mut app).await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/products/create")
+                    .method("POST")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(serde_json::to_string(&new_product).unwrap()))
+                    .unwrap()
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut events_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(events_request(Some("1")))
+        .await
+        .unwrap();
+
+    let frame = next_frame(&mut events_response).await;
+
+    assert!(frame.contains("id:2"));
+    assert!(frame.contains("\"entity\":\"product\",\"action\":\"created\""));
+}
+
+#[tokio::test]
+async fn requests_reset_when_history_is_unavailable() {
+    let ctx = Context::new(MOD);
+    let app = app(Some(ctx.database_url())).await;
+
+    let mut events_response = app.oneshot(events_request(Some("42"))).await.unwrap();
+
+    let frame = next_frame(&mut events_response).await;
+
+    assert!(frame.contains("event:reset"));
+}
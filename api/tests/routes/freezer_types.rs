@@ -0,0 +1,182 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::NaiveDate;
+use serde_json::json;
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    core::query::ShelfLife,
+    models::FreezerType,
+    routes::{freezer_types::ProductShelfLife, storage::StorageResponse},
+};
+use crate::common::{db::Context, db_data::{FREEZERS, PRODUCTS}, http::{call, json_request, request, status}};
+
+static MOD: &str = "router_freezer_types";
+
+/// Creates a freezer type and assigns it to the "Garage" freezer.
+async fn create_garage_type(app: &mut Router, multiplier: f32) -> FreezerType {
+    let create_response = ServiceExt::ready(app).await.unwrap()
+        .call(json_request("POST", "/api/freezer-types", json!({ "name": "One star", "shelfLifeMultiplier": multiplier })))
+        .await
+        .unwrap();
+    assert_eq!(create_response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(create_response.into_body()).await.unwrap();
+    let freezer_type: FreezerType = serde_json::from_slice(&body).unwrap();
+
+    let assign_response = ServiceExt::ready(app).await.unwrap()
+        .call(json_request(
+            "PATCH",
+            &format!("/api/freezers/id={}/type", FREEZERS[1].0),
+            json!({ "freezerTypeId": freezer_type.freezer_type_id }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(assign_response.status(), StatusCode::OK);
+
+    freezer_type
+}
+
+async fn get_storage_items(app: &mut Router, uri: &str) -> Vec<StorageResponse> {
+    let response = ServiceExt::ready(app).await.unwrap()
+        .call(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn freezer_type_multiplier_scales_shelf_life() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    create_garage_type(&mut app, 0.5).await;
+
+    // Spruiten, 12 months, stored on 2023-07-13 in the Garage and on 2023-05-21 in the Kelder.
+    let garage_item = &get_storage_items(&mut app, "/api/storage/26").await[0];
+    let kelder_item = &get_storage_items(&mut app, "/api/storage/15").await[0];
+    let date_in = NaiveDate::from_ymd_opt(2023, 7, 13).unwrap();
+    let halved = ShelfLife::Months(12).scaled(date_in, 0.5);
+
+    assert_eq!(halved, ShelfLife::Days(183));
+    assert_eq!(garage_item.expiration_date, halved.expiration_date(date_in));
+    assert_eq!(kelder_item.expiration_date, NaiveDate::from_ymd_opt(2024, 5, 21).unwrap());
+}
+
+#[tokio::test]
+async fn expiration_filters_honor_shelf_life_in_days_and_overrides() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let freezer_type = create_garage_type(&mut app, 2.0).await;
+    let shelf_life_uri = format!("/api/products/id={}/shelf-life", PRODUCTS[2].0);
+    let set_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("PATCH", &shelf_life_uri, json!({
+            "shelfLifeDays": 14,
+            "overrides": [{ "freezerTypeId": freezer_type.freezer_type_id, "shelfLifeDays": 7 }],
+        })))
+        .await
+        .unwrap();
+    assert_eq!(set_response.status(), StatusCode::OK);
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(&shelf_life_uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let shelf_life: ProductShelfLife = serde_json::from_slice(&body).unwrap();
+    assert_eq!(shelf_life.shelf_life_days, Some(14));
+    assert_eq!(shelf_life.overrides.len(), 1);
+    assert_eq!(shelf_life.overrides[0].product_id, PRODUCTS[2].0);
+
+    // Kelder items expire 14 days after 2023-05-21, the Garage item 7 days after 2023-07-13.
+    let items = get_storage_items(&mut app, "/api/storage?productName=Spruiten&expiresBeforeDate=2023-07-25").await;
+
+    assert_eq!(items.iter().map(|item| item.storage_id).collect::<Vec<i32>>(), vec![15, 16, 17, 26]);
+    assert_eq!(items[0].expiration_date, NaiveDate::from_ymd_opt(2023, 6, 4).unwrap());
+    assert_eq!(items[3].expiration_date, NaiveDate::from_ymd_opt(2023, 7, 20).unwrap());
+}
+
+#[tokio::test]
+async fn rejects_invalid_shelf_lives() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let multiplier_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("POST", "/api/freezer-types", json!({ "name": "Broken", "shelfLifeMultiplier": 0.0 })))
+        .await
+        .unwrap();
+    let unknown_type_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("PATCH", &format!("/api/products/id={}/shelf-life", PRODUCTS[0].0), json!({
+            "shelfLifeDays": null,
+            "overrides": [{ "freezerTypeId": 999, "shelfLifeDays": 7 }],
+        })))
+        .await
+        .unwrap();
+    let days_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("PATCH", &format!("/api/products/id={}/shelf-life", PRODUCTS[0].0), json!({ "shelfLifeDays": 0 })))
+        .await
+        .unwrap();
+
+    assert_eq!(multiplier_response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(unknown_type_response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(days_response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn rejects_shelf_lives_beyond_the_calendar() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let huge_multiplier = json_request("POST", "/api/freezer-types", json!({ "name": "Eternal", "shelfLifeMultiplier": 1e30 }));
+    assert_eq!(status(&mut app, huge_multiplier).await, StatusCode::BAD_REQUEST);
+    let garage_type = create_garage_type(&mut app, 10.0).await;
+    for shelf_life in [
+        json!({ "shelfLifeDays": i32::MAX }),
+        json!({ "shelfLifeDays": 36500, "overrides": [{ "freezerTypeId": garage_type.freezer_type_id, "shelfLifeDays": 36501 }] }),
+    ] {
+        let request = json_request("PATCH", &format!("/api/products/id={}/shelf-life", PRODUCTS[0].0), shelf_life);
+        assert_eq!(status(&mut app, request).await, StatusCode::BAD_REQUEST);
+    }
+
+    // The longest shelf life in the freezer type with the largest multiplier still has an expiration date.
+    let longest = json_request("PATCH", &format!("/api/products/id={}/shelf-life", PRODUCTS[0].0), json!({ "shelfLifeDays": 36500 }));
+    assert_eq!(status(&mut app, longest).await, StatusCode::OK);
+    let items = get_storage_items(&mut app, "/api/storage").await;
+    assert!(items.iter().any(|item| item.product_name == PRODUCTS[0].1));
+}
+
+#[tokio::test]
+async fn freezer_type_and_shelf_life_honor_if_match() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let freezer_type = create_garage_type(&mut app, 0.5).await;
+
+    for (uri, bodies) in [
+        (format!("/api/freezers/id={}/type", FREEZERS[0].0), [json!({ "freezerTypeId": freezer_type.freezer_type_id }), json!({ "freezerTypeId": null })]),
+        (format!("/api/products/id={}/shelf-life", PRODUCTS[0].0), [json!({ "shelfLifeDays": 14 }), json!({ "shelfLifeDays": 21 })]),
+    ] {
+        let get_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(request("GET", &uri))
+            .await
+            .unwrap();
+        let etag = get_response.headers()["etag"].to_str().unwrap().to_owned();
+
+        let mut statuses = Vec::new();
+        for body in bodies {
+            let mut request = json_request("PATCH", &uri, body);
+            request.headers_mut().insert("If-Match", etag.parse().unwrap());
+            statuses.push(status(&mut app, request).await);
+        }
+        assert_eq!(statuses, vec![StatusCode::OK, StatusCode::PRECONDITION_FAILED], "{}", uri);
+    }
+
+    let shelf_life: ProductShelfLife = call(&mut app, request("GET", &format!("/api/products/id={}/shelf-life", PRODUCTS[0].0))).await;
+    assert_eq!(shelf_life.shelf_life_days, Some(14));
+}
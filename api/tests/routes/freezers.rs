@@ -318,7 +318,7 @@ async fn deletes_freezer_correctly() {
     let mut app = app(Some(ctx.database_url())).await;
 
     let request = Request::builder()
-        .uri("/api/freezers/id=1")
+        .uri("/api/freezers/id=1?force=true")
         .method("DELETE")
         .body(Body::empty())
         .unwrap();
@@ -377,3 +377,32 @@ async fn delete_returns_error_on_nonexistent_id() {
 
     assert_eq!(error_text, "This freezer id does not exist");
 }
+
+#[tokio::test]
+async fn create_returns_error_on_name_differing_in_case() {
+    let ctx = Context::new(MOD);
+    let app = app(Some(ctx.database_url())).await;
+
+    let existing_freezer = NewFreezer {
+        name: format!(" {} ", FREEZERS[1].1.to_uppercase()),
+    };
+
+    let create_response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/freezers/create")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(ser::to_string(&existing_freezer).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = hyper::body::to_bytes(create_response.into_body())
+        .await
+        .unwrap();
+    let error_text = std::str::from_utf8(&body[..]).unwrap();
+
+    assert_eq!(error_text, "This freezer name already exists");
+}
@@ -0,0 +1,131 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::json;
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    core::import::{ImportReport, RowError},
+    models::{Product, Unit},
+    routes::storage::StorageResponse,
+};
+use crate::common::{db::Context, http::{call, call_with_status, request}};
+
+static MOD: &str = "router_import";
+
+const CSV: &str = "productName,freezerName,drawerName,weightGrams,unit,dateIn,dateOut,expirationDate,withdrawalReason
+brocoli,Garage,schuif 1,400,,2021-03-01,2021-06-01,,discarded
+Erwten,Zolder,Lade 1,250,grams,2021-04-12,,,
+Erwten,Zolder,Lade 1,6,pieces,2021-04-12,,2022-04-01,
+";
+
+fn import_request(uri: &str, content_type: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("Content-Type", content_type)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn get_storage(app: &mut Router, query: &str) -> Vec<StorageResponse> {
+    call(app, request("GET", &format!("/api/storage{}", query))).await
+}
+
+#[tokio::test]
+async fn csv_import_creates_missing_products_freezers_and_drawers() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let report: ImportReport = call(&mut app, import_request("/api/import?createMissing=true", "text/csv", CSV)).await;
+    assert_eq!(report, ImportReport {
+        dry_run: false,
+        rows: 3,
+        imported: 3,
+        created_products: vec![String::from("Erwten")],
+        created_freezers: vec![String::from("Zolder")],
+        created_drawers: vec![String::from("Zolder / Lade 1")],
+        errors: vec![],
+    });
+
+    let peas = get_storage(&mut app, "?productName=Erwten").await;
+    assert_eq!(peas.len(), 2);
+    assert!(peas.iter().all(|item| item.freezer_name == "Zolder" && item.drawer_name == "Lade 1"));
+    assert_eq!(peas.iter().map(|item| item.unit).collect::<Vec<Unit>>(), vec![Unit::Grams, Unit::Pieces]);
+    assert_eq!(peas[1].expiration_explicit, Some(true));
+
+    let discarded = get_storage(&mut app, "?withdrawalReason=discarded").await;
+    assert_eq!(discarded.len(), 1);
+    assert_eq!((discarded[0].product_name.as_str(), discarded[0].freezer_name.as_str()), ("Brocoli", "Garage"));
+    assert_eq!(discarded[0].quantity, 400.0);
+}
+
+#[tokio::test]
+async fn invalid_rows_are_reported_and_nothing_is_imported() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let before = get_storage(&mut app, "").await.len();
+
+    let rows = json!([
+        { "productName": "Brocoli", "freezerName": "Garage", "drawerName": "Schuif 1", "quantity": 400, "dateIn": "2021-03-01" },
+        { "productName": "Erwten", "freezerName": "Garage", "drawerName": "Schuif 9", "quantity": 250, "dateIn": "2021-04-12" },
+        { "productName": "Brocoli", "freezerName": "Garage", "drawerName": "Schuif 1", "quantity": 0, "dateIn": "2021-03-01" },
+        { "productName": "Brocoli", "freezerName": "Garage", "drawerName": "Schuif 1", "dateIn": "2021-03-01" },
+    ]);
+    let report: ImportReport = call_with_status(&mut app, import_request("/api/import", "application/json", &rows.to_string()), StatusCode::UNPROCESSABLE_ENTITY).await;
+
+    assert_eq!(report.rows, 4);
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.errors.iter().map(|error| error.row).collect::<Vec<usize>>(), vec![2, 3, 4]);
+    assert_eq!(report.errors[0], RowError {
+        row: 2,
+        errors: vec![String::from("Product Erwten not found"), String::from("Drawer Schuif 9 not found in freezer Garage")],
+    });
+    assert_eq!(report.errors[1].errors, vec![String::from("quantity must be positive")]);
+    assert!(report.errors[2].errors[0].contains("quantity"));
+    assert_eq!(get_storage(&mut app, "").await.len(), before);
+}
+
+#[tokio::test]
+async fn dry_run_only_validates() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let before = get_storage(&mut app, "").await.len();
+
+    let report: ImportReport = call(&mut app, import_request("/api/import?format=csv&dryRun=true&createMissing=true", "text/plain", CSV)).await;
+    assert!(report.dry_run);
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.created_products, vec![String::from("Erwten")]);
+    assert!(report.errors.is_empty());
+
+    assert_eq!(get_storage(&mut app, "").await.len(), before);
+    let products: Vec<Product> = call(&mut app, request("GET", "/api/products")).await;
+    assert!(products.iter().all(|product| product.name != "Erwten"));
+}
+
+#[tokio::test]
+async fn unreadable_files_are_rejected() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(import_request("/api/import", "application/json", r#"{"productName": "Brocoli"}"#))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn files_above_the_default_body_limit_are_accepted() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let body = format!("[{}]", " ".repeat(4 * 1024 * 1024));
+
+    let report: ImportReport = call(&mut app, import_request("/api/import?dryRun=true", "application/json", &body)).await;
+
+    assert_eq!(report.rows, 0);
+}
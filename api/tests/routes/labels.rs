@@ -0,0 +1,100 @@
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use tower::{Service, ServiceExt};
+
+use api::app;
+use crate::common::{db::Context, db_data::STORAGE};
+
+static MOD: &str = "router_labels";
+
+fn get_request(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn renders_label_in_all_formats() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    for (format, content_type, magic) in [
+        ("png", "image/png", &b"\x89PNG"[..]),
+        ("svg", "image/svg+xml", &b"<svg"[..]),
+        ("pdf", "application/pdf", &b"%PDF"[..]),
+    ] {
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(get_request(&format!("/api/storage/{}/label?format={}&width=50&height=25", STORAGE[0].0, format)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(body.starts_with(magic), "{} label has the wrong format", format);
+    }
+}
+
+#[tokio::test]
+async fn renders_sheet_of_labels_in_given_order() {
+    let ctx = Context::new(MOD);
+    let app = app(Some(ctx.database_url())).await;
+
+    let response = app
+        .oneshot(get_request(&format!("/api/storage/labels?format=svg&ids={},{}", STORAGE[30].0, STORAGE[8].0)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let svg = String::from_utf8(body.to_vec()).unwrap();
+    let spaghetti = svg.find("Spaghettisaus").unwrap();
+    let soup = svg.find("Groentensoep").unwrap();
+
+    assert!(spaghetti < soup);
+}
+
+#[tokio::test]
+async fn rejects_invalid_label_requests() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let size_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(get_request("/api/storage/1/label?width=5"))
+        .await
+        .unwrap();
+    let ids_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(get_request("/api/storage/labels?ids=1,a"))
+        .await
+        .unwrap();
+    let unknown_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(get_request("/api/storage/labels?ids=1,300"))
+        .await
+        .unwrap();
+
+    assert_eq!(size_response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(ids_response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(unknown_response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn sheets_are_limited_and_split_in_pages() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let too_many = vec![STORAGE[0].0.to_string(); 101].join(",");
+    let response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(get_request(&format!("/api/storage/labels?ids={}", too_many)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // 27 labels of the default size fit on an A4 page.
+    let two_pages = vec![STORAGE[0].0.to_string(); 28].join(",");
+    let response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(get_request(&format!("/api/storage/labels?ids={}", two_pages)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zip");
+}
@@ -3,3 +3,18 @@ mod freezers;
 mod products;
 mod storage;
 mod drawers;
+mod trash;
+mod events;
+mod categories;
+mod tags;
+mod freezer_types;
+mod labels;
+mod barcodes;
+mod shopping_list;
+mod recommendations;
+mod analytics;
+mod capacities;
+mod summary;
+mod stocktakes;
+mod import;
+mod backup;
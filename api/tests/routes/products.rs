@@ -1,11 +1,12 @@
 use crate::common::db_data::PRODUCTS;
 use crate::common::db::Context;
+use crate::common::http::json_request;
 use api::app;
 
 use log::{info};
 use axum::{
     body::Body,
-    http::Request,
+    http::{header, Request},
 };
 use hyper::StatusCode;
 use serde_json::{json, Value};
@@ -130,6 +131,7 @@ async fn create_product_simple_test() {
     let new_product = NewProduct {
         name: String::from("New Produce"),
         expiration_months: Some(24),
+        default_unit: None,
     };
     info!(target: "create_product", "{:?}", new_product);
     let new_product_json = serde_json::to_string(&new_product).unwrap();
@@ -150,6 +152,7 @@ async fn create_product() {
     let new_product = NewProduct {
         name: String::from("New Produce"),
         expiration_months: Some(24),
+        default_unit: None,
     };
     info!(target: "create_product", "{:?}", new_product);
     let request = Request::builder()
@@ -191,6 +194,7 @@ async fn cannot_create_existing_product() {
     let new_product = NewProduct {
         name: String::from("Brocoli"),
         expiration_months: Some(24),
+        default_unit: None,
     };
 
     let response = app
@@ -367,4 +371,214 @@ async fn delete_nonexistent_product_returns_error() {
     let error_text = std::str::from_utf8(&body[..]).unwrap();
 
     assert_eq!(error_text, "This product id does not exist");
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn update_product_with_stale_if_match_returns_precondition_failed() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let get_response = ServiceExt::ready(&mut app)
+        .await
+        .unwrap()
+        .call(Request::builder()
+            .uri("/api/products/id=2")
+            .body(Body::empty())
+            .unwrap()
+        )
+        .await
+        .unwrap();
+    let etag = get_response.headers().get(header::ETAG).unwrap().clone();
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let mut product: Product = serde_json::from_slice(&body).unwrap();
+
+    product.expiration_months = 10;
+    let first_update = ServiceExt::ready(&mut app)
+        .await
+        .unwrap()
+        .call(Request::builder()
+            .uri("/api/products")
+            .method("PATCH")
+            .header("Content-Type", "application/json")
+            .header(header::IF_MATCH, etag.clone())
+            .body(Body::from(serde_json::to_string(&product).unwrap()))
+            .unwrap()
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(first_update.status(), StatusCode::OK);
+
+    product.expiration_months = 8;
+    let second_update = app
+        .oneshot(Request::builder()
+            .uri("/api/products")
+            .method("PATCH")
+            .header("Content-Type", "application/json")
+            .header(header::IF_MATCH, etag)
+            .body(Body::from(serde_json::to_string(&product).unwrap()))
+            .unwrap()
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(second_update.status(), StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn names_are_normalized_and_unique_regardless_of_case() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let create = |name: &str| Request::builder()
+        .uri("/api/products/create")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "name": name, "expirationMonths": 6 }).to_string()))
+        .unwrap();
+
+    let created_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(create("  Rode   kool "))
+        .await
+        .unwrap();
+    assert_eq!(created_response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(created_response.into_body()).await.unwrap();
+    let created: Product = serde_json::from_slice(&body).unwrap();
+    assert_eq!(created.name, "Rode kool");
+
+    let duplicate_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(create("BROCOLI "))
+        .await
+        .unwrap();
+    let blank_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(create("   "))
+        .await
+        .unwrap();
+    assert_eq!(duplicate_response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(blank_response.status(), StatusCode::BAD_REQUEST);
+
+    let lookup_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/products/name=rode%20KOOL").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(lookup_response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(lookup_response.into_body()).await.unwrap();
+    let found: Product = serde_json::from_slice(&body).unwrap();
+    assert_eq!(found.product_id, created.product_id);
+}
+
+async fn search(app: &mut axum::Router, uri: &str) -> Vec<String> {
+    let response = ServiceExt::ready(app).await.unwrap()
+        .call(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    serde_json::from_slice::<Vec<Product>>(&body).unwrap()
+        .into_iter()
+        .map(|product| product.name)
+        .collect()
+}
+
+#[tokio::test]
+async fn search_ranks_prefix_substring_and_similar_names() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    assert_eq!(search(&mut app, "/api/products/search?q=spag").await, vec!["Spaghettisaus"]);
+    assert_eq!(search(&mut app, "/api/products/search?q=SOEP").await, vec!["Groentensoep", "Pastinaaksoep"]);
+    assert_eq!(search(&mut app, "/api/products/search?q=brocolli").await, vec!["Brocoli"]);
+    assert_eq!(search(&mut app, "/api/products/search?q=p&limit=2").await, vec!["Pastinaaksoep", "Puree"]);
+
+    let empty_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/products/search?q=%20").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(empty_response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn merge_moves_storage_barcodes_and_tags_to_target() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (target_id, target_name, _) = PRODUCTS[5];
+
+    let create_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("POST", "/api/products/create", json!({ "name": "Kip balletjes", "expirationMonths": 6 })))
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(create_response.into_body()).await.unwrap();
+    let duplicate: Product = serde_json::from_slice(&body).unwrap();
+    for (method, uri, body) in [
+        ("POST", String::from("/api/storage"), json!({ "productId": duplicate.product_id, "drawerId": 1, "quantity": 2.0, "dateIn": "2023-10-01" })),
+        ("PATCH", format!("/api/products/id={}/barcodes", duplicate.product_id), json!(["4006381333931"])),
+        ("PATCH", format!("/api/products/id={}/tags", duplicate.product_id), json!(["kip"])),
+    ] {
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request(method, &uri, body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let merge_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("POST", &format!("/api/products/id={}/merge", target_id), json!({ "sourceIds": [duplicate.product_id] })))
+        .await
+        .unwrap();
+    assert_eq!(merge_response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(merge_response.into_body()).await.unwrap();
+    let result: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(result, json!({ "productId": target_id, "mergedIds": [duplicate.product_id], "movedItems": 1 }));
+
+    let storage_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(format!("/api/storage?productName={}", target_name)).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(storage_response.into_body()).await.unwrap();
+    assert_eq!(serde_json::from_slice::<Vec<Value>>(&body).unwrap().len(), 2);
+
+    let barcode_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/products/barcode/4006381333931").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(barcode_response.into_body()).await.unwrap();
+    assert_eq!(serde_json::from_slice::<Product>(&body).unwrap().product_id, target_id);
+
+    let tags_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(format!("/api/products/id={}/tags", target_id)).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(tags_response.into_body()).await.unwrap();
+    assert_eq!(serde_json::from_slice::<Vec<Value>>(&body).unwrap()[0]["name"], "kip");
+
+    let source_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/products/name=Kip%20balletjes").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_ne!(source_response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn merge_rejects_invalid_sources() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let mut statuses = Vec::new();
+    for body in [json!({ "sourceIds": [] }), json!({ "sourceIds": [1] }), json!({ "sourceIds": [2, 999] })] {
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("POST", "/api/products/id=1/merge", body))
+            .await
+            .unwrap();
+        statuses.push(response.status());
+    }
+
+    assert_eq!(statuses, vec![StatusCode::BAD_REQUEST, StatusCode::BAD_REQUEST, StatusCode::NOT_FOUND]);
+
+    // Nothing was merged when a source is missing.
+    let product_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/products/id=2").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(product_response.status(), StatusCode::OK);
+}
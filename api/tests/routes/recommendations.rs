@@ -0,0 +1,73 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use tower::{Service, ServiceExt};
+
+use api::{app, routes::recommendations::{Recommendation, DEFAULT_RECOMMENDATION_LIMIT}};
+use crate::common::{db::Context, db_data::PRODUCTS};
+
+static MOD: &str = "router_recommendations";
+
+async fn get_recommendations(app: &mut Router, query: &str) -> Vec<Recommendation> {
+    let response = ServiceExt::ready(app).await.unwrap()
+        .call(Request::builder().uri(format!("/api/recommendations{}", query)).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn ranks_items_by_strategy() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    // Pastinaaksoep 14 went in first, Hamburgers 18 a bit later but keeps only 6 months.
+    let fifo = get_recommendations(&mut app, "?strategy=fifo").await;
+    assert_eq!(fifo[0].item.storage_id, 14);
+    assert!(fifo[0].reason.starts_with("In storage for "));
+
+    let fefo = get_recommendations(&mut app, "?strategy=fefo").await;
+    assert_eq!(fefo[0].item.storage_id, 18);
+    assert!(fefo[0].reason.starts_with("Expired "));
+    assert!(fefo.windows(2).all(|pair| pair[0].item.expiration_date <= pair[1].item.expiration_date));
+
+    let mixed = get_recommendations(&mut app, "").await;
+    assert_eq!(mixed.len(), DEFAULT_RECOMMENDATION_LIMIT);
+    assert_eq!(mixed[0].item.storage_id, 18);
+    assert!(mixed[0].reason.contains(", in storage for "));
+    assert!(mixed.windows(2).all(|pair| pair[0].score >= pair[1].score));
+}
+
+#[tokio::test]
+async fn filters_available_items() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (_, product_name, _) = PRODUCTS[6];
+
+    let recommendations = get_recommendations(&mut app, &format!("?strategy=fifo&productName={}&limit=20", product_name)).await;
+    // Items 36 and 37 are withdrawn.
+    let ids: Vec<i32> = recommendations.iter().map(|r| r.item.storage_id).collect();
+    assert_eq!(ids, vec![31, 32, 33, 34, 35]);
+
+    let limited = get_recommendations(&mut app, &format!("?productName={}&limit=2", product_name)).await;
+    assert_eq!(limited.len(), 2);
+}
+
+#[tokio::test]
+async fn rejects_invalid_parameters() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    for query in ["?limit=0", "?strategy=random"] {
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri(format!("/api/recommendations{}", query)).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,209 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::json;
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    models::{ShoppingListItem, Unit},
+    routes::shopping_list::{CheckedItem, ProductMinStock, ShoppingList},
+};
+use crate::common::{db::Context, db_data::{DRAWERS, PRODUCTS}, http::{call, json_request}};
+
+static MOD: &str = "router_shopping_list";
+
+async fn get_shopping_list(app: &mut Router) -> ShoppingList {
+    call(app, Request::builder().uri("/api/shopping-list").body(Body::empty()).unwrap()).await
+}
+
+#[tokio::test]
+async fn suggests_products_below_minimum_stock() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    // Brocoli is stored in grams, Groentensoep gets a minimum in portions it has none of.
+    let (brocoli_id, _, _) = PRODUCTS[0];
+    let (soup_id, soup_name, _) = PRODUCTS[1];
+
+    let min_stock: ProductMinStock = call(&mut app, json_request(
+        "PATCH", &format!("/api/products/id={}/min-stock", soup_id), json!({ "minQuantity": 4.0, "unit": "portions" }),
+    )).await;
+    assert_eq!(min_stock, ProductMinStock { product_id: soup_id, min_quantity: Some(4.0), unit: Some(Unit::Portions) });
+    let _: ProductMinStock = call(&mut app, json_request(
+        "PATCH", &format!("/api/products/id={}/min-stock", brocoli_id), json!({ "minQuantity": 1.0 }),
+    )).await;
+
+    let shopping_list = get_shopping_list(&mut app).await;
+    assert_eq!(shopping_list.suggestions.len(), 1);
+    assert_eq!(shopping_list.suggestions[0].product_name, soup_name);
+    assert_eq!(shopping_list.suggestions[0].missing_quantity, 4.0);
+
+    let _: serde_json::Value = call(&mut app, json_request("POST", "/api/storage", json!({
+        "productId": soup_id, "drawerId": DRAWERS[0].0, "quantity": 4.0, "unit": "portions", "dateIn": "2023-10-01",
+    }))).await;
+
+    assert!(get_shopping_list(&mut app).await.suggestions.is_empty());
+}
+
+#[tokio::test]
+async fn manual_items_can_be_checked_off_into_storage() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (product_id, product_name, _) = PRODUCTS[7];
+
+    let milk: ShoppingListItem = call(&mut app, json_request("POST", "/api/shopping-list/items", json!({ "name": " Melk " }))).await;
+    let burgers: ShoppingListItem = call(&mut app, json_request("POST", "/api/shopping-list/items", json!({
+        "productId": product_id, "quantity": 6.0, "unit": "pieces",
+    }))).await;
+    assert_eq!(milk.name, "Melk");
+    assert_eq!(burgers.name, product_name);
+    assert_eq!(get_shopping_list(&mut app).await.items, vec![milk.clone(), burgers.clone()]);
+
+    let checked_milk: CheckedItem = call(&mut app, Request::builder()
+        .uri(format!("/api/shopping-list/items/{}/check", milk.item_id))
+        .method("PATCH")
+        .body(Body::empty())
+        .unwrap()
+    ).await;
+    assert!(checked_milk.item.checked_at.is_some());
+    assert!(checked_milk.storage.is_empty());
+
+    let checked_burgers: CheckedItem = call(&mut app, json_request(
+        "PATCH", &format!("/api/shopping-list/items/{}/check", burgers.item_id), json!({ "store": { "drawerId": DRAWERS[0].0 } }),
+    )).await;
+    assert_eq!(checked_burgers.storage[0].product_name, product_name);
+    assert_eq!(checked_burgers.storage[0].unit, Unit::Pieces);
+    assert_eq!(checked_burgers.storage[0].quantity, 6.0);
+    assert!(get_shopping_list(&mut app).await.items.is_empty());
+
+    let recheck_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(json_request("PATCH", &format!("/api/shopping-list/items/{}/check", milk.item_id), json!({})))
+        .await
+        .unwrap();
+    assert_eq!(recheck_response.status(), StatusCode::CONFLICT);
+
+    let unchecked: ShoppingListItem = call(&mut app, json_request("PATCH", &format!("/api/shopping-list/items/{}/uncheck", milk.item_id), json!({}))).await;
+    assert_eq!(unchecked.checked_at, None);
+}
+
+#[tokio::test]
+async fn rejects_invalid_items() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let mut statuses = Vec::new();
+    for (uri, body) in [
+        ("/api/shopping-list/items", json!({ "name": "  " })),
+        ("/api/shopping-list/items", json!({ "name": "Melk", "quantity": -1.0 })),
+        ("/api/shopping-list/items", json!({ "productId": 999 })),
+    ] {
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("POST", uri, body))
+            .await
+            .unwrap();
+        statuses.push(response.status());
+    }
+    let store_response = {
+        let milk: ShoppingListItem = call(&mut app, json_request("POST", "/api/shopping-list/items", json!({ "name": "Melk" }))).await;
+        ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("PATCH", &format!("/api/shopping-list/items/{}/check", milk.item_id), json!({ "store": { "drawerId": 1 } })))
+            .await
+            .unwrap()
+    };
+
+    assert_eq!(statuses, vec![StatusCode::BAD_REQUEST, StatusCode::BAD_REQUEST, StatusCode::NOT_FOUND]);
+    assert_eq!(store_response.status(), StatusCode::BAD_REQUEST);
+    // The failed check-off was rolled back.
+    assert_eq!(get_shopping_list(&mut app).await.items.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_check_offs_store_the_item_once() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (product_id, product_name, _) = PRODUCTS[7];
+
+    let burgers: ShoppingListItem = call(&mut app, json_request("POST", "/api/shopping-list/items", json!({
+        "productId": product_id, "quantity": 6.0, "unit": "pieces",
+    }))).await;
+    let check_offs: Vec<_> = (0..4)
+        .map(|_| {
+            let app = app.clone();
+            let request = json_request(
+                "PATCH", &format!("/api/shopping-list/items/{}/check", burgers.item_id), json!({ "store": { "drawerId": DRAWERS[0].0 } }),
+            );
+            tokio::spawn(async move { app.oneshot(request).await.unwrap().status() })
+        })
+        .collect();
+    let mut statuses = Vec::new();
+    for check_off in check_offs {
+        statuses.push(check_off.await.unwrap());
+    }
+    statuses.sort();
+
+    assert_eq!(statuses, vec![StatusCode::OK, StatusCode::CONFLICT, StatusCode::CONFLICT, StatusCode::CONFLICT]);
+    let stored: Vec<serde_json::Value> = call(&mut app, Request::builder()
+        .uri(format!("/api/storage?productName={}&unit=pieces", product_name))
+        .body(Body::empty())
+        .unwrap()
+    ).await;
+    assert_eq!(stored.len(), 1);
+}
+
+#[tokio::test]
+async fn min_stock_honors_if_match() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let uri = format!("/api/products/id={}/min-stock", PRODUCTS[1].0);
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let etag = get_response.headers()["etag"].to_str().unwrap().to_owned();
+
+    let mut statuses = Vec::new();
+    for min_quantity in [4.0, 2.0] {
+        let mut request = json_request("PATCH", &uri, json!({ "minQuantity": min_quantity }));
+        request.headers_mut().insert("If-Match", etag.parse().unwrap());
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(request)
+            .await
+            .unwrap();
+        statuses.push(response.status());
+    }
+
+    assert_eq!(statuses, vec![StatusCode::OK, StatusCode::PRECONDITION_FAILED]);
+    let min_stock: ProductMinStock = call(&mut app, Request::builder().uri(&uri).body(Body::empty()).unwrap()).await;
+    assert_eq!(min_stock.min_quantity, Some(4.0));
+}
+
+#[tokio::test]
+async fn merge_moves_min_stock_and_shopping_list_items() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (target_id, target_name, _) = PRODUCTS[1];
+    let source_id = PRODUCTS[2].0;
+
+    let _: ProductMinStock = call(&mut app, json_request(
+        "PATCH", &format!("/api/products/id={}/min-stock", source_id), json!({ "minQuantity": 3.0, "unit": "portions" }),
+    )).await;
+    let item: ShoppingListItem = call(&mut app, json_request("POST", "/api/shopping-list/items", json!({ "productId": source_id }))).await;
+    let _: serde_json::Value = call(&mut app, json_request(
+        "POST", &format!("/api/products/id={}/merge", target_id), json!({ "sourceIds": [source_id] }),
+    )).await;
+
+    let min_stock: ProductMinStock = call(&mut app, Request::builder()
+        .uri(format!("/api/products/id={}/min-stock", target_id))
+        .body(Body::empty())
+        .unwrap()
+    ).await;
+    assert_eq!((min_stock.min_quantity, min_stock.unit), (Some(3.0), Some(Unit::Portions)));
+    let shopping_list = get_shopping_list(&mut app).await;
+    assert_eq!(shopping_list.items[0].item_id, item.item_id);
+    assert_eq!(shopping_list.items[0].product_id, Some(target_id));
+    assert_eq!(shopping_list.suggestions.len(), 1);
+    assert_eq!(shopping_list.suggestions[0].product_name, target_name);
+}
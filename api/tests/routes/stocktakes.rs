@@ -0,0 +1,125 @@
+use axum::{http::StatusCode, Router};
+use serde_json::json;
+
+use api::{
+    app,
+    models::{Stocktake, WithdrawalReason},
+    routes::{stocktakes::StocktakeReport, storage::StorageResponse},
+};
+use crate::common::{db::Context, db_data::FREEZERS, http::{call, json_request, request, status}};
+
+static MOD: &str = "router_stocktakes";
+
+async fn start(app: &mut Router, freezer_id: i32) -> StocktakeReport {
+    call(app, json_request("POST", "/api/stocktakes", json!({ "freezerId": freezer_id }))).await
+}
+
+fn ids(items: &[StorageResponse]) -> Vec<i32> {
+    items.iter().map(|item| item.storage_id).collect()
+}
+
+#[tokio::test]
+async fn completing_withdraws_unseen_items_as_missing() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (freezer_id, freezer_name) = FREEZERS[0];
+
+    let started = start(&mut app, freezer_id).await;
+    let id = started.stocktake.stocktake_id;
+    assert_eq!(started.freezer_name, freezer_name);
+    assert!(started.seen.is_empty());
+    assert_eq!(started.unseen.len(), 18);
+
+    for storage_id in 1..=7 {
+        let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/{}", id, storage_id))).await;
+    }
+    let item: Vec<StorageResponse> = call(&mut app, request("GET", "/api/storage/24")).await;
+    let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/code/{}", id, item[0].code.to_lowercase()))).await;
+    // Item 9 is registered in the Garage, item 36 as withdrawn.
+    let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/9", id))).await;
+    let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/36", id))).await;
+
+    let unseen: Vec<StorageResponse> = call(&mut app, request("GET", &format!("/api/stocktakes/{}/unseen", id))).await;
+    assert_eq!(ids(&unseen), vec![8, 27, 28, 29, 30, 31, 32, 33, 34, 35]);
+
+    let completed: StocktakeReport = call(&mut app, request("POST", &format!("/api/stocktakes/{}/complete", id))).await;
+    assert!(completed.stocktake.completed_at.is_some());
+    assert_eq!(ids(&completed.seen), vec![1, 2, 3, 4, 5, 6, 7, 9, 24, 36]);
+    assert_eq!(ids(&completed.unseen), ids(&unseen));
+    assert_eq!(ids(&completed.misplaced), vec![9]);
+    assert_eq!(ids(&completed.found_withdrawn), vec![36]);
+    assert!(completed.unseen.iter().all(|item| item.withdrawal_reason == Some(WithdrawalReason::Missing)
+        && item.out_storage_since.is_some()));
+
+    let missing: Vec<StorageResponse> = call(&mut app, request("GET", "/api/storage?withdrawalReason=missing")).await;
+    assert_eq!(ids(&missing), ids(&unseen));
+    // The report of a completed stocktake stays the same.
+    let reloaded: StocktakeReport = call(&mut app, request("GET", &format!("/api/stocktakes/{}", id))).await;
+    assert_eq!(ids(&reloaded.unseen), ids(&unseen));
+
+    assert_eq!(status(&mut app, request("POST", &format!("/api/stocktakes/{}/complete", id))).await, StatusCode::CONFLICT);
+    assert_eq!(status(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/8", id))).await, StatusCode::CONFLICT);
+    assert_eq!(status(&mut app, request("DELETE", &format!("/api/stocktakes/{}", id))).await, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn open_stocktakes_can_be_resumed() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (freezer_id, _) = FREEZERS[1];
+
+    let id = start(&mut app, freezer_id).await.stocktake.stocktake_id;
+    let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/9", id))).await;
+    let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/9", id))).await;
+    let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/10", id))).await;
+    assert_eq!(status(&mut app, json_request("POST", "/api/stocktakes", json!({ "freezerId": freezer_id }))).await, StatusCode::CONFLICT);
+
+    let open: Vec<Stocktake> = call(&mut app, request("GET", &format!("/api/stocktakes?freezerId={}&open=true", freezer_id))).await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].stocktake_id, id);
+    let resumed: StocktakeReport = call(&mut app, request("GET", &format!("/api/stocktakes/{}", id))).await;
+    assert_eq!(ids(&resumed.seen), vec![9, 10]);
+    assert!(resumed.misplaced.is_empty());
+
+    let unmarked: StocktakeReport = call(&mut app, request("DELETE", &format!("/api/stocktakes/{}/items/10", id))).await;
+    assert_eq!(ids(&unmarked.seen), vec![9]);
+    assert!(ids(&unmarked.unseen).contains(&10));
+
+    let deleted: i32 = call(&mut app, request("DELETE", &format!("/api/stocktakes/{}", id))).await;
+    assert_eq!(deleted, id);
+    assert_eq!(status(&mut app, request("GET", &format!("/api/stocktakes/{}", id))).await, StatusCode::NOT_FOUND);
+    let restarted = start(&mut app, freezer_id).await;
+    assert!(restarted.seen.is_empty());
+}
+
+#[tokio::test]
+async fn unknown_freezers_stocktakes_and_items_are_not_found() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    assert_eq!(status(&mut app, json_request("POST", "/api/stocktakes", json!({ "freezerId": 999 }))).await, StatusCode::NOT_FOUND);
+    assert_eq!(status(&mut app, request("GET", "/api/stocktakes/999")).await, StatusCode::NOT_FOUND);
+    assert_eq!(status(&mut app, request("POST", "/api/stocktakes/999/complete")).await, StatusCode::NOT_FOUND);
+
+    let id = start(&mut app, FREEZERS[2].0).await.stocktake.stocktake_id;
+    assert_eq!(status(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/999", id))).await, StatusCode::NOT_FOUND);
+    assert_eq!(status(&mut app, request("PATCH", &format!("/api/stocktakes/{}/code/ZZZZZZ", id))).await, StatusCode::NOT_FOUND);
+    assert_eq!(status(&mut app, request("PATCH", &format!("/api/stocktakes/{}/code/not-a-code", id))).await, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn purging_the_trash_removes_stocktakes_of_purged_freezers() {
+    use api::core::{connection::establish_connection, trash::purge_trash};
+
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let report = start(&mut app, FREEZERS[0].0).await;
+    let item_id = report.unseen[0].storage_id;
+    let _: StocktakeReport = call(&mut app, request("PATCH", &format!("/api/stocktakes/{}/items/{}", report.stocktake.stocktake_id, item_id))).await;
+    assert_eq!(status(&mut app, request("DELETE", &format!("/api/freezers/id={}?force=true", FREEZERS[0].0))).await, StatusCode::OK);
+
+    let conn = &mut establish_connection(Some(ctx.database_url()));
+    assert!(purge_trash(conn, chrono::Duration::zero()).unwrap() > 0);
+    assert_eq!(status(&mut app, request("GET", &format!("/api/stocktakes/{}", report.stocktake.stocktake_id))).await, StatusCode::NOT_FOUND);
+}
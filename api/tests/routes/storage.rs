@@ -6,7 +6,7 @@ use chrono::{Local, Months};
 use tower::{Service, ServiceExt};
 
 use api::{
-    app, models::{Drawer, Freezer, NewStorageItem, Product, Storage}, routes::storage::StorageResponse,
+    app, core::shelf_life::ShelfLifeRules, models::{Drawer, Freezer, NewStorageItem, Product, Storage}, routes::storage::StorageResponse,
 };
 
 use crate::common::db::Context;
@@ -32,7 +32,7 @@ fn storage_response_from_storage_item(storage: Storage) -> Vec<StorageResponse>
         product.clone(),
         drawer.clone(),
         freezer.clone(),
-    )])
+    )], &ShelfLifeRules::default())
 }
 
 fn storage_response_from_storage_vec(storage: Vec<Storage>) -> Vec<StorageResponse> {
@@ -49,6 +49,13 @@ enum Mod {
     Withdraw,
     Filter,
     Delete,
+    Concurrency,
+    Idempotency,
+    Units,
+    Expiration,
+    Codes,
+    Reasons,
+    Batch,
 }
 
 impl Mod {
@@ -60,6 +67,13 @@ impl Mod {
             Self::Withdraw => "storage_withdraw",
             Self::Delete => "storage_delete",
             Self::Filter => "storage_filter",
+            Self::Concurrency => "storage_concurrency",
+            Self::Idempotency => "storage_idempotency",
+            Self::Units => "storage_units",
+            Self::Expiration => "storage_expiration",
+            Self::Codes => "storage_codes",
+            Self::Reasons => "storage_reasons",
+            Self::Batch => "storage_batch",
         }
     }
 }
@@ -184,7 +198,7 @@ async fn creates_storage_correctly() {
         storage_response.in_storage_since,
         Local::now().date_naive()
     );
-    assert!(storage_response.weight_grams - 325.5 <= 1e-6);
+    assert!(storage_response.quantity - 325.5 <= 1e-6);
 }
 
 #[tokio::test]
@@ -551,6 +565,37 @@ mod storage_filters {
         assert_eq!(response_vec, expected_storage_vec);
     }
 
+    #[tokio::test]
+    async fn fuzzy_product_name_tolerates_typos_and_partial_names() {
+        let ctx = Context::new(Mod::Filter.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let expected_storage_vec = storage_response_from_storage_vec(
+            Storage::from_vec(STORAGE.to_vec()).into_iter().filter(|storage| {
+                storage.date_out.is_none()
+            }).collect::<Vec<Storage>>()
+        ).into_iter().filter(|storage| {
+            storage.product_name.eq(PRODUCTS[6].1)
+        }).collect::<Vec<StorageResponse>>();
+
+        for uri in ["/api/storage?productName=spaghetisaus&fuzzy=true", "/api/storage?productName=spag&fuzzy=true"] {
+            let response = ServiceExt::ready(&mut app).await.unwrap()
+                .call(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await.unwrap();
+            let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let response_vec = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+
+            assert_eq!(response_vec, expected_storage_vec);
+        }
+
+        let exact_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri("/api/storage?productName=spag").body(Body::empty()).unwrap())
+            .await.unwrap();
+        let bytes = hyper::body::to_bytes(exact_response.into_body()).await.unwrap();
+
+        assert!(serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn drawer_freezer_name_returns_correct_vec() {
         let ctx = Context::new(Mod::Filter.as_str());
@@ -751,7 +796,7 @@ mod storage_filters {
 
         let expected_vec = storage_response_from_storage_vec(
             Storage::from_vec(STORAGE.to_vec()).into_iter().filter(| storage | {
-                storage.weight_grams >= 500.0 && storage.date_out.is_none()
+                storage.quantity >= 500.0 && storage.date_out.is_none()
             }).collect::<Vec<Storage>>()
         );
 
@@ -777,7 +822,7 @@ mod storage_filters {
 
         let expected_vec = storage_response_from_storage_vec(
             Storage::from_vec(STORAGE.to_vec()).into_iter().filter(| storage | {
-                storage.weight_grams <= 400.0
+                storage.quantity <= 400.0
             }).collect::<Vec<Storage>>()
         );
 
@@ -796,3 +841,778 @@ mod storage_filters {
         assert_eq!(result_vec, expected_vec);
     }
 }
+
+mod storage_concurrency {
+    use super::*;
+    use axum::http::header;
+
+    #[tokio::test]
+    async fn update_with_stale_if_match_returns_precondition_failed() {
+        let ctx = Context::new(Mod::Concurrency.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let get_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage/25")
+                    .body(Body::empty())
+                    .unwrap()
+            ).await.unwrap();
+        let etag = get_response.headers().get(header::ETAG).unwrap().clone();
+        let bytes = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+        let mut storage = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap()[0].clone();
+        storage.quantity = 550.0;
+
+        let first_update = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage")
+                    .method("PATCH")
+                    .header("Content-Type", "application/json")
+                    .header(header::IF_MATCH, etag.clone())
+                    .body(Body::from(serde_json::to_string(&storage).unwrap()))
+                    .unwrap()
+            ).await.unwrap();
+
+        assert_eq!(first_update.status(), StatusCode::OK);
+        assert_ne!(first_update.headers().get(header::ETAG), Some(&etag), "Version was not incremented");
+
+        storage.quantity = 450.0;
+        let second_update = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage")
+                    .method("PATCH")
+                    .header("Content-Type", "application/json")
+                    .header(header::IF_MATCH, etag)
+                    .body(Body::from(serde_json::to_string(&storage).unwrap()))
+                    .unwrap()
+            ).await.unwrap();
+
+        assert_eq!(second_update.status(), StatusCode::PRECONDITION_FAILED);
+
+        let check_response = app.oneshot(
+            Request::builder()
+                .uri("/api/storage/25")
+                .body(Body::empty())
+                .unwrap()
+        ).await.unwrap();
+        let bytes = hyper::body::to_bytes(check_response.into_body()).await.unwrap();
+        let check_result = &serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap()[0];
+
+        assert!((check_result.quantity - 550.0).abs() <= 1e-6, "Stale update overwrote the first one");
+    }
+
+    #[tokio::test]
+    async fn delete_with_stale_if_match_returns_precondition_failed() {
+        let ctx = Context::new(Mod::Concurrency.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let get_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage/5")
+                    .body(Body::empty())
+                    .unwrap()
+            ).await.unwrap();
+        let etag = get_response.headers().get(header::ETAG).unwrap().clone();
+
+        let withdraw_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage/5/withdraw")
+                    .method("PATCH")
+                    .header(header::IF_MATCH, etag.clone())
+                    .body(Body::empty())
+                    .unwrap()
+            ).await.unwrap();
+
+        assert_eq!(withdraw_response.status(), StatusCode::OK);
+
+        let delete_response = app.oneshot(
+            Request::builder()
+                .uri("/api/storage/5")
+                .method("DELETE")
+                .header(header::IF_MATCH, etag)
+                .body(Body::empty())
+                .unwrap()
+        ).await.unwrap();
+
+        assert_eq!(delete_response.status(), StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[tokio::test]
+    async fn list_with_matching_if_none_match_returns_not_modified() {
+        let ctx = Context::new(Mod::Concurrency.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let list_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage")
+                    .body(Body::empty())
+                    .unwrap()
+            ).await.unwrap();
+        let etag = list_response.headers().get(header::ETAG).unwrap().clone();
+
+        let cached_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage")
+                    .header(header::IF_NONE_MATCH, etag.clone())
+                    .body(Body::empty())
+                    .unwrap()
+            ).await.unwrap();
+
+        assert_eq!(cached_response.status(), StatusCode::NOT_MODIFIED);
+
+        let withdraw_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(
+                Request::builder()
+                    .uri("/api/storage/1/withdraw")
+                    .method("PATCH")
+                    .body(Body::empty())
+                    .unwrap()
+            ).await.unwrap();
+
+        assert!(withdraw_response.status().is_success());
+
+        let changed_response = app.oneshot(
+            Request::builder()
+                .uri("/api/storage")
+                .header(header::IF_NONE_MATCH, etag)
+                .body(Body::empty())
+                .unwrap()
+        ).await.unwrap();
+
+        assert_eq!(changed_response.status(), StatusCode::OK);
+    }
+}
+
+mod storage_idempotency {
+    use super::*;
+
+    fn create_request(new_storage: &NewStorageItem, key: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/api/storage")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .header("Idempotency-Key", key)
+            .body(Body::from(serde_json::to_string(new_storage).unwrap()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retried_create_is_replayed_without_duplicate() {
+        let ctx = Context::new(Mod::Idempotency.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let new_storage = NewStorageItem::from(PRODUCTS[1].0, DRAWERS[3].0, 250.0, Local::now().date_naive());
+
+        let first_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(create_request(&new_storage, "kitchen-tablet-1"))
+            .await.unwrap();
+
+        assert!(first_response.status().is_success());
+        assert!(first_response.headers().get("Idempotent-Replayed").is_none());
+
+        let bytes = hyper::body::to_bytes(first_response.into_body()).await.unwrap();
+        let first_result = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+
+        let retry_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(create_request(&new_storage, "kitchen-tablet-1"))
+            .await.unwrap();
+
+        assert!(retry_response.status().is_success());
+        assert_eq!(retry_response.headers().get("Idempotent-Replayed").unwrap(), "true");
+
+        let bytes = hyper::body::to_bytes(retry_response.into_body()).await.unwrap();
+        let retry_result = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+
+        assert_eq!(retry_result, first_result);
+
+        let list_response = app.oneshot(
+            Request::builder()
+                .uri("/api/storage?minWeight=249&maxWeight=251")
+                .body(Body::empty())
+                .unwrap()
+        ).await.unwrap();
+        let bytes = hyper::body::to_bytes(list_response.into_body()).await.unwrap();
+        let list_result = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+
+        assert_eq!(list_result.len(), 1, "Retried request created a duplicate storage item");
+    }
+
+    #[tokio::test]
+    async fn reused_key_with_different_body_returns_conflict() {
+        let ctx = Context::new(Mod::Idempotency.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let new_storage = NewStorageItem::from(PRODUCTS[1].0, DRAWERS[3].0, 250.0, Local::now().date_naive());
+        let other_storage = NewStorageItem::from(PRODUCTS[2].0, DRAWERS[3].0, 250.0, Local::now().date_naive());
+
+        let first_response = ServiceExt::ready(&mut app)
+            .await.unwrap()
+            .call(create_request(&new_storage, "kitchen-tablet-2"))
+            .await.unwrap();
+
+        assert!(first_response.status().is_success());
+
+        let conflict_response = app
+            .oneshot(create_request(&other_storage, "kitchen-tablet-2"))
+            .await.unwrap();
+
+        assert_eq!(conflict_response.status(), StatusCode::CONFLICT);
+
+        let bytes = hyper::body::to_bytes(conflict_response.into_body()).await.unwrap();
+        let err_msg = std::str::from_utf8(&bytes[..]).unwrap();
+
+        assert_eq!(err_msg, "This Idempotency-Key was already used for a different request");
+    }
+
+    #[tokio::test]
+    async fn abandoned_key_is_released_after_timeout() {
+        use diesel::RunQueryDsl;
+
+        let mut ctx = Context::new(Mod::Idempotency.as_str());
+        let app = app(Some(ctx.database_url())).await;
+
+        let new_storage = NewStorageItem::from(PRODUCTS[1].0, DRAWERS[3].0, 250.0, Local::now().date_naive());
+        let conn = &mut ctx.establish_connection();
+        // A reservation left behind by a request that never completed.
+        diesel::sql_query(
+            "INSERT INTO idempotency_keys (idempotency_key, request_fingerprint, created_at) \
+             VALUES ('kitchen-tablet-3', 'unknown', now() - interval '2 minutes')"
+        ).execute(conn).unwrap();
+
+        let response = app
+            .oneshot(create_request(&new_storage, "kitchen-tablet-3"))
+            .await.unwrap();
+
+        assert!(response.status().is_success());
+        assert!(response.headers().get("Idempotent-Replayed").is_none());
+    }
+}
+
+mod storage_units {
+    use super::*;
+    use api::models::{NewProduct, Unit};
+    use api::routes::storage::StorageTotal;
+
+    fn post_json<T: serde::Serialize>(uri: &str, body: &T) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_string(body).unwrap()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn new_item_defaults_to_product_unit() {
+        let ctx = Context::new(Mod::Units.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let new_product = NewProduct {
+            name: String::from("Lasagne"),
+            expiration_months: Some(3),
+            default_unit: Some(Unit::Portions),
+        };
+        let product_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(post_json("/api/products/create", &new_product))
+            .await.unwrap();
+        let bytes = hyper::body::to_bytes(product_response.into_body()).await.unwrap();
+        let product = serde_json::from_slice::<Product>(&bytes).unwrap();
+        assert_eq!(product.default_unit, Unit::Portions);
+
+        let new_storage = NewStorageItem {
+            product_id: product.product_id,
+            drawer_id: DRAWERS[0].0,
+            quantity: 2.0,
+            unit: None,
+            date_in: Local::now().date_naive(),
+            expiration_date: None,
+        };
+        let create_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(post_json("/api/storage", &new_storage))
+            .await.unwrap();
+        assert_eq!(create_response.status(), StatusCode::OK);
+
+        let bytes = hyper::body::to_bytes(create_response.into_body()).await.unwrap();
+        let storage_response = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+
+        assert_eq!(storage_response[0].unit, Unit::Portions);
+        assert!((storage_response[0].quantity - 2.0).abs() <= 1e-6);
+    }
+
+    #[tokio::test]
+    async fn filters_and_totals_respect_units() {
+        let ctx = Context::new(Mod::Units.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        // Hamburgers, so far only stored by weight.
+        let (hamburgers_id, hamburgers_name, _) = PRODUCTS[7];
+        let new_storage = NewStorageItem {
+            product_id: hamburgers_id,
+            drawer_id: DRAWERS[0].0,
+            quantity: 6.0,
+            unit: Some(Unit::Pieces),
+            date_in: Local::now().date_naive(),
+            expiration_date: None,
+        };
+        ServiceExt::ready(&mut app).await.unwrap()
+            .call(post_json("/api/storage", &new_storage))
+            .await.unwrap();
+
+        let pieces_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri("/api/storage?unit=pieces").body(Body::empty()).unwrap())
+            .await.unwrap();
+        let bytes = hyper::body::to_bytes(pieces_response.into_body()).await.unwrap();
+        let pieces = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].unit, Unit::Pieces);
+
+        // Weight bounds without a unit only apply to grams.
+        let weight_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri("/api/storage?maxWeight=10").body(Body::empty()).unwrap())
+            .await.unwrap();
+        let bytes = hyper::body::to_bytes(weight_response.into_body()).await.unwrap();
+        assert!(serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap().is_empty());
+
+        let totals_response = app
+            .oneshot(Request::builder().uri(format!("/api/storage/totals?productName={}", hamburgers_name)).body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(totals_response.status(), StatusCode::OK);
+
+        let bytes = hyper::body::to_bytes(totals_response.into_body()).await.unwrap();
+        let totals = serde_json::from_slice::<Vec<StorageTotal>>(&bytes).unwrap();
+        let stored_grams: f32 = STORAGE.iter()
+            .filter(|item| item.1 == hamburgers_id && item.4.is_empty())
+            .map(|item| item.2)
+            .sum();
+
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].unit, Unit::Grams);
+        assert!((totals[0].quantity - stored_grams).abs() <= 1e-3);
+        assert_eq!(totals[1].unit, Unit::Pieces);
+        assert_eq!(totals[1].items, 1);
+    }
+
+    #[tokio::test]
+    async fn update_accepts_weight_grams_from_older_clients() {
+        let ctx = Context::new(Mod::Units.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let get_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri("/api/storage/3").body(Body::empty()).unwrap())
+            .await.unwrap();
+        let bytes = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+        let mut item = serde_json::from_slice::<Vec<serde_json::Value>>(&bytes).unwrap().remove(0);
+        let item_fields = item.as_object_mut().unwrap();
+        item_fields.remove("quantity");
+        item_fields.remove("unit");
+        item_fields.insert(String::from("weightGrams"), serde_json::json!(321.0));
+
+        let update_response = app
+            .oneshot(Request::builder()
+                .uri("/api/storage")
+                .method("PATCH")
+                .header("Content-Type", "application/json")
+                .body(Body::from(item.to_string()))
+                .unwrap())
+            .await.unwrap();
+        assert_eq!(update_response.status(), StatusCode::OK);
+
+        let bytes = hyper::body::to_bytes(update_response.into_body()).await.unwrap();
+        let updated = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+        assert_eq!(updated[0].unit, Unit::Grams);
+        assert!((updated[0].quantity - 321.0).abs() <= 1e-6);
+    }
+}
+
+mod storage_expiration {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::common::http::json_request;
+
+    async fn response_items(response: axum::response::Response) -> Vec<StorageResponse> {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn explicit_expiration_date_takes_precedence_in_response_and_filters() {
+        let ctx = Context::new(Mod::Expiration.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let best_before = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap();
+        let new_storage = NewStorageItem {
+            expiration_date: Some(best_before),
+            ..NewStorageItem::from(PRODUCTS[0].0, DRAWERS[0].0, 500.0, NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+        };
+        let create_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("POST", "/api/storage", serde_json::to_value(&new_storage).unwrap()))
+            .await.unwrap();
+        assert_eq!(create_response.status(), StatusCode::OK);
+        let created = &response_items(create_response).await[0];
+
+        assert_eq!(created.expiration_explicit, Some(true));
+        assert_eq!(created.expiration_date, best_before);
+
+        let filter_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri("/api/storage?expiresBeforeDate=2023-03-01").body(Body::empty()).unwrap())
+            .await.unwrap();
+        let filtered = response_items(filter_response).await;
+
+        assert!(filtered.iter().any(|item| item.storage_id == created.storage_id));
+    }
+
+    #[tokio::test]
+    async fn update_sets_and_clears_explicit_expiration_date() {
+        let ctx = Context::new(Mod::Expiration.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let storage_item = Storage::from_tuple(STORAGE[0]);
+        let computed = storage_response_from_storage_item(storage_item)[0].clone();
+        let best_before = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        let explicit = StorageResponse { expiration_date: best_before, expiration_explicit: Some(true), ..computed.clone() };
+        let set_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("PATCH", "/api/storage", serde_json::to_value(&explicit).unwrap()))
+            .await.unwrap();
+        assert_eq!(set_response.status(), StatusCode::OK);
+        let updated = &response_items(set_response).await[0];
+        assert_eq!(updated.expiration_explicit, Some(true));
+        assert_eq!(updated.expiration_date, best_before);
+
+        // Older clients leave the flag out, keeping the explicit date.
+        let mut unchanged = serde_json::to_value(&explicit).unwrap();
+        unchanged.as_object_mut().unwrap().remove("expirationExplicit");
+        let keep_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("PATCH", "/api/storage", unchanged))
+            .await.unwrap();
+        assert_eq!(keep_response.status(), StatusCode::OK);
+        let kept = &response_items(keep_response).await[0];
+        assert_eq!(kept.expiration_explicit, Some(true));
+        assert_eq!(kept.expiration_date, best_before);
+
+        let cleared = StorageResponse { expiration_explicit: Some(false), ..explicit };
+        let clear_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(json_request("PATCH", "/api/storage", serde_json::to_value(&cleared).unwrap()))
+            .await.unwrap();
+        assert_eq!(clear_response.status(), StatusCode::OK);
+        assert_eq!(response_items(clear_response).await, vec![computed]);
+    }
+
+    #[tokio::test]
+    async fn rejects_expiration_date_before_date_in() {
+        let ctx = Context::new(Mod::Expiration.as_str());
+        let app = app(Some(ctx.database_url())).await;
+
+        let new_storage = NewStorageItem {
+            expiration_date: Some(NaiveDate::from_ymd_opt(2022, 12, 31).unwrap()),
+            ..NewStorageItem::from(PRODUCTS[0].0, DRAWERS[0].0, 500.0, NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+        };
+        let response = app
+            .oneshot(json_request("POST", "/api/storage", serde_json::to_value(&new_storage).unwrap()))
+            .await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+mod storage_codes {
+    use super::*;
+    use api::core::item_code::{ALPHABET, CODE_LENGTH};
+
+    async fn get_item(app: &mut axum::Router, uri: &str) -> Vec<StorageResponse> {
+        let response = ServiceExt::ready(app).await.unwrap()
+            .call(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap()
+    }
+
+    fn patch(uri: &str) -> Request<Body> {
+        Request::builder().uri(uri).method("PATCH").body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn items_get_unique_codes_from_the_alphabet() {
+        let ctx = Context::new(Mod::Codes.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let items = get_item(&mut app, "/api/storage").await;
+        let mut codes = items.iter().map(|item| item.code.clone()).collect::<Vec<String>>();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(codes.len(), items.len());
+        assert!(codes.iter().all(|code| code.len() == CODE_LENGTH && code.chars().all(|c| ALPHABET.contains(c))));
+    }
+
+    #[tokio::test]
+    async fn get_withdraw_and_re_enter_by_code() {
+        let ctx = Context::new(Mod::Codes.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let item = get_item(&mut app, "/api/storage/1").await.remove(0);
+        let by_code = get_item(&mut app, &format!("/api/storage/code/{}", item.code.to_lowercase())).await;
+        assert_eq!(by_code, vec![item.clone()]);
+
+        let withdraw_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(patch(&format!("/api/storage/code/{}/withdraw", item.code)))
+            .await.unwrap();
+        assert_eq!(withdraw_response.status(), StatusCode::OK);
+        assert_eq!(get_item(&mut app, "/api/storage/1").await[0].out_storage_since, Some(Local::now().date_naive()));
+
+        let re_enter_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(patch(&format!("/api/storage/code/{}/re-enter", item.code)))
+            .await.unwrap();
+        assert_eq!(re_enter_response.status(), StatusCode::OK);
+        assert_eq!(get_item(&mut app, "/api/storage/1").await[0].out_storage_since, None);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_and_unknown_codes() {
+        let ctx = Context::new(Mod::Codes.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let invalid_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri("/api/storage/code/AB0OI1").body(Body::empty()).unwrap())
+            .await.unwrap();
+        // Codes are random, so look for one which is not taken.
+        let taken = get_item(&mut app, "/api/storage").await
+            .into_iter()
+            .map(|item| item.code)
+            .collect::<Vec<String>>();
+        let unknown = ["222222", "333333", "444444"].into_iter().find(|code| !taken.contains(&code.to_string())).unwrap();
+        let unknown_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(patch(&format!("/api/storage/code/{}/withdraw", unknown)))
+            .await.unwrap();
+
+        assert_eq!(invalid_response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(unknown_response.status(), StatusCode::NOT_FOUND);
+    }
+}
+
+mod storage_withdrawal_reasons {
+    use super::*;
+    use api::models::WithdrawalReason;
+
+    async fn get_items(app: &mut axum::Router, uri: &str) -> Vec<StorageResponse> {
+        let response = ServiceExt::ready(app).await.unwrap()
+            .call(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap()
+    }
+
+    fn withdraw(id: i32, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .uri(format!("/api/storage/{}/withdraw", id))
+            .method("PATCH")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn withdraw_stores_reason_and_note() {
+        let ctx = Context::new(Mod::Reasons.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(withdraw(1, serde_json::json!({ "reason": "discarded", "note": " freezer burn " })))
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(withdraw(2, serde_json::json!({ "reason": "eaten" })))
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let item = get_items(&mut app, "/api/storage/1").await.remove(0);
+        assert_eq!(item.withdrawal_reason, Some(WithdrawalReason::Discarded));
+        assert_eq!(item.withdrawal_note.as_deref(), Some("freezer burn"));
+
+        let discarded = get_items(&mut app, "/api/storage?withdrawalReason=discarded").await;
+        assert_eq!(discarded.iter().map(|item| item.storage_id).collect::<Vec<i32>>(), vec![1]);
+        let eaten = get_items(&mut app, "/api/storage?withdrawalReason=eaten").await;
+        assert_eq!(eaten.iter().map(|item| item.storage_id).collect::<Vec<i32>>(), vec![2]);
+
+        let re_enter_response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder().uri("/api/storage/1/re-enter").method("PATCH").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(re_enter_response.status(), StatusCode::OK);
+        let item = get_items(&mut app, "/api/storage/1").await.remove(0);
+        assert_eq!((item.out_storage_since, item.withdrawal_reason, item.withdrawal_note), (None, None, None));
+    }
+
+    #[tokio::test]
+    async fn unknown_reason_is_rejected() {
+        let ctx = Context::new(Mod::Reasons.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(withdraw(1, serde_json::json!({ "reason": "lost" })))
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(get_items(&mut app, "/api/storage/1").await[0].out_storage_since, None);
+    }
+
+    #[tokio::test]
+    async fn empty_json_body_withdraws_without_reason() {
+        let ctx = Context::new(Mod::Reasons.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder()
+                .uri("/api/storage/1/withdraw")
+                .method("PATCH")
+                .header("Content-Type", "application/json")
+                .body(Body::empty())
+                .unwrap())
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let item = &get_items(&mut app, "/api/storage/1").await[0];
+        assert_eq!(item.out_storage_since, Some(Local::now().date_naive()));
+        assert_eq!(item.withdrawal_reason, None);
+    }
+}
+
+mod storage_batches {
+    use super::*;
+    use axum::http::header::{CONTENT_TYPE, WARNING};
+
+    fn create_batch(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn storage_count(app: &mut axum::Router) -> usize {
+        let response = ServiceExt::ready(app).await.unwrap()
+            .call(Request::builder().uri("/api/storage").body(Body::empty()).unwrap())
+            .await.unwrap();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn template_creates_count_identical_items() {
+        let ctx = Context::new(Mod::Batch.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+        let before = storage_count(&mut app).await;
+
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(create_batch("/api/storage/batch", serde_json::json!({
+                "productId": PRODUCTS[1].0, "drawerId": DRAWERS[0].0, "quantity": 1.0, "unit": "portions",
+                "dateIn": "2023-10-01", "count": 8,
+            })))
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let created = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+
+        assert_eq!(created.len(), 8);
+        assert!(created.windows(2).all(|pair| pair[0].storage_id < pair[1].storage_id && pair[0].code != pair[1].code));
+        assert!(created.iter().all(|item| item.product_name == PRODUCTS[1].1 && item.quantity == 1.0));
+        assert_eq!(storage_count(&mut app).await, before + 8);
+    }
+
+    #[tokio::test]
+    async fn array_of_items_is_created_in_one_transaction() {
+        let ctx = Context::new(Mod::Batch.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+        let before = storage_count(&mut app).await;
+        let drawer_id = DRAWERS[0].0;
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(Request::builder()
+                .uri(format!("/api/drawers/{}/capacity", drawer_id))
+                .method("PATCH")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::json!({ "capacity": 4000.0 }).to_string()))
+                .unwrap())
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The unknown drawer of the last item rolls back the whole batch.
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(create_batch("/api/storage/batch", serde_json::json!([
+                { "productId": PRODUCTS[0].0, "drawerId": drawer_id, "quantity": 500.0, "dateIn": "2023-10-01" },
+                { "productId": PRODUCTS[0].0, "drawerId": 999, "quantity": 500.0, "dateIn": "2023-10-01" },
+            ])))
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(storage_count(&mut app).await, before);
+
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(create_batch("/api/storage/batch", serde_json::json!([
+                { "productId": PRODUCTS[0].0, "drawerId": drawer_id, "quantity": 500.0, "dateIn": "2023-10-01" },
+                { "productId": PRODUCTS[7].0, "drawerId": drawer_id, "quantity": 600.0, "dateIn": "2023-10-02" },
+            ])))
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get_all(WARNING).iter().count(), 1);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let created = serde_json::from_slice::<Vec<StorageResponse>>(&bytes).unwrap();
+        assert_eq!(created.iter().map(|item| item.product_name.as_str()).collect::<Vec<&str>>(), vec![PRODUCTS[0].1, PRODUCTS[7].1]);
+        assert_eq!(storage_count(&mut app).await, before + 2);
+    }
+
+    #[tokio::test]
+    async fn labels_of_the_new_items_can_be_returned() {
+        let ctx = Context::new(Mod::Batch.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        let response = ServiceExt::ready(&mut app).await.unwrap()
+            .call(create_batch("/api/storage/batch?format=pdf", serde_json::json!({
+                "productId": PRODUCTS[1].0, "drawerId": DRAWERS[0].0, "quantity": 1.0, "dateIn": "2023-10-01", "count": 3,
+            })))
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/pdf");
+    }
+
+    #[tokio::test]
+    async fn batch_size_is_limited() {
+        let ctx = Context::new(Mod::Batch.as_str());
+        let mut app = app(Some(ctx.database_url())).await;
+
+        for body in [
+            serde_json::json!([]),
+            serde_json::json!({
+                "productId": PRODUCTS[1].0, "drawerId": DRAWERS[0].0, "quantity": 1.0, "dateIn": "2023-10-01", "count": 0,
+            }),
+            serde_json::json!({
+                "productId": PRODUCTS[1].0, "drawerId": DRAWERS[0].0, "quantity": 1.0, "dateIn": "2023-10-01", "count": 101,
+            }),
+            // Rejected before any item is built.
+            serde_json::json!({
+                "productId": PRODUCTS[1].0, "drawerId": DRAWERS[0].0, "quantity": 1.0, "dateIn": "2023-10-01", "count": 1_000_000_000_000u64,
+            }),
+        ] {
+            let response = ServiceExt::ready(&mut app).await.unwrap()
+                .call(create_batch("/api/storage/batch", body))
+                .await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use chrono::{Days, Local, NaiveDate};
+use diesel::{prelude::*, sql_types::{Date, Int4}};
+use serde_json::json;
+
+use api::{
+    app,
+    models::{FreezerType, Unit},
+    routes::{storage::StorageResponse, summary::Summary},
+};
+use crate::common::{db::Context, db_data::{DRAWERS, FREEZERS, PRODUCTS}, http::{call, json_request, request}};
+
+static MOD: &str = "router_summary";
+
+#[tokio::test]
+async fn summarizes_freezers_and_drawers() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (_, freezer_name) = FREEZERS[0];
+    let (drawer_id, drawer_name, _) = DRAWERS[0];
+
+    let summary: Summary = call(&mut app, request("GET", "/api/summary")).await;
+
+    assert_eq!(summary.freezers.len(), FREEZERS.len());
+    let berging = &summary.freezers[0];
+    assert_eq!(berging.freezer_name, freezer_name);
+    assert_eq!(berging.totals.items, 18);
+    assert!((berging.totals.quantity[&Unit::Grams] - 9366.5).abs() < 0.1);
+    // All seeded items went in in 2023 and expired long ago.
+    assert_eq!((berging.totals.expired, berging.totals.expiring), (18, 0));
+    let oldest = berging.totals.oldest_item.as_ref().unwrap();
+    assert_eq!((oldest.storage_id, oldest.in_storage_since), (24, NaiveDate::from_ymd_opt(2023, 7, 13).unwrap()));
+    let top: Vec<(&str, i64)> = berging.totals.top_products.iter().map(|p| (p.product_name.as_str(), p.items)).collect();
+    assert_eq!(top, vec![("Brocoli", 8), ("Spaghettisaus", 5), ("Pastinaaksoep", 4)]);
+
+    assert_eq!(berging.drawers.len(), 5);
+    let schuif_1 = &berging.drawers[0];
+    assert_eq!((schuif_1.drawer_id, schuif_1.drawer_name.as_str()), (drawer_id, drawer_name));
+    assert_eq!(schuif_1.totals.items, 8);
+    assert_eq!(schuif_1.totals.top_products.len(), 1);
+    let empty = &berging.drawers[2];
+    assert_eq!((empty.totals.items, empty.totals.oldest_item.clone()), (0, None));
+}
+
+#[tokio::test]
+async fn counts_items_expiring_within_days() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+    let (product_id, _, _) = PRODUCTS[0];
+    let (drawer_id, _, _) = DRAWERS[2];
+    let today = Local::now().date_naive();
+
+    let _: Vec<StorageResponse> = call(&mut app, json_request("POST", "/api/storage", json!({
+        "productId": product_id, "drawerId": drawer_id, "quantity": 500.0, "unit": "grams",
+        "dateIn": today, "expirationDate": today + Days::new(3),
+    }))).await;
+
+    let summary: Summary = call(&mut app, request("GET", "/api/summary")).await;
+    assert_eq!(summary.expires_in_days, 7);
+    assert_eq!(summary.freezers[0].drawers[2].totals.expiring, 1);
+    assert_eq!(summary.freezers[0].totals.expiring, 1);
+
+    let summary: Summary = call(&mut app, request("GET", "/api/summary?expiresInDays=2&top=0")).await;
+    assert_eq!(summary.freezers[0].drawers[2].totals.expiring, 0);
+    assert!(summary.freezers[0].totals.top_products.is_empty());
+}
+
+#[derive(QueryableByName)]
+struct ViewRow {
+    #[diesel(sql_type = Int4)]
+    storage_id: i32,
+    #[diesel(sql_type = Date)]
+    expiration_date: NaiveDate,
+}
+
+#[tokio::test]
+async fn expiration_view_matches_shelf_life_rules() {
+    let mut ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    // Garage halves shelf lives, Spruiten keep 7 days there and Brocoli 10 days anywhere.
+    let freezer_type: FreezerType = call(&mut app, json_request(
+        "POST", "/api/freezer-types", json!({ "name": "One star", "shelfLifeMultiplier": 0.5 }),
+    )).await;
+    let _: serde_json::Value = call(&mut app, json_request(
+        "PATCH", &format!("/api/freezers/id={}/type", FREEZERS[1].0), json!({ "freezerTypeId": freezer_type.freezer_type_id }),
+    )).await;
+    let _: serde_json::Value = call(&mut app, json_request("PATCH", &format!("/api/products/id={}/shelf-life", PRODUCTS[2].0), json!({
+        "overrides": [{ "freezerTypeId": freezer_type.freezer_type_id, "shelfLifeDays": 7 }],
+    }))).await;
+    let _: serde_json::Value = call(&mut app, json_request(
+        "PATCH", &format!("/api/products/id={}/shelf-life", PRODUCTS[0].0), json!({ "shelfLifeDays": 10 }),
+    )).await;
+
+    let mut items: Vec<StorageResponse> = call(&mut app, request("GET", "/api/storage")).await;
+    items.extend(call::<Vec<StorageResponse>>(&mut app, request("GET", "/api/storage?isWithdrawn=true")).await);
+    let conn = &mut ctx.establish_connection();
+    let view: HashMap<i32, NaiveDate> = diesel::sql_query("SELECT storage_id, expiration_date FROM storage_expirations")
+        .load::<ViewRow>(conn)
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.storage_id, row.expiration_date))
+        .collect();
+
+    assert_eq!(view.len(), items.len());
+    for item in items {
+        assert_eq!(view[&item.storage_id], item.expiration_date, "storage item {}", item.storage_id);
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    models::Tag,
+    routes::storage::StorageResponse,
+};
+use crate::common::{db::Context, db_data::{PRODUCTS, STORAGE}};
+
+static MOD: &str = "router_tags";
+
+fn set_tags_request(product_id: i32, tags: &[&str]) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/api/products/id={}/tags", product_id))
+        .method("PATCH")
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(tags).unwrap()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sets_product_tags_creating_unknown_tags() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let set_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(set_tags_request(PRODUCTS[0].0, &["vegetarian", " green ", "vegetarian", ""]))
+        .await
+        .unwrap();
+    assert_eq!(set_response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(set_response.into_body()).await.unwrap();
+    let tags: Vec<Tag> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<&str>>(), vec!["green", "vegetarian"]);
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri(format!("/api/products/id={}/tags", PRODUCTS[0].0)).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let product_tags: Vec<Tag> = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(product_tags, tags);
+}
+
+#[tokio::test]
+async fn filters_storage_by_any_of_multiple_tags() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    for (product_id, tags) in [(PRODUCTS[5].0, ["meat"]), (PRODUCTS[7].0, ["beef"]), (PRODUCTS[0].0, ["vegetarian"])] {
+        ServiceExt::ready(&mut app).await.unwrap()
+            .call(set_tags_request(product_id, &tags))
+            .await
+            .unwrap();
+    }
+
+    let get_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(Request::builder().uri("/api/storage?tag=meat,beef").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(get_response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let items: Vec<StorageResponse> = serde_json::from_slice(&body).unwrap();
+    let expected = STORAGE.iter()
+        .filter(|item| item.4.is_empty() && (item.1 == PRODUCTS[5].0 || item.1 == PRODUCTS[7].0))
+        .count();
+
+    assert_eq!(items.len(), expected);
+}
@@ -0,0 +1,130 @@
+use axum::http::StatusCode;
+use chrono::Duration;
+use tower::{Service, ServiceExt};
+
+use api::{
+    app,
+    core::{connection::establish_connection, trash::purge_trash},
+    models::{Drawer, Freezer},
+    routes::trash::TrashResponse,
+};
+use crate::common::{db::Context, db_data::{DRAWERS, STORAGE}, http::{body_text, request}};
+
+static MOD: &str = "router_trash";
+
+#[tokio::test]
+async fn deleted_freezer_moves_to_trash_with_contents() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    let delete_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("DELETE", "/api/freezers/id=1?force=true"))
+        .await
+        .unwrap();
+    assert_eq!(delete_response.status(), StatusCode::OK);
+
+    let trash_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("GET", "/api/trash"))
+        .await
+        .unwrap();
+    assert_eq!(trash_response.status(), StatusCode::OK);
+
+    let trash: TrashResponse = serde_json::from_str(&body_text(trash_response).await).unwrap();
+    let freezer_drawers = DRAWERS.iter().filter(|drawer| drawer.2 == 1).count();
+    let freezer_storage = STORAGE.iter()
+        .filter(|item| DRAWERS.iter().any(|drawer| drawer.2 == 1 && drawer.0 == item.5))
+        .count();
+
+    assert_eq!(trash.freezers.len(), 1);
+    assert_eq!(trash.freezers[0].item.freezer_id, 1);
+    assert_eq!(trash.drawers.len(), freezer_drawers);
+    assert_eq!(trash.storage.len(), freezer_storage);
+    assert!(trash.drawers.iter().all(|drawer| drawer.deleted_at == trash.freezers[0].deleted_at));
+}
+
+#[tokio::test]
+async fn restores_freezer_with_contents() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("DELETE", "/api/freezers/id=1?force=true"))
+        .await
+        .unwrap();
+
+    let restore_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("PATCH", "/api/trash/freezers/1/restore"))
+        .await
+        .unwrap();
+    assert_eq!(restore_response.status(), StatusCode::OK);
+
+    let freezer: Freezer = serde_json::from_str(&body_text(restore_response).await).unwrap();
+    assert_eq!(freezer.freezer_id, 1);
+
+    let drawers_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("GET", "/api/drawers?freezerId=1"))
+        .await
+        .unwrap();
+    let drawers: Vec<Drawer> = serde_json::from_str(&body_text(drawers_response).await).unwrap();
+    assert_eq!(drawers.len(), DRAWERS.iter().filter(|drawer| drawer.2 == 1).count());
+
+    let trash_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("GET", "/api/trash"))
+        .await
+        .unwrap();
+    let trash: TrashResponse = serde_json::from_str(&body_text(trash_response).await).unwrap();
+    assert!(trash.freezers.is_empty() && trash.drawers.is_empty() && trash.storage.is_empty());
+}
+
+#[tokio::test]
+async fn restore_drawer_requires_active_freezer() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("DELETE", "/api/freezers/id=1?force=true"))
+        .await
+        .unwrap();
+
+    let restore_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("PATCH", "/api/trash/drawers/1/restore"))
+        .await
+        .unwrap();
+
+    assert_eq!(restore_response.status(), StatusCode::CONFLICT);
+    assert_eq!(body_text(restore_response).await, "Restore the freezer of this drawer first");
+}
+
+#[tokio::test]
+async fn restore_returns_not_found_for_active_item() {
+    let ctx = Context::new(MOD);
+    let app = app(Some(ctx.database_url())).await;
+
+    let response = app.oneshot(request("PATCH", "/api/trash/products/1/restore")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(body_text(response).await, "Product not found in the trash");
+}
+
+#[tokio::test]
+async fn purge_removes_expired_trash() {
+    let ctx = Context::new(MOD);
+    let mut app = app(Some(ctx.database_url())).await;
+
+    ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("DELETE", "/api/drawers/1?force=true"))
+        .await
+        .unwrap();
+
+    let conn = &mut establish_connection(Some(ctx.database_url()));
+    assert_eq!(purge_trash(conn, Duration::days(1)).unwrap(), 0);
+    let purged = purge_trash(conn, Duration::zero()).unwrap();
+    assert_eq!(purged, 1 + STORAGE.iter().filter(|item| item.5 == 1).count());
+
+    let trash_response = ServiceExt::ready(&mut app).await.unwrap()
+        .call(request("GET", "/api/trash"))
+        .await
+        .unwrap();
+    let trash: TrashResponse = serde_json::from_str(&body_text(trash_response).await).unwrap();
+    assert!(trash.drawers.is_empty() && trash.storage.is_empty());
+}
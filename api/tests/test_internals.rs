@@ -54,6 +54,46 @@ mod migrations {
                     .execute(conn)
                     .unwrap();
 
+                diesel::sql_query("SELECT * FROM idempotency_keys;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM categories;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM tags;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM product_tags;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM freezer_types;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM product_shelf_lives;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM product_barcodes;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM shopping_list_items;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM stocktakes;")
+                    .execute(conn)
+                    .unwrap();
+
+                diesel::sql_query("SELECT * FROM stocktake_items;")
+                    .execute(conn)
+                    .unwrap();
+
                 let false_table_returns_error = diesel::sql_query("SELECT * FROM does_not_exist")
                     .execute(conn)
                     .is_err();
@@ -83,7 +123,7 @@ mod migrations {
             let mut ctx = Context::new(CTX);
             let conn = &mut ctx.establish_connection();
 
-            let storage_result = diesel::sql_query("SELECT storage_id, product_id, weight_grams, date_in, date_out, drawer_id FROM storage;")
+            let storage_result = diesel::sql_query("SELECT storage_id, product_id, quantity, date_in, date_out, drawer_id, code, withdrawal_reason, withdrawal_note FROM storage;")
                 .execute(conn)
                 .is_ok();
 
@@ -95,7 +135,7 @@ mod migrations {
             let mut ctx = Context::new(CTX);
             let conn = &mut ctx.establish_connection();
 
-            let res = diesel::sql_query("SELECT freezer_id, name FROM freezers;")
+            let res = diesel::sql_query("SELECT freezer_id, name, capacity, capacity_unit FROM freezers;")
                 .execute(conn)
                 .is_ok();
 
@@ -107,11 +147,34 @@ mod migrations {
             let mut ctx = Context::new(CTX);
             let conn = &mut ctx.establish_connection();
 
-            let res = diesel::sql_query("SELECT drawer_id, name, freezer_id FROM drawers;")
+            let res = diesel::sql_query("SELECT drawer_id, name, freezer_id, capacity, capacity_unit FROM drawers;")
                 .execute(conn)
                 .is_ok();
 
             assert!(res);
         }
     }
+
+    mod indexes {
+        use super::*;
+        use test_log::test;
+
+        static CTX: &str = "indexes";
+
+        #[test]
+        fn names_are_unique_regardless_of_case() {
+            let mut ctx = Context::new(CTX);
+            let conn = &mut ctx.establish_connection();
+
+            let products_result = diesel::sql_query("INSERT INTO products (name) VALUES ('Erwtensoep'), ('ERWTENSOEP');")
+                .execute(conn)
+                .is_err();
+            let freezers_result = diesel::sql_query("INSERT INTO freezers (name) VALUES ('Zolder'), ('zolder');")
+                .execute(conn)
+                .is_err();
+
+            assert!(products_result);
+            assert!(freezers_result);
+        }
+    }
 }